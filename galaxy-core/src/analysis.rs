@@ -0,0 +1,15 @@
+//! Analysis of the state of a simulation, measuring the structure that forms as it evolves.
+
+pub mod correlation;
+pub mod friends_of_friends;
+pub mod tidal_tails;
+pub mod force_error;
+pub mod velocity_distribution;
+pub mod power_spectrum;
+pub mod radial_profile;
+pub mod density_field;
+pub mod acceleration_field;
+pub mod population;
+pub mod extremes;
+pub mod tree_balance;
+pub mod state_diff;
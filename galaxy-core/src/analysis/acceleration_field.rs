@@ -0,0 +1,45 @@
+use crate::config::Config;
+use crate::quadtree::Quadtree;
+use crate::simulation::{GalaxySim, Region};
+use crate::star_world::StarHandle;
+use crate::types::Vec2d;
+
+/// The magnitude of the gravitational acceleration over a rectangle, sampled on a grid using the
+/// quadtree's approximation and including the static halo. It peaks around the supermassive black
+/// hole and picks out the wakes satellites raise as they pass through the disk.
+#[derive(Clone, Debug, Default)]
+pub struct AccelerationField {
+    pub width: usize,
+    pub height: usize,
+
+    /// The magnitude of the acceleration at the center of each cell of the grid, row by row from
+    /// the minimum y.
+    pub values: Vec<f64>,
+
+    /// The largest value in the grid.
+    pub max: f64,
+}
+
+impl AccelerationField {
+    /// Sample the magnitude of the acceleration over the rectangle with the given minimum corner
+    /// and size, in parsecs, on a grid of the given size. The quadtree's mass distribution must be
+    /// up to date.
+    pub fn sample(quadtree: &Quadtree<StarHandle, Region>,
+                  config: &Config,
+                  offset: Vec2d,
+                  size: Vec2d,
+                  width: usize,
+                  height: usize) -> Self
+    {
+        let values: Vec<f64> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| {
+            let point = offset + Vec2d::new((x as f64 + 0.5) / width as f64 * size.x,
+                                            (y as f64 + 0.5) / height as f64 * size.y);
+            let acceleration = GalaxySim::acceleration_at_point(quadtree, config, point);
+            f64::sqrt(acceleration.x * acceleration.x + acceleration.y * acceleration.y)
+        }).collect();
+
+        let max = values.iter().copied().fold(0.0, f64::max);
+
+        Self { width, height, values, max }
+    }
+}
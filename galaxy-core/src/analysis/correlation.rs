@@ -0,0 +1,137 @@
+use crate::quadtree::{Quadtree, Spatial};
+use crate::types::Vec2d;
+
+/// The two-point correlation function ξ(r) of a set of positions: how much more likely a point is
+/// to have a neighbour at distance r than if the points were spread uniformly. This is 0 for an
+/// unclustered distribution and grows on the scales at which clumps form.
+#[derive(Clone, Debug, Default)]
+pub struct CorrelationFunction {
+    /// The edges of the radial bins in parsecs, spaced logarithmically. There's one more edge than
+    /// there are bins, or none if the bins couldn't be made from the radii asked for.
+    pub bin_edges: Vec<f64>,
+
+    /// The value of ξ in each bin.
+    pub values: Vec<f64>,
+}
+
+impl CorrelationFunction {
+    /// Measure the correlation function of the items in a quadtree, between a minimum and maximum
+    /// radius in parsecs. The neighbours of up to `sample_count` items, spread evenly through the
+    /// list, are counted using the quadtree, and compared to the count expected if the same number
+    /// of items were spread uniformly over their bounding box.
+    pub fn measure<T: Spatial, Internal>(quadtree: &Quadtree<T, Internal>,
+                                         min_radius: f64,
+                                         max_radius: f64,
+                                         bin_count: usize,
+                                         sample_count: usize) -> Self
+    {
+        // The bins are spaced logarithmically, so they need a positive range of radii.
+        if bin_count == 0 || min_radius <= 0.0 || max_radius <= min_radius {
+            return Self::default();
+        }
+
+        let bin_edges: Vec<f64> = (0..=bin_count)
+            .map(|i| min_radius * (max_radius / min_radius).powf(i as f64 / bin_count as f64))
+            .collect();
+
+        // The items are compared against the same number spread uniformly over their bounding box,
+        // which needs some area.
+        let items = &quadtree.items;
+        let (min, max) = Self::bounding_box(items);
+        let area = (max.x - min.x) * (max.y - min.y);
+        if items.len() < 2 || sample_count == 0 || area <= 0.0 {
+            return Self { bin_edges, values: vec![0.0; bin_count] };
+        }
+
+        // Count the neighbours of each sampled item in each bin.
+        let log_ratio = (max_radius / min_radius).ln();
+        let stride = usize::max(1, items.len() / sample_count);
+        let mut pair_counts = vec![0usize; bin_count];
+        let mut samples = 0;
+
+        for item in items.iter().step_by(stride) {
+            let center = *item.xy();
+            quadtree.items_in_radius(center, max_radius, |_, neighbour| {
+                let diff = *neighbour.xy() - center;
+                let dist = f64::sqrt(diff.x * diff.x + diff.y * diff.y);
+
+                // The item itself, and any others closer than the first bin, are left out.
+                if dist >= min_radius && dist < max_radius {
+                    let bin = ((dist / min_radius).ln() / log_ratio * bin_count as f64) as usize;
+                    pair_counts[bin.min(bin_count - 1)] += 1;
+                }
+            });
+            samples += 1;
+        }
+
+        // The mean density of the other items, if they were spread uniformly.
+        let mean_density = (items.len() - 1) as f64 / area;
+
+        let values = pair_counts.iter()
+            .enumerate()
+            .map(|(bin, &count)| {
+                let annulus_area = std::f64::consts::PI * (bin_edges[bin + 1].powi(2) - bin_edges[bin].powi(2));
+                let expected = samples as f64 * mean_density * annulus_area;
+                count as f64 / expected - 1.0
+            })
+            .collect();
+
+        Self { bin_edges, values }
+    }
+
+    /// The geometric center of a bin, in parsecs.
+    pub fn bin_radius(&self, bin: usize) -> f64 {
+        f64::sqrt(self.bin_edges[bin] * self.bin_edges[bin + 1])
+    }
+
+    /// The bounding box of a set of items.
+    fn bounding_box<T: Spatial>(items: &[T]) -> (Vec2d, Vec2d) {
+        let mut min = Vec2d::new(f64::MAX, f64::MAX);
+        let mut max = Vec2d::new(f64::MIN, f64::MIN);
+        for item in items {
+            let pos = item.xy();
+            min = Vec2d::new(min.x.min(pos.x), min.y.min(pos.y));
+            max = Vec2d::new(max.x.max(pos.x), max.y.max(pos.y));
+        }
+        (min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::simulation::Star;
+
+    /// A quadtree of stars spread uniformly over a square 1000 pc wide.
+    fn uniform_field(count: usize) -> Quadtree<Star> {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut quadtree = Quadtree::new(Vec2d::new(0.0, 0.0), Vec2d::new(1000.0, 1000.0)).unwrap();
+        for _ in 0..count {
+            let position = Vec2d::new(rng.gen_range(0.0..1000.0), rng.gen_range(0.0..1000.0));
+            quadtree.add(Star { position, ..Default::default() });
+        }
+        quadtree
+    }
+
+    #[test]
+    fn uniform_field_is_uncorrelated() {
+        let quadtree = uniform_field(20_000);
+        let correlation = CorrelationFunction::measure(&quadtree, 5.0, 50.0, 8, 2000);
+        assert_eq!(correlation.values.len(), 8);
+        for (bin, value) in correlation.values.iter().enumerate() {
+            assert!(value.abs() < 0.15, "ξ({:.1} pc) = {value} for a uniform field", correlation.bin_radius(bin));
+        }
+    }
+
+    #[test]
+    fn invalid_bins_are_empty() {
+        let quadtree = uniform_field(100);
+        for (min_radius, max_radius, bin_count) in [(5.0, 50.0, 0), (0.0, 50.0, 8), (-1.0, 50.0, 8), (50.0, 5.0, 8)] {
+            let correlation = CorrelationFunction::measure(&quadtree, min_radius, max_radius, bin_count, 100);
+            assert!(correlation.values.is_empty() && correlation.bin_edges.is_empty());
+        }
+    }
+}
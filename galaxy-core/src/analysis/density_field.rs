@@ -0,0 +1,64 @@
+use std::f64::consts::PI;
+
+use crate::quadtree::Quadtree;
+use crate::simulation::Region;
+use crate::star_world::StarHandle;
+use crate::types::Vec2d;
+
+/// A continuous estimate of the surface density of the stars over a rectangle, sampled on a grid,
+/// made by kernel density estimation. Each sample gathers the stars within the kernel's bandwidth
+/// of it using the quadtree, weighting them by an Epanechnikov kernel, which falls smoothly to zero
+/// at the bandwidth so the field has no hard edges.
+#[derive(Clone, Debug, Default)]
+pub struct DensityField {
+    pub width: usize,
+    pub height: usize,
+
+    /// The surface density at the center of each cell of the grid in Msun pc^-2, row by row from
+    /// the minimum y.
+    pub values: Vec<f64>,
+
+    /// The largest value in the grid.
+    pub max: f64,
+}
+
+impl DensityField {
+    /// Estimate the surface density of the stars in a quadtree over the rectangle with the given
+    /// minimum corner and size, in parsecs, on a grid of the given size. The bandwidth is the radius
+    /// of the kernel in parsecs. Only actual stars are counted, not black holes or dark matter.
+    pub fn estimate(quadtree: &Quadtree<StarHandle, Region>,
+                    offset: Vec2d,
+                    size: Vec2d,
+                    width: usize,
+                    height: usize,
+                    bandwidth: f64) -> Self
+    {
+        if bandwidth <= 0.0 {
+            return Self { width, height, values: vec![0.0; width * height], max: 0.0 };
+        }
+
+        // The two dimensional Epanechnikov kernel is 2 / (pi h^2) (1 - r^2 / h^2), which
+        // integrates to one over the disk of radius h.
+        let bandwidth_squared = bandwidth * bandwidth;
+        let normalization = 2.0 / (PI * bandwidth_squared);
+
+        let values: Vec<f64> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| {
+            let center = offset + Vec2d::new((x as f64 + 0.5) / width as f64 * size.x,
+                                             (y as f64 + 0.5) / height as f64 * size.y);
+
+            let mut density = 0.0;
+            quadtree.items_in_radius(center, bandwidth, |_, star| {
+                if star.species.is_star() {
+                    let diff = star.position - center;
+                    let distance_squared = diff.x * diff.x + diff.y * diff.y;
+                    density += star.mass.0 * normalization * (1.0 - distance_squared / bandwidth_squared);
+                }
+            });
+            density
+        }).collect();
+
+        let max = values.iter().copied().fold(0.0, f64::max);
+
+        Self { width, height, values, max }
+    }
+}
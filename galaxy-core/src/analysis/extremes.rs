@@ -0,0 +1,76 @@
+use crate::simulation::{GalaxySim, Star};
+use crate::types::Vec2d;
+
+/// A query for the star that's the most extreme in some way. Only actual stars are considered,
+/// not black holes, dark matter or tracers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Extreme {
+    MostMassive,
+    Fastest,
+    /// The star with the lowest specific orbital energy, i.e. the one held most tightly by the
+    /// galaxy's gravity.
+    MostBound,
+    /// The star furthest from the galaxy's center of mass.
+    Furthest,
+}
+
+impl Extreme {
+    /// All of the queries, for listing in the UI.
+    pub const ALL: [Extreme; 4] = [Extreme::MostMassive, Extreme::Fastest, Extreme::MostBound, Extreme::Furthest];
+
+    /// The name of the query for display.
+    pub fn name(self) -> &'static str {
+        match self {
+            Extreme::MostMassive => "Most massive",
+            Extreme::Fastest => "Fastest",
+            Extreme::MostBound => "Most bound",
+            Extreme::Furthest => "Furthest from center",
+        }
+    }
+
+    /// Find the index of the star that's most extreme, if there are any stars. Most of the
+    /// queries are a single scan of the stars, but finding the most bound star evaluates the
+    /// potential at every star with the quadtree built in the last step, so it takes about as long
+    /// as a step's force evaluation.
+    pub fn find(self, sim: &GalaxySim) -> Option<usize> {
+        let stars = &sim.stars().to_vec();
+        match self {
+            Extreme::MostMassive => Self::max_by(stars, |star| star.mass.0),
+            Extreme::Fastest => Self::max_by(stars, |star| Self::length_squared(star.velocity)),
+            Extreme::MostBound => Self::max_by(stars, |star| {
+                let potential = GalaxySim::potential_at_point(&sim.quadtree, sim.config(), star.position);
+                -(0.5 * Self::length_squared(star.velocity) + potential)
+            }),
+            Extreme::Furthest => {
+                let center = Self::center_of_mass(stars);
+                Self::max_by(stars, |star| Self::length_squared(star.position - center))
+            },
+        }
+    }
+
+    /// The index of the star with the largest value of a key.
+    fn max_by(stars: &[Star], key: impl Fn(&Star) -> f64) -> Option<usize> {
+        stars.iter()
+            .enumerate()
+            .filter(|(_, star)| star.is_star())
+            .map(|(i, star)| (i, key(star)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+    }
+
+    /// The center of mass of everything with gravity, or the origin if nothing has any mass.
+    fn center_of_mass(stars: &[Star]) -> Vec2d {
+        let (weighted, mass) = stars.iter().fold((Vec2d::new(0.0, 0.0), 0.0), |(weighted, mass), star| {
+            let star_mass = star.gravitating_mass().0;
+            (weighted + star.position * star_mass, mass + star_mass)
+        });
+        match mass > 0.0 {
+            true => weighted / mass,
+            false => Vec2d::new(0.0, 0.0),
+        }
+    }
+
+    fn length_squared(v: Vec2d) -> f64 {
+        v.x * v.x + v.y * v.y
+    }
+}
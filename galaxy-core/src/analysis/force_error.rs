@@ -0,0 +1,96 @@
+use crate::quadtree::{NodeIndex, Quadtree};
+use crate::simulation::{GalaxySim, Region};
+use crate::star_world::{Position, StarHandle};
+
+/// The error of the Barnes-Hut approximation of the force on one star.
+#[derive(Clone, Copy, Debug)]
+pub struct ForceErrorSample {
+    /// The index of the star.
+    pub star: NodeIndex,
+
+    /// The magnitude of the difference between the approximate and exact accelerations, relative to
+    /// the magnitude of the exact acceleration.
+    pub relative_error: f64,
+}
+
+/// The error of the quadtree's force approximation, measured for a subset of the stars by
+/// comparing it to summing the gravity of every star directly. This shows how the opening angle
+/// trades accuracy for speed.
+#[derive(Clone, Debug, Default)]
+pub struct ForceErrors {
+    /// The sampled stars, in order of their index.
+    pub samples: Vec<ForceErrorSample>,
+}
+
+impl ForceErrors {
+    /// Measure the force error for up to `sample_count` stars, spread evenly through the list. The
+    /// stars have moved since the simulation's quadtree was built in its last step, so a new one is
+    /// built from their current positions to measure only the error of the approximation. The
+    /// supermassive black hole isn't integrated, so it's left out.
+    pub fn measure(sim: &GalaxySim, sample_count: usize) -> Self {
+        let stars = sim.stars();
+        if stars.len() < 2 || sample_count == 0 {
+            return Self::default();
+        }
+
+        let mut quadtree = Quadtree::<StarHandle, Region>::new(sim.quadtree.min, sim.quadtree.max)
+            .expect("Failed to create quadtree");
+        for star in stars.handles() {
+            quadtree.add(star);
+        }
+        GalaxySim::update_mass_distribution(&mut quadtree);
+
+        let first_star = match sim.config().generation.has_black_hole() {
+            true => 1,
+            false => 0,
+        };
+        let stride = usize::max(1, (stars.len() - first_star) / sample_count);
+
+        let samples = (first_star..stars.len()).step_by(stride).filter_map(|star| {
+            let position = stars.component::<Position>(star)?.0;
+            let approximate = GalaxySim::acceleration_at_point(&quadtree, sim.config(), position);
+            let exact = GalaxySim::direct_acceleration_at_point(stars, sim.config(), position);
+
+            let error = approximate - exact;
+            let exact_magnitude = f64::sqrt(exact.x * exact.x + exact.y * exact.y);
+
+            // Stars with no force on them have no meaningful relative error.
+            (exact_magnitude > 0.0).then(|| ForceErrorSample {
+                star,
+                relative_error: f64::sqrt(error.x * error.x + error.y * error.y) / exact_magnitude,
+            })
+        }).collect();
+
+        Self { samples }
+    }
+
+    /// The relative error that the given fraction of the samples are below, e.g. 0.5 for the
+    /// median, or 0 if there are no samples.
+    pub fn percentile(&self, fraction: f64) -> f64 {
+        let mut errors: Vec<f64> = self.samples.iter().map(|sample| sample.relative_error).collect();
+        errors.sort_by(f64::total_cmp);
+
+        match errors.is_empty() {
+            true => 0.0,
+            false => errors[((errors.len() - 1) as f64 * fraction.clamp(0.0, 1.0)).round() as usize],
+        }
+    }
+
+    /// Count the samples in bins of log10 of the relative error, evenly spaced between
+    /// `min_log_error` and `max_log_error`. Errors outside the range are counted in the first or
+    /// last bin.
+    pub fn log_histogram(&self, min_log_error: f64, max_log_error: f64, bin_count: usize) -> Vec<usize> {
+        let mut counts = vec![0; bin_count];
+        if bin_count == 0 {
+            return counts;
+        }
+
+        for sample in &self.samples {
+            let log_error = sample.relative_error.max(f64::MIN_POSITIVE).log10();
+            let bin = (log_error - min_log_error) / (max_log_error - min_log_error) * bin_count as f64;
+            counts[(bin.max(0.0) as usize).min(bin_count - 1)] += 1;
+        }
+
+        counts
+    }
+}
@@ -0,0 +1,119 @@
+use crate::quadtree::{NodeIndex, Quadtree, Spatial};
+
+/// Groups found with the friends-of-friends algorithm, which links every pair of items closer
+/// together than a linking length, and takes the groups to be the connected sets of items. With a
+/// linking length of a fraction of the mean separation, the groups are the clusters that have
+/// formed out of the background.
+#[derive(Clone, Debug, Default)]
+pub struct FriendsOfFriends {
+    /// The indexes of the items in each group, largest group first. Groups with fewer than the
+    /// minimum number of members are left out.
+    pub groups: Vec<Vec<NodeIndex>>,
+}
+
+impl FriendsOfFriends {
+    /// Find the groups among the items in a quadtree that `include` accepts, with a linking length
+    /// in parsecs, using the quadtree to find each item's neighbours. For stellar groups, pass
+    /// `Star::is_star` so that tracers and dark matter particles are neither linked nor link stars.
+    pub fn find<T, Internal, F>(quadtree: &Quadtree<T, Internal>,
+                                linking_length: f64,
+                                min_members: usize,
+                                include: F) -> Self
+        where T: Spatial,
+              F: Fn(&T) -> bool
+    {
+        // A disjoint set forest, where each item points towards the root item of its group.
+        let mut parents: Vec<NodeIndex> = (0..quadtree.items.len()).collect();
+
+        for (index, item) in quadtree.items.iter().enumerate() {
+            if !include(item) {
+                continue;
+            }
+
+            quadtree.items_in_radius(*item.xy(), linking_length, |neighbour, neighbour_item| {
+                if !include(neighbour_item) {
+                    return;
+                }
+
+                let root = Self::find_root(&mut parents, index);
+                let neighbour_root = Self::find_root(&mut parents, neighbour);
+                if root != neighbour_root {
+                    parents[neighbour_root] = root;
+                }
+            });
+        }
+
+        // Gather the members of each group under their root.
+        let mut members: Vec<Vec<NodeIndex>> = vec![Vec::new(); parents.len()];
+        for index in (0..parents.len()).filter(|&index| include(&quadtree.items[index])) {
+            let root = Self::find_root(&mut parents, index);
+            members[root].push(index);
+        }
+
+        let mut groups: Vec<Vec<NodeIndex>> = members.into_iter()
+            .filter(|group| !group.is_empty() && group.len() >= min_members)
+            .collect();
+        groups.sort_by_key(|group| std::cmp::Reverse(group.len()));
+
+        Self { groups }
+    }
+
+    /// Find the root of an item's group, pointing the items on the way at their grandparents so
+    /// that later searches are shorter.
+    fn find_root(parents: &mut [NodeIndex], mut index: NodeIndex) -> NodeIndex {
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+            index = parents[index];
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{Star, StarComponent};
+    use crate::types::Vec2d;
+
+    #[test]
+    fn separated_clumps_are_separate_groups() {
+        let mut quadtree: Quadtree<Star> = Quadtree::new(Vec2d::new(-1000.0, -1000.0), Vec2d::new(1000.0, 1000.0))
+            .unwrap();
+        let star = |x: f64, y: f64, component: StarComponent| Star {
+            position: Vec2d::new(x, y),
+            component,
+            ..Default::default()
+        };
+
+        // Two clumps of stars on a 1 pc grid, 500 pc apart, a bridge of dark matter and tracers
+        // between them which mustn't join them, and an isolated star.
+        for i in 0..5 {
+            for j in 0..4 {
+                quadtree.add(star(-250.0 + i as f64, j as f64, StarComponent::Disk));
+                quadtree.add(star(250.0 + i as f64, j as f64, StarComponent::Bulge));
+            }
+        }
+        for x in -245..250 {
+            let component = match x % 2 == 0 {
+                true => StarComponent::Halo,
+                false => StarComponent::Tracer,
+            };
+            quadtree.add(star(x as f64, 0.5, component));
+        }
+        quadtree.add(star(0.0, 600.0, StarComponent::Disk));
+
+        let fof = FriendsOfFriends::find(&quadtree, 1.5, 2, Star::is_star);
+        assert_eq!(fof.groups.len(), 2);
+        for group in &fof.groups {
+            assert_eq!(group.len(), 20);
+            let first_x = quadtree.items[group[0]].position.x;
+            assert!(group.iter().all(|&i| quadtree.items[i].is_star()
+                && (quadtree.items[i].position.x - first_x).abs() < 10.0));
+        }
+
+        // Without the filter, the bridge joins everything but the isolated star into one group.
+        let fof = FriendsOfFriends::find(&quadtree, 1.5, 2, |_| true);
+        assert_eq!(fof.groups.len(), 1);
+        assert_eq!(fof.groups[0].len(), quadtree.items.len() - 1);
+    }
+}
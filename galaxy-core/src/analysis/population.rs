@@ -0,0 +1,35 @@
+use crate::simulation::GalaxySim;
+use crate::star_world::Species;
+
+/// How many stars are in the galaxy, and how many have left or joined it since it was generated,
+/// at a point in simulation time. Black holes, halo particles and tracers aren't counted as stars.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Population {
+    /// The simulation time, in Myr.
+    pub elapsed_myr: f64,
+
+    /// The number of stars in the galaxy.
+    pub alive: usize,
+
+    /// The number of stars that have merged with black holes, by being torn apart by them.
+    pub merged: usize,
+
+    /// The number of stars that have escaped the bounds of the simulation.
+    pub escaped: usize,
+
+    /// The number of stars that have been added to the galaxy, by scenario scripts.
+    pub formed: usize,
+}
+
+impl Population {
+    /// Count the stars of a simulation.
+    pub fn measure(sim: &GalaxySim) -> Self {
+        Self {
+            elapsed_myr: sim.elapsed_myr(),
+            alive: sim.stars().query::<&Species>().filter(|species| species.0.is_star()).count(),
+            merged: sim.disrupted_count,
+            escaped: sim.escaped_count,
+            formed: sim.formed_count,
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::f64::consts::PI;
+
+use crate::simulation::Star;
+
+/// The azimuthally averaged power spectrum of the surface density of the stars, which shows how
+/// much structure there is on each scale. Spiral arms and clumps show up as power on the scales of
+/// their widths and separations, which grows as they form.
+#[derive(Clone, Debug, Default)]
+pub struct PowerSpectrum {
+    /// The wavelength of each bin in parsecs, from the largest scale to the smallest.
+    pub wavelengths: Vec<f64>,
+
+    /// The power of the density contrast in each bin, in pc^2.
+    pub power: Vec<f64>,
+}
+
+impl PowerSpectrum {
+    /// Measure the power spectrum of the stars within a square of the given half-width about the
+    /// origin, in parsecs, by binning their mass onto a grid and taking its fourier transform. The
+    /// grid size must be a power of two, and the spectrum has a bin for each integer wavenumber up to
+    /// half the grid size.
+    pub fn measure(stars: &[Star], half_width: f64, grid_size: usize) -> Self {
+        assert!(grid_size.is_power_of_two(), "The power spectrum grid size {grid_size} isn't a power of two");
+
+        // Bin the mass of the stars onto the grid.
+        let mut grid = vec![Complex::default(); grid_size * grid_size];
+        let mut total_mass = 0.0;
+        for star in stars {
+            let x = (star.position.x / half_width * 0.5 + 0.5) * grid_size as f64;
+            let y = (star.position.y / half_width * 0.5 + 0.5) * grid_size as f64;
+            if (0.0..grid_size as f64).contains(&x) && (0.0..grid_size as f64).contains(&y) {
+                grid[y as usize * grid_size + x as usize].re += star.mass.0;
+                total_mass += star.mass.0;
+            }
+        }
+
+        let bin_count = grid_size / 2;
+        let wavelengths = (1..=bin_count).map(|k| 2.0 * half_width / k as f64).collect();
+        if total_mass == 0.0 {
+            return Self { wavelengths, power: vec![0.0; bin_count] };
+        }
+
+        // Take the transform of the density contrast, i.e. the density relative to the mean minus one.
+        let mean_mass = total_mass / grid.len() as f64;
+        for cell in &mut grid {
+            cell.re = cell.re / mean_mass - 1.0;
+        }
+        Self::fft_2d(&mut grid, grid_size);
+
+        // Average the power in rings of the same wavenumber. The constant (k = 0) term is left
+        // out, as it's always zero for the density contrast.
+        let mut power = vec![0.0; bin_count];
+        let mut counts = vec![0usize; bin_count];
+        let cell_area = (2.0 * half_width / grid_size as f64).powi(2);
+        let normalization = cell_area / grid.len() as f64;
+
+        for y in 0..grid_size {
+            for x in 0..grid_size {
+                // Frequencies above the Nyquist frequency are the negative ones.
+                let kx = Self::signed_frequency(x, grid_size);
+                let ky = Self::signed_frequency(y, grid_size);
+                let k = f64::sqrt((kx * kx + ky * ky) as f64).round() as usize;
+
+                if k >= 1 && k <= bin_count {
+                    let cell = grid[y * grid_size + x];
+                    power[k - 1] += (cell.re * cell.re + cell.im * cell.im) * normalization;
+                    counts[k - 1] += 1;
+                }
+            }
+        }
+
+        for (power, count) in power.iter_mut().zip(counts) {
+            if count > 0 {
+                *power /= count as f64;
+            }
+        }
+
+        Self { wavelengths, power }
+    }
+
+    /// The frequency of an index in the output of a transform of the given size.
+    fn signed_frequency(index: usize, size: usize) -> i64 {
+        match index <= size / 2 {
+            true => index as i64,
+            false => index as i64 - size as i64,
+        }
+    }
+
+    /// Take the fourier transform of a square grid in place, by transforming each row and then
+    /// each column.
+    fn fft_2d(grid: &mut [Complex], size: usize) {
+        for row in grid.chunks_mut(size) {
+            Self::fft(row);
+        }
+
+        let mut column = vec![Complex::default(); size];
+        for x in 0..size {
+            for y in 0..size {
+                column[y] = grid[y * size + x];
+            }
+            Self::fft(&mut column);
+            for y in 0..size {
+                grid[y * size + x] = column[y];
+            }
+        }
+    }
+
+    /// An in place radix-2 Cooley-Tukey fast fourier transform. The length must be a power of two.
+    fn fft(data: &mut [Complex]) {
+        let n = data.len();
+
+        // Put the data in bit reversed order, so that each pass can combine adjacent pairs of
+        // smaller transforms.
+        let mut j = 0;
+        for i in 1..n {
+            let mut bit = n >> 1;
+            while j & bit != 0 {
+                j ^= bit;
+                bit >>= 1;
+            }
+            j |= bit;
+            if i < j {
+                data.swap(i, j);
+            }
+        }
+
+        let mut length = 2;
+        while length <= n {
+            let angle = -2.0 * PI / length as f64;
+            let twiddle_step = Complex { re: angle.cos(), im: angle.sin() };
+
+            for start in (0..n).step_by(length) {
+                let mut twiddle = Complex { re: 1.0, im: 0.0 };
+                for i in 0..length / 2 {
+                    let even = data[start + i];
+                    let odd = data[start + i + length / 2].mul(twiddle);
+                    data[start + i] = Complex { re: even.re + odd.re, im: even.im + odd.im };
+                    data[start + i + length / 2] = Complex { re: even.re - odd.re, im: even.im - odd.im };
+                    twiddle = twiddle.mul(twiddle_step);
+                }
+            }
+
+            length *= 2;
+        }
+    }
+}
+
+/// A complex number, for the fourier transform.
+#[derive(Clone, Copy, Debug, Default)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn mul(self, other: Complex) -> Complex {
+        Complex {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::simulation::Star;
+
+/// The rotation curve and surface density profile of a set of stars, in equally spaced rings about
+/// the origin.
+#[derive(Clone, Debug, Default)]
+pub struct RadialProfile {
+    /// The outer radius of the outermost ring, in parsecs.
+    pub max_radius: f64,
+
+    /// The mass-weighted mean tangential velocity of the stars in each ring, in km/s. Positive
+    /// velocities are anticlockwise.
+    pub rotation_velocity: Vec<f64>,
+
+    /// The mass per unit area of each ring, in solar masses per square parsec.
+    pub surface_density: Vec<f64>,
+}
+
+impl RadialProfile {
+    /// Measure the profile of some stars out to a radius, with the given number of rings.
+    pub fn measure(stars: &[Star], max_radius: f64, bin_count: usize) -> Self {
+        let mut mass = vec![0.0; bin_count];
+        let mut tangential_momentum = vec![0.0; bin_count];
+
+        if max_radius > 0.0 {
+            for star in stars {
+                let radius = star.position.x.hypot(star.position.y);
+                let bin = (radius / max_radius * bin_count as f64) as usize;
+                if radius > 0.0 && bin < bin_count {
+                    let tangential_velocity = (star.position.x * star.velocity.y - star.position.y * star.velocity.x) / radius;
+                    mass[bin] += star.mass.0;
+                    tangential_momentum[bin] += star.mass.0 * tangential_velocity;
+                }
+            }
+        }
+
+        let ring_width = max_radius / bin_count as f64;
+        Self {
+            max_radius,
+            rotation_velocity: tangential_momentum.iter().zip(&mass)
+                .map(|(&tangential_momentum, &mass)| match mass > 0.0 {
+                    true => tangential_momentum / mass,
+                    false => 0.0,
+                })
+                .collect(),
+            surface_density: mass.iter().enumerate()
+                .map(|(bin, &mass)| {
+                    let (inner, outer) = (bin as f64 * ring_width, (bin + 1) as f64 * ring_width);
+                    mass / (std::f64::consts::PI * (outer * outer - inner * inner))
+                })
+                .collect(),
+        }
+    }
+
+    /// The radius at the middle of a ring, in parsecs.
+    pub fn bin_radius(&self, bin: usize) -> f64 {
+        (bin as f64 + 0.5) * self.max_radius / self.rotation_velocity.len() as f64
+    }
+}
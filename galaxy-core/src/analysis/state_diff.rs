@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use crate::simulation::Star;
+use crate::types::Vec2d;
+
+/// How far a star is from where it is in another state of the simulation.
+#[derive(Clone, Copy, Debug)]
+pub struct StarDisplacement {
+    pub id: u64,
+
+    /// The star's position in the first state and the second, in parsecs.
+    pub first: Vec2d,
+    pub second: Vec2d,
+
+    /// The distance between the two, in parsecs.
+    pub distance: f64,
+}
+
+/// The differences between the positions of the stars in two states of a simulation, e.g. the same
+/// seed run with different integrators or timesteps, to quantify how far they've diverged. Stars
+/// are matched by their IDs, so stars removed or added in only one of the states are counted but
+/// otherwise left out.
+#[derive(Clone, Debug, Default)]
+pub struct StateDiff {
+    /// The displacements of the stars in both states, sorted from smallest to largest.
+    pub displacements: Vec<StarDisplacement>,
+
+    /// The number of stars only in the first state, and only in the second.
+    pub only_in_first: usize,
+    pub only_in_second: usize,
+}
+
+impl StateDiff {
+    /// Compare the stars of two states.
+    pub fn compare(first: &[Star], second: &[Star]) -> Self {
+        let second_positions: HashMap<u64, Vec2d> = second.iter().map(|star| (star.id, star.position)).collect();
+
+        let mut displacements: Vec<StarDisplacement> = first.iter()
+            .filter_map(|star| {
+                let second = *second_positions.get(&star.id)?;
+                let offset = second - star.position;
+                Some(StarDisplacement {
+                    id: star.id,
+                    first: star.position,
+                    second,
+                    distance: offset.x.hypot(offset.y),
+                })
+            })
+            .collect();
+        displacements.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+
+        let matched = displacements.len();
+        Self {
+            displacements,
+            only_in_first: first.len() - matched,
+            only_in_second: second.len() - matched,
+        }
+    }
+
+    /// The mean distance between the matched stars' positions, in parsecs.
+    pub fn mean_distance(&self) -> f64 {
+        let total: f64 = self.displacements.iter().map(|displacement| displacement.distance).sum();
+        total / usize::max(self.displacements.len(), 1) as f64
+    }
+
+    /// The root mean square distance between the matched stars' positions, in parsecs.
+    pub fn rms_distance(&self) -> f64 {
+        let total: f64 = self.displacements.iter().map(|displacement| displacement.distance.powi(2)).sum();
+        (total / usize::max(self.displacements.len(), 1) as f64).sqrt()
+    }
+
+    /// The distance that the given fraction of the matched stars have moved less than, in parsecs.
+    pub fn percentile(&self, fraction: f64) -> f64 {
+        match self.displacements.is_empty() {
+            true => 0.0,
+            false => {
+                let index = ((self.displacements.len() - 1) as f64 * fraction.clamp(0.0, 1.0)).round() as usize;
+                self.displacements[index].distance
+            },
+        }
+    }
+
+    /// The star that moved the furthest, if any were matched.
+    pub fn largest(&self) -> Option<&StarDisplacement> {
+        self.displacements.last()
+    }
+
+    /// Count the matched stars in bins of log10 of the distance in parsecs, evenly spaced between
+    /// `min_log_distance` and `max_log_distance`. Distances outside the range are counted in the
+    /// first or last bin.
+    pub fn log_histogram(&self, min_log_distance: f64, max_log_distance: f64, bin_count: usize) -> Vec<usize> {
+        let mut counts = vec![0; bin_count];
+        if bin_count == 0 {
+            return counts;
+        }
+
+        for displacement in &self.displacements {
+            let log_distance = displacement.distance.max(f64::MIN_POSITIVE).log10();
+            let bin = (log_distance - min_log_distance) / (max_log_distance - min_log_distance) * bin_count as f64;
+            counts[(bin.max(0.0) as usize).min(bin_count - 1)] += 1;
+        }
+
+        counts
+    }
+}
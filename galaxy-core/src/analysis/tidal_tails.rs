@@ -0,0 +1,152 @@
+use crate::simulation::{GalaxySim, Star};
+use crate::types::Vec2d;
+
+/// The fewest stars the shrinking sphere used to find the center of the main body can contain.
+const MIN_CENTER_STARS: usize = 100;
+
+/// How much the sphere shrinks by at each iteration when finding the center of the main body.
+const CENTER_SHRINK_FACTOR: f64 = 0.75;
+
+/// Where a star is relative to the main body of the galaxy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TidalState {
+    /// Within the main body.
+    Body,
+
+    /// Far outside the main body but still bound to the system, e.g. in a tidal tail or bridge.
+    Tail,
+
+    /// Outside the main body and no longer bound, but bound recently enough that it was stripped
+    /// from the galaxy rather than always being on its way out.
+    Stripped,
+
+    /// Outside the main body and unbound, and hasn't been bound recently.
+    Unbound,
+}
+
+/// Finds the stars that are far outside the main body of a galaxy, such as the tidal tails and
+/// bridges pulled out of galaxies during mergers and flybys. The main body is centered on its
+/// densest part, found with a shrinking sphere so that distant tails and companions don't pull
+/// the center away from it, and stars outside a multiple of the radius containing half the stars
+/// are classified by whether they're still bound.
+#[derive(Default)]
+pub struct TidalTails {
+    /// The state of each star, in the same order as the stars.
+    pub states: Vec<TidalState>,
+
+    /// The center of the main body, in parsecs.
+    pub center: Vec2d,
+
+    /// The radius outside which stars are considered far from the main body, in parsecs.
+    pub body_radius: f64,
+
+    /// When each star was last bound, in Myr, or None if it hasn't been since the tracking
+    /// started.
+    last_bound_myr: Vec<Option<f64>>,
+}
+
+impl TidalTails {
+    /// Classify the stars of a simulation, using the quadtree built in its last step to find their
+    /// potential energy. Stars further from the center than `radius_factor` times the radius
+    /// containing half the stars are outside the main body, and those that are unbound are counted
+    /// as stripped if they were bound within the last `stripped_myr` Myr.
+    pub fn update(&mut self, sim: &GalaxySim, radius_factor: f64, stripped_myr: f64) {
+        let stars = &sim.stars().to_vec();
+        let elapsed_myr = sim.elapsed_myr();
+
+        // The stars' bound history doesn't mean anything if they've been added or removed.
+        if self.last_bound_myr.len() != stars.len() {
+            self.last_bound_myr = vec![None; stars.len()];
+        }
+
+        self.center = Self::find_center(stars);
+        let (half_radius, bulk_velocity) = self.half_radius(stars);
+        self.body_radius = half_radius * radius_factor;
+
+        self.states = stars.iter().zip(&mut self.last_bound_myr).map(|(star, last_bound_myr)| {
+            let offset = star.position - self.center;
+            if offset.x * offset.x + offset.y * offset.y <= self.body_radius * self.body_radius {
+                *last_bound_myr = Some(elapsed_myr);
+                return TidalState::Body;
+            }
+
+            // Stars are bound if their kinetic energy relative to the main body is less than the
+            // depth of the potential well they're in.
+            let velocity = star.velocity - bulk_velocity;
+            let kinetic_energy = 0.5 * (velocity.x * velocity.x + velocity.y * velocity.y);
+            let potential = GalaxySim::potential_at_point(&sim.quadtree, sim.config(), star.position);
+
+            if kinetic_energy + potential < 0.0 {
+                *last_bound_myr = Some(elapsed_myr);
+                TidalState::Tail
+            }
+            else if last_bound_myr.is_some_and(|last_bound_myr| elapsed_myr - last_bound_myr <= stripped_myr) {
+                TidalState::Stripped
+            }
+            else {
+                TidalState::Unbound
+            }
+        }).collect();
+    }
+
+    /// The number of stars in a state.
+    pub fn count(&self, state: TidalState) -> usize {
+        self.states.iter().filter(|&&star_state| star_state == state).count()
+    }
+
+    /// Find the center of the densest part of a set of stars, by repeatedly taking the center of
+    /// mass of the stars within a shrinking sphere about the last center.
+    fn find_center(stars: &[Star]) -> Vec2d {
+        let mut center = Self::center_of_mass(stars.iter());
+        let mut radius = stars.iter()
+            .map(|star| Self::distance(star.position, center))
+            .fold(0.0, f64::max);
+
+        let min_stars = usize::max(MIN_CENTER_STARS, stars.len() / 10);
+        loop {
+            radius *= CENTER_SHRINK_FACTOR;
+            let inside = || stars.iter().filter(|star| Self::distance(star.position, center) <= radius);
+            if inside().count() < min_stars {
+                break center;
+            }
+            center = Self::center_of_mass(inside());
+        }
+    }
+
+    /// The radius about the center containing half of the stars, and their mean velocity. This
+    /// counts stars rather than weighing them by mass, so that a massive central black hole
+    /// doesn't shrink it to nothing.
+    fn half_radius(&self, stars: &[Star]) -> (f64, Vec2d) {
+        let mut distances: Vec<(f64, &Star)> = stars.iter()
+            .map(|star| (Self::distance(star.position, self.center), star))
+            .collect();
+        distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let half_count = distances.len().div_ceil(2);
+        let radius = distances.get(half_count.saturating_sub(1)).map_or(0.0, |&(distance, _)| distance);
+        let velocity_sum = distances[..half_count].iter()
+            .fold(Vec2d::new(0.0, 0.0), |sum, (_, star)| sum + star.velocity);
+
+        (radius, velocity_sum / usize::max(half_count, 1) as f64)
+    }
+
+    /// The center of mass of some stars.
+    fn center_of_mass<'a, I: Iterator<Item = &'a Star>>(stars: I) -> Vec2d {
+        let mut mass = 0.0;
+        let mut weighted_position = Vec2d::new(0.0, 0.0);
+        for star in stars {
+            mass += star.mass.0;
+            weighted_position = weighted_position + star.position * star.mass.0;
+        }
+
+        match mass > 0.0 {
+            true => weighted_position / mass,
+            false => weighted_position,
+        }
+    }
+
+    fn distance(a: Vec2d, b: Vec2d) -> f64 {
+        let diff = a - b;
+        f64::sqrt(diff.x * diff.x + diff.y * diff.y)
+    }
+}
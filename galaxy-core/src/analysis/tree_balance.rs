@@ -0,0 +1,102 @@
+use crate::quadtree::{Quadtree, QuadtreeNode, Spatial};
+
+/// The largest number of items in a leaf counted separately in the histogram, with fuller leaves
+/// counted together with it.
+pub const MAX_COUNTED_LEAF_ITEMS: usize = 8;
+
+/// How many levels deeper than a perfectly balanced tree of the same number of leaves the deepest
+/// leaf can be before the tree is considered pathologically deep.
+const PATHOLOGICAL_EXTRA_DEPTH: f64 = 8.0;
+
+/// How balanced a quadtree is and how full its nodes are, to guide the choice of its bounds and
+/// maximum depth.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TreeBalance {
+    /// The number of leaves at each depth, from the root at depth 0 to the tree's maximum depth.
+    pub leaf_depths: Vec<usize>,
+
+    /// The number of leaves holding each number of items, from 1 to MAX_COUNTED_LEAF_ITEMS, with
+    /// fuller leaves counted in the last. Leaves only hold more than one item when the items are
+    /// too close together to separate before the maximum depth.
+    pub leaf_items: [usize; MAX_COUNTED_LEAF_ITEMS],
+
+    /// The number of internal nodes with each number of children, from 1 to 4.
+    pub internal_children: [usize; 4],
+
+    /// The number of items in leaves.
+    pub item_count: usize,
+
+    /// The most items in any one leaf.
+    pub max_leaf_items: usize,
+
+    /// The exclusive maximum depth of the tree's nodes.
+    pub max_depth: u8,
+}
+
+impl TreeBalance {
+    /// Measure a quadtree by walking all of its nodes.
+    pub fn measure<T: Spatial, Internal>(quadtree: &Quadtree<T, Internal>) -> Self {
+        let mut balance = Self {
+            leaf_depths: vec![0; quadtree.max_depth() as usize],
+            max_depth: quadtree.max_depth(),
+            ..Default::default()
+        };
+
+        quadtree.walk_nodes(|index, node| match node {
+            &QuadtreeNode::Leaf(first_item) => {
+                let items = quadtree.leaf_items(first_item).count();
+                balance.leaf_depths[index.depth() as usize] += 1;
+                balance.leaf_items[usize::clamp(items, 1, MAX_COUNTED_LEAF_ITEMS) - 1] += 1;
+                balance.item_count += items;
+                balance.max_leaf_items = usize::max(balance.max_leaf_items, items);
+            },
+            QuadtreeNode::Internal(_) => {
+                let children = index.children().into_iter().filter(|&child| quadtree.get(child).is_some()).count();
+                if children > 0 {
+                    balance.internal_children[children - 1] += 1;
+                }
+            },
+        });
+
+        balance
+    }
+
+    /// The number of leaves.
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_depths.iter().sum()
+    }
+
+    /// The depth of the deepest leaf.
+    pub fn deepest_leaf(&self) -> usize {
+        self.leaf_depths.iter().rposition(|&count| count > 0).unwrap_or(0)
+    }
+
+    /// The mean depth of the leaves.
+    pub fn mean_leaf_depth(&self) -> f64 {
+        let total: usize = self.leaf_depths.iter().enumerate().map(|(depth, count)| depth * count).sum();
+        total as f64 / usize::max(self.leaf_count(), 1) as f64
+    }
+
+    /// The depth the leaves would all be at if the tree were perfectly balanced.
+    pub fn balanced_depth(&self) -> f64 {
+        (usize::max(self.leaf_count(), 1) as f64).log(4.0)
+    }
+
+    /// A warning about the shape of the tree if it's pathologically deep, or items have had to
+    /// share leaves because it hit its maximum depth, along with what to change.
+    pub fn warning(&self) -> Option<String> {
+        let deepest = self.deepest_leaf();
+        if self.max_leaf_items > 1 {
+            Some(format!("Stars share leaves at the maximum depth of {}, so their forces on each other \
+                          aren't resolved. Increase the maximum depth or shrink the bounds.", self.max_depth))
+        }
+        else if deepest as f64 > self.balanced_depth() + PATHOLOGICAL_EXTRA_DEPTH {
+            Some(format!("The deepest leaf is at depth {deepest}, far deeper than the {:.1} of a balanced tree, \
+                          so tree walks are slow. Tight clumps of stars or bounds much larger than the galaxy \
+                          cause this.", self.balanced_depth()))
+        }
+        else {
+            None
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::simulation::Star;
+
+/// The fraction of stars whose speeds are within the range of the histograms, so that a few very
+/// fast stars, e.g. those close to the central black hole, don't squash everything else into the
+/// first bin.
+const SPEED_RANGE_FRACTION: f64 = 0.99;
+
+/// Histograms of the velocities of stars, of their speeds, and of the radial and tangential
+/// components of their velocities about the origin, along with the Maxwellian distribution that
+/// best fits the speeds.
+#[derive(Clone, Debug, Default)]
+pub struct VelocityDistribution {
+    /// The speed at the top of the speed histogram, and the magnitude of the largest component in
+    /// the radial and tangential histograms, in km/s.
+    pub max_speed: f64,
+
+    /// The number of stars in each bin of speed, from 0 to `max_speed`.
+    pub speeds: Vec<usize>,
+
+    /// The number of stars in each bin of radial velocity, from `-max_speed` to `max_speed`.
+    /// Positive velocities are away from the origin.
+    pub radial: Vec<usize>,
+
+    /// The number of stars in each bin of tangential velocity, from `-max_speed` to `max_speed`.
+    /// Positive velocities are anticlockwise.
+    pub tangential: Vec<usize>,
+
+    /// The velocity dispersion in each direction of the Maxwellian distribution fit to the speeds,
+    /// in km/s.
+    pub dispersion: f64,
+
+    /// The number of stars the histograms were made from.
+    pub star_count: usize,
+}
+
+impl VelocityDistribution {
+    /// Measure the distribution of the velocities of some stars, with the given number of bins in
+    /// each histogram.
+    pub fn measure(stars: &[Star], bin_count: usize) -> Self {
+        if stars.is_empty() || bin_count == 0 {
+            return Self::default();
+        }
+
+        let speeds: Vec<f64> = stars.iter()
+            .map(|star| f64::sqrt(star.velocity.x * star.velocity.x + star.velocity.y * star.velocity.y))
+            .collect();
+
+        // The maximum likelihood fit of a two dimensional Maxwellian distribution, which is the
+        // Rayleigh distribution, has a dispersion of sqrt(mean(v^2) / 2).
+        let mean_square_speed = speeds.iter().map(|speed| speed * speed).sum::<f64>() / speeds.len() as f64;
+        let dispersion = f64::sqrt(mean_square_speed / 2.0);
+
+        let mut sorted_speeds = speeds.clone();
+        sorted_speeds.sort_by(f64::total_cmp);
+        let max_speed = sorted_speeds[((speeds.len() - 1) as f64 * SPEED_RANGE_FRACTION) as usize];
+        if max_speed <= 0.0 {
+            return Self { dispersion, star_count: stars.len(), ..Self::default() };
+        }
+
+        let bin = |value: f64, min: f64| {
+            let bin = (value - min) / (max_speed - min) * bin_count as f64;
+            (bin >= 0.0 && bin < bin_count as f64).then_some(bin as usize)
+        };
+
+        let mut distribution = Self {
+            max_speed,
+            speeds: vec![0; bin_count],
+            radial: vec![0; bin_count],
+            tangential: vec![0; bin_count],
+            dispersion,
+            star_count: stars.len(),
+        };
+
+        for (star, &speed) in stars.iter().zip(&speeds) {
+            if let Some(bin) = bin(speed, 0.0) {
+                distribution.speeds[bin] += 1;
+            }
+
+            let distance = f64::sqrt(star.position.x * star.position.x + star.position.y * star.position.y);
+            if distance > 0.0 {
+                let radial = (star.position.x * star.velocity.x + star.position.y * star.velocity.y) / distance;
+                let tangential = (star.position.x * star.velocity.y - star.position.y * star.velocity.x) / distance;
+
+                if let Some(bin) = bin(radial, -max_speed) {
+                    distribution.radial[bin] += 1;
+                }
+                if let Some(bin) = bin(tangential, -max_speed) {
+                    distribution.tangential[bin] += 1;
+                }
+            }
+        }
+
+        distribution
+    }
+
+    /// The number of stars the fitted Maxwellian distribution expects in each bin of the speed
+    /// histogram.
+    pub fn maxwellian(&self) -> Vec<f64> {
+        let bin_count = self.speeds.len();
+        let bin_width = self.max_speed / bin_count as f64;
+        let sigma_squared = self.dispersion * self.dispersion;
+
+        (0..bin_count).map(|bin| {
+            // The probability density of a speed is v / sigma^2 * e^(-v^2 / 2 sigma^2), which is
+            // sampled at the center of the bin.
+            let speed = (bin as f64 + 0.5) * bin_width;
+            let density = match sigma_squared > 0.0 {
+                true => speed / sigma_squared * f64::exp(-speed * speed / (2.0 * sigma_squared)),
+                false => 0.0,
+            };
+            density * bin_width * self.star_count as f64
+        }).collect()
+    }
+}
@@ -0,0 +1,103 @@
+//! Export of per-star time series in the Apache Arrow IPC stream format, which pandas, polars and
+//! pyarrow can load directly, e.g. with `polars.read_ipc_stream`, rather than parsing huge CSVs.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow_array::types::UInt8Type;
+use arrow_array::{ArrayRef, DictionaryArray, Float64Array, RecordBatch, StringArray, UInt64Array, UInt8Array};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{DataType, Field, Schema};
+
+use crate::simulation::{GalaxySim, Star, StarComponent};
+
+/// Writes the state of every star at each snapshot of a simulation to an Arrow IPC stream, in long
+/// format with one row per star per snapshot, and a record batch per snapshot. The columns are:
+///
+/// * `step` and `elapsed_myr`, when the snapshot was taken
+/// * `id`, the star's stable ID, to follow it between snapshots
+/// * `component`, the name of the component of the galaxy it belongs to
+/// * `x` and `y` in parsecs, `vx` and `vy` in km/s, and `mass` in solar masses
+/// * `age` in Gyr and `metallicity` in dex
+///
+/// Each batch is flushed as it's written, so the stream can be read even if the writer is never
+/// finished, e.g. because the viewer was closed.
+pub struct ArrowExporter {
+    writer: StreamWriter<BufWriter<File>>,
+    schema: Arc<Schema>,
+
+    /// The names of the components, which every batch's component column is encoded against.
+    component_names: Arc<StringArray>,
+}
+
+impl ArrowExporter {
+    /// Create a new Arrow IPC stream at the given path and write the schema to it.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        log::info!("Exporting star time series to {}", path.as_ref().display());
+
+        let float = |name| Field::new(name, DataType::Float64, false);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("step", DataType::UInt64, false),
+            float("elapsed_myr"),
+            Field::new("id", DataType::UInt64, false),
+            Field::new("component", DataType::Dictionary(Box::new(DataType::UInt8), Box::new(DataType::Utf8)), false),
+            float("x"),
+            float("y"),
+            float("vx"),
+            float("vy"),
+            float("mass"),
+            float("age"),
+            float("metallicity"),
+        ]));
+
+        let writer = StreamWriter::try_new_buffered(File::create(path)?, &schema)?;
+        let component_names = Arc::new(StringArray::from_iter_values(StarComponent::ALL.iter().map(|component| component.name())));
+
+        Ok(Self { writer, schema, component_names })
+    }
+
+    /// Write a snapshot of the stars of a simulation, taken after the given step.
+    pub fn write(&mut self, sim: &GalaxySim, step: u64) -> Result<(), Box<dyn Error>> {
+        self.write_stars(&sim.stars().to_vec(), step, sim.elapsed_myr())
+    }
+
+    /// Write a snapshot of a list of stars, taken after the given step and at the given simulated
+    /// time in Myr.
+    pub fn write_stars(&mut self, stars: &[Star], step: u64, elapsed_myr: f64) -> Result<(), Box<dyn Error>> {
+        let float = |value: fn(&Star) -> f64| -> ArrayRef {
+            Arc::new(Float64Array::from_iter_values(stars.iter().map(value)))
+        };
+
+        let component_keys = UInt8Array::from_iter_values(stars.iter().map(|star| {
+            StarComponent::ALL.iter().position(|&component| component == star.component).unwrap_or(0) as u8
+        }));
+        let components = DictionaryArray::<UInt8Type>::try_new(component_keys, self.component_names.clone())?;
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from_value(step, stars.len())),
+            Arc::new(Float64Array::from_value(elapsed_myr, stars.len())),
+            Arc::new(UInt64Array::from_iter_values(stars.iter().map(|star| star.id))),
+            Arc::new(components),
+            float(|star| star.position.x),
+            float(|star| star.position.y),
+            float(|star| star.velocity.x),
+            float(|star| star.velocity.y),
+            float(|star| star.mass.0),
+            float(|star| star.age),
+            float(|star| star.metallicity),
+        ];
+
+        self.writer.write(&RecordBatch::try_new(self.schema.clone(), columns)?)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Mark the end of the stream.
+    pub fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::simulation::{Star, StarComponent, SOLAR_AGE};
+use crate::types::Vec2d;
+use crate::units::SolarMass;
+
+/// The rotation matrix from ICRS (equatorial) to galactic cartesian coordinates, from the Gaia
+/// documentation (section 4.1.7, "Transformations of astrometric data and error propagation").
+#[allow(clippy::excessive_precision)]
+const ICRS_TO_GALACTIC: [[f64; 3]; 3] = [
+    [-0.0548755604162154, -0.8734370902348850, -0.4838350155487132],
+    [ 0.4941094278755837, -0.4448296299600112,  0.7469822444972189],
+    [-0.8676661490190047, -0.1980763734312015,  0.4559837761750669],
+];
+
+/// Converts a proper motion in mas/yr at a distance in kpc to a velocity in km/s.
+const KM_PER_S_PER_MAS_PER_YR_KPC: f64 = 4.740470446;
+
+/// The distance of the sun from the galactic center, in parsecs (GRAVITY Collaboration 2019).
+const SUN_GALACTOCENTRIC_DISTANCE: f64 = 8178.0;
+
+/// The velocity of the sun relative to the galactic center in galactic (U, V) coordinates, in km/s.
+/// This is the solar peculiar motion (Schönrich et al. 2010) plus a circular velocity of 233 km/s.
+const SUN_GALACTOCENTRIC_VELOCITY: (f64, f64) = (11.1, 233.0 + 12.24);
+
+/// The absolute magnitude of the sun in the Gaia G band.
+const SUN_ABSOLUTE_G_MAGNITUDE: f64 = 4.67;
+
+/// The mass of stars without a magnitude in the catalogue, in solar masses.
+const DEFAULT_STAR_MASS: f64 = 1.0;
+
+/// The range of masses estimated from the mass-luminosity relation, in solar masses.
+const ESTIMATED_MASS_RANGE: (f64, f64) = (0.08, 100.0);
+
+/// A single star from the catalogue, in the catalogue's own units.
+#[derive(Debug)]
+struct CatalogEntry {
+    /// Right ascension in degrees.
+    ra: f64,
+
+    /// Declination in degrees.
+    dec: f64,
+
+    /// Parallax in milliarcseconds.
+    parallax: f64,
+
+    /// Proper motion in right ascension (multiplied by cos(dec)) in mas/yr.
+    pmra: f64,
+
+    /// Proper motion in declination in mas/yr.
+    pmdec: f64,
+
+    /// Radial velocity in km/s, if known.
+    radial_velocity: Option<f64>,
+
+    /// Mean apparent G band magnitude, if known.
+    phot_g_mean_mag: Option<f64>,
+}
+
+/// Load a subset of the Gaia catalogue from a CSV or VOTable file (as exported from the Gaia
+/// archive, with at least the ra, dec, parallax, pmra and pmdec columns), and convert it to stars
+/// in the simulation's units, projected onto the galactic disk plane about the galactic center.
+/// The supermassive black hole from the config, if any, is added as the first star.
+pub fn load_catalog<P: AsRef<Path>>(path: P, config: &Config) -> Result<Vec<Star>, Box<dyn Error>> {
+    let path = path.as_ref();
+    log::info!("Loading star catalogue from {}", path.display());
+
+    let contents = std::fs::read_to_string(path)?;
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+    let rows = match extension.as_str() {
+        "csv" => parse_csv(&contents)?,
+        "vot" | "votable" | "xml" => parse_votable(&contents)?,
+        _ => return Err(format!("Unrecognised catalogue file extension '{extension}'").into()),
+    };
+
+    let mut stars = Vec::with_capacity(rows.len() + 1);
+    if config.generation.has_black_hole() {
+        stars.push(Star {
+            mass: SolarMass(config.generation.supermassive_black_hole_mass),
+            component: StarComponent::BlackHole,
+            ..Default::default()
+        });
+    }
+    let black_hole_count = stars.len();
+
+    let mut skipped = 0;
+    for row in &rows {
+        match parse_entry(row)? {
+            Some(entry) => stars.push(entry.to_star()),
+            None => skipped += 1,
+        }
+    }
+
+    log::info!("Loaded {} stars from catalogue, skipped {skipped} without a usable parallax",
+        stars.len() - black_hole_count);
+
+    Ok(stars)
+}
+
+/// Parse a catalogue entry from a row of named columns. Returns None for rows that can't be
+/// placed, i.e. those with a missing or non-positive parallax.
+fn parse_entry(row: &HashMap<String, String>) -> Result<Option<CatalogEntry>, Box<dyn Error>> {
+    let optional = |name: &str| -> Result<Option<f64>, Box<dyn Error>> {
+        match row.get(name).map(|value| value.trim()) {
+            None | Some("") | Some("NaN") | Some("null") => Ok(None),
+            Some(value) => Ok(Some(value.parse()
+                .map_err(|err| format!("Invalid value '{value}' in column {name}: {err}"))?)),
+        }
+    };
+    let required = |name: &str| -> Result<f64, Box<dyn Error>> {
+        optional(name)?.ok_or_else(|| format!("Missing value in column {name}").into())
+    };
+
+    let parallax = match optional("parallax")? {
+        Some(parallax) if parallax > 0.0 => parallax,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(CatalogEntry {
+        ra: required("ra")?,
+        dec: required("dec")?,
+        parallax,
+        pmra: optional("pmra")?.unwrap_or(0.0),
+        pmdec: optional("pmdec")?.unwrap_or(0.0),
+        radial_velocity: optional("radial_velocity")?,
+        phot_g_mean_mag: optional("phot_g_mean_mag")?,
+    }))
+}
+
+impl CatalogEntry {
+    /// Convert the entry to a star in galactocentric coordinates, in parsecs and km/s, projected
+    /// onto the disk plane.
+    fn to_star(&self) -> Star {
+        let (ra, dec) = (self.ra.to_radians(), self.dec.to_radians());
+        let distance = 1000.0 / self.parallax;
+
+        // Unit vectors in ICRS: towards the star, and in the directions of increasing ra and dec.
+        let r_hat = [dec.cos() * ra.cos(), dec.cos() * ra.sin(), dec.sin()];
+        let ra_hat = [-ra.sin(), ra.cos(), 0.0];
+        let dec_hat = [-dec.sin() * ra.cos(), -dec.sin() * ra.sin(), dec.cos()];
+
+        // Heliocentric velocity in ICRS from the proper motions and radial velocity.
+        let distance_kpc = distance / 1000.0;
+        let v_ra = KM_PER_S_PER_MAS_PER_YR_KPC * self.pmra * distance_kpc;
+        let v_dec = KM_PER_S_PER_MAS_PER_YR_KPC * self.pmdec * distance_kpc;
+        let v_r = self.radial_velocity.unwrap_or(0.0);
+        let velocity_icrs: [f64; 3] = std::array::from_fn(|i| {
+            v_r * r_hat[i] + v_ra * ra_hat[i] + v_dec * dec_hat[i]
+        });
+
+        // Rotate into galactic coordinates, where x points towards the galactic center and y in
+        // the direction of galactic rotation, and then drop z to project onto the disk.
+        let position_icrs = r_hat.map(|c| c * distance);
+        let position = rotate_to_galactic(&position_icrs);
+        let velocity = rotate_to_galactic(&velocity_icrs);
+
+        // Move the origin to the galactic center.
+        let position = Vec2d::new(position[0] - SUN_GALACTOCENTRIC_DISTANCE, position[1]);
+        let velocity = Vec2d::new(velocity[0] + SUN_GALACTOCENTRIC_VELOCITY.0,
+                                  velocity[1] + SUN_GALACTOCENTRIC_VELOCITY.1);
+
+        // The catalogue doesn't give ages or metallicities, so assume they're like the sun.
+        Star {
+            id: 0,
+            position,
+            velocity,
+            mass: SolarMass(self.estimate_mass(distance)),
+            age: SOLAR_AGE,
+            metallicity: 0.0,
+            component: StarComponent::Disk,
+            binary: None,
+            tag: None,
+        }
+    }
+
+    /// Estimate the mass of the star from its absolute magnitude, using the main sequence
+    /// mass-luminosity relation L ~ M^3.5.
+    fn estimate_mass(&self, distance: f64) -> f64 {
+        match self.phot_g_mean_mag {
+            Some(apparent_magnitude) => {
+                let absolute_magnitude = apparent_magnitude - 5.0 * f64::log10(distance / 10.0);
+                let luminosity = f64::powf(10.0, -0.4 * (absolute_magnitude - SUN_ABSOLUTE_G_MAGNITUDE));
+                luminosity.powf(1.0 / 3.5).clamp(ESTIMATED_MASS_RANGE.0, ESTIMATED_MASS_RANGE.1)
+            },
+            None => DEFAULT_STAR_MASS,
+        }
+    }
+}
+
+/// Rotate a vector from ICRS to galactic coordinates.
+fn rotate_to_galactic(v: &[f64; 3]) -> [f64; 3] {
+    ICRS_TO_GALACTIC.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+/// Parse a CSV file with a header row into rows of named columns.
+fn parse_csv(contents: &str) -> Result<Vec<HashMap<String, String>>, Box<dyn Error>> {
+    let mut lines = contents.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#'));
+
+    let header: Vec<String> = match lines.next() {
+        Some(header) => split_csv_line(header),
+        None => return Err("Catalogue CSV file is empty".into()),
+    };
+
+    Ok(lines.map(|line| header.iter().cloned().zip(split_csv_line(line)).collect()).collect())
+}
+
+/// Split a CSV line into fields, removing surrounding quotes and whitespace. Commas inside quoted
+/// fields, such as Gaia's designations, don't split them, and doubled quotes inside them are
+/// unescaped.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field).trim().to_string()),
+            (c, _) => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+
+    fields
+}
+
+/// Parse a VOTable file into rows of named columns. Only the TABLEDATA serialization is supported,
+/// which is what the Gaia archive produces for "VOTable (plain)" exports.
+fn parse_votable(contents: &str) -> Result<Vec<HashMap<String, String>>, Box<dyn Error>> {
+    if !contents.contains("<TABLEDATA") {
+        return Err("Only VOTables with TABLEDATA serialization are supported".into());
+    }
+
+    // Column names come from the FIELD elements, in order.
+    let mut columns = Vec::new();
+    let mut rest = contents;
+    while let Some(start) = find_start_tag(rest, "FIELD") {
+        let end = rest[start..].find('>').ok_or("Unterminated FIELD element")? + start;
+        let name = xml_attribute(&rest[start..end], "name").ok_or("FIELD element without a name")?;
+        columns.push(name.to_string());
+        rest = &rest[end..];
+    }
+
+    // Each TR element is a row, containing one TD element per column.
+    let mut rows = Vec::new();
+    let mut rest = contents;
+    while let Some(start) = find_start_tag(rest, "TR") {
+        let end = rest[start..].find("</TR>").ok_or("Unterminated TR element")? + start;
+        let row_xml = &rest[start..end];
+
+        let mut row = HashMap::new();
+        let mut cells = row_xml;
+        let mut column = 0;
+        while let Some(cell_start) = find_start_tag(cells, "TD") {
+            let cell = &cells[cell_start..];
+            let open_end = cell.find('>').ok_or("Unterminated TD element")? + 1;
+
+            // Empty cells may be written as <TD/>.
+            let (value, cell_len) = if cell[..open_end].ends_with("/>") {
+                ("", open_end)
+            }
+            else {
+                let close = cell.find("</TD>").ok_or("Unterminated TD element")?;
+                (&cell[open_end..close], close + "</TD>".len())
+            };
+
+            if let Some(name) = columns.get(column) {
+                row.insert(name.clone(), xml_unescape(value));
+            }
+
+            column += 1;
+            cells = &cell[cell_len..];
+        }
+
+        rows.push(row);
+        rest = &rest[end..];
+    }
+
+    Ok(rows)
+}
+
+/// Find the position of the next start tag with the given name, e.g. `<TD>` or `<TD/>` but not
+/// `<TDX>`.
+fn find_start_tag(xml: &str, name: &str) -> Option<usize> {
+    let pattern = format!("<{name}");
+    let mut offset = 0;
+    while let Some(start) = xml[offset..].find(&pattern) {
+        let start = offset + start;
+        match xml[start + pattern.len()..].chars().next() {
+            Some(c) if c.is_whitespace() || c == '>' || c == '/' => return Some(start),
+            _ => offset = start + pattern.len(),
+        }
+    }
+    None
+}
+
+/// Get the value of an attribute from an XML start tag.
+fn xml_attribute<'a>(tag: &'a str, attribute: &str) -> Option<&'a str> {
+    let pattern = format!(" {attribute}=");
+    let start = tag.find(&pattern)? + pattern.len();
+    let quote = tag[start..].chars().next()?;
+    let value = &tag[start + 1..];
+    let end = value.find(quote)?;
+    Some(&value[..end])
+}
+
+/// Unescape the predefined XML entities.
+fn xml_unescape(value: &str) -> String {
+    value.trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_columns_are_found_by_header() {
+        let csv = "# Exported from the Gaia archive\n\
+                   designation,phot_variable_flag,parallax,dec,ra,pmdec,pmra\n\
+                   \"Gaia DR3 1, with a comma\",\"NOT_AVAILABLE\",2.5,-30.0,120.0,1.5,-2.0\n\
+                   \"Gaia DR3 \"\"2\"\"\",VARIABLE,NaN,10.0,20.0,0.0,0.0\n\
+                   \n\
+                   Gaia DR3 3,,,10.0,20.0,0.0,0.0\n";
+        let rows = parse_csv(csv).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0]["designation"], "Gaia DR3 1, with a comma");
+        assert_eq!(rows[0]["ra"], "120.0");
+        assert_eq!(rows[1]["designation"], "Gaia DR3 \"2\"");
+
+        let entry = parse_entry(&rows[0]).unwrap().unwrap();
+        assert_eq!((entry.ra, entry.dec, entry.parallax, entry.pmra, entry.pmdec), (120.0, -30.0, 2.5, -2.0, 1.5));
+        assert_eq!(entry.radial_velocity, None);
+
+        // Stars with a missing or NaN parallax can't be placed, so they're skipped.
+        assert!(parse_entry(&rows[1]).unwrap().is_none());
+        assert!(parse_entry(&rows[2]).unwrap().is_none());
+
+        assert!(parse_csv("").is_err());
+    }
+
+    #[test]
+    fn bad_rows_are_errors() {
+        let rows = parse_csv("ra,dec,parallax\n1.0,abc,2.0\n1.0,,2.0\n1.0,2.0,-1.0\n").unwrap();
+        assert!(parse_entry(&rows[0]).is_err());
+        assert!(parse_entry(&rows[1]).is_err());
+        assert!(parse_entry(&rows[2]).unwrap().is_none());
+    }
+
+    #[test]
+    fn votable_columns_are_found_by_field() {
+        let votable = r#"<?xml version="1.0"?>
+            <VOTABLE><RESOURCE><TABLE>
+              <FIELD datatype="char" name="designation"/>
+              <FIELD datatype="double" name="ra" unit="deg"/>
+              <FIELD datatype="double" name="dec" unit="deg"/>
+              <FIELD datatype="double" name="parallax" unit="mas"/>
+              <FIELD datatype="float" name="radial_velocity"/>
+              <DATA><TABLEDATA>
+                <TR><TD>Gaia DR3 1 &amp; 2</TD><TD>120.0</TD><TD>-30.0</TD><TD>2.5</TD><TD>-12.5</TD></TR>
+                <TR><TD>Gaia DR3 3</TD><TD>20.0</TD><TD>10.0</TD><TD/><TD>NaN</TD></TR>
+                <TR><TD>Gaia DR3 4</TD><TD>20.0</TD><TD>bad</TD><TD>1.0</TD><TD/></TR>
+              </TABLEDATA></DATA>
+            </TABLE></RESOURCE></VOTABLE>"#;
+        let rows = parse_votable(votable).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0]["designation"], "Gaia DR3 1 & 2");
+
+        let entry = parse_entry(&rows[0]).unwrap().unwrap();
+        assert_eq!((entry.ra, entry.dec, entry.parallax), (120.0, -30.0, 2.5));
+        assert_eq!(entry.radial_velocity, Some(-12.5));
+        assert!(parse_entry(&rows[1]).unwrap().is_none());
+        assert!(parse_entry(&rows[2]).is_err());
+
+        assert!(parse_votable("<VOTABLE><BINARY/></VOTABLE>").is_err());
+    }
+}
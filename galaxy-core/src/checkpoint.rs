@@ -0,0 +1,179 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{CheckpointConfig, Config};
+use crate::simulation::{GalaxySim, Star};
+
+/// A snapshot of the simulation state that can be written to disk and later resumed from.
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The seed the galaxy was generated with.
+    pub seed: u64,
+
+    /// The config the galaxy was generated with.
+    pub config: Config,
+
+    /// The simulation time elapsed when the checkpoint was taken, in simulation time units.
+    pub elapsed_time: f64,
+
+    /// The rate of the simulation when the checkpoint was taken, in Myr per second.
+    pub myr_per_second: f64,
+
+    /// The number of stars that had escaped when the checkpoint was taken. This and the other
+    /// counts default to zero for checkpoints written before they were saved.
+    #[serde(default)]
+    pub escaped_count: usize,
+
+    /// The number of stars that had been torn apart by black holes when the checkpoint was taken.
+    #[serde(default)]
+    pub disrupted_count: usize,
+
+    /// The number of stars that had gone supernova when the checkpoint was taken.
+    #[serde(default)]
+    pub supernova_count: usize,
+
+    /// The number of stars that had been added to the galaxy when the checkpoint was taken.
+    #[serde(default)]
+    pub formed_count: usize,
+
+    /// All stars in the galaxy.
+    pub stars: Vec<Star>,
+}
+
+impl Checkpoint {
+    /// Take a checkpoint of the current state of a simulation.
+    pub fn from_sim(sim: &GalaxySim, seed: u64) -> Self {
+        Self {
+            seed,
+            config: sim.config().clone(),
+            elapsed_time: sim.elapsed_time,
+            myr_per_second: sim.myr_per_second,
+            escaped_count: sim.escaped_count,
+            disrupted_count: sim.disrupted_count,
+            supernova_count: sim.supernova_count,
+            formed_count: sim.formed_count,
+            stars: sim.stars().to_vec(),
+        }
+    }
+
+    /// Recreate the simulation the checkpoint was taken of.
+    pub fn into_sim(self) -> Result<GalaxySim, Box<dyn Error>> {
+        let mut sim = GalaxySim::from_stars(&self.config, self.stars)?;
+        sim.elapsed_time = self.elapsed_time;
+        sim.myr_per_second = self.myr_per_second;
+        sim.escaped_count = self.escaped_count;
+        sim.disrupted_count = self.disrupted_count;
+        sim.supernova_count = self.supernova_count;
+        sim.formed_count = self.formed_count;
+        Ok(sim)
+    }
+
+    /// Write the checkpoint to a file. The checkpoint is written to a temporary file first and
+    /// then renamed, so a crash while writing never leaves a truncated checkpoint behind.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        let temp_path = path.with_extension("tmp");
+
+        serde_json::to_writer(BufWriter::new(File::create(&temp_path)?), self)?;
+        std::fs::rename(&temp_path, path)?;
+
+        Ok(())
+    }
+
+    /// Load a checkpoint from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
+    /// Find the most recently written checkpoint in a directory, if there is one.
+    pub fn latest<P: AsRef<Path>>(directory: P) -> Result<Option<PathBuf>, Box<dyn Error>> {
+        let directory = directory.as_ref();
+        if !directory.exists() {
+            return Ok(None);
+        }
+
+        let mut latest = None;
+        for entry in std::fs::read_dir(directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if !Checkpointer::is_checkpoint_file(&path) {
+                continue;
+            }
+
+            let modified = entry.metadata()?.modified()?;
+            if latest.as_ref().is_none_or(|(latest_modified, _)| modified > *latest_modified) {
+                latest = Some((modified, path));
+            }
+        }
+
+        Ok(latest.map(|(_, path)| path))
+    }
+}
+
+/// Writes checkpoints of a simulation every fixed interval of simulated time, rotating between a fixed
+/// number of files so that old checkpoints don't pile up.
+pub struct Checkpointer {
+    config: CheckpointConfig,
+
+    /// The simulated time in Myr at which the next checkpoint should be written.
+    next_checkpoint_myr: f64,
+
+    /// The index of the next checkpoint file to write, which wraps around at max_files.
+    next_file: usize,
+}
+
+impl Checkpointer {
+    /// Create a new checkpointer, the first checkpoint is written after one interval of
+    /// simulated time has passed since `elapsed_myr`.
+    pub fn new(config: &CheckpointConfig, elapsed_myr: f64) -> Self {
+        Self {
+            config: config.clone(),
+            next_checkpoint_myr: elapsed_myr + config.interval_myr,
+            next_file: 0,
+        }
+    }
+
+    /// Reset the checkpoint interval, e.g. when the galaxy is regenerated.
+    pub fn reset(&mut self, elapsed_myr: f64) {
+        self.next_checkpoint_myr = elapsed_myr + self.config.interval_myr;
+    }
+
+    /// Write a checkpoint if enough simulated time has passed since the last one.
+    pub fn update(&mut self, sim: &GalaxySim, seed: u64) {
+        let elapsed_myr = sim.elapsed_myr();
+        if self.config.interval_myr <= 0.0 || elapsed_myr < self.next_checkpoint_myr {
+            return;
+        }
+
+        // Skip any intervals that were missed entirely, e.g. at very high time scales.
+        let intervals_passed = ((elapsed_myr - self.next_checkpoint_myr) / self.config.interval_myr).floor();
+        self.next_checkpoint_myr += (intervals_passed + 1.0) * self.config.interval_myr;
+
+        if let Err(err) = self.save(sim, seed) {
+            log::error!("Failed to write checkpoint: {err}");
+        }
+    }
+
+    /// Write a checkpoint immediately.
+    pub fn save(&mut self, sim: &GalaxySim, seed: u64) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(&self.config.directory)?;
+
+        let path = self.config.directory.join(format!("checkpoint_{}.json", self.next_file));
+        log::info!("Writing checkpoint at {:.1} Myr to {}", sim.elapsed_myr(), path.display());
+        Checkpoint::from_sim(sim, seed).save(&path)?;
+
+        self.next_file = (self.next_file + 1) % self.config.max_files.max(1);
+
+        Ok(())
+    }
+
+    /// Whether a path is a checkpoint file written by a Checkpointer.
+    fn is_checkpoint_file(path: &Path) -> bool {
+        let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        file_name.starts_with("checkpoint_") && file_name.ends_with(".json")
+    }
+}
@@ -0,0 +1,1233 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::generation;
+
+/// The default path of the config file, relative to the working directory.
+pub const DEFAULT_CONFIG_PATH: &str = "galaxy.toml";
+
+/// The simulation config, loaded from a TOML file at startup so that experiments don't require
+/// recompiling. Any values missing from the file fall back to their defaults, so the file only
+/// needs to contain the values being changed.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub simulation: SimulationConfig,
+    pub generation: GenerationConfig,
+    pub rendering: RenderingConfig,
+    pub checkpoint: CheckpointConfig,
+    pub rewind: RewindConfig,
+    pub triggers: TriggerConfig,
+}
+
+/// Physical constants and parameters of the n-body simulation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationConfig {
+    /// The gravitational constant in `km^2 pc Msun^-1 s^-2`.
+    /// https://lweb.cfa.harvard.edu/~dfabricant/huchra/ay145/constants.html
+    pub gravitational_constant: f64,
+
+    /// The Barnes-Hut opening angle (theta). Regions whose size divided by their distance from a
+    /// point is smaller than this are approximated by their center of mass.
+    pub opening_angle: f64,
+
+    /// The exclusive maximum depth of the quadtree, at most 16. Stars closer together than the
+    /// smallest nodes can separate share a leaf node rather than splitting it any further.
+    pub max_tree_depth: u8,
+
+    /// The spatial acceleration structure used to find each star's neighbours and approximate the
+    /// gravity of distant stars. The quadtree is built every step either way, for everything else
+    /// that uses it.
+    pub spatial_backend: SpatialBackend,
+
+    /// The size of the cells of the spatial hash grid backend, in parsecs.
+    pub grid_cell_size: f64,
+
+    /// Minimum distance^2 in gravity calculation, below which it is clamped to this value.
+    pub min_gravity_distance_squared: f64,
+
+    /// The initial rate of the simulation, in Myr of simulated time per second of real time.
+    pub initial_myr_per_second: f64,
+
+    /// The most simulated time a single substep can cover, in Myr. Steps covering more are split
+    /// into several substeps so that faster rates don't make the integration inaccurate. 0 never
+    /// splits steps.
+    pub max_step_myr: f64,
+
+    /// The furthest stars should move in a substep, as a fraction of the distance to their
+    /// neighbours. Steps in which stars would move further are split into several substeps, so
+    /// that fast rates don't destroy their orbits. 0 never splits steps for this.
+    pub max_displacement_fraction: f64,
+
+    /// The most substeps a step can be split into. If a step needs more, the simulation runs
+    /// slower than its rate instead.
+    pub max_substeps: usize,
+
+    /// How far stars can move in a substep, as a fraction of the distance to their neighbours,
+    /// before the integration is considered unreliable and a warning is shown. 0 disables it.
+    pub warn_displacement_fraction: f64,
+
+    /// How much stars' velocities can change in a substep, as a fraction of their speed, before
+    /// the integration is considered unreliable and a warning is shown. 0 disables it.
+    pub warn_velocity_change_fraction: f64,
+
+    /// Whether the viewer should initially slow the simulation down automatically while the
+    /// integration is unreliable.
+    pub auto_slowdown: bool,
+
+    /// Whether to move the stars into the center of momentum frame at the start of every step,
+    /// removing their bulk motion and recentering them on their barycenter, so that they don't
+    /// slowly drift out of the quadtree's fixed bounds.
+    pub recenter: bool,
+
+    /// The distance from a black hole within which stars are torn apart by its tides and accreted
+    /// onto it, in parsecs. 0 never disrupts stars.
+    pub tidal_disruption_radius: f64,
+
+    /// A static dark matter halo surrounding the galaxy.
+    pub halo: HaloConfig,
+
+    /// The drag massive bodies feel from the stars around them.
+    pub dynamical_friction: DynamicalFrictionConfig,
+
+    /// The kicks massive stars give the stars around them when they go supernova.
+    pub supernovae: SupernovaConfig,
+
+    /// The mass stars lose to winds and supernovae as they age.
+    pub mass_loss: MassLossConfig,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            gravitational_constant: 4.3e-3,
+            opening_angle: 1.0,
+            max_tree_depth: 16,
+            spatial_backend: SpatialBackend::Quadtree,
+            grid_cell_size: 500.0,
+            min_gravity_distance_squared: 0.0,
+            initial_myr_per_second: 1000.0,
+            max_step_myr: 20.0,
+            max_displacement_fraction: 0.5,
+            max_substeps: 16,
+            warn_displacement_fraction: 1.0,
+            warn_velocity_change_fraction: 0.5,
+            auto_slowdown: false,
+            recenter: false,
+            tidal_disruption_radius: 0.0,
+            halo: Default::default(),
+            dynamical_friction: Default::default(),
+            supernovae: Default::default(),
+            mass_loss: Default::default(),
+        }
+    }
+}
+
+/// The spatial acceleration structures the simulation can use to calculate gravity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpatialBackend {
+    /// A Barnes-Hut quadtree, which approximates groups of distant stars at every scale.
+    Quadtree,
+
+    /// A uniform spatial hash grid, which approximates distant cells by their center of mass.
+    Grid,
+}
+
+impl SpatialBackend {
+    /// All of the backends, in the order they're listed in the UI.
+    pub const ALL: [SpatialBackend; 2] = [SpatialBackend::Quadtree, SpatialBackend::Grid];
+
+    /// The backend's name, for display.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpatialBackend::Quadtree => "Quadtree",
+            SpatialBackend::Grid => "Spatial hash grid",
+        }
+    }
+}
+
+/// Parameters for a static dark matter halo centered on the origin, which adds to the gravity of the
+/// stars without being simulated itself. It follows a Hernquist profile, whose density falls off as
+/// `r^-1` near the center and `r^-4` far from it.
+/// https://ui.adsabs.harvard.edu/abs/1990ApJ...356..359H
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HaloConfig {
+    /// The total mass of the halo in solar masses, or 0 for no halo.
+    pub mass: f64,
+
+    /// The scale radius of the halo in parsecs, which contains a quarter of its mass.
+    pub scale_radius: f64,
+}
+
+impl HaloConfig {
+    /// The mass of the halo within a radius of the center, in solar masses.
+    pub fn enclosed_mass(&self, radius: f64) -> f64 {
+        let fraction = radius / (radius + self.scale_radius);
+        self.mass * fraction * fraction
+    }
+
+    /// The density of the halo at a radius from the center, in solar masses per cubic parsec.
+    pub fn density(&self, radius: f64) -> f64 {
+        self.mass * self.scale_radius / (2.0 * std::f64::consts::PI * radius * (radius + self.scale_radius).powi(3))
+    }
+}
+
+impl Default for HaloConfig {
+    fn default() -> Self {
+        Self {
+            mass: 0.0,
+            scale_radius: 20000.0,
+        }
+    }
+}
+
+/// Parameters for Chandrasekhar dynamical friction, the drag a massive body feels from the wake of
+/// stars its gravity pulls in behind it, which makes satellites and black holes sink towards the
+/// center of the galaxy. The stars are individual particles, so the simulation produces this
+/// itself given enough of them, but at the usual star counts it needs adding explicitly. It's only
+/// applied to bodies much more massive than the stars around them, as it's estimated from their
+/// surroundings each substep.
+/// https://ui.adsabs.harvard.edu/abs/1943ApJ....97..255C
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DynamicalFrictionConfig {
+    /// The Coulomb logarithm ln(Λ), which scales the strength of the drag, or 0 for no dynamical
+    /// friction. It's typically between 3 and 10.
+    pub coulomb_logarithm: f64,
+
+    /// The mass a body must have to feel dynamical friction, in solar masses.
+    pub min_mass: f64,
+
+    /// The radius around a body that the density and velocity dispersion of the stars it moves
+    /// through are measured within, in parsecs.
+    pub sample_radius: f64,
+}
+
+impl Default for DynamicalFrictionConfig {
+    fn default() -> Self {
+        Self {
+            coulomb_logarithm: 0.0,
+            min_mass: 1e5,
+            sample_radius: 1000.0,
+        }
+    }
+}
+
+/// Parameters for supernova feedback. Stars age as the simulation runs, and those massive enough
+/// explode at the end of their lives, pushing the stars around them outwards, which stirs up the
+/// disk. Stars generated older than their lifetime are assumed to have already exploded before the
+/// simulation started.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SupernovaConfig {
+    /// The speed a supernova kicks the stars right next to it away at, in km/s, falling off to
+    /// nothing at the kick radius, or 0 for no supernovae.
+    pub kick_speed: f64,
+
+    /// The radius around a supernova that stars are kicked within, in parsecs.
+    pub kick_radius: f64,
+
+    /// The mass a star must have to go supernova, in solar masses.
+    pub min_mass: f64,
+
+    /// The mass of the neutron star a supernova leaves behind, in solar masses, or 0 to remove the
+    /// star entirely.
+    pub remnant_mass: f64,
+}
+
+impl Default for SupernovaConfig {
+    fn default() -> Self {
+        Self {
+            kick_speed: 0.0,
+            kick_radius: 50.0,
+            min_mass: 8.0,
+            remnant_mass: 1.4,
+        }
+    }
+}
+
+/// Parameters for continuous stellar mass loss. Stars lose mass to winds and supernovae as they age,
+/// quickly at first and then ever more slowly, so over long simulations the disk gradually
+/// lightens and expands. Each star loses mass as though it were a population of stars of its age,
+/// following Jungwiert et al. 2001, which is 0.05 and 5 Myr for a Kroupa initial mass function.
+/// https://ui.adsabs.harvard.edu/abs/2001A%26A...376...85J
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MassLossConfig {
+    /// The fraction of their mass stars lose per e-fold of their age in units of the timescale
+    /// (C0), or 0 for no mass loss.
+    pub rate: f64,
+
+    /// The age at which stars start to lose mass quickly, in Myr (λ).
+    pub timescale_myr: f64,
+}
+
+impl Default for MassLossConfig {
+    fn default() -> Self {
+        Self {
+            rate: 0.0,
+            timescale_myr: 5.0,
+        }
+    }
+}
+
+/// Parameters for generating a new galaxy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationConfig {
+    /// The seed of the first generated galaxy, which is incremented each time it's regenerated.
+    pub seed: u64,
+
+    /// The number of stars in the main body of the galaxy, i.e. its disk, or the spheroid of an
+    /// elliptical.
+    pub star_count: usize,
+
+    /// The minimum mass of each star in the main body, in solar masses.
+    pub star_mass_min: f64,
+
+    /// The maximum mass of each star in the main body, in solar masses.
+    pub star_mass_max: f64,
+
+    /// The mass of a supermassive black hole at a galaxy's core, in solar masses, or 0 for no black
+    /// hole.
+    pub supermassive_black_hole_mass: f64,
+
+    /// Diameter of the galaxy in parsecs.
+    pub galaxy_diameter: f64,
+
+    /// The morphology of the galaxy.
+    pub galaxy_type: GalaxyType,
+
+    /// Parameters for the stellar disk of disk, spiral and barred spiral galaxies.
+    pub disk: DiskConfig,
+
+    /// Random motions added to the ordered rotation of disk stars.
+    pub dispersion: DispersionConfig,
+
+    /// Parameters for the arms of spiral and barred spiral galaxies.
+    pub spiral: SpiralConfig,
+
+    /// Parameters for the bar of barred spiral galaxies.
+    pub bar: BarConfig,
+
+    /// Parameters for elliptical galaxies.
+    pub elliptical: EllipticalConfig,
+
+    /// Parameters for Plummer sphere clusters.
+    pub plummer: PlummerConfig,
+
+    /// Parameters for King model clusters.
+    pub king: KingConfig,
+
+    /// A noise density field that modulates where stars are placed.
+    pub density: DensityConfig,
+
+    /// The central bulge, a component separate from the main body.
+    pub bulge: BulgeConfig,
+
+    /// Dark matter halo particles, simulated along with the stars.
+    pub live_halo: LiveHaloConfig,
+
+    /// Compact star clusters orbiting in the galaxy's halo.
+    pub globular_clusters: GlobularClusterConfig,
+
+    /// The ages and metallicities of the stars.
+    pub population: PopulationConfig,
+
+    /// Tight binary stars.
+    pub binaries: BinaryConfig,
+
+    /// Dwarf satellite galaxies on eccentric orbits.
+    pub satellites: SatelliteConfig,
+
+    /// A cloud of massless tracer particles.
+    pub tracers: TracerConfig,
+
+    /// Generate a cluster of several galaxies instead of a single one.
+    pub cluster: GalaxyClusterConfig,
+
+    /// Generate the restricted three-body problem instead of a galaxy.
+    pub three_body: ThreeBodyConfig,
+}
+
+/// The morphology of a generated galaxy.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GalaxyType {
+    /// Stars spread uniformly over a square.
+    Uniform,
+
+    /// A smooth disk of stars following an exponential surface density profile.
+    Disk,
+
+    /// Stars placed along logarithmic spiral arms.
+    Spiral,
+
+    /// A central bar, with logarithmic spiral arms starting from its ends.
+    BarredSpiral,
+
+    /// A spheroidal distribution of stars on random orbits, with no overall rotation.
+    Elliptical,
+
+    /// A Plummer sphere, a classic model of a star cluster with a simple analytic form.
+    Plummer,
+
+    /// A King model, a star cluster with a dense core and a finite tidal radius.
+    King,
+}
+
+/// Parameters for the stellar disk. The surface density of stars falls off exponentially with
+/// radius, `e^(-r / scale_length)`, truncated at the galaxy's radius.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiskConfig {
+    /// The radius over which the surface density falls by a factor of e, in parsecs.
+    pub scale_length: f64,
+
+    /// The fraction of the disk's stars that orbit in the opposite direction to the rest, between
+    /// 0 and 1.
+    pub counter_rotating_fraction: f64,
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        Self {
+            scale_length: 3000.0,
+            counter_rotating_fraction: 0.0,
+        }
+    }
+}
+
+/// The velocity dispersion of disk stars, random motions on top of their circular orbits that keep
+/// the disk from starting perfectly cold. The dispersion can either be fixed or chosen at each radius
+/// to give the disk a particular Toomre Q, the measure of its stability against collapsing into
+/// clumps and rings. By default the disk is cold.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DispersionConfig {
+    /// The standard deviation of the velocity towards or away from the center, in km/s.
+    pub radial: f64,
+
+    /// The standard deviation of the velocity along the orbit, in km/s.
+    pub tangential: f64,
+
+    /// If set, the radial dispersion is calculated at each radius to give this Toomre Q, and the
+    /// tangential dispersion from the epicyclic approximation, ignoring `radial` and `tangential`.
+    /// Values above 1 are stable.
+    pub toomre_q: Option<f64>,
+}
+
+/// Parameters for the logarithmic spiral arms of spiral galaxies.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpiralConfig {
+    /// The number of arms.
+    pub arm_count: u32,
+
+    /// The pitch angle of the arms in degrees, i.e. the angle between an arm and a circle around
+    /// the center. Smaller angles give more tightly wound arms.
+    pub pitch_angle: f64,
+
+    /// The standard deviation of the distance of stars from the center of their arm, in parsecs.
+    pub arm_width: f64,
+}
+
+impl Default for SpiralConfig {
+    fn default() -> Self {
+        Self {
+            arm_count: 2,
+            pitch_angle: 15.0,
+            arm_width: 600.0,
+        }
+    }
+}
+
+/// Parameters for the central bar of barred spiral galaxies.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BarConfig {
+    /// The length of the bar from end to end in parsecs. The spiral arms start at its ends.
+    pub length: f64,
+
+    /// The ratio of the bar's width to its length.
+    pub axis_ratio: f64,
+
+    /// The fraction of the stars that are placed in the bar rather than the arms.
+    pub star_fraction: f64,
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        Self {
+            length: 8000.0,
+            axis_ratio: 0.3,
+            star_fraction: 0.3,
+        }
+    }
+}
+
+/// Parameters for elliptical galaxies.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EllipticalConfig {
+    /// The effective radius of the stars' de Vaucouleurs profile in parsecs, which would contain
+    /// half of the stars if the profile weren't truncated at the galaxy's radius.
+    pub effective_radius: f64,
+
+    /// The ratio of the minor axis to the major axis, 1.0 for a circular galaxy.
+    pub axis_ratio: f64,
+}
+
+impl Default for EllipticalConfig {
+    fn default() -> Self {
+        Self {
+            effective_radius: 4000.0,
+            axis_ratio: 0.7,
+        }
+    }
+}
+
+/// Parameters for a Plummer sphere. The star velocities are sampled from its distribution function,
+/// so it starts in equilibrium, which makes it useful for validating the simulation against
+/// analytic results. It's three dimensional, so it's projected onto the plane. Set the
+/// supermassive black hole's mass to 0 for a pure Plummer sphere.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlummerConfig {
+    /// The Plummer radius in parsecs, the scale of the dense core.
+    pub radius: f64,
+}
+
+impl Default for PlummerConfig {
+    fn default() -> Self {
+        Self {
+            radius: 1000.0,
+        }
+    }
+}
+
+/// Parameters for a King model. As with the Plummer sphere, the velocities are sampled from its
+/// distribution function and it's projected onto the plane.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KingConfig {
+    /// The King radius in parsecs, the scale of the core.
+    pub core_radius: f64,
+
+    /// The dimensionless central potential W0, which sets how concentrated the cluster is. Typical
+    /// values are between 1 and 12, larger values having a smaller core relative to the tidal
+    /// radius.
+    pub central_potential: f64,
+}
+
+impl Default for KingConfig {
+    fn default() -> Self {
+        Self {
+            core_radius: 500.0,
+            central_potential: 6.0,
+        }
+    }
+}
+
+/// Parameters for the perlin noise density field. When enabled, stars are placed by rejection
+/// sampling against the field, so the galaxy's overall shape still comes from its type but is
+/// broken up into clumps and filaments.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DensityConfig {
+    /// Whether to use the density field.
+    pub enabled: bool,
+
+    /// The seed of the noise.
+    pub seed: u32,
+
+    /// The size of the largest features of the noise, in parsecs.
+    pub feature_size: f64,
+
+    /// The exponent applied to the noise, higher values give sharper, sparser clumps.
+    pub contrast: f64,
+}
+
+impl Default for DensityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seed: 0,
+            feature_size: 4000.0,
+            contrast: 3.0,
+        }
+    }
+}
+
+/// Parameters for the central bulge, a spheroid of stars on random orbits following a Hernquist
+/// profile. Its stars are in addition to `star_count`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BulgeConfig {
+    /// The number of stars in the bulge.
+    pub star_count: usize,
+
+    /// The minimum mass of each star, in solar masses.
+    pub star_mass_min: f64,
+
+    /// The maximum mass of each star, in solar masses.
+    pub star_mass_max: f64,
+
+    /// The scale radius of the Hernquist profile in parsecs, which contains a quarter of the stars.
+    pub scale_radius: f64,
+}
+
+impl Default for BulgeConfig {
+    fn default() -> Self {
+        Self {
+            star_count: 0,
+            star_mass_min: 0.1,
+            star_mass_max: 2.0,
+            scale_radius: 500.0,
+        }
+    }
+}
+
+/// Parameters for a live dark matter halo, made of particles that are simulated like the stars
+/// rather than the static halo in the simulation config. The particles follow a Hernquist profile,
+/// truncated at twice the galaxy's radius, and can be flattened into a spheroid.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LiveHaloConfig {
+    /// The number of particles, or 0 for no live halo.
+    pub particle_count: usize,
+
+    /// The total mass of the particles, in solar masses.
+    pub mass: f64,
+
+    /// The scale radius of the Hernquist profile in parsecs.
+    pub scale_radius: f64,
+
+    /// The ratio of the minor axis to the major axis, 1.0 for a spherical halo.
+    pub axis_ratio: f64,
+}
+
+impl Default for LiveHaloConfig {
+    fn default() -> Self {
+        Self {
+            particle_count: 0,
+            mass: 1e11,
+            scale_radius: 20000.0,
+            axis_ratio: 1.0,
+        }
+    }
+}
+
+/// Parameters for globular clusters, which are generated as Plummer spheres orbiting the galaxy's
+/// center. Their stars are in addition to `star_count`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlobularClusterConfig {
+    /// The number of clusters.
+    pub count: usize,
+
+    /// The number of stars in each cluster.
+    pub star_count: usize,
+
+    /// The Plummer radius of each cluster in parsecs, the scale of its dense core.
+    pub plummer_radius: f64,
+
+    /// The minimum distance of a cluster from the galaxy's center, in parsecs.
+    pub min_distance: f64,
+
+    /// The maximum distance of a cluster from the galaxy's center, in parsecs.
+    pub max_distance: f64,
+}
+
+impl Default for GlobularClusterConfig {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            star_count: 300,
+            plummer_radius: 5.0,
+            min_distance: 8000.0,
+            max_distance: 16000.0,
+        }
+    }
+}
+
+/// Parameters for the metallicities of the stars, [Fe/H] in dex relative to the sun. Disk stars get
+/// more metal poor further out, the stars of bulges and ellipticals have the central metallicity,
+/// and halo stars, globular clusters and satellites are metal poor. The stars' ages are chosen by
+/// which component they're in.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PopulationConfig {
+    /// The metallicity at the center of the galaxy, in dex.
+    pub central_metallicity: f64,
+
+    /// How much the metallicity of the disk changes per kiloparsec from the center, in dex/kpc.
+    pub metallicity_gradient: f64,
+
+    /// The standard deviation of stars' metallicities about the mean for their population, in dex.
+    pub metallicity_scatter: f64,
+}
+
+impl Default for PopulationConfig {
+    fn default() -> Self {
+        Self {
+            central_metallicity: 0.3,
+            metallicity_gradient: -0.06,
+            metallicity_scatter: 0.15,
+        }
+    }
+}
+
+/// Parameters for tight binary stars. Each binary is simulated as a single particle at the pair's
+/// center of mass, with their orbit around each other advanced analytically, as it's far too fast
+/// and close to integrate. Separations follow Öpik's law, spread evenly in log over a decade up to
+/// the maximum, eccentricities follow the thermal distribution, and the companion is between a
+/// tenth of the primary's mass and equal to it, out of the mass the star was generated with.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BinaryConfig {
+    /// The fraction of stars that are binaries, or 0 for none. Dark matter particles are never
+    /// binaries.
+    pub fraction: f64,
+
+    /// The largest semi-major axis of a binary's orbit, in parsecs.
+    pub max_separation: f64,
+}
+
+impl Default for BinaryConfig {
+    fn default() -> Self {
+        Self {
+            fraction: 0.0,
+            max_separation: 0.01,
+        }
+    }
+}
+
+/// Parameters for dwarf satellite galaxies, which start at the far end of eccentric orbits around
+/// the galaxy so that they're tidally stripped into streams as they pass close to it. Each satellite
+/// is a Plummer sphere, and their stars are in addition to `star_count`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SatelliteConfig {
+    /// The number of satellites.
+    pub count: usize,
+
+    /// The number of stars in each satellite.
+    pub star_count: usize,
+
+    /// The Plummer radius of each satellite in parsecs.
+    pub plummer_radius: f64,
+
+    /// The minimum starting distance of a satellite from the galaxy's center, in parsecs.
+    pub min_distance: f64,
+
+    /// The maximum starting distance of a satellite from the galaxy's center, in parsecs.
+    pub max_distance: f64,
+
+    /// The eccentricity of the satellites' orbits, between 0 for circular and 1 for falling
+    /// straight in.
+    pub eccentricity: f64,
+
+    /// The mass of a single particle at the center of each satellite, standing in for its dense
+    /// core, in solar masses, or 0 for none. A massive enough core feels dynamical friction, so the
+    /// satellite sinks as it orbits.
+    pub core_mass: f64,
+}
+
+impl Default for SatelliteConfig {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            star_count: 200,
+            plummer_radius: 300.0,
+            min_distance: 20000.0,
+            max_distance: 30000.0,
+            eccentricity: 0.6,
+            core_mass: 0.0,
+        }
+    }
+}
+
+/// Parameters for a cloud of tracer particles, which are moved by gravity but exert none and are
+/// left out of the quadtree, so a dense cloud of them can show how the galaxy flows without
+/// changing how it evolves. The tracers are spread evenly over a disk, each on a
+/// circular orbit about the galaxy's center, and are in addition to `star_count`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TracerConfig {
+    /// The number of tracers, or 0 for none.
+    pub count: usize,
+
+    /// The radius of the cloud, in parsecs.
+    pub radius: f64,
+
+    /// The position of the center of the cloud relative to the galaxy's center, in parsecs.
+    pub center_x: f64,
+    pub center_y: f64,
+}
+
+impl Default for TracerConfig {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            radius: 1000.0,
+            center_x: 8000.0,
+            center_y: 0.0,
+        }
+    }
+}
+
+/// Parameters for generating a cluster of galaxies. Each member galaxy is generated from the rest of
+/// the generation config with a random orientation, and the members are placed in a Plummer sphere
+/// with velocities about their common barycenter sampled from its distribution function. In a
+/// cluster the first star is a massless marker at the barycenter rather than a supermassive black
+/// hole, and each galaxy has its own black hole.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GalaxyClusterConfig {
+    /// The number of galaxies, 1 for a single galaxy.
+    pub galaxy_count: usize,
+
+    /// The Plummer radius of the cluster in parsecs.
+    pub radius: f64,
+}
+
+impl GalaxyClusterConfig {
+    /// Whether to generate a cluster rather than a single galaxy.
+    pub fn enabled(&self) -> bool {
+        self.galaxy_count > 1
+    }
+}
+
+impl Default for GalaxyClusterConfig {
+    fn default() -> Self {
+        Self {
+            galaxy_count: 1,
+            radius: 100000.0,
+        }
+    }
+}
+
+/// Parameters for the restricted three-body mode, which replaces the galaxy with two massive bodies
+/// on a Kepler orbit around each other and a disk of massless test particles moving in their field.
+/// The bodies' orbit is advanced analytically, and the particles feel the bodies directly rather
+/// than through the quadtree. The bodies start at pericenter, and the particles start spread evenly
+/// over an annulus around their barycenter, on circular orbits about their total mass.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThreeBodyConfig {
+    /// Whether to generate the restricted three-body problem rather than a galaxy.
+    pub enabled: bool,
+
+    /// The mass of the primary body, which is the first star, in solar masses.
+    pub primary_mass: f64,
+
+    /// The mass of the secondary body, which is the second star, in solar masses.
+    pub secondary_mass: f64,
+
+    /// The semi-major axis of the bodies' orbit around each other, in parsecs.
+    pub semi_major_axis: f64,
+
+    /// The eccentricity of the bodies' orbit, 0 for a circular orbit.
+    pub eccentricity: f64,
+
+    /// The number of test particles.
+    pub particle_count: usize,
+
+    /// The inner and outer radius of the annulus the particles start in, in parsecs.
+    pub particle_min_radius: f64,
+    pub particle_max_radius: f64,
+}
+
+impl ThreeBodyConfig {
+    /// The radius in parsecs within which the bodies and particles start.
+    pub fn extent_radius(&self) -> f64 {
+        f64::max(self.semi_major_axis * (1.0 + self.eccentricity), self.particle_max_radius)
+    }
+}
+
+impl Default for ThreeBodyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            primary_mass: 1e10,
+            secondary_mass: 1e9,
+            semi_major_axis: 5000.0,
+            eccentricity: 0.0,
+            particle_count: 5000,
+            particle_min_radius: 1000.0,
+            particle_max_radius: 10000.0,
+        }
+    }
+}
+
+impl GenerationConfig {
+    /// Whether galaxies have a supermassive black hole, which is then the first star.
+    pub fn has_black_hole(&self) -> bool {
+        self.supermassive_black_hole_mass > 0.0
+    }
+
+    /// Radius of the galaxy in parsecs, calculated.
+    pub fn galaxy_radius(&self) -> f64 {
+        self.galaxy_diameter / 2.0
+    }
+
+    /// The radius in parsecs within which all the generated stars start, which is larger than the
+    /// galaxy's radius if it has distant satellites or is a cluster of galaxies.
+    pub fn extent_radius(&self) -> f64 {
+        if self.three_body.enabled {
+            return self.three_body.extent_radius();
+        }
+
+        let mut extent_radius = self.galaxy_radius();
+        if self.satellites.count > 0 {
+            let satellite_radius = self.satellites.plummer_radius * generation::MAX_PLUMMER_RADII;
+            extent_radius = extent_radius.max(self.satellites.max_distance + satellite_radius);
+        }
+        if self.cluster.enabled() {
+            extent_radius += self.cluster.radius * generation::MAX_PLUMMER_RADII;
+        }
+        extent_radius
+    }
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        Self {
+            seed: 152,
+            star_count: 5,
+            star_mass_min: 0.1,
+            star_mass_max: 10.0,
+            supermassive_black_hole_mass: 4e6,
+            galaxy_diameter: 32408.0,
+            galaxy_type: GalaxyType::Spiral,
+            disk: Default::default(),
+            dispersion: Default::default(),
+            spiral: Default::default(),
+            bar: Default::default(),
+            elliptical: Default::default(),
+            density: Default::default(),
+            bulge: Default::default(),
+            live_halo: Default::default(),
+            globular_clusters: Default::default(),
+            plummer: Default::default(),
+            king: Default::default(),
+            population: Default::default(),
+            binaries: Default::default(),
+            satellites: Default::default(),
+            tracers: Default::default(),
+            cluster: Default::default(),
+            three_body: Default::default(),
+        }
+    }
+}
+
+/// Parameters for rendering the galaxy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderingConfig {
+    /// The star texture width.
+    pub texture_width: usize,
+
+    /// The star texture height.
+    pub texture_height: usize,
+
+    /// How many times larger in each dimension than the star texture the stars are rendered
+    /// before being downsampled to it, for smoother, higher quality images, up to 4. 1 renders them
+    /// at the star texture's size.
+    pub render_scale: usize,
+
+    /// Whether to draw the dark matter particles of a live halo, which are drawn dimly in their
+    /// own colour so they don't drown out the stars.
+    pub show_dark_matter: bool,
+
+    /// The dust and nebula layer.
+    pub dust: DustConfig,
+
+    /// How the stars are drawn as glowing sprites.
+    pub glow: GlowConfig,
+
+    /// Motion trails left behind the stars.
+    pub trails: TrailsConfig,
+}
+
+impl Default for RenderingConfig {
+    fn default() -> Self {
+        Self {
+            texture_width: 512,
+            texture_height: 512,
+            render_scale: 1,
+            show_dark_matter: true,
+            dust: Default::default(),
+            glow: Default::default(),
+            trails: Default::default(),
+        }
+    }
+}
+
+/// How the stars are coloured when they're drawn as glowing sprites.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StarColorMode {
+    /// By age and metallicity, from blue for young, metal poor stars to red for old, metal rich
+    /// ones.
+    Population,
+
+    /// All white, so only their brightness shows.
+    White,
+
+    /// By brightness, through a black body like ramp from red to white.
+    Heat,
+}
+
+impl StarColorMode {
+    /// All of the colour modes, in the order they're listed in the UI.
+    pub const ALL: [StarColorMode; 3] = [StarColorMode::Population, StarColorMode::White, StarColorMode::Heat];
+
+    /// The colour mode's name, for display.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StarColorMode::Population => "Population",
+            StarColorMode::White => "White",
+            StarColorMode::Heat => "Heat",
+        }
+    }
+}
+
+/// Parameters for drawing the stars as glowing sprites, each with a solid core surrounded by a glow
+/// that falls off with distance, rather than as single pixels in the star texture. Sizes are in
+/// pixels of the star texture, so they look the same whatever the window size. Each star's sprite
+/// is scaled by its mass and the camera's zoom, so that massive stars are visibly larger.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlowConfig {
+    /// Whether to draw the stars as glowing sprites. Otherwise they're drawn as single pixels.
+    pub enabled: bool,
+
+    /// The radius of the solid core of a star of the reference mass, in texels.
+    pub core_size: f64,
+
+    /// The radius of the glow around a star of the reference mass, in texels, at which it falls to
+    /// zero.
+    pub glow_radius: f64,
+
+    /// The mass of a star drawn at the core size and glow radius when the camera isn't zoomed in,
+    /// in solar masses. Tracers and dark matter are always drawn at that size before zooming.
+    pub reference_mass: f64,
+
+    /// The power of a star's mass relative to the reference mass its sprite is scaled by.
+    pub mass_size_exponent: f64,
+
+    /// The power of the camera's zoom factor the sprites are scaled by. 0 keeps them the same size
+    /// at every zoom level, and 1 scales them with the view.
+    pub zoom_size_exponent: f64,
+
+    /// The smallest and largest radius a star's glow is drawn at after scaling, in texels.
+    pub min_point_size: f64,
+    pub max_point_size: f64,
+
+    /// How sharply the glow falls off from the core, as the power of the remaining distance to the
+    /// glow radius. 1 is linear, and higher values keep the glow closer to the core.
+    pub falloff: f64,
+
+    /// A multiplier on the brightness of the stars, used when automatic exposure is off.
+    pub exposure: f64,
+
+    /// Whether to adjust the exposure automatically from a histogram of the brightness of the
+    /// stars in the view, so that both dense and sparse regions stay visible as the camera zooms.
+    pub auto_exposure: bool,
+
+    /// The brightness automatic exposure aims to draw the region of the view at the percentile
+    /// below at, as the total brightness of the stars in a cell of its grid.
+    pub exposure_key: f64,
+
+    /// The percentile of the brightness of the non-empty cells of the grid, from 0 to 1, that's
+    /// drawn at the key brightness. Lower values brighten sparse regions at the expense of
+    /// saturating dense ones.
+    pub exposure_percentile: f64,
+
+    /// How the stars are coloured.
+    pub color_mode: StarColorMode,
+}
+
+impl Default for GlowConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            core_size: 0.5,
+            glow_radius: 3.0,
+            reference_mass: 1.0,
+            mass_size_exponent: 0.5,
+            zoom_size_exponent: 0.5,
+            min_point_size: 1.0,
+            max_point_size: 12.0,
+            falloff: 3.0,
+            exposure: 1.0,
+            auto_exposure: true,
+            exposure_key: 1.0,
+            exposure_percentile: 0.5,
+            color_mode: StarColorMode::Population,
+        }
+    }
+}
+
+/// Parameters for motion trails, which blend each frame of the stars with a faded copy of the frames
+/// before it, so that fast moving stars leave smooth streaks at high time scales without any
+/// history being kept per star.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrailsConfig {
+    /// Whether to draw the trails.
+    pub enabled: bool,
+
+    /// How much of the previous frame's brightness is kept each frame, from 0 to 1. Higher values
+    /// leave longer trails.
+    pub decay: f64,
+}
+
+impl Default for TrailsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            decay: 0.9,
+        }
+    }
+}
+
+/// Parameters for the dust and nebula layer, which can be added over the stars from the layers
+/// window. The dust follows lanes along the inner edges of the spiral arms, broken up by noise, and
+/// is spread across the whole disk for galaxies without arms.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DustConfig {
+    /// The seed of the noise.
+    pub seed: u32,
+
+    /// The size of the largest features of the noise, in parsecs.
+    pub feature_size: f64,
+
+    /// The standard deviation of the distance of dust from the center of its lane, in parsecs.
+    pub lane_width: f64,
+
+    /// How far the lanes are from the centers of the arms, towards their inner edges, in parsecs.
+    pub lane_offset: f64,
+
+    /// The initial opacity of the layer, between 0 and 1.
+    pub opacity: f64,
+}
+
+impl Default for DustConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            feature_size: 1500.0,
+            lane_width: 500.0,
+            lane_offset: 500.0,
+            opacity: 0.7,
+        }
+    }
+}
+
+/// Parameters for automatic checkpointing.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CheckpointConfig {
+    /// Whether to automatically write checkpoints.
+    pub enabled: bool,
+
+    /// The directory to write checkpoints to.
+    pub directory: PathBuf,
+
+    /// How often to write a checkpoint, in Myr of simulated time.
+    pub interval_myr: f64,
+
+    /// The number of checkpoint files to rotate between.
+    pub max_files: usize,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: PathBuf::from("checkpoints"),
+            interval_myr: 1000.0,
+            max_files: 3,
+        }
+    }
+}
+
+/// Parameters for the rewind buffer, which keeps recent states of the simulation in memory so that
+/// it can be stepped back through.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RewindConfig {
+    /// The most memory the recorded states can use, in megabytes. The oldest states are discarded
+    /// to keep within it.
+    pub memory_budget_mb: usize,
+
+    /// How many steps there are between each recorded state.
+    pub interval_steps: u64,
+}
+
+impl Default for RewindConfig {
+    fn default() -> Self {
+        Self {
+            memory_budget_mb: 256,
+            interval_steps: 10,
+        }
+    }
+}
+
+/// Conditions that automatically pause the simulation when they're met, to catch rare events as
+/// they happen. Each is disabled by default.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TriggerConfig {
+    /// Pause when two stars come closer than this, in parsecs, or 0 to disable.
+    pub close_encounter_pc: f64,
+
+    /// Pause when a bound star reaches escape velocity.
+    pub escape: bool,
+
+    /// Pause when the total energy drifts by more than this percentage since the simulation
+    /// started or last paused for it, or 0 to disable.
+    pub energy_drift_percent: f64,
+
+    /// How many steps there are between each check of the conditions.
+    pub interval_steps: u64,
+}
+
+impl Default for TriggerConfig {
+    fn default() -> Self {
+        Self {
+            close_encounter_pc: 0.0,
+            escape: false,
+            energy_drift_percent: 0.0,
+            interval_steps: 10,
+        }
+    }
+}
+
+impl Config {
+    /// Load the config from the given TOML file. If the file doesn't exist the default config is
+    /// used instead, but a file that exists and fails to parse is an error.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            log::info!("Config file {} not found, using defaults", path.display());
+            return Ok(Default::default());
+        }
+
+        log::info!("Loading config from {}", path.display());
+        let contents = std::fs::read_to_string(path)?;
+        let config = toml::from_str(&contents)?;
+
+        Ok(config)
+    }
+}
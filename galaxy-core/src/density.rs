@@ -0,0 +1,32 @@
+use noise::{Fbm, NoiseFn, Perlin};
+
+use crate::config::DensityConfig;
+use crate::types::Vec2d;
+
+/// A spatially varying density made from fractal perlin noise, used to give generated galaxies
+/// clumpy, filamentary structure.
+pub struct DensityField {
+    noise: Fbm<Perlin>,
+    feature_size: f64,
+    contrast: f64,
+}
+
+impl DensityField {
+    /// Create the density field described by the config.
+    pub fn new(config: &DensityConfig) -> Self {
+        Self {
+            noise: Fbm::new(config.seed),
+            feature_size: config.feature_size,
+            contrast: config.contrast,
+        }
+    }
+
+    /// Sample the density at a position in parsecs, between 0 and 1.
+    pub fn sample(&self, position: Vec2d) -> f64 {
+        let point = [position.x / self.feature_size, position.y / self.feature_size];
+
+        // The noise is roughly in the range -1 to 1, raising it to a power emphasises the peaks.
+        let density = (self.noise.get(point) * 0.5 + 0.5).clamp(0.0, 1.0);
+        density.powf(self.contrast)
+    }
+}
@@ -0,0 +1,71 @@
+use std::f64::consts::PI;
+
+use noise::{Fbm, NoiseFn, Perlin};
+
+use crate::config::{DustConfig, GalaxyType, GenerationConfig};
+use crate::generation;
+use crate::types::Vec2d;
+
+/// The density of dust in a disk galaxy, used to draw dust lanes and nebulae over the stars. The
+/// dust is concentrated in lanes along the inner edges of the spiral arms, where gas overtaking them
+/// piles up as it enters them, and broken up into clouds by fractal perlin noise. Galaxies without arms have dust spread
+/// over the whole disk, and spheroidal galaxies have none.
+pub struct DustField {
+    noise: Fbm<Perlin>,
+    config: DustConfig,
+    generation: GenerationConfig,
+}
+
+impl DustField {
+    /// Create the dust field for a galaxy generated from the given config.
+    pub fn new(generation: &GenerationConfig, config: &DustConfig) -> Self {
+        Self {
+            noise: Fbm::new(config.seed),
+            config: config.clone(),
+            generation: generation.clone(),
+        }
+    }
+
+    /// Sample the density of dust at a position in parsecs, between 0 and 1.
+    pub fn sample(&self, position: Vec2d) -> f64 {
+        let generation = &self.generation;
+        let radius = f64::sqrt(position.x * position.x + position.y * position.y);
+        if radius > generation.galaxy_radius() {
+            return 0.0;
+        }
+
+        let lane = match generation.galaxy_type {
+            GalaxyType::Uniform | GalaxyType::Disk => 1.0,
+            GalaxyType::Spiral => self.lane_density(position, radius, 0.0),
+            GalaxyType::BarredSpiral => self.lane_density(position, radius, generation.bar.length / 2.0),
+            GalaxyType::Elliptical | GalaxyType::Plummer | GalaxyType::King => return 0.0,
+        };
+
+        // Dust extends further out than the stars, so it falls off more slowly than the disk.
+        let disk = f64::exp(-radius / (2.0 * generation.disk.scale_length));
+
+        let point = [position.x / self.config.feature_size, position.y / self.config.feature_size];
+        let clouds = (self.noise.get(point) * 0.5 + 0.5).clamp(0.0, 1.0);
+
+        (lane * disk * clouds * 4.0).min(1.0)
+    }
+
+    /// The density of the dust lane nearest to a position, between 0 and 1, for spiral arms
+    /// starting at `inner_radius`.
+    fn lane_density(&self, position: Vec2d, radius: f64, inner_radius: f64) -> f64 {
+        if radius < inner_radius {
+            return 0.0;
+        }
+
+        // The galaxy rotates clockwise, so gas enters the arms from the anticlockwise side. Find the
+        // angle from the center of the nearest lane, as the arms are evenly spaced.
+        let arm_spacing = 2.0 * PI / self.generation.spiral.arm_count.max(1) as f64;
+        let lane_angle = generation::spiral_arm_angle(&self.generation, inner_radius, radius, 0)
+            + self.config.lane_offset / radius.max(1.0);
+        let angle = f64::atan2(position.y, position.x) - lane_angle;
+        let angle_from_lane = (angle + arm_spacing / 2.0).rem_euclid(arm_spacing) - arm_spacing / 2.0;
+
+        let distance = angle_from_lane * radius;
+        f64::exp(-distance * distance / (2.0 * self.config.lane_width * self.config.lane_width))
+    }
+}
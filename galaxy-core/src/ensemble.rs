@@ -0,0 +1,121 @@
+use std::error::Error;
+use std::path::Path;
+
+use crate::analysis::radial_profile::RadialProfile;
+use crate::checkpoint::Checkpoint;
+use crate::config::Config;
+use crate::rng::RngStreams;
+use crate::simulation::{GalaxySim, Star, StarComponent};
+use crate::sweep;
+
+/// One simulation in an ensemble, and its profile.
+#[derive(Clone, Debug)]
+pub struct EnsembleMember {
+    /// What the member is, e.g. its seed.
+    pub label: String,
+
+    pub profile: RadialProfile,
+}
+
+/// The mean and spread of a curve across the members of an ensemble.
+#[derive(Clone, Debug, Default)]
+pub struct EnsembleCurve {
+    pub mean: Vec<f64>,
+
+    /// The standard deviation of the members about the mean.
+    pub spread: Vec<f64>,
+}
+
+impl EnsembleCurve {
+    /// The mean and spread of some curves, bin by bin. Bins past the end of the shortest curve are
+    /// left out.
+    pub fn of<C: AsRef<[f64]>>(curves: &[C]) -> Self {
+        let bin_count = curves.iter().map(|curve| curve.as_ref().len()).min().unwrap_or(0);
+        let curve_count = curves.len() as f64;
+
+        let mean: Vec<f64> = (0..bin_count)
+            .map(|bin| curves.iter().map(|curve| curve.as_ref()[bin]).sum::<f64>() / curve_count)
+            .collect();
+
+        let spread = mean.iter().enumerate()
+            .map(|(bin, &mean)| {
+                let variance = curves.iter()
+                    .map(|curve| (curve.as_ref()[bin] - mean).powi(2))
+                    .sum::<f64>() / curve_count;
+                variance.sqrt()
+            })
+            .collect();
+
+        Self { mean, spread }
+    }
+}
+
+/// The radial profiles of several simulations of the same parameters with different seeds, to tell
+/// real trends apart from the noise of any one seed.
+#[derive(Clone, Debug, Default)]
+pub struct Ensemble {
+    pub members: Vec<EnsembleMember>,
+}
+
+impl Ensemble {
+    /// Generate a galaxy from each seed and run it to a simulated time, with each step covering
+    /// `step_myr`, measuring their profiles at the end. `progress` is called with the fraction of
+    /// the seeds finished so far.
+    pub fn run(config: &Config,
+               seeds: &[u64],
+               end_myr: f64,
+               step_myr: f64,
+               max_radius: f64,
+               bin_count: usize,
+               progress: &mut dyn FnMut(f64)) -> Result<Self, Box<dyn Error>>
+    {
+        let mut members = Vec::with_capacity(seeds.len());
+        for (i, &seed) in seeds.iter().enumerate() {
+            let mut sim = GalaxySim::new(config, &RngStreams::new(seed))?;
+            sweep::run_until(&mut sim, end_myr, step_myr);
+
+            members.push(EnsembleMember {
+                label: format!("Seed {seed}"),
+                profile: Self::measure(&sim, max_radius, bin_count),
+            });
+            progress((i + 1) as f64 / seeds.len() as f64);
+        }
+
+        Ok(Self { members })
+    }
+
+    /// Measure the profiles of the simulations in some checkpoints, e.g. of several seeds run
+    /// separately.
+    pub fn from_checkpoints<P: AsRef<Path>>(paths: &[P], max_radius: f64, bin_count: usize) -> Result<Self, Box<dyn Error>> {
+        let mut members = Vec::with_capacity(paths.len());
+        for path in paths {
+            let checkpoint = Checkpoint::load(path)?;
+            let seed = checkpoint.seed;
+            let sim = checkpoint.into_sim()?;
+
+            members.push(EnsembleMember {
+                label: format!("Seed {seed} at {:.0} Myr", sim.elapsed_myr()),
+                profile: Self::measure(&sim, max_radius, bin_count),
+            });
+        }
+
+        Ok(Self { members })
+    }
+
+    /// The mean and spread of one of the members' curves, e.g. their rotation curves.
+    pub fn curve<F>(&self, f: F) -> EnsembleCurve
+        where F: Fn(&RadialProfile) -> &[f64]
+    {
+        let curves: Vec<&[f64]> = self.members.iter().map(|member| f(&member.profile)).collect();
+        EnsembleCurve::of(&curves)
+    }
+
+    /// Measure the profile of a simulation, leaving out black holes, wherever they are in the list
+    /// of stars, as they would dominate the central density.
+    fn measure(sim: &GalaxySim, max_radius: f64, bin_count: usize) -> RadialProfile {
+        let stars: Vec<Star> = sim.stars().iter()
+            .filter(|star| star.component != StarComponent::BlackHole)
+            .collect();
+        RadialProfile::measure(&stars, max_radius, bin_count)
+    }
+}
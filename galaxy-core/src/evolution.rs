@@ -0,0 +1,42 @@
+//! Stellar evolution: how long stars live, and what happens to them as they age. Stars only age
+//! while the simulation runs, so this is about the stars changing over the course of a simulation
+//! rather than the populations they're generated with.
+
+use crate::config::MassLossConfig;
+use crate::units::SolarMass;
+
+/// The main sequence lifetime of the sun, in Gyr.
+const SOLAR_LIFETIME: f64 = 10.0;
+
+/// How steeply main sequence lifetimes fall with mass, as the exponent of the mass in solar
+/// masses. Massive stars burn through their fuel far faster than they gain more of it.
+const LIFETIME_MASS_EXPONENT: f64 = -2.5;
+
+/// How long a star of the given mass spends on the main sequence, in Gyr.
+pub fn main_sequence_lifetime(mass: SolarMass) -> f64 {
+    SOLAR_LIFETIME * mass.0.powf(LIFETIME_MASS_EXPONENT)
+}
+
+/// Whether a star of the given mass reaches the end of its life while aging from `age_before` to
+/// `age_after`, in Gyr. Stars already past the end of their lives don't reach it again.
+pub fn dies_between(mass: SolarMass, age_before: f64, age_after: f64) -> bool {
+    let lifetime = main_sequence_lifetime(mass);
+    age_before < lifetime && lifetime <= age_after
+}
+
+/// The fraction of its initial mass a stellar population has lost to winds and supernovae by the
+/// given age in Gyr, following the fit of Jungwiert et al. 2001.
+/// https://ui.adsabs.harvard.edu/abs/2001A%26A...376...85J
+pub fn mass_loss_fraction(config: &MassLossConfig, age: f64) -> f64 {
+    match config.rate > 0.0 && config.timescale_myr > 0.0 {
+        true => config.rate * (1.0 + f64::max(age, 0.0) * 1000.0 / config.timescale_myr).ln(),
+        false => 0.0,
+    }
+}
+
+/// The fraction of its mass a star keeps while aging from `age_before` to `age_after`, in Gyr.
+/// Stars are generated having already lost the mass they would have by their age, so this only
+/// depends on how much more they lose.
+pub fn remaining_mass_fraction(config: &MassLossConfig, age_before: f64, age_after: f64) -> f64 {
+    (1.0 - mass_loss_fraction(config, age_after)) / (1.0 - mass_loss_fraction(config, age_before))
+}
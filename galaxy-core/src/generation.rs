@@ -0,0 +1,590 @@
+mod bulge;
+mod globular_clusters;
+mod live_halo;
+mod models;
+mod morphology;
+mod satellites;
+mod tracers;
+
+use std::f64::consts::PI;
+use std::ops::Range;
+
+use rand::{Rng, RngCore};
+use rand_distr::{Distribution, Normal};
+
+use crate::config::{BinaryConfig, Config, GenerationConfig, HaloConfig};
+use crate::rng::{self, RngStreams};
+use crate::simulation::{Binary, Star, StarComponent};
+use crate::types::Vec2d;
+use crate::units::SolarMass;
+
+use bulge::Bulge;
+use globular_clusters::GlobularClusters;
+use live_halo::LiveHalo;
+use models::plummer_star;
+use morphology::Morphology;
+use satellites::Satellites;
+use tracers::TracerCloud;
+
+pub use models::MAX_PLUMMER_RADII;
+pub use morphology::spiral_arm_angle;
+
+/// How a star moves, chosen when it's placed. The velocities are calculated once all the stars have
+/// been placed, as they depend on the mass enclosed by each star's orbit.
+enum Motion {
+    /// A circular orbit around the center.
+    Circular,
+
+    /// A circular orbit in the opposite direction to the rest of the disk.
+    CounterRotating,
+
+    /// Moving at the circular speed in the given direction, e.g. streaming along a bar.
+    Streaming(Vec2d),
+
+    /// Random motion with an isotropic velocity dispersion.
+    Random,
+
+    /// Moving within a cluster whose center is at the far end of an orbit with the given
+    /// eccentricity, which is circular for 0.
+    Cluster { center: Vec2d, internal_velocity: Vec2d, eccentricity: f64 },
+
+    /// A velocity chosen when the star was placed, e.g. from a model's distribution function.
+    Fixed(Vec2d),
+}
+
+/// The stellar population a star belongs to, which determines its age and metallicity.
+#[derive(Clone, Copy)]
+enum Population {
+    /// Stars of all ages in the disk, with a radial metallicity gradient.
+    Disk,
+
+    /// The old, metal rich stars of a bulge or elliptical galaxy.
+    Spheroid,
+
+    /// The oldest, metal poor stars of the halo, globular clusters and satellites.
+    Halo,
+
+    /// Dark matter and tracer particles, which have no age or metallicity.
+    DarkMatter,
+}
+
+/// A star that has been placed by a component, but not yet given a velocity.
+struct PlacedStar {
+    position: Vec2d,
+    mass: f64,
+    motion: Motion,
+    population: Population,
+}
+
+/// A component of a galaxy, such as its disk or bulge. Each component places its own stars and
+/// chooses how they move, and then velocities are calculated for the stars of all the components
+/// together.
+trait Component {
+    /// Which component this is, which the stars it places are tagged with.
+    fn kind(&self) -> StarComponent;
+
+    /// Place the component's stars, adding them to `stars`.
+    fn place_stars(&self, rng: &mut dyn RngCore, stars: &mut Vec<PlacedStar>);
+}
+
+/// The mass enclosed within each radius of the galaxy's center, including the stars, the
+/// supermassive black hole and the dark matter halo. The stars are treated as if they were
+/// distributed spherically, which is a good enough approximation to start the disk close to
+/// equilibrium.
+struct EnclosedMass {
+    gravitational_constant: f64,
+    halo: HaloConfig,
+
+    /// The radius of each star sorted from the center outwards, and the total mass of the stars
+    /// up to and including it.
+    cumulative_masses: Vec<(f64, f64)>,
+}
+
+impl EnclosedMass {
+    /// Calculate the enclosed mass profile of the given stars, as positions and masses.
+    fn new<I: IntoIterator<Item = (Vec2d, f64)>>(config: &Config, stars: I) -> Self {
+        let mut radii: Vec<(f64, f64)> = stars.into_iter()
+            .map(|(position, mass)| (f64::sqrt(position.x * position.x + position.y * position.y), mass))
+            .collect();
+        radii.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut total_mass = 0.0;
+        let cumulative_masses = radii.into_iter()
+            .map(|(radius, mass)| {
+                total_mass += mass;
+                (radius, total_mass)
+            })
+            .collect();
+
+        Self {
+            gravitational_constant: config.simulation.gravitational_constant,
+            halo: config.simulation.halo.clone(),
+            cumulative_masses,
+        }
+    }
+
+    /// The mass within a radius of the center, in solar masses.
+    fn at_radius(&self, radius: f64) -> f64 {
+        self.stars_at_radius(radius) + self.halo.enclosed_mass(radius)
+    }
+
+    /// The mass of the stars within a radius of the center, in solar masses.
+    fn stars_at_radius(&self, radius: f64) -> f64 {
+        let stars_inside = self.cumulative_masses.partition_point(|&(star_radius, _)| star_radius < radius);
+        match stars_inside {
+            0 => 0.0,
+            count => self.cumulative_masses[count - 1].1,
+        }
+    }
+
+    /// The surface density of stars at a radius in solar masses per square parsec, averaged over
+    /// an annulus around it.
+    fn star_surface_density(&self, radius: f64, half_width: f64) -> f64 {
+        let inner_radius = (radius - half_width).max(0.0);
+        let outer_radius = radius + half_width;
+        let mass = self.stars_at_radius(outer_radius) - self.stars_at_radius(inner_radius);
+        mass / (PI * (outer_radius * outer_radius - inner_radius * inner_radius))
+    }
+
+    /// Calculate the speed of a circular orbit through a position.
+    /// https://www.nagwa.com/en/explainers/142168516704/
+    fn circular_speed(&self, position: Vec2d) -> f64 {
+        let distance_from_center = f64::sqrt(position.x * position.x + position.y * position.y);
+        if distance_from_center == 0.0 {
+            return 0.0;
+        }
+
+        f64::sqrt(self.gravitational_constant * self.at_radius(distance_from_center) / distance_from_center)
+    }
+}
+
+/// Calculate the radial and tangential velocity dispersion of disk stars at a position.
+fn velocity_dispersion(config: &Config, enclosed_mass: &EnclosedMass, position: Vec2d) -> (f64, f64) {
+    let dispersion = &config.generation.dispersion;
+    let toomre_q = match dispersion.toomre_q {
+        Some(toomre_q) => toomre_q,
+        None => return (dispersion.radial, dispersion.tangential),
+    };
+
+    let radius = f64::sqrt(position.x * position.x + position.y * position.y);
+    if radius == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    // Differentiate numerically over a tenth of the radius, which is wide enough to smooth out the
+    // noise from individual stars.
+    let half_width = radius * 0.05;
+    let speed = enclosed_mass.circular_speed(position);
+    let speed_gradient = (enclosed_mass.circular_speed(position * ((radius + half_width) / radius))
+        - enclosed_mass.circular_speed(position * ((radius - half_width) / radius))) / (2.0 * half_width);
+
+    // The epicyclic frequency, kappa^2 = (2v / r) (v / r + dv/dr), is how fast a star oscillates
+    // around its circular orbit when nudged.
+    let angular_speed = speed / radius;
+    let kappa = f64::sqrt((2.0 * angular_speed * (angular_speed + speed_gradient)).max(0.0));
+    if kappa == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    // Toomre's stability criterion for a stellar disk is Q = sigma_r kappa / (3.36 G Sigma), and in
+    // the epicyclic approximation sigma_t / sigma_r = kappa / 2 omega.
+    let surface_density = enclosed_mass.star_surface_density(radius, half_width);
+    let radial = toomre_q * 3.36 * config.simulation.gravitational_constant * surface_density / kappa;
+    let tangential = radial * kappa / (2.0 * angular_speed);
+
+    (radial, tangential)
+}
+
+/// Generate the stars of a new galaxy, or a cluster of galaxies if configured. For a single galaxy
+/// the first star is the supermassive black hole if it has one, and for a cluster it's a massless
+/// marker at the barycenter. The random numbers are drawn from the generation streams.
+pub fn generate_stars(config: &Config, streams: &RngStreams) -> Vec<Star> {
+    generate_stars_with_progress(config, streams, &mut |_| {})
+}
+
+/// Generate the stars of a new galaxy like `generate_stars`, calling `progress` with the fraction
+/// of the work done so far, between 0 and 1, as it goes.
+pub fn generate_stars_with_progress(config: &Config,
+                                    streams: &RngStreams,
+                                    progress: &mut dyn FnMut(f64)) -> Vec<Star>
+{
+    let streams = streams.substreams(rng::GENERATION);
+    let stars = if config.generation.three_body.enabled {
+        generate_three_body(config, &streams)
+    }
+    else if config.generation.cluster.enabled() {
+        generate_cluster(config, &streams, progress)
+    }
+    else {
+        generate_galaxy(config, &streams, progress)
+    };
+    progress(1.0);
+    stars
+}
+
+/// Generate a cluster of galaxies, composed of single galaxies offset to their positions and
+/// velocities in the cluster. Each galaxy is generated from its own streams, so the number of
+/// galaxies doesn't change the ones before it.
+fn generate_cluster(config: &Config, streams: &RngStreams, progress: &mut dyn FnMut(f64)) -> Vec<Star> {
+    let cluster = &config.generation.cluster;
+
+    // Generate the member galaxies first, as their velocities depend on the total mass.
+    let galaxy_count = cluster.galaxy_count;
+    let galaxies: Vec<Vec<Star>> = (0..galaxy_count)
+        .map(|i| generate_galaxy(config,
+                                 &streams.substreams(&format!("galaxy {i}")),
+                                 &mut |fraction| progress((i as f64 + fraction) / galaxy_count as f64)))
+        .collect();
+    let total_mass = galaxies.iter().flatten().map(|star| star.mass).sum::<SolarMass>().0;
+
+    let mut stars = vec![Star { component: StarComponent::BlackHole, ..Default::default() }];
+
+    let mut rng = streams.stream("cluster");
+    for galaxy in galaxies {
+        let (offset, velocity) =
+            plummer_star(cluster.radius, total_mass, config.simulation.gravitational_constant, &mut rng);
+        let angle = rng.gen_range(0.0..(2.0 * PI));
+
+        stars.extend(galaxy.into_iter().map(|star| Star {
+            position: offset + rotate(star.position, angle),
+            velocity: velocity + rotate(star.velocity, angle),
+            ..star
+        }));
+    }
+
+    // Move the barycenter to the origin and remove any net momentum, so the cluster stays put.
+    let weighted_sum = |value: fn(&Star) -> Vec2d| stars.iter()
+        .fold(Vec2d::new(0.0, 0.0), |sum, star| sum + value(star) * star.mass.0) / total_mass;
+    let barycenter = weighted_sum(|star| star.position);
+    let barycenter_velocity = weighted_sum(|star| star.velocity);
+    for star in stars.iter_mut().skip(1) {
+        star.position = star.position - barycenter;
+        star.velocity = star.velocity - barycenter_velocity;
+    }
+
+    stars
+}
+
+/// Generate the restricted three-body problem: the primary and secondary bodies, which are the first
+/// two stars, starting at pericenter about their barycenter at the origin, followed by the massless
+/// test particles.
+fn generate_three_body(config: &Config, streams: &RngStreams) -> Vec<Star> {
+    let three_body = &config.generation.three_body;
+    let gravitational_constant = config.simulation.gravitational_constant;
+    let total_mass = three_body.primary_mass + three_body.secondary_mass;
+    let secondary_fraction = match total_mass > 0.0 {
+        true => three_body.secondary_mass / total_mass,
+        false => 0.0,
+    };
+
+    // At pericenter the relative speed of the bodies is sqrt(G M (1 + e) / r).
+    let eccentricity = three_body.eccentricity.clamp(0.0, 0.99);
+    let pericenter = Vec2d::new(three_body.semi_major_axis * (1.0 - eccentricity), 0.0);
+    let speed = f64::sqrt(gravitational_constant * total_mass * (1.0 + eccentricity) / pericenter.x);
+    let relative_velocity = orbital_velocity(pericenter, speed);
+
+    let mut stars = Vec::with_capacity(three_body.particle_count + 2);
+    stars.push(Star {
+        position: pericenter * -secondary_fraction,
+        velocity: relative_velocity * -secondary_fraction,
+        mass: SolarMass(three_body.primary_mass),
+        component: StarComponent::BlackHole,
+        ..Default::default()
+    });
+    stars.push(Star {
+        position: pericenter * (1.0 - secondary_fraction),
+        velocity: relative_velocity * (1.0 - secondary_fraction),
+        mass: SolarMass(three_body.secondary_mass),
+        component: StarComponent::BlackHole,
+        ..Default::default()
+    });
+
+    // Spread the particles evenly over the area of the annulus, going the same way round as the
+    // bodies.
+    let mut rng = streams.stream("three body");
+    let (min_squared, max_squared) = (three_body.particle_min_radius.powi(2), three_body.particle_max_radius.powi(2));
+    for _ in 0..three_body.particle_count {
+        let radius = f64::sqrt(rng.gen_range(min_squared..=max_squared.max(min_squared)));
+        let angle = rng.gen_range(0.0..(2.0 * PI));
+        let position = Vec2d::new(angle.cos(), angle.sin()) * radius;
+        let speed = f64::sqrt(gravitational_constant * total_mass / radius);
+        stars.push(Star {
+            position,
+            velocity: orbital_velocity(position, speed),
+            component: StarComponent::Tracer,
+            ..Default::default()
+        });
+    }
+
+    stars
+}
+
+/// Rotate a vector anticlockwise by an angle in radians.
+fn rotate(vector: Vec2d, angle: f64) -> Vec2d {
+    let (sin, cos) = angle.sin_cos();
+    Vec2d::new(vector.x * cos - vector.y * sin, vector.x * sin + vector.y * cos)
+}
+
+/// Generate the stars of a single galaxy from its components. The first star is the supermassive
+/// black hole, if there is one. Each component places its stars from its own stream.
+fn generate_galaxy(config: &Config, streams: &RngStreams, progress: &mut dyn FnMut(f64)) -> Vec<Star> {
+    /// How many stars to give velocities between progress reports.
+    const PROGRESS_INTERVAL: usize = 4096;
+
+    let generation = &config.generation;
+
+    let components: [Box<dyn Component>; 6] = [
+        Box::new(Morphology::new(generation, &config.simulation)),
+        Box::new(Bulge::new(generation)),
+        Box::new(LiveHalo::new(generation)),
+        Box::new(GlobularClusters::new(generation, &config.simulation)),
+        Box::new(Satellites::new(generation, &config.simulation)),
+        Box::new(TracerCloud::new(generation)),
+    ];
+
+    // Place the stars of every component, which is roughly the first half of the work.
+    let mut placed_stars = Vec::new();
+    let mut star_components = Vec::new();
+    for (i, component) in components.iter().enumerate() {
+        component.place_stars(&mut streams.stream(component.kind().name()), &mut placed_stars);
+        star_components.resize(placed_stars.len(), component.kind());
+        progress(0.5 * (i + 1) as f64 / components.len() as f64);
+    }
+
+    // Add supermassive black hole at center of galaxy.
+    let mut stars = Vec::with_capacity(placed_stars.len() + 1);
+    if generation.has_black_hole() {
+        stars.push(Star {
+            mass: SolarMass(generation.supermassive_black_hole_mass),
+            component: StarComponent::BlackHole,
+            ..Default::default()
+        });
+    }
+
+    // Now that all the stars are placed, give them velocities that balance the gravity of the mass
+    // inside their orbits.
+    let enclosed_mass = EnclosedMass::new(config, stars.iter().map(|star| (star.position, star.mass.0))
+        .chain(placed_stars.iter().map(|star| (star.position, star.mass))));
+    let first_placed_star = stars.len();
+    let placed_count = placed_stars.len();
+    let mut populations = Vec::with_capacity(placed_count);
+    let placed_stars = placed_stars.into_iter().zip(star_components);
+    let mut rng = streams.stream("velocities");
+    for (i, (PlacedStar { position, mass, motion, population }, component)) in placed_stars.enumerate() {
+        let velocity = star_velocity(config, &enclosed_mass, position, motion, &mut rng);
+        stars.push(Star { position, velocity, mass: SolarMass(mass), component, ..Default::default() });
+        populations.push(population);
+
+        if i % PROGRESS_INTERVAL == 0 {
+            progress(0.5 + 0.5 * i as f64 / placed_count as f64);
+        }
+    }
+
+    // Sample the stars' ages and metallicities from their own stream, so that they don't change the
+    // positions and velocities generated from a seed.
+    let mut rng = streams.stream("populations");
+    for (star, population) in stars[first_placed_star..].iter_mut().zip(populations) {
+        (star.age, star.metallicity) = sample_population(generation, population, star.position, &mut rng);
+    }
+
+    // Pair some of the stars up as binaries, also from their own stream.
+    let binaries = &generation.binaries;
+    if binaries.fraction > 0.0 {
+        let mut rng = streams.stream("binaries");
+        for star in stars[first_placed_star..].iter_mut().filter(|star| star.is_star()) {
+            if rng.gen_bool(binaries.fraction.min(1.0)) {
+                star.binary = Some(random_binary(binaries, star.mass, config.simulation.gravitational_constant, &mut rng));
+            }
+        }
+    }
+
+    stars
+}
+
+/// Generate the orbit of a binary star with the given total mass, starting at pericenter.
+fn random_binary(config: &BinaryConfig, mass: SolarMass, gravitational_constant: f64, rng: &mut dyn RngCore) -> Binary {
+    /// The most eccentric a binary's orbit can be, so that the stars don't start on top of each
+    /// other.
+    const MAX_ECCENTRICITY: f64 = 0.95;
+
+    let semi_major_axis = config.max_separation * 10f64.powf(rng.gen_range(-1.0..=0.0));
+    let eccentricity = f64::min(rng.gen::<f64>().sqrt(), MAX_ECCENTRICITY);
+    let mass_ratio = rng.gen_range(0.1..=1.0);
+
+    // Orient the orbit randomly, going either way round.
+    let pericenter = semi_major_axis * (1.0 - eccentricity);
+    let speed = f64::sqrt(gravitational_constant * mass.0 * (1.0 + eccentricity) / pericenter);
+    let angle = rng.gen_range(0.0..(2.0 * PI));
+    let direction = Vec2d::new(angle.cos(), angle.sin());
+    let speed = match rng.gen_bool(0.5) {
+        true => speed,
+        false => -speed,
+    };
+
+    Binary {
+        companion_fraction: mass_ratio / (1.0 + mass_ratio),
+        separation: direction * pericenter,
+        relative_velocity: Vec2d::new(-direction.y, direction.x) * speed,
+    }
+}
+
+/// Calculate the velocity of a placed star according to how it moves.
+fn star_velocity(config: &Config,
+                 enclosed_mass: &EnclosedMass,
+                 position: Vec2d,
+                 motion: Motion,
+                 rng: &mut dyn RngCore) -> Vec2d
+{
+    let speed = enclosed_mass.circular_speed(position);
+    let ordered_velocity = match motion {
+        Motion::Circular => orbital_velocity(position, speed),
+        Motion::CounterRotating => orbital_velocity(position, -speed),
+        Motion::Streaming(direction) => direction * speed,
+        Motion::Random => return random_velocity(speed, rng),
+        Motion::Cluster { center, internal_velocity, eccentricity } => {
+            // At apocenter the speed of an orbit around a point mass is the circular speed scaled
+            // by sqrt(1 - e).
+            let center_speed = enclosed_mass.circular_speed(center) * f64::sqrt(1.0 - eccentricity.clamp(0.0, 1.0));
+            return orbital_velocity(center, center_speed) + internal_velocity;
+        },
+        Motion::Fixed(velocity) => return velocity,
+    };
+
+    // Disk stars also get random motions in the plane of their orbit.
+    let (radial_dispersion, tangential_dispersion) = velocity_dispersion(config, enclosed_mass, position);
+    let tangential_direction = orbital_velocity(position, 1.0);
+    let radial_direction = Vec2d::new(-tangential_direction.y, tangential_direction.x);
+    ordered_velocity
+        + radial_direction * gaussian(radial_dispersion, rng)
+        + tangential_direction * gaussian(tangential_dispersion, rng)
+}
+
+/// Sample the age in Gyr and metallicity in dex of a star in a population.
+fn sample_population(generation: &GenerationConfig,
+                     population: Population,
+                     position: Vec2d,
+                     rng: &mut dyn RngCore) -> (f64, f64)
+{
+    /// The age of the universe in Gyr, the oldest a star can be.
+    const MAX_AGE: f64 = 13.0;
+
+    /// The age in Gyr of the oldest stars in the disk, which has formed stars at a constant rate
+    /// since.
+    const DISK_AGE: f64 = 10.0;
+
+    /// The age in Gyr of the youngest stars in spheroids, which stopped forming stars long ago.
+    const SPHEROID_MIN_AGE: f64 = 8.0;
+
+    /// The age in Gyr of the youngest halo stars.
+    const HALO_MIN_AGE: f64 = 11.0;
+
+    /// The mean metallicity of halo stars, in dex.
+    const HALO_METALLICITY: f64 = -1.5;
+
+    let population_config = &generation.population;
+    let scatter = gaussian(population_config.metallicity_scatter, rng);
+    match population {
+        Population::Disk => {
+            let radius_kpc = f64::sqrt(position.x * position.x + position.y * position.y) / 1000.0;
+            let metallicity = population_config.central_metallicity
+                + population_config.metallicity_gradient * radius_kpc;
+            (rng.gen_range(0.0..DISK_AGE), metallicity + scatter)
+        },
+        Population::Spheroid => {
+            (rng.gen_range(SPHEROID_MIN_AGE..MAX_AGE), population_config.central_metallicity + scatter)
+        },
+        Population::Halo => (rng.gen_range(HALO_MIN_AGE..MAX_AGE), HALO_METALLICITY + scatter),
+        Population::DarkMatter => (0.0, 0.0),
+    }
+}
+
+/// A Plummer sphere of stars orbiting the galaxy, such as a globular cluster or satellite galaxy.
+struct PlummerCluster {
+    center: Vec2d,
+    eccentricity: f64,
+    star_count: usize,
+    star_mass_range: Range<f64>,
+    plummer_radius: f64,
+    gravitational_constant: f64,
+
+    /// The mass of a particle at the cluster's center, or 0 for none.
+    core_mass: f64,
+}
+
+/// Place the stars of a Plummer sphere orbiting the galaxy.
+fn place_plummer_cluster(cluster: &PlummerCluster, rng: &mut dyn RngCore, stars: &mut Vec<PlacedStar>) {
+    // Generate the stars' masses first, as the velocities depend on the total mass.
+    let masses: Vec<f64> = (0..cluster.star_count)
+        .map(|_| rng.gen_range(cluster.star_mass_range.clone()))
+        .collect();
+    let cluster_mass = masses.iter().sum::<f64>() + cluster.core_mass;
+
+    if cluster.core_mass > 0.0 {
+        stars.push(PlacedStar {
+            position: cluster.center,
+            mass: cluster.core_mass,
+            motion: Motion::Cluster {
+                center: cluster.center,
+                internal_velocity: Vec2d::new(0.0, 0.0),
+                eccentricity: cluster.eccentricity,
+            },
+            population: Population::Halo,
+        });
+    }
+
+    for mass in masses {
+        let (offset, internal_velocity) =
+            plummer_star(cluster.plummer_radius, cluster_mass, cluster.gravitational_constant, rng);
+        stars.push(PlacedStar {
+            position: cluster.center + offset,
+            mass,
+            motion: Motion::Cluster { center: cluster.center, internal_velocity, eccentricity: cluster.eccentricity },
+            population: Population::Halo,
+        });
+    }
+}
+
+/// Sample a radius from a Hernquist profile, whose density falls off as `r^-1` near the center and
+/// `r^-4` far from it, resampling radii beyond `max_radius`.
+fn hernquist_radius<R: Rng + ?Sized>(scale_radius: f64, max_radius: f64, rng: &mut R) -> f64 {
+    // The fraction of the mass within r is r^2 / (r + a)^2, which can be inverted directly.
+    loop {
+        let root_fraction = f64::sqrt(rng.gen::<f64>());
+        let radius = scale_radius * root_fraction / (1.0 - root_fraction);
+        if radius <= max_radius {
+            return radius;
+        }
+    }
+}
+
+/// Generate an isotropic random velocity, with a dispersion that gives the same mean squared
+/// speed as a circular orbit at the same radius.
+fn random_velocity<R: Rng + ?Sized>(circular_speed: f64, rng: &mut R) -> Vec2d {
+    // The dispersion is per component, so the total mean squared speed is twice its square.
+    let dispersion = circular_speed / f64::sqrt(2.0);
+    Vec2d::new(gaussian(dispersion, rng), gaussian(dispersion, rng))
+}
+
+/// Pick a random direction in three dimensions, returning its projection onto the plane.
+fn random_projected_direction<R: Rng + ?Sized>(rng: &mut R) -> Vec2d {
+    let z: f64 = rng.gen_range(-1.0..=1.0);
+    let angle = rng.gen_range(0.0..(2.0 * PI));
+    let planar_length = f64::sqrt(1.0 - z * z);
+    Vec2d::new(f64::cos(angle) * planar_length, f64::sin(angle) * planar_length)
+}
+
+/// Sample a gaussian with a mean of zero, or return zero if the standard deviation is invalid.
+fn gaussian<R: Rng + ?Sized>(standard_deviation: f64, rng: &mut R) -> f64 {
+    match Normal::new(0.0, standard_deviation) {
+        Ok(normal) => normal.sample(rng),
+        Err(_) => 0.0,
+    }
+}
+
+/// Calculate the velocity of a circular orbit around the center with the given speed.
+fn orbital_velocity(position: Vec2d, speed: f64) -> Vec2d {
+    // Figure out direction perpendicular to center.
+    let angle = f64::atan2(position.x, position.y) + PI / 2.0;
+    let direction = Vec2d::new(f64::sin(angle), f64::cos(angle));
+    direction * speed
+}
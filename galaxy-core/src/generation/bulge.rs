@@ -0,0 +1,35 @@
+use rand::{Rng, RngCore};
+
+use crate::config::GenerationConfig;
+use crate::simulation::StarComponent;
+
+use super::{hernquist_radius, random_projected_direction, Component, Motion, PlacedStar, Population};
+
+/// The galaxy's central bulge, a spheroid of stars on random orbits following a Hernquist profile.
+pub struct Bulge<'a> {
+    generation: &'a GenerationConfig,
+}
+
+impl<'a> Bulge<'a> {
+    /// Create the bulge described by the generation config.
+    pub fn new(generation: &'a GenerationConfig) -> Self {
+        Self { generation }
+    }
+}
+
+impl<'a> Component for Bulge<'a> {
+    fn kind(&self) -> StarComponent {
+        StarComponent::Bulge
+    }
+
+    fn place_stars(&self, rng: &mut dyn RngCore, stars: &mut Vec<PlacedStar>) {
+        let bulge = &self.generation.bulge;
+
+        for _ in 0..bulge.star_count {
+            let mass = rng.gen_range(bulge.star_mass_min..bulge.star_mass_max);
+            let radius = hernquist_radius(bulge.scale_radius, self.generation.galaxy_radius(), rng);
+            let position = random_projected_direction(rng) * radius;
+            stars.push(PlacedStar { position, mass, motion: Motion::Random, population: Population::Spheroid });
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::f64::consts::PI;
+
+use rand::{Rng, RngCore};
+
+use crate::config::{GenerationConfig, SimulationConfig};
+use crate::simulation::StarComponent;
+use crate::types::Vec2d;
+
+use super::{place_plummer_cluster, Component, PlacedStar, PlummerCluster};
+
+/// Globular clusters on circular orbits in the halo. Each cluster is a Plummer sphere.
+pub struct GlobularClusters<'a> {
+    generation: &'a GenerationConfig,
+    gravitational_constant: f64,
+}
+
+impl<'a> GlobularClusters<'a> {
+    /// Create the globular clusters described by the config.
+    pub fn new(generation: &'a GenerationConfig, simulation: &SimulationConfig) -> Self {
+        Self { generation, gravitational_constant: simulation.gravitational_constant }
+    }
+
+    /// Generate a single cluster.
+    fn place_cluster(&self, rng: &mut dyn RngCore, stars: &mut Vec<PlacedStar>) {
+        let generation = self.generation;
+        let clusters = &generation.globular_clusters;
+
+        // Pick the cluster's orbit.
+        let distance = rng.gen_range(clusters.min_distance..=clusters.max_distance.max(clusters.min_distance));
+        let angle = rng.gen_range(0.0..(2.0 * PI));
+        let center = Vec2d::new(f64::cos(angle) * distance, f64::sin(angle) * distance);
+
+        place_plummer_cluster(&PlummerCluster {
+            center,
+            eccentricity: 0.0,
+            star_count: clusters.star_count,
+            star_mass_range: generation.star_mass_min..generation.star_mass_max,
+            plummer_radius: clusters.plummer_radius,
+            gravitational_constant: self.gravitational_constant,
+            core_mass: 0.0,
+        }, rng, stars);
+    }
+}
+
+impl<'a> Component for GlobularClusters<'a> {
+    fn kind(&self) -> StarComponent {
+        StarComponent::GlobularCluster
+    }
+
+    fn place_stars(&self, rng: &mut dyn RngCore, stars: &mut Vec<PlacedStar>) {
+        for _ in 0..self.generation.globular_clusters.count {
+            self.place_cluster(rng, stars);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use rand::RngCore;
+
+use crate::config::GenerationConfig;
+use crate::simulation::StarComponent;
+use crate::types::Vec2d;
+
+use super::{hernquist_radius, random_projected_direction, Component, Motion, PlacedStar, Population};
+
+/// A dark matter halo made of particles that are simulated like the stars, unlike the static halo
+/// in the simulation config. The particles follow a Hernquist profile, truncated at twice the
+/// galaxy's radius so that they start within the simulation's bounds, and squashed along the y axis
+/// by the axis ratio.
+pub struct LiveHalo<'a> {
+    generation: &'a GenerationConfig,
+}
+
+impl<'a> LiveHalo<'a> {
+    /// Create the live halo described by the generation config.
+    pub fn new(generation: &'a GenerationConfig) -> Self {
+        Self { generation }
+    }
+}
+
+impl<'a> Component for LiveHalo<'a> {
+    fn kind(&self) -> StarComponent {
+        StarComponent::Halo
+    }
+
+    fn place_stars(&self, rng: &mut dyn RngCore, stars: &mut Vec<PlacedStar>) {
+        let halo = &self.generation.live_halo;
+        if halo.particle_count == 0 {
+            return;
+        }
+
+        let mass = halo.mass / halo.particle_count as f64;
+        for _ in 0..halo.particle_count {
+            let radius = hernquist_radius(halo.scale_radius, self.generation.galaxy_radius() * 2.0, rng);
+            let direction = random_projected_direction(rng);
+            let position = Vec2d::new(direction.x, direction.y * halo.axis_ratio) * radius;
+            stars.push(PlacedStar { position, mass, motion: Motion::Random, population: Population::DarkMatter });
+        }
+    }
+}
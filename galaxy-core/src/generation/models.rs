@@ -0,0 +1,184 @@
+use rand::Rng;
+
+use crate::types::Vec2d;
+
+use super::random_projected_direction;
+
+/// Plummer spheres extend forever, so stars further than this many Plummer radii from the center are
+/// resampled.
+pub const MAX_PLUMMER_RADII: f64 = 10.0;
+
+/// The step size used to integrate the King model's potential, in core radii.
+const KING_STEP: f64 = 0.01;
+
+/// The number of points to search for the peak of the King model's speed distribution at a
+/// radius, which is used as the bound for rejection sampling.
+const KING_SPEED_SAMPLES: usize = 32;
+
+/// Sample the position and velocity of a star in a Plummer sphere, relative to its center. The
+/// velocities are sampled from the sphere's distribution function, so the sphere starts in
+/// equilibrium. It's sampled in three dimensions and projected onto the plane.
+/// https://articles.adsabs.harvard.edu/pdf/1974A%26A....37..183A
+pub fn plummer_star<R: Rng + ?Sized>(plummer_radius: f64,
+                                     total_mass: f64,
+                                     gravitational_constant: f64,
+                                     rng: &mut R) -> (Vec2d, Vec2d)
+{
+    // Invert the Plummer sphere's cumulative mass distribution to get the radius.
+    let radius = loop {
+        let radius = plummer_radius / f64::sqrt(rng.gen::<f64>().powf(-2.0 / 3.0) - 1.0);
+        if radius <= plummer_radius * MAX_PLUMMER_RADII {
+            break radius;
+        }
+    };
+
+    // Pick the speed as a fraction q of the escape speed, whose distribution is proportional to
+    // q^2 (1 - q^2)^(7/2), which has a maximum below 0.1.
+    let q = loop {
+        let q: f64 = rng.gen();
+        if rng.gen::<f64>() * 0.1 < q * q * (1.0 - q * q).powf(3.5) {
+            break q;
+        }
+    };
+    let escape_speed = f64::sqrt(2.0 * gravitational_constant * total_mass
+                                 / f64::sqrt(radius * radius + plummer_radius * plummer_radius));
+
+    (random_projected_direction(rng) * radius, random_projected_direction(rng) * (q * escape_speed))
+}
+
+/// A King model, the lowered isothermal sphere often used for globular clusters. Unlike a Plummer
+/// sphere it has a finite tidal radius, and its concentration is set by the dimensionless central
+/// potential W0. The potential has no closed form, so it's integrated numerically and tabulated in
+/// units of the core radius and velocity dispersion parameter.
+/// https://ui.adsabs.harvard.edu/abs/1966AJ.....71...64K
+pub struct KingModel {
+    /// Radius, dimensionless potential W and the fraction of the mass enclosed, from the center out
+    /// to the tidal radius.
+    table: Vec<(f64, f64, f64)>,
+
+    /// `-r^2 dW/dr` at the tidal radius, which is proportional to the total mass.
+    mass_scale: f64,
+}
+
+impl KingModel {
+    /// Integrate the King model with the given central potential.
+    pub fn new(central_potential: f64) -> Self {
+        let central_density = king_density(central_potential);
+
+        // Poisson's equation in units of the core radius is W'' + 2 W' / r = -9 rho(W) / rho(W0).
+        let derivatives = |radius: f64, (potential, gradient): (f64, f64)| {
+            (gradient, -9.0 * king_density(potential) / central_density - 2.0 * gradient / radius)
+        };
+
+        // Start slightly away from the center to avoid the singularity, using the series solution
+        // W = W0 - 3r^2 / 2.
+        let mut radius = KING_STEP;
+        let mut state = (central_potential - 1.5 * radius * radius, -3.0 * radius);
+        let mut table = vec![(0.0, central_potential, 0.0)];
+
+        // Integrate outwards with RK4 until the potential reaches zero at the tidal radius.
+        while state.0 > 0.0 {
+            table.push((radius, state.0, -radius * radius * state.1));
+
+            let h = KING_STEP;
+            let k1 = derivatives(radius, state);
+            let k2 = derivatives(radius + h / 2.0, (state.0 + h / 2.0 * k1.0, state.1 + h / 2.0 * k1.1));
+            let k3 = derivatives(radius + h / 2.0, (state.0 + h / 2.0 * k2.0, state.1 + h / 2.0 * k2.1));
+            let k4 = derivatives(radius + h, (state.0 + h * k3.0, state.1 + h * k3.1));
+            state.0 += h / 6.0 * (k1.0 + 2.0 * k2.0 + 2.0 * k3.0 + k4.0);
+            state.1 += h / 6.0 * (k1.1 + 2.0 * k2.1 + 2.0 * k3.1 + k4.1);
+            radius += h;
+        }
+
+        // The enclosed mass is proportional to -r^2 W', so normalise it by its value at the edge.
+        let mass_scale = -radius * radius * state.1;
+        table.push((radius, 0.0, mass_scale));
+        for entry in &mut table {
+            entry.2 /= mass_scale;
+        }
+
+        Self { table, mass_scale }
+    }
+
+    /// The tidal radius, beyond which there are no stars, in core radii.
+    pub fn tidal_radius(&self) -> f64 {
+        self.table.last().map_or(0.0, |&(radius, _, _)| radius)
+    }
+
+    /// Sample the position and velocity of a star relative to the center of a King model with the
+    /// given core radius and mass. As with the Plummer sphere, it's sampled in three dimensions and
+    /// projected onto the plane.
+    pub fn sample_star<R: Rng + ?Sized>(&self,
+                                        core_radius: f64,
+                                        total_mass: f64,
+                                        gravitational_constant: f64,
+                                        rng: &mut R) -> (Vec2d, Vec2d)
+    {
+        // Invert the enclosed mass table to pick a radius, interpolating between entries.
+        let fraction: f64 = rng.gen();
+        let index = self.table.partition_point(|&(_, _, mass_fraction)| mass_fraction < fraction)
+            .clamp(1, self.table.len() - 1);
+        let (inner_radius, inner_potential, inner_fraction) = self.table[index - 1];
+        let (outer_radius, outer_potential, outer_fraction) = self.table[index];
+        let t = match outer_fraction - inner_fraction {
+            width if width > 0.0 => (fraction - inner_fraction) / width,
+            _ => 0.0,
+        };
+        let radius = inner_radius + t * (outer_radius - inner_radius);
+        let potential = inner_potential + t * (outer_potential - inner_potential);
+
+        // The velocity dispersion parameter follows from the core radius and total mass, as
+        // sigma^2 = G M / (r0 (-r^2 W') at the tidal radius).
+        let sigma = f64::sqrt(gravitational_constant * total_mass / (core_radius * self.mass_scale));
+
+        // The speed in units of sigma is distributed as x^2 (e^(W - x^2/2) - 1) up to the escape
+        // speed, sampled by rejection against its peak.
+        let max_speed = f64::sqrt(2.0 * potential.max(0.0));
+        let speed_density = |x: f64| x * x * (f64::exp(potential - x * x / 2.0) - 1.0);
+        let peak = (1..KING_SPEED_SAMPLES)
+            .map(|i| speed_density(max_speed * i as f64 / KING_SPEED_SAMPLES as f64))
+            .fold(0.0, f64::max) * 1.1;
+        let speed = if peak > 0.0 {
+            loop {
+                let x = rng.gen::<f64>() * max_speed;
+                if rng.gen::<f64>() * peak < speed_density(x) {
+                    break x;
+                }
+            }
+        }
+        else {
+            0.0
+        };
+
+        (random_projected_direction(rng) * (radius * core_radius),
+         random_projected_direction(rng) * (speed * sigma))
+    }
+}
+
+/// The density of a King model at a dimensionless potential W, up to a constant factor, found by
+/// integrating its distribution function over velocity.
+fn king_density(potential: f64) -> f64 {
+    if potential <= 0.0 {
+        return 0.0;
+    }
+
+    // rho(W) = e^W erf(sqrt W) - sqrt(4W / pi) (1 + 2W / 3), but it's integrated directly with
+    // Simpson's rule as the closed form loses precision near the tidal radius.
+    const STEPS: usize = 64;
+    let max_speed = f64::sqrt(2.0 * potential);
+    let h = max_speed / STEPS as f64;
+    let integrand = |x: f64| x * x * (f64::exp(potential - x * x / 2.0) - 1.0);
+    let sum: f64 = (0..=STEPS)
+        .map(|i| {
+            let weight = match i {
+                0 => 1.0,
+                i if i == STEPS => 1.0,
+                i if i % 2 == 1 => 4.0,
+                _ => 2.0,
+            };
+            weight * integrand(i as f64 * h)
+        })
+        .sum();
+
+    sum * h / 3.0
+}
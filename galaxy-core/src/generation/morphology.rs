@@ -0,0 +1,243 @@
+use std::f64::consts::PI;
+
+use rand::{Rng, RngCore};
+use rand_distr::{Distribution, Gamma};
+
+use crate::config::{GalaxyType, GenerationConfig, SimulationConfig};
+use crate::density::DensityField;
+use crate::simulation::StarComponent;
+use crate::types::Vec2d;
+
+use super::models::{plummer_star, KingModel};
+use super::{gaussian, Component, Motion, PlacedStar, Population};
+
+/// The number of times to try placing a star in the density field before giving up and placing it
+/// anywhere, so that a field that is almost empty can't hang generation.
+const MAX_DENSITY_ATTEMPTS: usize = 1000;
+
+/// The main body of the galaxy, shaped according to its type: a disk, spiral arms, a bar, the
+/// spheroid of an elliptical, or a star cluster model. Disks rotate, while ellipticals and clusters
+/// are supported by the random motions of their stars.
+pub struct Morphology<'a> {
+    generation: &'a GenerationConfig,
+    gravitational_constant: f64,
+    density: Option<DensityField>,
+
+    /// The integrated King model, if the galaxy is one.
+    king_model: Option<KingModel>,
+}
+
+impl<'a> Morphology<'a> {
+    /// Create the main body described by the config.
+    pub fn new(generation: &'a GenerationConfig, simulation: &SimulationConfig) -> Self {
+        let density = generation.density.enabled.then(|| DensityField::new(&generation.density));
+        let king_model = (generation.galaxy_type == GalaxyType::King)
+            .then(|| KingModel::new(generation.king.central_potential));
+
+        // Stars beyond twice the galaxy's radius are outside the simulation's bounds.
+        if let Some(king_model) = &king_model {
+            let tidal_radius = king_model.tidal_radius() * generation.king.core_radius;
+            if tidal_radius > generation.galaxy_radius() * 2.0 {
+                log::warn!("The King model's tidal radius of {tidal_radius:.0} pc is larger than the simulation's bounds, \
+                            increase the galaxy diameter to contain it");
+            }
+        }
+
+        Self { generation, gravitational_constant: simulation.gravitational_constant, density, king_model }
+    }
+
+    /// Generate the position of a star, and how it should move. The total mass of the main body is
+    /// needed for the cluster models' velocities.
+    fn place_star(&self, total_mass: f64, rng: &mut dyn RngCore) -> (Vec2d, Motion) {
+        let generation = self.generation;
+        match generation.galaxy_type {
+            GalaxyType::Uniform => (uniform_position(generation, rng), Motion::Circular),
+            GalaxyType::Disk => (disk_position(generation, rng), Motion::Circular),
+            GalaxyType::Spiral => (spiral_position(generation, 0.0, rng), Motion::Circular),
+            GalaxyType::BarredSpiral => {
+                if rng.gen::<f64>() < generation.bar.star_fraction {
+                    let (position, direction) = bar_position(generation, rng);
+                    (position, Motion::Streaming(direction))
+                }
+                else {
+                    (spiral_position(generation, generation.bar.length / 2.0, rng), Motion::Circular)
+                }
+            },
+            GalaxyType::Elliptical => (elliptical_position(generation, rng), Motion::Random),
+            GalaxyType::Plummer => {
+                let (position, velocity) =
+                    plummer_star(generation.plummer.radius, total_mass, self.gravitational_constant, rng);
+                (position, Motion::Fixed(velocity))
+            },
+            GalaxyType::King => {
+                let king_model = self.king_model.as_ref().expect("King model not integrated");
+                let (position, velocity) =
+                    king_model.sample_star(generation.king.core_radius, total_mass, self.gravitational_constant, rng);
+                (position, Motion::Fixed(velocity))
+            },
+        }
+    }
+}
+
+impl<'a> Component for Morphology<'a> {
+    fn kind(&self) -> StarComponent {
+        StarComponent::Disk
+    }
+
+    fn place_stars(&self, rng: &mut dyn RngCore, stars: &mut Vec<PlacedStar>) {
+        let generation = self.generation;
+
+        // Generate the masses first, as the cluster models' velocities depend on the total mass.
+        let masses: Vec<f64> = (0..generation.star_count)
+            .map(|_| rng.gen_range(generation.star_mass_min..generation.star_mass_max))
+            .collect();
+        let total_mass = masses.iter().sum();
+
+        for mass in masses {
+            // Generate position, rejecting stars in low density regions of the density field if
+            // there is one.
+            let (mut position, mut motion) = self.place_star(total_mass, rng);
+            if let Some(density) = &self.density {
+                for _ in 0..MAX_DENSITY_ATTEMPTS {
+                    if rng.gen::<f64>() < density.sample(position) {
+                        break;
+                    }
+                    (position, motion) = self.place_star(total_mass, rng);
+                }
+            }
+
+            // Send some of the disk's stars around the other way. The random number is only drawn if
+            // there's a counter-rotating component, so existing seeds generate the same galaxies.
+            let counter_rotating_fraction = generation.disk.counter_rotating_fraction;
+            if matches!(motion, Motion::Circular) && counter_rotating_fraction > 0.0
+                && rng.gen::<f64>() < counter_rotating_fraction
+            {
+                motion = Motion::CounterRotating;
+            }
+
+            // Ellipticals and the cluster models are spheroids, the rest are disks.
+            let population = match generation.galaxy_type {
+                GalaxyType::Elliptical | GalaxyType::Plummer | GalaxyType::King => Population::Spheroid,
+                _ => Population::Disk,
+            };
+
+            stars.push(PlacedStar { position, mass, motion, population });
+        }
+    }
+}
+
+/// Generate a position uniformly distributed in a square the size of the galaxy.
+fn uniform_position<R: Rng + ?Sized>(generation: &GenerationConfig, rng: &mut R) -> Vec2d {
+    let galaxy_radius = generation.galaxy_radius();
+    let position_bounds = (-galaxy_radius)..galaxy_radius;
+    Vec2d::new(rng.gen_range(position_bounds.clone()), rng.gen_range(position_bounds))
+}
+
+/// Generate a position in an axisymmetric exponential disk.
+fn disk_position<R: Rng + ?Sized>(generation: &GenerationConfig, rng: &mut R) -> Vec2d {
+    let radius = disk_radius(generation, 0.0, rng);
+    let angle = rng.gen_range(0.0..(2.0 * PI));
+    Vec2d::new(f64::cos(angle) * radius, f64::sin(angle) * radius)
+}
+
+/// Generate a radius following the exponential disk profile, between `inner_radius` and the edge of
+/// the galaxy.
+fn disk_radius<R: Rng + ?Sized>(generation: &GenerationConfig, inner_radius: f64, rng: &mut R) -> f64 {
+    /// The number of times to try sampling a radius in range before falling back to picking one
+    /// uniformly by area, in case the range is far out in the disk's tail.
+    const MAX_ATTEMPTS: usize = 1000;
+
+    let outer_radius = generation.galaxy_radius();
+
+    // The number of stars at each radius is proportional to r e^(-r / h), which is a gamma
+    // distribution. Stars outside the range are rejected and resampled.
+    if let Ok(gamma) = Gamma::new(2.0, generation.disk.scale_length) {
+        for _ in 0..MAX_ATTEMPTS {
+            let radius = gamma.sample(rng);
+            if (inner_radius..=outer_radius).contains(&radius) {
+                return radius;
+            }
+        }
+    }
+
+    let inner_radius_squared = inner_radius * inner_radius;
+    let outer_radius_squared = outer_radius * outer_radius;
+    f64::sqrt(inner_radius_squared + rng.gen::<f64>() * (outer_radius_squared - inner_radius_squared))
+}
+
+/// Generate a position on one of the arms of a logarithmic spiral. Each arm follows
+/// `r = r0 e^(theta * tan(pitch))`, rotated evenly around the center, and stars are scattered across
+/// the arm with a gaussian of standard deviation `arm_width`. The arms start at `inner_radius` on
+/// the x axis, where the ends of the bar are in barred spirals.
+fn spiral_position<R: Rng + ?Sized>(generation: &GenerationConfig, inner_radius: f64, rng: &mut R) -> Vec2d {
+    let spiral = &generation.spiral;
+
+    // Pick a radius from the disk between the inner radius and the edge of the galaxy.
+    let radius = disk_radius(generation, inner_radius, rng);
+
+    // Find the angle of the arm at that radius.
+    let arm = rng.gen_range(0..spiral.arm_count.max(1));
+    let arm_angle = spiral_arm_angle(generation, inner_radius, radius, arm);
+
+    // Scatter the star across the arm.
+    let offset = gaussian(spiral.arm_width, rng);
+    let angle = arm_angle + offset / radius.max(1.0);
+
+    Vec2d::new(f64::cos(angle) * radius, f64::sin(angle) * radius)
+}
+
+/// The angle in radians of the center of a spiral arm at a radius. The arms are logarithmic spirals
+/// starting at `inner_radius`, evenly spaced around the galaxy.
+pub fn spiral_arm_angle(generation: &GenerationConfig, inner_radius: f64, radius: f64, arm: u32) -> f64 {
+    let spiral = &generation.spiral;
+    let arm_count = spiral.arm_count.max(1);
+    let pitch_angle = spiral.pitch_angle.to_radians();
+    f64::ln(radius.max(1.0) / inner_radius.max(1.0)) / f64::tan(pitch_angle)
+        + 2.0 * PI * arm as f64 / arm_count as f64
+}
+
+/// Generate a position in the central bar of a barred spiral, returning it along with the direction
+/// the star moves in. The bar lies along the x axis and its stars are spread uniformly over an
+/// ellipse, streaming around it on elongated orbits in the same direction as the rest of the
+/// galaxy rotates.
+fn bar_position<R: Rng + ?Sized>(generation: &GenerationConfig, rng: &mut R) -> (Vec2d, Vec2d) {
+    let bar = &generation.bar;
+    let semi_major_axis = bar.length / 2.0;
+
+    // Pick a point uniformly by area in the ellipse, as the parametric angle around the ellipse
+    // and the fraction of the way out from the center.
+    let scale = semi_major_axis * f64::sqrt(rng.gen::<f64>());
+    let angle = rng.gen_range(0.0..(2.0 * PI));
+    let position = Vec2d::new(f64::cos(angle) * scale, f64::sin(angle) * scale * bar.axis_ratio);
+
+    // Move along the tangent of the ellipse through this point, clockwise like the disk.
+    let tangent = Vec2d::new(f64::sin(angle), -f64::cos(angle) * bar.axis_ratio);
+    let tangent_length = f64::sqrt(tangent.x * tangent.x + tangent.y * tangent.y);
+
+    (position, tangent / tangent_length)
+}
+
+/// Generate a position in an elliptical galaxy, following a de Vaucouleurs (`R^1/4`) surface
+/// density profile.
+fn elliptical_position<R: Rng + ?Sized>(generation: &GenerationConfig, rng: &mut R) -> Vec2d {
+    /// The constant in the de Vaucouleurs profile that makes the effective radius contain half of
+    /// the light, `exp(-b (R/Re)^(1/4))`.
+    const DE_VAUCOULEURS_B: f64 = 7.669;
+
+    let elliptical = &generation.elliptical;
+
+    // With x = b (R/Re)^(1/4), the number of stars at each x is proportional to x^7 e^-x, which is
+    // a gamma distribution. Stars outside the galaxy's radius are rejected and resampled.
+    let gamma = Gamma::new(8.0, 1.0).unwrap();
+    let radius = loop {
+        let x: f64 = gamma.sample(rng);
+        let radius = elliptical.effective_radius * (x / DE_VAUCOULEURS_B).powi(4);
+        if radius <= generation.galaxy_radius() {
+            break radius;
+        }
+    };
+
+    // Squash the circle into an ellipse.
+    let angle = rng.gen_range(0.0..(2.0 * PI));
+    Vec2d::new(f64::cos(angle) * radius, f64::sin(angle) * radius * elliptical.axis_ratio)
+}
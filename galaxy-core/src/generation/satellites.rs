@@ -0,0 +1,50 @@
+use std::f64::consts::PI;
+
+use rand::{Rng, RngCore};
+
+use crate::config::{GenerationConfig, SimulationConfig};
+use crate::simulation::StarComponent;
+use crate::types::Vec2d;
+
+use super::{place_plummer_cluster, Component, PlacedStar, PlummerCluster};
+
+/// Dwarf satellite galaxies on eccentric orbits around the galaxy, which get tidally stripped into
+/// streams as they pass close to it. Each satellite is a Plummer sphere starting at the far end of
+/// its orbit.
+pub struct Satellites<'a> {
+    generation: &'a GenerationConfig,
+    gravitational_constant: f64,
+}
+
+impl<'a> Satellites<'a> {
+    /// Create the satellites described by the config.
+    pub fn new(generation: &'a GenerationConfig, simulation: &SimulationConfig) -> Self {
+        Self { generation, gravitational_constant: simulation.gravitational_constant }
+    }
+}
+
+impl<'a> Component for Satellites<'a> {
+    fn kind(&self) -> StarComponent {
+        StarComponent::Satellite
+    }
+
+    fn place_stars(&self, rng: &mut dyn RngCore, stars: &mut Vec<PlacedStar>) {
+        let satellites = &self.generation.satellites;
+
+        for _ in 0..satellites.count {
+            let distance = rng.gen_range(satellites.min_distance..=satellites.max_distance.max(satellites.min_distance));
+            let angle = rng.gen_range(0.0..(2.0 * PI));
+            let center = Vec2d::new(f64::cos(angle) * distance, f64::sin(angle) * distance);
+
+            place_plummer_cluster(&PlummerCluster {
+                center,
+                eccentricity: satellites.eccentricity,
+                star_count: satellites.star_count,
+                star_mass_range: self.generation.star_mass_min..self.generation.star_mass_max,
+                plummer_radius: satellites.plummer_radius,
+                gravitational_constant: self.gravitational_constant,
+                core_mass: satellites.core_mass,
+            }, rng, stars);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::f64::consts::PI;
+
+use rand::{Rng, RngCore};
+
+use crate::config::GenerationConfig;
+use crate::simulation::StarComponent;
+use crate::types::Vec2d;
+
+use super::{Component, Motion, PlacedStar, Population};
+
+/// A cloud of massless tracer particles spread evenly over a disk, each on a circular orbit about
+/// the galaxy's center, so that the cloud is sheared out as it shows how the galaxy flows.
+pub struct TracerCloud<'a> {
+    generation: &'a GenerationConfig,
+}
+
+impl<'a> TracerCloud<'a> {
+    /// Create the tracer cloud described by the generation config.
+    pub fn new(generation: &'a GenerationConfig) -> Self {
+        Self { generation }
+    }
+}
+
+impl<'a> Component for TracerCloud<'a> {
+    fn kind(&self) -> StarComponent {
+        StarComponent::Tracer
+    }
+
+    fn place_stars(&self, rng: &mut dyn RngCore, stars: &mut Vec<PlacedStar>) {
+        let tracers = &self.generation.tracers;
+        let center = Vec2d::new(tracers.center_x, tracers.center_y);
+
+        for _ in 0..tracers.count {
+            // The square root spreads the tracers evenly over the area of the disk.
+            let radius = tracers.radius * rng.gen::<f64>().sqrt();
+            let angle = rng.gen_range(0.0..(2.0 * PI));
+            let position = center + Vec2d::new(angle.cos(), angle.sin()) * radius;
+            stars.push(PlacedStar { position, mass: 0.0, motion: Motion::Circular, population: Population::DarkMatter });
+        }
+    }
+}
@@ -19,7 +19,7 @@ pub const MAX_DEPTH: u8 = 16;
 /// number of nodes in the current level.
 /// A 32-bit index lets us store 16 full levels of quadtree, or 1_431_655_765 nodes this way
 /// (4^0 + 4^1 + ... + 4^15).
-pub const _DEPTH_OFFSETS: [usize; 16] = [0, 1, 5, 21, 85, 341, 1365, 5461, 21845, 87381, 349525, 1398101,
+pub const DEPTH_OFFSETS: [usize; 16] = [0, 1, 5, 21, 85, 341, 1365, 5461, 21845, 87381, 349525, 1398101,
                                         5592405, 22369621, 89478485, 357913941];
 
 impl HilbertIndex {
@@ -95,13 +95,13 @@ impl HilbertIndex {
     }
 
     /// Calculate the linear array index of this hilbert index at this quadtree depth.
-    pub fn _array_index(&self) -> usize {
+    pub fn array_index(&self) -> usize {
         let depth = self.depth();
         if depth >= MAX_DEPTH {
             panic!("Hilbert Index depth of {} is greater than maximum depth of {}", depth, MAX_DEPTH);
         }
 
-        _DEPTH_OFFSETS[depth as usize] + self.index() as usize
+        DEPTH_OFFSETS[depth as usize] + self.index() as usize
     }
 
     /// Get the children of this hilbert index, i.e. the four nodes in the same location as this
@@ -118,17 +118,23 @@ impl HilbertIndex {
         ]
     }
 
+    /// Get the size of the node referred to by this hilbert index, assuming a given root node's
+    /// bounds. All nodes at the same depth are the same size.
+    pub fn node_size(&self, root_min: Vec2d, root_max: Vec2d) -> Vec2d {
+        // Get the node scale at this depth in a normalized range, where the root node is scale 1,
+        // the nodes under it scale 0.5, etc.
+        let node_scale = 1.0 / (1 << self.depth()) as f64;
+
+        (root_max - root_min) * node_scale
+    }
+
     /// Get the bounds referred to by this hilbert index, assuming a given root node's bounds.
     pub fn bounds(&self, root_min: Vec2d, root_max: Vec2d) -> (Vec2d, Vec2d) {
         // Get the x, y coordinates of this node.
         let (x, y) = self.to_xy();
 
-        // Get the node scale at this depth in a normalized range, where the root node is scale 1,
-        // the nodes under it scale 0.5, etc.
-        let node_scale = 1.0 / (1 << self.depth()) as f64;
-
         // The actual dimensions of nodes at this depth.
-        let node_size = (root_max - root_min) * node_scale;
+        let node_size = self.node_size(root_min, root_max);
 
         let min = root_min + Vec2d::new(node_size.x * x as f64, node_size.y * y as f64);
         let max = min + node_size;
@@ -136,6 +142,13 @@ impl HilbertIndex {
         (min, max)
     }
 
+    /// Get the center of the node referred to by this hilbert index, assuming a given root node's
+    /// bounds.
+    pub fn center(&self, root_min: Vec2d, root_max: Vec2d) -> Vec2d {
+        let (min, max) = self.bounds(root_min, root_max);
+        (min + max) * 0.5
+    }
+
     /// Rotate/flip a quadrant appropriately.
     /// https://en.wikipedia.org/wiki/Hilbert_curve#Applications_and_mapping_algorithms
     fn rot(n: u32, x: &mut u32, y: &mut u32, rx: u32, ry: u32) {
@@ -292,6 +305,19 @@ mod test {
             (Vec2d::new(-32000.0, 40000.0), Vec2d::new(-8000.0, 64000.0)));
     }
 
+    #[test]
+    fn hilbert_node_size_and_center() {
+        let (root_min, root_max) = (Vec2d::new(-32000.0, -32000.0), Vec2d::new(64000.0, 64000.0));
+
+        assert_eq!(HilbertIndex(0, 0).node_size(root_min, root_max), Vec2d::new(96000.0, 96000.0));
+        assert_eq!(HilbertIndex(3, 1).node_size(root_min, root_max), Vec2d::new(48000.0, 48000.0));
+        assert_eq!(HilbertIndex(5, 2).node_size(root_min, root_max), Vec2d::new(24000.0, 24000.0));
+
+        assert_eq!(HilbertIndex(0, 0).center(root_min, root_max), Vec2d::new(16000.0, 16000.0));
+        assert_eq!(HilbertIndex(3, 1).center(root_min, root_max), Vec2d::new(40000.0, -8000.0));
+        assert_eq!(HilbertIndex(5, 2).center(root_min, root_max), Vec2d::new(-20000.0, 52000.0));
+    }
+
     quickcheck! {
         fn hilbert_from_xy_to_xy_reversible(input: ValidHilbertXYDepth) -> bool {
             match input {
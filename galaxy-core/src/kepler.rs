@@ -0,0 +1,59 @@
+//! Analytic two-body orbits, for bodies orbiting each other far too quickly to integrate step by
+//! step. Rather than integrating the orbit, it's advanced exactly by solving Kepler's equation,
+//! so it takes the same time however many orbits a step covers.
+
+use std::f64::consts::PI;
+
+use crate::types::Vec2d;
+
+/// The most iterations of Newton's method to take when solving Kepler's equation.
+const MAX_ITERATIONS: usize = 50;
+
+/// The change in eccentric anomaly below which Kepler's equation is considered solved.
+const TOLERANCE: f64 = 1e-12;
+
+/// Advance the position and velocity of a body relative to the one it orbits by a time in
+/// simulation time units, given the gravitational parameter `G (M + m)` of the pair in
+/// pc (km/s)^2. This uses the f and g functions of the orbit, so the result is exact for any time.
+/// Unbound bodies are moved in a straight line instead, as they don't stay close for long.
+pub fn advance(gravitational_parameter: f64, position: Vec2d, velocity: Vec2d, time_delta: f64) -> (Vec2d, Vec2d) {
+    let distance = position.x.hypot(position.y);
+    let speed_squared = velocity.x * velocity.x + velocity.y * velocity.y;
+    let inverse_semi_major_axis = 2.0 / distance - speed_squared / gravitational_parameter;
+    if distance == 0.0 || gravitational_parameter <= 0.0 || inverse_semi_major_axis <= 0.0 {
+        return (position + velocity * time_delta, velocity);
+    }
+
+    let semi_major_axis = 1.0 / inverse_semi_major_axis;
+    let mean_motion = f64::sqrt(gravitational_parameter * inverse_semi_major_axis.powi(3));
+
+    // The eccentricity times the cosine and sine of the starting eccentric anomaly.
+    let e_cos = 1.0 - distance * inverse_semi_major_axis;
+    let e_sin = (position.x * velocity.x + position.y * velocity.y)
+        / f64::sqrt(gravitational_parameter * semi_major_axis);
+
+    // Whole orbits bring the body back to where it started, so only the remainder matters.
+    let mean_anomaly_change = (mean_motion * time_delta).rem_euclid(2.0 * PI);
+
+    // Solve Kepler's equation for the change in eccentric anomaly with Newton's method.
+    let mut anomaly_change = mean_anomaly_change;
+    for _ in 0..MAX_ITERATIONS {
+        let (sin, cos) = anomaly_change.sin_cos();
+        let error = anomaly_change - e_cos * sin + e_sin * (1.0 - cos) - mean_anomaly_change;
+        let step = error / (1.0 - e_cos * cos + e_sin * sin);
+        anomaly_change -= step;
+        if step.abs() < TOLERANCE {
+            break;
+        }
+    }
+
+    let (sin, cos) = anomaly_change.sin_cos();
+    let f = 1.0 - semi_major_axis / distance * (1.0 - cos);
+    let g = (mean_anomaly_change - (anomaly_change - sin)) / mean_motion;
+    let new_position = position * f + velocity * g;
+
+    let new_distance = new_position.x.hypot(new_position.y);
+    let f_dot = -f64::sqrt(gravitational_parameter * semi_major_axis) / (new_distance * distance) * sin;
+    let g_dot = 1.0 - semi_major_axis / new_distance * (1.0 - cos);
+    (new_position, position * f_dot + velocity * g_dot)
+}
@@ -0,0 +1,37 @@
+//! The galaxy simulation core: stars, the quadtree, physics, config and checkpoints. This has no
+//! rendering dependencies, so it can be driven headlessly by the viewer, tests, or the Python
+//! bindings in `python/`.
+
+pub mod types;
+pub mod units;
+pub mod rng;
+pub mod config;
+pub mod simulation;
+pub mod star_world;
+pub mod evolution;
+pub mod kepler;
+pub mod three_body;
+pub mod validation;
+pub mod generation;
+pub mod density;
+pub mod dust;
+pub mod presets;
+pub mod quadtree;
+pub mod spatial;
+pub mod hilbert;
+pub mod tree_export;
+pub mod svg_export;
+pub mod checkpoint;
+pub mod arrow_export;
+pub mod rewind;
+pub mod trajectory;
+pub mod snapshot;
+pub mod shared_snapshot;
+pub mod triggers;
+pub mod catalog;
+pub mod scenario;
+pub mod metrics;
+pub mod sweep;
+pub mod ensemble;
+pub mod analysis;
+pub mod profiler;
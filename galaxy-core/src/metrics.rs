@@ -0,0 +1,254 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::simulation::{GalaxySim, Star, StarComponent};
+use crate::star_world::{Mass, Position, Species, Velocity};
+use crate::types::Vec2d;
+use crate::units::SolarMass;
+
+/// Metrics describing the state of the simulation after a step, for offline analysis. Energies
+/// are in `Msun km^2 s^-2`, momenta in `Msun km s^-1` and angular momenta in `Msun pc km s^-1`.
+#[derive(Clone, Debug, Serialize)]
+pub struct StepMetrics {
+    pub step: u64,
+    pub elapsed_myr: f64,
+    pub star_count: usize,
+    pub kinetic_energy: f64,
+    pub potential_energy: f64,
+    pub total_energy: f64,
+    pub momentum_x: f64,
+    pub momentum_y: f64,
+
+    /// The angular momentum of all the stars, and of the main components of the galaxy.
+    pub angular_momentum: f64,
+    pub disk_angular_momentum: f64,
+    pub bulge_angular_momentum: f64,
+    pub halo_angular_momentum: f64,
+    pub globular_cluster_angular_momentum: f64,
+    pub satellite_angular_momentum: f64,
+
+    /// The total number of stars that have left the simulation area so far.
+    pub escaped_count: usize,
+
+    pub tree_depth: u8,
+    pub quadtree_ms: f64,
+    pub mass_distribution_ms: f64,
+    pub integrate_ms: f64,
+    pub grid_ms: f64,
+}
+
+impl StepMetrics {
+    /// The CSV header, matching the field order of the struct.
+    const CSV_HEADER: &'static str = "step,elapsed_myr,star_count,kinetic_energy,potential_energy,\
+        total_energy,momentum_x,momentum_y,angular_momentum,disk_angular_momentum,bulge_angular_momentum,\
+        halo_angular_momentum,globular_cluster_angular_momentum,satellite_angular_momentum,escaped_count,\
+        tree_depth,quadtree_ms,mass_distribution_ms,integrate_ms,grid_ms";
+
+    /// Measure the current state of a simulation. The potential energy is calculated using the
+    /// quadtree built in the last step, so it's as approximate as the forces are.
+    pub fn measure(sim: &GalaxySim, step: u64) -> Self {
+        let mut momentum_x = 0.0;
+        let mut momentum_y = 0.0;
+        for (velocity, mass) in sim.stars().query::<(&Velocity, &Mass)>() {
+            momentum_x += mass.0.0 * velocity.0.x;
+            momentum_y += mass.0.0 * velocity.0.y;
+        }
+
+        let energy = Energy::measure(sim);
+        let (kinetic_energy, potential_energy) = (energy.kinetic(), energy.potential());
+        let angular_momentum = AngularMomentum::measure(&sim.stars().to_vec());
+
+        Self {
+            step,
+            elapsed_myr: sim.elapsed_myr(),
+            star_count: sim.stars().len(),
+            kinetic_energy,
+            potential_energy,
+            total_energy: kinetic_energy + potential_energy,
+            momentum_x,
+            momentum_y,
+            angular_momentum: angular_momentum.total,
+            disk_angular_momentum: angular_momentum.component(StarComponent::Disk),
+            bulge_angular_momentum: angular_momentum.component(StarComponent::Bulge),
+            halo_angular_momentum: angular_momentum.component(StarComponent::Halo),
+            globular_cluster_angular_momentum: angular_momentum.component(StarComponent::GlobularCluster),
+            satellite_angular_momentum: angular_momentum.component(StarComponent::Satellite),
+            escaped_count: sim.escaped_count,
+            tree_depth: sim.quadtree.depth(),
+            quadtree_ms: sim.timings.quadtree_ms,
+            mass_distribution_ms: sim.timings.mass_distribution_ms,
+            integrate_ms: sim.timings.integrate_ms,
+            grid_ms: sim.timings.grid_ms,
+        }
+    }
+
+    /// Format the metrics as a CSV row.
+    fn to_csv_row(&self) -> String {
+        format!("{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                self.step, self.elapsed_myr, self.star_count, self.kinetic_energy,
+                self.potential_energy, self.total_energy, self.momentum_x, self.momentum_y,
+                self.angular_momentum, self.disk_angular_momentum, self.bulge_angular_momentum,
+                self.halo_angular_momentum, self.globular_cluster_angular_momentum,
+                self.satellite_angular_momentum, self.escaped_count, self.tree_depth, self.quadtree_ms, self.mass_distribution_ms,
+                self.integrate_ms, self.grid_ms)
+    }
+}
+
+/// The angular momentum of the stars about the origin in `Msun pc km s^-1`, in total and for each
+/// component of the galaxy. It should be conserved, so drift exposes errors from the integrator or
+/// the tree's force approximation. Anticlockwise is positive, so galaxies generated rotating
+/// clockwise have negative angular momentum.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AngularMomentum {
+    pub total: f64,
+
+    /// The angular momentum of each component, in the order of `StarComponent::ALL`.
+    pub components: [f64; StarComponent::ALL.len()],
+}
+
+impl AngularMomentum {
+    /// Measure the angular momentum of a set of stars.
+    pub fn measure(stars: &[Star]) -> Self {
+        let mut angular_momentum = Self::default();
+        for star in stars {
+            let star_angular_momentum =
+                star.mass.0 * (star.position.x * star.velocity.y - star.position.y * star.velocity.x);
+            angular_momentum.total += star_angular_momentum;
+            angular_momentum.components[star.component as usize] += star_angular_momentum;
+        }
+        angular_momentum
+    }
+
+    /// The angular momentum of one component.
+    pub fn component(&self, component: StarComponent) -> f64 {
+        self.components[component as usize]
+    }
+}
+
+/// The kinetic and potential energy of the stars in `Msun km^2 s^-2`, for each component of the
+/// galaxy. Energy flows between the two as the galaxy evolves, while their sum should be conserved.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Energy {
+    /// The kinetic and potential energy of each component, in the order of `StarComponent::ALL`.
+    pub kinetic: [f64; StarComponent::ALL.len()],
+    pub potential: [f64; StarComponent::ALL.len()],
+}
+
+impl Energy {
+    /// Measure the energy of a simulation's stars. The potential energy is calculated using the
+    /// quadtree built in the last step, so it's as approximate as the forces are.
+    pub fn measure(sim: &GalaxySim) -> Self {
+        let mut energy = Self::default();
+        for (position, velocity, mass, species) in sim.stars().query::<(&Position, &Velocity, &Mass, &Species)>() {
+            let component = species.0 as usize;
+            let speed_squared = velocity.0.x * velocity.0.x + velocity.0.y * velocity.0.y;
+            energy.kinetic[component] += 0.5 * mass.0.0 * speed_squared;
+
+            // Each pair is counted twice when summing over all stars, hence the half.
+            let potential = GalaxySim::potential_at_point(&sim.quadtree, sim.config(), position.0);
+            energy.potential[component] += 0.5 * mass.0.0 * potential;
+        }
+        energy
+    }
+
+    /// The total kinetic energy.
+    pub fn kinetic(&self) -> f64 {
+        self.kinetic.iter().sum()
+    }
+
+    /// The total potential energy.
+    pub fn potential(&self) -> f64 {
+        self.potential.iter().sum()
+    }
+
+    /// The total energy.
+    pub fn total(&self) -> f64 {
+        self.kinetic() + self.potential()
+    }
+}
+
+/// The Lagrange radii of a set of stars, the radii about their center of mass containing each of
+/// the given fractions of their mass, in parsecs. How they change over time shows whether a system
+/// is contracting or expanding in its core and outskirts.
+pub fn lagrange_radii(stars: &[Star], fractions: &[f64]) -> Vec<f64> {
+    let total_mass = stars.iter().map(|star| star.mass).sum::<SolarMass>().0;
+    if total_mass <= 0.0 {
+        return vec![0.0; fractions.len()];
+    }
+
+    let center = stars.iter().fold(Vec2d::new(0.0, 0.0), |sum, star| sum + star.position * star.mass.0) / total_mass;
+    let mut distances: Vec<(f64, f64)> = stars.iter()
+        .map(|star| {
+            let offset = star.position - center;
+            (offset.x.hypot(offset.y), star.mass.0)
+        })
+        .collect();
+    distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    fractions.iter().map(|&fraction| {
+        let mut enclosed_mass = 0.0;
+        distances.iter()
+            .find(|&&(_, mass)| {
+                enclosed_mass += mass;
+                enclosed_mass >= fraction * total_mass
+            })
+            .or(distances.last())
+            .map_or(0.0, |&(distance, _)| distance)
+    }).collect()
+}
+
+/// The file format to write metrics in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetricsFormat {
+    Csv,
+    JsonLines,
+}
+
+/// Writes step metrics to a CSV or JSON lines file.
+pub struct MetricsWriter {
+    writer: BufWriter<File>,
+    format: MetricsFormat,
+}
+
+impl MetricsWriter {
+    /// Create a metrics file. The format is chosen by the extension, `.csv` for CSV and anything
+    /// else (e.g. `.jsonl`) for JSON lines.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        log::info!("Writing metrics to {}", path.display());
+
+        let format = match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => MetricsFormat::Csv,
+            _ => MetricsFormat::JsonLines,
+        };
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        if format == MetricsFormat::Csv {
+            writeln!(writer, "{}", StepMetrics::CSV_HEADER)?;
+        }
+
+        Ok(Self { writer, format })
+    }
+
+    /// Write a row of metrics.
+    pub fn write(&mut self, metrics: &StepMetrics) -> Result<(), Box<dyn Error>> {
+        match self.format {
+            MetricsFormat::Csv => writeln!(self.writer, "{}", metrics.to_csv_row())?,
+            MetricsFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, metrics)?;
+                self.writer.write_all(b"\n")?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Flush any buffered metrics to the file.
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, DensityConfig, GalaxyType, GenerationConfig, HaloConfig};
+
+/// A named template for a type of galaxy, bundling its size, morphology, masses and components.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    /// A large barred spiral like our own galaxy.
+    MilkyWay,
+
+    /// A larger, more massive spiral like M31.
+    Andromeda,
+
+    /// A small, clumpy, dark matter dominated disk with no ordered structure.
+    DwarfIrregular,
+
+    /// A small, dense elliptical like M32.
+    CompactElliptical,
+}
+
+impl Preset {
+    /// All the presets, in the order they're listed in the UI.
+    pub const ALL: [Preset; 4] = [Preset::MilkyWay, Preset::Andromeda, Preset::DwarfIrregular, Preset::CompactElliptical];
+
+    /// The name of the preset as written in config files and on the command line.
+    pub fn key(self) -> &'static str {
+        match self {
+            Preset::MilkyWay => "milky_way",
+            Preset::Andromeda => "andromeda",
+            Preset::DwarfIrregular => "dwarf_irregular",
+            Preset::CompactElliptical => "compact_elliptical",
+        }
+    }
+
+    /// The name of the preset as shown in the UI.
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::MilkyWay => "Milky Way-like",
+            Preset::Andromeda => "M31-like",
+            Preset::DwarfIrregular => "Dwarf irregular",
+            Preset::CompactElliptical => "Compact elliptical",
+        }
+    }
+
+    /// Apply the preset to a config, replacing all of the generation parameters apart from the
+    /// seed and star count, and the dark matter halo.
+    pub fn apply(self, config: &mut Config) {
+        let mut generation = GenerationConfig {
+            seed: config.generation.seed,
+            star_count: config.generation.star_count,
+            ..Default::default()
+        };
+        let mut halo = HaloConfig::default();
+
+        match self {
+            Preset::MilkyWay => {
+                generation.galaxy_type = GalaxyType::BarredSpiral;
+                generation.galaxy_diameter = 32408.0;
+                generation.supermassive_black_hole_mass = 4.3e6;
+                generation.disk.scale_length = 2600.0;
+                generation.bar.length = 10000.0;
+                generation.bar.star_fraction = 0.2;
+                generation.spiral.pitch_angle = 12.0;
+                generation.dispersion.toomre_q = Some(1.5);
+                generation.globular_clusters.count = 4;
+                halo.mass = 1e12;
+                halo.scale_radius = 25000.0;
+            },
+            Preset::Andromeda => {
+                generation.galaxy_type = GalaxyType::Spiral;
+                generation.galaxy_diameter = 46000.0;
+                generation.supermassive_black_hole_mass = 1.4e8;
+                generation.disk.scale_length = 5300.0;
+                generation.spiral.pitch_angle = 8.0;
+                generation.spiral.arm_width = 900.0;
+                generation.dispersion.toomre_q = Some(1.5);
+                generation.globular_clusters.count = 6;
+                generation.globular_clusters.min_distance = 12000.0;
+                generation.globular_clusters.max_distance = 23000.0;
+                halo.mass = 1.5e12;
+                halo.scale_radius = 30000.0;
+            },
+            Preset::DwarfIrregular => {
+                generation.galaxy_type = GalaxyType::Disk;
+                generation.galaxy_diameter = 4000.0;
+                generation.star_mass_max = 5.0;
+                generation.supermassive_black_hole_mass = 1e4;
+                generation.disk.scale_length = 800.0;
+                generation.dispersion.toomre_q = Some(2.0);
+                generation.density = DensityConfig {
+                    enabled: true,
+                    feature_size: 800.0,
+                    ..Default::default()
+                };
+                halo.mass = 1e10;
+                halo.scale_radius = 3000.0;
+            },
+            Preset::CompactElliptical => {
+                generation.galaxy_type = GalaxyType::Elliptical;
+                generation.galaxy_diameter = 2000.0;
+                generation.star_mass_max = 2.0;
+                generation.supermassive_black_hole_mass = 2.5e6;
+                generation.elliptical.effective_radius = 150.0;
+                generation.elliptical.axis_ratio = 0.8;
+            },
+        }
+
+        config.generation = generation;
+        config.simulation.halo = halo;
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Preset::ALL.into_iter()
+            .find(|preset| preset.key() == s)
+            .ok_or_else(|| {
+                let keys: Vec<&str> = Preset::ALL.iter().map(|preset| preset.key()).collect();
+                format!("Unknown preset {s}, expected one of: {}", keys.join(", "))
+            })
+    }
+}
@@ -0,0 +1,156 @@
+//! A lightweight hierarchical profiler. Code is timed by opening spans around it with the
+//! `profile_scope!` and `profile_span!` macros, and spans opened while another is open are nested
+//! inside it. The spans of each thread are recorded separately, between `begin_frame` and
+//! `end_frame`, and outside of a frame they're timed but not recorded, so headless runs that never
+//! begin a frame don't accumulate them.
+
+use std::cell::RefCell;
+use std::time::Instant;
+
+/// The most spans recorded in a frame. Any more are timed but dropped, so that a frame that steps
+/// the simulation many times doesn't record an unbounded number of them.
+const MAX_SPANS: usize = 4096;
+
+/// A span of time a named piece of code took, within a frame.
+#[derive(Clone, Debug)]
+pub struct Span {
+    pub name: &'static str,
+
+    /// How many spans it's nested inside.
+    pub depth: usize,
+
+    /// When it started after the start of the frame, and how long it took, in milliseconds.
+    pub start_ms: f64,
+    pub duration_ms: f64,
+}
+
+/// The spans recorded on a thread, in the order they were opened.
+struct Recorder {
+    /// When the current frame started, or None if there isn't one.
+    frame_start: Option<Instant>,
+
+    spans: Vec<Span>,
+
+    /// The number of spans that are open.
+    depth: usize,
+
+    /// The spans of the last completed frame.
+    last_frame: Vec<Span>,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Recorder> = const { RefCell::new(Recorder {
+        frame_start: None,
+        spans: Vec::new(),
+        depth: 0,
+        last_frame: Vec::new(),
+    }) };
+}
+
+/// Start recording the spans of a frame on this thread, discarding any of an unfinished one.
+pub fn begin_frame() {
+    RECORDER.with(|recorder| {
+        let mut recorder = recorder.borrow_mut();
+        recorder.frame_start = Some(Instant::now());
+        recorder.spans.clear();
+    });
+}
+
+/// Finish recording the spans of the frame on this thread, keeping them as the last frame's.
+pub fn end_frame() {
+    RECORDER.with(|recorder| {
+        let mut recorder = recorder.borrow_mut();
+        if recorder.frame_start.take().is_some() {
+            recorder.last_frame = std::mem::take(&mut recorder.spans);
+        }
+    });
+}
+
+/// The spans of the last completed frame on this thread, in the order they were opened.
+pub fn last_frame() -> Vec<Span> {
+    RECORDER.with(|recorder| recorder.borrow().last_frame.clone())
+}
+
+/// An open span, which is closed when it's finished or dropped.
+pub struct SpanGuard {
+    start: Instant,
+
+    /// The index of the span in the frame's spans, or None if it isn't recorded.
+    index: Option<usize>,
+
+    finished: bool,
+}
+
+impl SpanGuard {
+    /// Open a span with the given name, nested inside any that are already open.
+    pub fn enter(name: &'static str) -> Self {
+        let start = Instant::now();
+        let index = RECORDER.with(|recorder| {
+            let mut recorder = recorder.borrow_mut();
+            let depth = recorder.depth;
+            recorder.depth += 1;
+
+            match recorder.frame_start {
+                Some(frame_start) if recorder.spans.len() < MAX_SPANS => {
+                    recorder.spans.push(Span {
+                        name,
+                        depth,
+                        start_ms: start.duration_since(frame_start).as_secs_f64() * 1000.0,
+                        duration_ms: 0.0,
+                    });
+                    Some(recorder.spans.len() - 1)
+                },
+                _ => None,
+            }
+        });
+
+        Self { start, index, finished: false }
+    }
+
+    /// Close the span, returning how long it took in milliseconds.
+    pub fn finish(mut self) -> f64 {
+        self.close()
+    }
+
+    fn close(&mut self) -> f64 {
+        let duration_ms = self.start.elapsed().as_secs_f64() * 1000.0;
+        self.finished = true;
+
+        RECORDER.with(|recorder| {
+            let mut recorder = recorder.borrow_mut();
+            recorder.depth = recorder.depth.saturating_sub(1);
+
+            // The frame may have ended, and another begun, while the span was open.
+            if let Some(span) = self.index.and_then(|index| recorder.spans.get_mut(index)) {
+                span.duration_ms = duration_ms;
+            }
+        });
+
+        duration_ms
+    }
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.close();
+        }
+    }
+}
+
+/// Time the rest of the enclosing scope as a span with the given name.
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_span = $crate::profiler::SpanGuard::enter($name);
+    };
+}
+
+/// Open a span with the given name, returning a guard that closes it when it's finished or
+/// dropped. `finish` returns how long it took, for code that also reports its own timings.
+#[macro_export]
+macro_rules! profile_span {
+    ($name:expr) => {
+        $crate::profiler::SpanGuard::enter($name)
+    };
+}
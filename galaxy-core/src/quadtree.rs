@@ -0,0 +1,754 @@
+use std::collections::{BinaryHeap, HashMap};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
+use std::{error::Error, collections::VecDeque};
+
+use crate::types::Vec2d;
+use crate::hilbert;
+use crate::hilbert::HilbertIndex;
+
+/// TODO: now that the quadtree maintains a map of the current leaf node location of each item, when
+/// updating items we could automatically check if they've moved outside of their current parent
+/// node bounds and move them appropriately.
+///
+/// TODO: I think it's also good if the tree itself is an actual tree data structure, and refers to
+/// nodes only by this index. That way the tree structure itself can be sparse without using
+/// potentially an insane amount of memory for deep trees (for example 16 levels deep should be
+/// reasonable as that results in about a 1 parsec grid size on galactic scales). Currently a tree
+/// this deep uses many gigabytes of memory, even with the block size above.
+///
+/// TODO: finally, it might also be a good idea that the leaf nodes contain a list of items rather
+/// than a single item, and that we use a different heuristic for splitting, maybe number of nodes.
+/// This keeps our tree structure a reasonable size, but may make the results a little less
+/// accurate or the N-body algorithm a little less efficient.
+///
+/// TODO: we should probably handle fully removing nodes from the tree and remove them from the
+/// flat list too at some point (e.g. if the particle goes outside of the qaudtree bounds we
+/// probably need to do that unless we want to re-create it with new bounds.) For now these nodes
+/// just keep existing in the flat list but are not in the tree structure, which is a space leak.
+
+/// The type for node indexes into our flat list. The way our quadtree works is that we store all
+/// items in a flat list that also works as a lookup table for the item's current location in the
+/// tree, and this type indexes into that list.
+pub type NodeIndex = usize;
+
+/// The map type for the quadtree nodes. This uses a hasher with fixed keys rather than the default
+/// randomly seeded one, so that traversal order is the same between runs and sessions can be
+/// replayed deterministically.
+type NodeMap = HashMap<HilbertIndex, QuadtreeNode, BuildHasherDefault<DefaultHasher>>;
+
+/// A trait for objects with a position.
+pub trait Spatial {
+    fn xy(&self) -> &Vec2d;
+}
+
+/// A quadtree node item, either an internal node, a leaf node, or empty (i.e. a sparse region
+/// where we can stop traversal).
+#[derive(PartialEq)]
+pub enum QuadtreeNode {
+    Internal(NodeIndex),
+    Leaf(NodeIndex)
+}
+
+impl QuadtreeNode {
+    pub fn is_leaf(&self) -> bool {
+        match self {
+            QuadtreeNode::Leaf(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_internal(&self) -> bool {
+        match self {
+            QuadtreeNode::Internal(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl core::fmt::Debug for QuadtreeNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Internal(index) => f.debug_tuple("Internal").field(index).finish(),
+            Self::Leaf(index) => f.debug_tuple("Leaf").field(index).finish(),
+        }
+    }
+}
+
+/// A node to visit in a nearest item search, ordered so that the closest node is the greatest and
+/// is popped first from a max-heap.
+struct NodeDistance {
+    distance_squared: f64,
+    index: HilbertIndex,
+}
+
+impl PartialEq for NodeDistance {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for NodeDistance {}
+
+impl PartialOrd for NodeDistance {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NodeDistance {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.distance_squared.total_cmp(&self.distance_squared)
+    }
+}
+
+/// A sparse quadtree which is represented by a flat list of spatially indexed nodes. The leaf
+/// nodes own their contained items and the tree grows dynamically like a Vec. The type `T` is the
+/// type to be stored in the quadtree, and one is present in each leaf node of the tree. The
+/// optional type parameter `Internal` can be used to specify a type for internal nodes.
+pub struct Quadtree<T: Spatial, Internal = ()> {
+    /// The min of the bounds of the quadtree's root node, both values must be less than the ones
+    /// in Quadtree::max.
+    pub min: Vec2d,
+
+    /// The max of the bounds of the quadtree's root node, both values must be greater than the
+    /// ones in Quadtree::min.
+    pub max: Vec2d,
+
+    /// The exclusive maximum depth of the tree's nodes, which is at most `hilbert::MAX_DEPTH`.
+    /// Items that would have to be split any deeper share a leaf instead.
+    max_depth: u8,
+
+    /// Items stored in the quadtree as a flat list, along with the node index they're in.
+    pub items: Vec<T>,
+
+    /// The leaf node each item is in, by item index, or None for items that aren't in the tree.
+    /// This is only kept up to date by the quadtree's own methods, so it's out of date if `items`
+    /// is changed directly until the tree is rebuilt.
+    item_nodes: Vec<Option<HilbertIndex>>,
+
+    /// The next item in the same leaf node as each item, by item index, for items that are too
+    /// close together to be separated into different leaves.
+    next_in_leaf: Vec<Option<NodeIndex>>,
+
+    /// Internal node values in the quadtree.
+    internal: Vec<Option<Internal>>,
+
+    /// The quadtree nodes, as a flat list.
+    nodes: NodeMap,
+}
+
+impl<T: Spatial, Internal> Quadtree<T, Internal> {
+    /// Create a new quadtree with the given bounds.
+    pub fn new(min: Vec2d, max: Vec2d) -> Result<Self, Box<dyn Error>> {
+        Self::with_max_depth(min, max, hilbert::MAX_DEPTH)
+    }
+
+    /// Create a new quadtree with the given bounds and exclusive maximum depth, which must be
+    /// between 1 and `hilbert::MAX_DEPTH`, as the Hilbert indices can't represent deeper nodes.
+    pub fn with_max_depth(min: Vec2d, max: Vec2d, max_depth: u8) -> Result<Self, Box<dyn Error>> {
+        if max_depth == 0 || max_depth > hilbert::MAX_DEPTH {
+            return Err(format!("Quadtree maximum depth of {} is outside the range 1 to {}",
+                               max_depth, hilbert::MAX_DEPTH).into());
+        }
+
+        Ok(Self {
+            min,
+            max,
+            max_depth,
+            items: Vec::new(),
+            item_nodes: Vec::new(),
+            next_in_leaf: Vec::new(),
+            internal: Vec::new(),
+            nodes: NodeMap::default(),
+        })
+    }
+
+    /// The exclusive maximum depth of the tree's nodes.
+    pub fn max_depth(&self) -> u8 {
+        self.max_depth
+    }
+
+    pub fn get_item(&self, index: NodeIndex) -> Option<&T> {
+        self.items.get(index)
+    }
+
+    /// The index of the leaf node an item is in, or None if it isn't in the tree, such as items
+    /// added with add_unindexed.
+    pub fn node_of(&self, item: NodeIndex) -> Option<HilbertIndex> {
+        self.item_nodes.get(item).copied().flatten()
+    }
+
+    pub fn get_internal(&self, index: NodeIndex) -> Option<&Internal> {
+        self.internal.get(index)
+            .map(Option::as_ref)
+            .flatten()
+    }
+
+    pub fn set_internal(&mut self, index: NodeIndex, value: Option<Internal>) {
+        if self.internal.len() <= index {
+            panic!("Attempted to set the value of the nonexistent internal node {index:?}");
+        }
+        self.internal[index] = value;
+    }
+
+    pub fn get(&self, index: HilbertIndex) -> Option<&QuadtreeNode> {
+        self.nodes.get(&index)
+        //let index = index.array_index();
+        //let block = index / BLOCK_SIZE;
+        //let index_in_block = index - (block * BLOCK_SIZE);
+
+        //match self.blocks.get(block) {
+        //    Some(Some(block)) => block.get(index_in_block).unwrap_or(&QuadtreeNode::Empty),
+        //    _ => &QuadtreeNode::Empty,
+        //}
+    }
+
+    pub fn get_mut(&mut self, index: HilbertIndex) -> Option<&mut QuadtreeNode> {
+        self.nodes.get_mut(&index)
+        //let index = index.array_index();
+        //let block = index / BLOCK_SIZE;
+        //let index_in_block = index - (block * BLOCK_SIZE);
+
+        //match self.blocks.get_mut(block) {
+        //    Some(Some(block)) => block.get_mut(index_in_block),
+        //    _ => None,
+        //}
+    }
+
+    /// Safely insert a node at an index, resizing the internal vector if necessary.
+    fn safe_insert(&mut self, index: HilbertIndex, node: QuadtreeNode) {
+        self.nodes.insert(index, node);
+        //let index = index.array_index();
+        //let block = index / BLOCK_SIZE;
+        //let index_in_block = index - (block * BLOCK_SIZE);
+
+        //if self.blocks.len() <= block {
+        //    self.blocks.resize_with(block + 1, Default::default);
+        //}
+
+        //let block = self.blocks[block].get_or_insert_with(|| {
+        //    let mut block = Vec::new();
+        //    block.resize_with(BLOCK_SIZE, || QuadtreeNode::Empty);
+        //    block
+        //});
+
+        //block[index_in_block] = node;
+    }
+
+    /// Add a new item to the quadtree.
+    pub fn add(&mut self, item: T) {
+        // If item is outside the bounds of the quadtree, do nothing.
+        let pos = item.xy();
+        if !self.contains(pos) {
+            // TODO: re-add this?
+            //log::warn!("Item at position {pos:?} is outside of quadtree area, discarding");
+            return;
+        }
+
+        // Find an insert position for the item by recursively walking the tree.
+        let insert_pos = self.find_insert_pos(pos);
+
+        // Add item to internal list.
+        let index = self.items.len();
+        self.items.push(item);
+        self.item_nodes.push(None);
+        self.next_in_leaf.push(None);
+
+        // If it's empty, (e.g. in the case where this is the first item added to the tree), we can
+        // just add this node directly to the specified index.
+        if self.get(insert_pos).is_none() {
+            log::trace!("Inserting first node into tree at index {insert_pos:?}");
+            self.safe_insert(insert_pos, QuadtreeNode::Leaf(index));
+            self.item_nodes[index] = Some(insert_pos);
+            return;
+        }
+        // Otherwise, we have to split the current leaf node until the two items are in separate quadrants.
+        else {
+            self.split_and_insert(insert_pos, index);
+        }
+    }
+
+    /// Add an item to the list of items without placing it in a node, so that it doesn't change the
+    /// shape of the tree, and isn't found by walking it. As with add, items outside the bounds of
+    /// the quadtree are discarded.
+    pub fn add_unindexed(&mut self, item: T) {
+        if self.contains(item.xy()) {
+            self.items.push(item);
+            self.item_nodes.push(None);
+            self.next_in_leaf.push(None);
+        }
+    }
+
+    /// Whether a position is within the bounds of the quadtree.
+    fn contains(&self, pos: &Vec2d) -> bool {
+        pos.x >= self.min.x && pos.x <= self.max.x && pos.y >= self.min.y && pos.y <= self.max.y
+    }
+
+    /// Find the insert position of an item. The position might already contain another item, in
+    /// which case it will need to be split recursively until the items end up in different nodes.
+    fn find_insert_pos(&self, pos: &Vec2d) -> HilbertIndex {
+        // Start at the root and recursively search for an appropriate insert position (leaf node)
+        // to insert the item.
+        let mut cur_xy = (0, 0);
+        let mut cur_index = HilbertIndex(0, 0);
+        let mut cur_min = self.min;
+        let mut cur_max = self.max;
+
+        // If the current node is an internal node (as opposed to a leaf or an empty node), we have
+        // to keep searching.
+        loop {
+            // If the current node is empty or a leaf node, we can insert here (splitting if necessary).
+            let cur_node = self.get(cur_index);
+            if cur_node.is_none() || cur_node.unwrap().is_leaf() {
+                break;
+            }
+
+            // Find out which quadrant the item is and descend into the tree.
+            let cur_center = cur_max * 0.5 + cur_min * 0.5;
+            let (quadrant_x, quadrant_y) = Self::quadrant(&cur_center, pos);
+
+            // Descend into child.
+            cur_xy = (cur_xy.0 * 2 + quadrant_x, cur_xy.1 * 2 + quadrant_y);
+            cur_index = HilbertIndex::from_xy_depth(cur_xy, cur_index.depth() + 1);
+
+            // Update bounds.
+            if quadrant_x == 0 {
+                cur_max.x = cur_center.x;
+            }
+            else {
+                cur_min.x = cur_center.x;
+            }
+
+            if quadrant_y == 0 {
+                cur_max.y = cur_center.y;
+            }
+            else {
+                cur_min.y = cur_center.y;
+            }
+        }
+
+        cur_index
+    }
+
+    /// Split the specified leaf node and insert the new item. In order to do this, we need to
+    /// descend until the item in the existing leaf node and the new item are in different
+    /// quadrants, if necessary.
+    fn split_and_insert(&mut self, mut insert_pos: HilbertIndex, item: NodeIndex) {
+        // Get position of items.
+        let item_a = match self.get(insert_pos) {
+            Some(&QuadtreeNode::Leaf(index)) => index,
+            _ => panic!("Tried to split a non-leaf node")
+        };
+        let a_xy = *self.items[item_a].xy();
+        let b_xy = *self.items[item].xy();
+
+        // Items at exactly the same position can never be separated, and neither can items that
+        // would have to be split deeper than the maximum depth, so they share the leaf instead.
+        if a_xy == b_xy || insert_pos.depth() + 1 >= self.max_depth {
+            log::trace!("Adding item at {b_xy:?} to the leaf node at {insert_pos:?}");
+            self.add_to_leaf(insert_pos, item_a, item);
+            return;
+        }
+
+        // Otherwise, we have to split the current leaf node until the two items are in separate
+        // leaf nodes.
+        log::trace!("Splitting leaf node at {insert_pos:?}");
+
+        let internal_index = self.new_internal();
+
+        // Replace leaf node in tree with internal node, and prepare to insert our two nodes
+        // further down the tree.
+        let a = std::mem::replace(self.get_mut(insert_pos).expect("Nonexistent leaf node"),
+            QuadtreeNode::Internal(internal_index));
+        let b = QuadtreeNode::Leaf(item);
+
+        // Calculate bounds of current node.
+        let (mut x, mut y) = insert_pos.to_xy();
+        let (mut node_min, mut node_max) = insert_pos.bounds(self.min, self.max);
+
+        loop {
+            let insert_depth = insert_pos.depth() + 1;
+            let node_center = node_max * 0.5 + node_min * 0.5;
+            let quadrant_a = Self::quadrant(&node_center, &a_xy);
+            let quadrant_b = Self::quadrant(&node_center, &b_xy);
+
+            // If the two nodes are in different quadrants, we can just insert them.
+            if quadrant_a.0 != quadrant_b.0 || quadrant_a.1 != quadrant_b.1 {
+                let insert_depth = insert_pos.depth() + 1;
+
+                let index_a = HilbertIndex::from_xy_depth((x*2 + quadrant_a.0, y*2 + quadrant_a.1),
+                    insert_depth);
+
+                let index_b = HilbertIndex::from_xy_depth((x*2 + quadrant_b.0, y*2 + quadrant_b.1),
+                    insert_depth);
+
+                self.set_leaf_node(item_a, index_a);
+                self.item_nodes[item] = Some(index_b);
+                self.safe_insert(index_a, a);
+                self.safe_insert(index_b, b);
+                break;
+            }
+            // Otherwise, we have to insert a new internal node, and descend down the tree until we
+            // find an appropriate place for them.
+            else {
+                // Descend into quadrant, updating node position and bounds.
+                (x, y) = (x * 2 + quadrant_a.0, y * 2 + quadrant_a.1);
+                insert_pos = HilbertIndex::from_xy_depth((x, y), insert_depth);
+
+                if quadrant_a.0 == 0 {
+                    node_max.x = node_center.x;
+                }
+                else {
+                    node_min.x = node_center.x;
+                }
+
+                if quadrant_a.1 == 0 {
+                    node_max.y = node_center.y;
+                }
+                else {
+                    node_min.y = node_center.y;
+                }
+
+                // If the node's children would be deeper than the maximum depth, the items have to
+                // share this node as a leaf.
+                if insert_depth + 1 >= self.max_depth {
+                    self.safe_insert(insert_pos, a);
+                    self.set_leaf_node(item_a, insert_pos);
+                    self.add_to_leaf(insert_pos, item_a, item);
+                    break;
+                }
+
+                // Insert internal node here, and repeat. Each internal node needs its own value, or
+                // their mass distributions would overwrite each other.
+                let internal_index = self.new_internal();
+                self.safe_insert(insert_pos, QuadtreeNode::Internal(internal_index));
+            }
+        }
+    }
+
+    /// Add an item to the end of the list of items in a leaf node, given the first item in it.
+    fn add_to_leaf(&mut self, leaf: HilbertIndex, first_item: NodeIndex, item: NodeIndex) {
+        let last_item = self.leaf_items(first_item).last().unwrap_or(first_item);
+        self.next_in_leaf[last_item] = Some(item);
+        self.item_nodes[item] = Some(leaf);
+    }
+
+    /// Record that the items in a leaf node, given the first item in it, are now in another node.
+    fn set_leaf_node(&mut self, first_item: NodeIndex, leaf: HilbertIndex) {
+        let mut item = Some(first_item);
+        while let Some(index) = item {
+            self.item_nodes[index] = Some(leaf);
+            item = self.next_in_leaf[index];
+        }
+    }
+
+    /// The indices of the items in a leaf node, given the index in the node, which is the first of
+    /// them. A leaf usually has a single item, but items too close together to separate share one.
+    pub fn leaf_items(&self, first_item: NodeIndex) -> impl Iterator<Item = NodeIndex> + '_ {
+        std::iter::successors(Some(first_item), |&item| self.next_in_leaf.get(item).copied().flatten())
+    }
+
+    /// Allocate the value of a new internal node, returning its index.
+    /// TODO: this could also reuse the values of deleted internal nodes.
+    fn new_internal(&mut self) -> NodeIndex {
+        self.internal.push(None);
+        self.internal.len() - 1
+    }
+
+    /// Get the quadrant of a point with regards to the specified cell center.
+    fn quadrant(center: &Vec2d, point: &Vec2d) -> (u32, u32) {
+        (if point.x < center.x { 0 } else { 1 },
+         if point.y < center.y { 0 } else { 1 })
+    }
+
+    /// The depth of the deepest node in the tree, where the root node has depth 0.
+    pub fn depth(&self) -> u8 {
+        self.nodes.keys().map(|index| index.depth()).max().unwrap_or(0)
+    }
+
+    /// Call the callback with the index of every item within the given radius of a point. Nodes
+    /// whose bounds are entirely further away than the radius are skipped without being visited.
+    pub fn items_in_radius<F>(&self, center: Vec2d, radius: f64, mut f: F)
+        where F: FnMut(NodeIndex, &T)
+    {
+        let radius_squared = radius * radius;
+
+        let mut stack = vec![HilbertIndex(0, 0)];
+        while let Some(index) = stack.pop() {
+            match self.get(index) {
+                Some(&QuadtreeNode::Leaf(first_item)) => {
+                    for item_index in self.leaf_items(first_item) {
+                        let item = &self.items[item_index];
+                        let diff = *item.xy() - center;
+                        if diff.x * diff.x + diff.y * diff.y <= radius_squared {
+                            f(item_index, item);
+                        }
+                    }
+                },
+                Some(&QuadtreeNode::Internal(_)) => {
+                    // The closest point of the node to the center, which is the center itself if
+                    // it's inside the node.
+                    let (min, max) = index.bounds(self.min, self.max);
+                    let closest = Vec2d::new(center.x.clamp(min.x, max.x), center.y.clamp(min.y, max.y));
+                    let diff = closest - center;
+
+                    if diff.x * diff.x + diff.y * diff.y <= radius_squared {
+                        stack.extend(index.children());
+                    }
+                },
+                None => {},
+            }
+        }
+    }
+
+    /// The index of the item nearest to a point, or None if the tree is empty. Nodes are visited
+    /// closest first, and the search stops as soon as the next node is further away than the
+    /// nearest item found so far, so it only visits the nodes around the point.
+    pub fn nearest_item(&self, point: Vec2d) -> Option<NodeIndex> {
+        let mut nearest = None;
+        let mut nearest_distance_squared = f64::INFINITY;
+
+        let mut queue = BinaryHeap::new();
+        queue.push(NodeDistance { distance_squared: 0.0, index: HilbertIndex(0, 0) });
+        while let Some(NodeDistance { distance_squared, index }) = queue.pop() {
+            if distance_squared > nearest_distance_squared {
+                break;
+            }
+
+            match self.get(index) {
+                Some(&QuadtreeNode::Leaf(first_item)) => {
+                    for item_index in self.leaf_items(first_item) {
+                        let diff = *self.items[item_index].xy() - point;
+                        let item_distance_squared = diff.x * diff.x + diff.y * diff.y;
+                        if item_distance_squared < nearest_distance_squared {
+                            nearest = Some(item_index);
+                            nearest_distance_squared = item_distance_squared;
+                        }
+                    }
+                },
+                Some(&QuadtreeNode::Internal(_)) => {
+                    for child_index in index.children() {
+                        // The distance to the closest point of the child, which is zero if the
+                        // point is inside it.
+                        let (min, max) = child_index.bounds(self.min, self.max);
+                        let closest = Vec2d::new(point.x.clamp(min.x, max.x), point.y.clamp(min.y, max.y));
+                        let diff = closest - point;
+                        queue.push(NodeDistance {
+                            distance_squared: diff.x * diff.x + diff.y * diff.y,
+                            index: child_index,
+                        });
+                    }
+                },
+                None => {},
+            }
+        }
+
+        nearest
+    }
+
+    /// The indices of all the items in the subtree under a node, including the node itself, in
+    /// depth-first order. This is empty if the node doesn't exist.
+    pub fn items_under(&self, index: HilbertIndex) -> Vec<NodeIndex> {
+        let mut items = Vec::new();
+
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            match self.get(index) {
+                Some(&QuadtreeNode::Leaf(first_item)) => items.extend(self.leaf_items(first_item)),
+                Some(&QuadtreeNode::Internal(_)) => stack.extend(index.children()),
+                None => {},
+            }
+        }
+
+        items
+    }
+
+    /// Walk the quadtree depth-first, calling the specified callback with the hilbert index.
+    pub fn walk_indices<F>(&self, mut f: F)
+        where F: FnMut(HilbertIndex) -> ()
+    {
+        // Recursively walk the tree in depth-first order, visiting every node and calling the
+        // callback. I don't know if it's best to manually maintain a stack like this or use
+        // recursion, but I thought I'd try this for a change. Adds the root node to start with.
+        let mut stack = VecDeque::<HilbertIndex>::new();
+        stack.push_back(HilbertIndex(0, 0));
+
+        while let Some(hilbert_index) = stack.pop_back() {
+            // Get (x, y) of cell and depth in tree.
+            let depth = hilbert_index.depth();
+
+            // Call the callback
+            f(hilbert_index);
+
+            // Add children to stack.
+            if depth + 1 < self.max_depth {
+                for i in 0..4 {
+                    let child_index = HilbertIndex(hilbert_index.index() * 4 + i, depth + 1);
+                    let child_node = self.get(child_index);
+
+                    if child_node.is_some() {
+                        stack.push_back(child_index);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Walk the quadtree depth-first, calling the specified callback with the hilbert index and node.
+    pub fn walk_nodes<F>(&self, mut f: F)
+        where F: FnMut(HilbertIndex, &QuadtreeNode) -> ()
+    {
+        self.walk_indices(|index| {
+            if let Some(node) = self.get(index) {
+                f(index, node);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    /// A point to store in the quadtree in tests.
+    #[derive(Clone, Copy, Debug)]
+    struct Point(Vec2d);
+
+    impl Spatial for Point {
+        fn xy(&self) -> &Vec2d {
+            &self.0
+        }
+    }
+
+    /// Build a quadtree from the bounds -100 to 100 with the given points, as the simulation does
+    /// each step.
+    fn build(points: &[Vec2d], max_depth: u8) -> Quadtree<Point> {
+        let mut quadtree = Quadtree::with_max_depth(Vec2d::new(-100.0, -100.0), Vec2d::new(100.0, 100.0), max_depth)
+            .unwrap();
+        for &point in points {
+            quadtree.add(Point(point));
+        }
+        quadtree
+    }
+
+    /// Check that every item is in exactly one leaf, that it's the leaf `item_nodes` says it's in,
+    /// and that the leaf is inside the tree's maximum depth.
+    fn assert_consistent(quadtree: &Quadtree<Point>) {
+        let mut found = vec![0; quadtree.items.len()];
+        quadtree.walk_nodes(|index, node| {
+            if let &QuadtreeNode::Leaf(first_item) = node {
+                assert!(index.depth() < quadtree.max_depth());
+                for item in quadtree.leaf_items(first_item) {
+                    found[item] += 1;
+                    assert_eq!(quadtree.node_of(item), Some(index));
+                }
+            }
+        });
+        assert!(found.iter().all(|&count| count == 1), "Items not in exactly one leaf: {found:?}");
+    }
+
+    #[test]
+    fn coincident_items_share_a_leaf() {
+        let shared = Vec2d::new(12.5, -30.0);
+        let mut points = vec![shared; 100];
+        points.extend([Vec2d::new(-50.0, 50.0), Vec2d::new(12.5, -29.0), Vec2d::new(90.0, 90.0)]);
+
+        let quadtree = build(&points, hilbert::MAX_DEPTH);
+        assert_consistent(&quadtree);
+
+        let leaf = quadtree.node_of(0).unwrap();
+        let first_item = match quadtree.get(leaf) {
+            Some(&QuadtreeNode::Leaf(first_item)) => first_item,
+            node => panic!("Expected a leaf, found {node:?}"),
+        };
+        let mut shared_items: Vec<NodeIndex> = quadtree.leaf_items(first_item).collect();
+        shared_items.sort();
+        assert_eq!(shared_items, (0..100).collect::<Vec<_>>());
+
+        // Items are removed by rebuilding the tree without them, as the simulation does. Remove
+        // every other coincident item, then add them back at the end.
+        let (kept, removed): (Vec<usize>, Vec<usize>) = (0..points.len()).partition(|&i| i >= 100 || i % 2 == 0);
+        let mut quadtree = build(&kept.iter().map(|&i| points[i]).collect::<Vec<_>>(), hilbert::MAX_DEPTH);
+        assert_consistent(&quadtree);
+        assert_eq!(quadtree.leaf_items(0).count(), 50);
+
+        for &i in &removed {
+            quadtree.add(Point(points[i]));
+        }
+        assert_consistent(&quadtree);
+        assert_eq!(quadtree.items.len(), points.len());
+        assert_eq!(quadtree.leaf_items(0).count(), 100);
+        assert_eq!(quadtree.items_under(HilbertIndex(0, 0)).len(), points.len());
+    }
+
+    #[test]
+    fn depth_is_capped() {
+        // With a maximum depth of 4 the smallest cells are 25 wide, and these items are much
+        // closer together than that, as well as some being coincident.
+        let max_depth = 4;
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut points: Vec<Vec2d> = (0..50)
+            .map(|_| Vec2d::new(rng.gen_range(40.0..40.5), rng.gen_range(-3.0..-2.5)))
+            .collect();
+        points.extend([Vec2d::new(40.25, -2.75); 10]);
+        points.extend((0..50).map(|_| Vec2d::new(rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0))));
+
+        let quadtree = build(&points, max_depth);
+        assert_consistent(&quadtree);
+        assert!(quadtree.depth() < max_depth, "Depth {} isn't under the cap of {max_depth}", quadtree.depth());
+
+        for (i, &point) in points.iter().enumerate() {
+            let mut found = false;
+            quadtree.items_in_radius(point, 0.0, |item, _| found |= item == i);
+            assert!(found, "Item {i} at {point:?} wasn't found");
+        }
+        assert_eq!(quadtree.items_under(HilbertIndex(0, 0)).len(), points.len());
+
+        assert!(Quadtree::<Point>::with_max_depth(Vec2d::new(0.0, 0.0), Vec2d::new(1.0, 1.0), 0).is_err());
+        assert!(Quadtree::<Point>::with_max_depth(Vec2d::new(0.0, 0.0), Vec2d::new(1.0, 1.0), hilbert::MAX_DEPTH + 1).is_err());
+    }
+
+    #[test]
+    fn searches_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let points: Vec<Vec2d> = (0..500)
+            .map(|_| Vec2d::new(rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0)))
+            .collect();
+        let quadtree = build(&points, hilbert::MAX_DEPTH);
+        let distance_squared = |a: Vec2d, b: Vec2d| (a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y);
+
+        // Random queries, queries on the boundaries between cells at several depths, and queries
+        // outside of the root's bounds.
+        let mut queries: Vec<Vec2d> = (0..100)
+            .map(|_| Vec2d::new(rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0)))
+            .collect();
+        for boundary in [0.0, 50.0, -25.0, 12.5, -100.0, 100.0] {
+            queries.push(Vec2d::new(boundary, rng.gen_range(-100.0..100.0)));
+            queries.push(Vec2d::new(rng.gen_range(-100.0..100.0), boundary));
+            queries.push(Vec2d::new(boundary, boundary));
+        }
+        queries.extend([Vec2d::new(-150.0, 0.0), Vec2d::new(130.0, 170.0), Vec2d::new(0.0, -1000.0)]);
+
+        for query in queries {
+            let nearest = quadtree.nearest_item(query).unwrap();
+            let brute_force = points.iter().map(|&point| distance_squared(point, query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(distance_squared(points[nearest], query), brute_force, "Wrong nearest item to {query:?}");
+
+            for radius in [0.0, 5.0, 30.0, 300.0] {
+                let mut found = Vec::new();
+                quadtree.items_in_radius(query, radius, |item, _| found.push(item));
+                found.sort();
+                let expected: Vec<usize> = (0..points.len())
+                    .filter(|&i| distance_squared(points[i], query) <= radius * radius)
+                    .collect();
+                assert_eq!(found, expected, "Wrong items within {radius} of {query:?}");
+            }
+        }
+
+        let empty = build(&[], hilbert::MAX_DEPTH);
+        assert_eq!(empty.nearest_item(Vec2d::new(0.0, 0.0)), None);
+    }
+}
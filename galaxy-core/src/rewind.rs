@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+use std::error::Error;
+
+use crate::config::RewindConfig;
+use crate::simulation::{GalaxySim, Star};
+use crate::units::Myr;
+
+/// A state of the simulation recorded in the rewind buffer.
+pub struct RewindState {
+    /// The simulation time elapsed when the state was recorded, in simulation time units.
+    pub elapsed_time: f64,
+
+    /// The rate of the simulation when the state was recorded, in Myr per second.
+    pub myr_per_second: f64,
+
+    /// The number of stars that had escaped when the state was recorded.
+    pub escaped_count: usize,
+
+    /// The number of stars that had been torn apart by black holes when the state was recorded.
+    pub disrupted_count: usize,
+
+    /// The number of stars that had gone supernova when the state was recorded.
+    pub supernova_count: usize,
+
+    /// The number of stars that had been added to the galaxy when the state was recorded.
+    pub formed_count: usize,
+
+    /// All stars in the galaxy.
+    pub stars: Vec<Star>,
+}
+
+impl RewindState {
+    /// Record the current state of a simulation.
+    fn from_sim(sim: &GalaxySim) -> Self {
+        Self {
+            elapsed_time: sim.elapsed_time,
+            myr_per_second: sim.myr_per_second,
+            escaped_count: sim.escaped_count,
+            disrupted_count: sim.disrupted_count,
+            supernova_count: sim.supernova_count,
+            formed_count: sim.formed_count,
+            stars: sim.stars().to_vec(),
+        }
+    }
+
+    /// Recreate the simulation in this state, with the config of the given simulation.
+    pub fn to_sim(&self, sim: &GalaxySim) -> Result<GalaxySim, Box<dyn Error>> {
+        let mut restored = GalaxySim::from_stars(sim.config(), self.stars.clone())?;
+        restored.elapsed_time = self.elapsed_time;
+        restored.myr_per_second = self.myr_per_second;
+        restored.escaped_count = self.escaped_count;
+        restored.disrupted_count = self.disrupted_count;
+        restored.supernova_count = self.supernova_count;
+        restored.formed_count = self.formed_count;
+        Ok(restored)
+    }
+
+    /// The simulation time elapsed when the state was recorded, in Myr.
+    pub fn elapsed_myr(&self) -> f64 {
+        Myr::from_time_units(self.elapsed_time).0
+    }
+
+    /// The approximate amount of memory the state uses, in bytes.
+    fn size_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.stars.capacity() * std::mem::size_of::<Star>()
+    }
+}
+
+/// A labelled state of the simulation that can be jumped back to.
+pub struct Bookmark {
+    pub label: String,
+    pub state: RewindState,
+}
+
+impl Bookmark {
+    /// Bookmark the current state of a simulation.
+    pub fn new(label: String, sim: &GalaxySim) -> Self {
+        Self {
+            label,
+            state: RewindState::from_sim(sim),
+        }
+    }
+}
+
+/// A ring buffer of recent states of the simulation, so that it can be scrubbed back through and
+/// resumed from an earlier point. A state is recorded every few steps, and the oldest states are
+/// discarded to keep within a memory budget.
+pub struct RewindBuffer {
+    states: VecDeque<RewindState>,
+
+    /// The memory used by the recorded states, in bytes.
+    used_bytes: usize,
+
+    /// The most memory the recorded states can use, in bytes.
+    budget_bytes: usize,
+
+    interval_steps: u64,
+
+    /// The number of steps since the last state was recorded, or None if one should be recorded
+    /// in the next step.
+    steps_since_record: Option<u64>,
+}
+
+impl RewindBuffer {
+    /// Create an empty rewind buffer.
+    pub fn new(config: &RewindConfig) -> Self {
+        Self {
+            states: VecDeque::new(),
+            used_bytes: 0,
+            budget_bytes: config.memory_budget_mb * 1024 * 1024,
+            interval_steps: u64::max(config.interval_steps, 1),
+            steps_since_record: None,
+        }
+    }
+
+    /// Record the state of the simulation after a step, if it's time to.
+    pub fn record(&mut self, sim: &GalaxySim) {
+        match self.steps_since_record {
+            Some(steps) if steps + 1 < self.interval_steps => {
+                self.steps_since_record = Some(steps + 1);
+                return;
+            },
+            _ => self.steps_since_record = Some(0),
+        }
+
+        let state = RewindState::from_sim(sim);
+        self.used_bytes += state.size_bytes();
+        self.states.push_back(state);
+
+        // Always keep the latest state, even if it's over budget by itself.
+        while self.used_bytes > self.budget_bytes && self.states.len() > 1 {
+            if let Some(oldest) = self.states.pop_front() {
+                self.used_bytes -= oldest.size_bytes();
+            }
+        }
+    }
+
+    /// Discard the states recorded after the given index, e.g. when the simulation is resumed
+    /// from an earlier state, so that the history stays consistent with it.
+    pub fn truncate(&mut self, index: usize) {
+        while self.states.len() > index + 1 {
+            if let Some(newest) = self.states.pop_back() {
+                self.used_bytes -= newest.size_bytes();
+            }
+        }
+        self.steps_since_record = Some(0);
+    }
+
+    /// Discard the states recorded after the given simulation time, e.g. when jumping back to a
+    /// bookmark, so that the history stays consistent with it once it's resumed.
+    pub fn discard_after(&mut self, elapsed_time: f64) {
+        while self.states.back().is_some_and(|newest| newest.elapsed_time > elapsed_time) {
+            if let Some(newest) = self.states.pop_back() {
+                self.used_bytes -= newest.size_bytes();
+            }
+        }
+        self.steps_since_record = None;
+    }
+
+    /// Discard all recorded states, e.g. when the galaxy is replaced.
+    pub fn clear(&mut self) {
+        self.states.clear();
+        self.used_bytes = 0;
+        self.steps_since_record = None;
+    }
+
+    /// Get a recorded state, where 0 is the oldest.
+    pub fn get(&self, index: usize) -> Option<&RewindState> {
+        self.states.get(index)
+    }
+
+    /// The number of recorded states.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Whether there are no recorded states.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// The memory used by the recorded states, in bytes.
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+}
@@ -0,0 +1,63 @@
+//! Deterministic random number streams. Everything random is drawn from a named stream derived
+//! from the seed, rather than from one generator shared by everything, so that adding a new
+//! random feature, or drawing more numbers in one part of the generation, doesn't shift the
+//! results of every other part for the same seed.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// The stream new galaxies are generated from.
+pub const GENERATION: &str = "generation";
+
+/// The stream random perturbations of a running simulation are drawn from.
+pub const PERTURBATIONS: &str = "perturbations";
+
+/// The stream the viewer's randomize controls draw from, such as picking a random seed.
+pub const UI_RANDOMIZE: &str = "ui randomize";
+
+/// The stream the viewer's background starfield is scattered from.
+pub const STARFIELD: &str = "starfield";
+
+/// A source of named, independently seeded random number streams, all derived from one seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RngStreams {
+    seed: u64,
+}
+
+impl RngStreams {
+    /// Create the streams for a seed.
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// The seed the streams are derived from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The stream with the given name. The same seed and name always give the same stream, so the
+    /// names must not change once numbers are drawn from them.
+    pub fn stream(&self, name: &str) -> StdRng {
+        StdRng::seed_from_u64(self.derive_seed(name))
+    }
+
+    /// A set of streams nested under the given name, for something that needs several streams of
+    /// its own, such as each galaxy of a cluster.
+    pub fn substreams(&self, name: &str) -> RngStreams {
+        RngStreams::new(self.derive_seed(name))
+    }
+
+    /// The seed of the stream with the given name. This hashes the name with FNV-1a, which unlike
+    /// the standard library's hasher is stable between Rust versions, and mixes it into the seed
+    /// with SplitMix64 so that similar seeds and names give unrelated streams.
+    fn derive_seed(&self, name: &str) -> u64 {
+        let name_hash = name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+
+        let mut z = (self.seed ^ name_hash).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
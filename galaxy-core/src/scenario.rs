@@ -0,0 +1,255 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::path::Path;
+use std::rc::Rc;
+
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST, INT};
+
+use crate::simulation::{GalaxySim, Star, StarComponent, SOLAR_AGE};
+use crate::star_world::{Mass, Position, StarWorld, Velocity};
+use crate::types::Vec2d;
+use crate::units::SolarMass;
+
+/// The state of the simulation while a script hook is running. The stars are moved in here for the
+/// duration of the call and moved back afterwards, so scripts can modify them without copying.
+struct ScriptState {
+    stars: StarWorld,
+    elapsed_myr: f64,
+    previous_myr: f64,
+    myr_per_second: f64,
+    opening_angle: f64,
+
+    /// Whether the first star is the supermassive black hole, which can't be removed.
+    has_black_hole: bool,
+
+    /// The number of stars the hook has added.
+    added_count: usize,
+}
+
+/// The simulation as seen by scripts, passed to each hook as `sim`. Rhai passes function arguments
+/// by value, so the state is shared to make changes visible after the hook returns.
+#[derive(Clone)]
+struct ScriptSim(Rc<RefCell<ScriptState>>);
+
+impl ScriptSim {
+    /// Check that a star index from a script is valid, converting it to a usize.
+    fn star_index(&self, index: INT) -> Result<usize, Box<EvalAltResult>> {
+        let star_count = self.0.borrow().stars.len();
+        match usize::try_from(index) {
+            Ok(index) if index < star_count => Ok(index),
+            _ => Err(format!("Star index {index} out of range, there are {star_count} stars").into()),
+        }
+    }
+}
+
+/// A scenario script, which can set up initial conditions and trigger events as the simulation
+/// runs. Scripts are written in Rhai and may define any of these functions:
+///
+/// * `init(sim)` - called once after the galaxy is generated
+/// * `on_step(sim)` - called after every step
+///
+/// `sim` has the properties `elapsed_myr`, `star_count`, `myr_per_second` and `opening_angle` (the
+/// last two can be set), and the methods:
+///
+/// * `star(index)` - get a star, which has the properties `x`, `y`, `vx`, `vy`, `mass`, `age` and
+///   `metallicity`
+/// * `add_star(x, y, vx, vy, mass)` - add a star with the sun's age and metallicity, returning its
+///   index
+/// * `remove_star(index)` - remove a star, changing the index of every star after it
+/// * `set_position(index, x, y)`, `set_velocity(index, vx, vy)` and `set_mass(index, mass)`
+/// * `crossed(time_myr)` - whether the last step passed the given simulated time, for one-off
+///   events
+///
+/// Positions are in parsecs, velocities in km/s, masses in solar masses, ages in Gyr and
+/// metallicities in dex. If the galaxy has a supermassive black hole it's star 0, and can't be
+/// removed. Note that Rhai doesn't convert integers to floats, so numbers must be written as e.g.
+/// `100.0` rather than `100`.
+pub struct Scenario {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    has_init: bool,
+    has_on_step: bool,
+
+    /// The simulated time in Myr when the last hook ran.
+    previous_myr: f64,
+}
+
+impl Scenario {
+    /// Load a scenario script from a file and run its top level statements.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        log::info!("Loading scenario script {}", path.display());
+
+        let engine = Self::create_engine();
+        let ast = engine.compile_file(path.to_path_buf())?;
+
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast)?;
+
+        let has_function = |name| ast.iter_functions().any(|function| function.name == name && function.params.len() == 1);
+        let has_init = has_function("init");
+        let has_on_step = has_function("on_step");
+
+        Ok(Self { engine, ast, scope, has_init, has_on_step, previous_myr: 0.0 })
+    }
+
+    /// Run the script's `init` hook on a newly generated galaxy.
+    pub fn init(&mut self, sim: &mut GalaxySim) -> Result<(), Box<dyn Error>> {
+        self.previous_myr = sim.elapsed_myr();
+        if self.has_init {
+            self.call("init", sim)?;
+        }
+        Ok(())
+    }
+
+    /// Run the script's `on_step` hook after a step.
+    pub fn on_step(&mut self, sim: &mut GalaxySim) -> Result<(), Box<dyn Error>> {
+        if self.has_on_step {
+            self.call("on_step", sim)?;
+        }
+        self.previous_myr = sim.elapsed_myr();
+        Ok(())
+    }
+
+    /// Call a hook in the script, applying any changes it made to the simulation.
+    fn call(&mut self, name: &str, sim: &mut GalaxySim) -> Result<(), Box<dyn Error>> {
+        let state = ScriptSim(Rc::new(RefCell::new(ScriptState {
+            stars: std::mem::take(sim.stars_mut()),
+            elapsed_myr: sim.elapsed_myr(),
+            previous_myr: self.previous_myr,
+            myr_per_second: sim.myr_per_second,
+            opening_angle: sim.config().simulation.opening_angle,
+            has_black_hole: sim.config().generation.has_black_hole(),
+            added_count: 0,
+        })));
+
+        let result = self.engine.call_fn::<Dynamic>(&mut self.scope, &self.ast, name, (state.clone(),));
+
+        // Apply the changes even if the script failed part way through, so the stars aren't lost.
+        let mut state = state.0.borrow_mut();
+        *sim.stars_mut() = std::mem::take(&mut state.stars);
+        sim.stars_mut().update_render_colors();
+        sim.myr_per_second = state.myr_per_second;
+        sim.set_opening_angle(state.opening_angle);
+        sim.formed_count += state.added_count;
+        sim.assign_star_ids();
+
+        result.map(|_| ())?;
+        Ok(())
+    }
+
+    /// Create a script engine with the simulation API registered.
+    fn create_engine() -> Engine {
+        let mut engine = Engine::new();
+
+        engine.on_print(|text| log::info!("[script] {text}"));
+        engine.on_debug(|text, _, pos| log::debug!("[script] {pos:?} {text}"));
+
+        engine.register_type_with_name::<Star>("Star")
+            .register_get("x", |star: &mut Star| star.position.x)
+            .register_get("y", |star: &mut Star| star.position.y)
+            .register_get("vx", |star: &mut Star| star.velocity.x)
+            .register_get("vy", |star: &mut Star| star.velocity.y)
+            .register_get("mass", |star: &mut Star| star.mass.0)
+            .register_get("age", |star: &mut Star| star.age)
+            .register_get("metallicity", |star: &mut Star| star.metallicity);
+
+        engine.register_type_with_name::<ScriptSim>("Sim")
+            .register_get("elapsed_myr", |sim: &mut ScriptSim| sim.0.borrow().elapsed_myr)
+            .register_get("star_count", |sim: &mut ScriptSim| sim.0.borrow().stars.len() as INT)
+            .register_get_set("myr_per_second",
+                              |sim: &mut ScriptSim| sim.0.borrow().myr_per_second,
+                              |sim: &mut ScriptSim, myr_per_second: f64| sim.0.borrow_mut().myr_per_second = myr_per_second)
+            .register_get_set("opening_angle",
+                              |sim: &mut ScriptSim| sim.0.borrow().opening_angle,
+                              |sim: &mut ScriptSim, opening_angle: f64| sim.0.borrow_mut().opening_angle = opening_angle)
+            .register_fn("crossed", |sim: &mut ScriptSim, time_myr: f64| {
+                let state = sim.0.borrow();
+                state.previous_myr < time_myr && time_myr <= state.elapsed_myr
+            })
+            .register_fn("star", |sim: &mut ScriptSim, index: INT| -> Result<Star, Box<EvalAltResult>> {
+                let index = sim.star_index(index)?;
+                Ok(sim.0.borrow().stars.get(index).unwrap_or_default())
+            })
+            .register_fn("add_star", |sim: &mut ScriptSim, x: f64, y: f64, vx: f64, vy: f64, mass: f64| {
+                let mut state = sim.0.borrow_mut();
+                state.stars.push(Star {
+                    id: 0,
+                    position: Vec2d::new(x, y),
+                    velocity: Vec2d::new(vx, vy),
+                    mass: SolarMass(mass),
+                    age: SOLAR_AGE,
+                    metallicity: 0.0,
+                    component: StarComponent::Disk,
+                    binary: None,
+                    tag: None,
+                });
+                state.added_count += 1;
+                (state.stars.len() - 1) as INT
+            })
+            .register_fn("add_tracer", |sim: &mut ScriptSim, x: f64, y: f64, vx: f64, vy: f64| {
+                let mut state = sim.0.borrow_mut();
+                state.stars.push(Star {
+                    position: Vec2d::new(x, y),
+                    velocity: Vec2d::new(vx, vy),
+                    component: StarComponent::Tracer,
+                    ..Default::default()
+                });
+                (state.stars.len() - 1) as INT
+            })
+            .register_fn("remove_star", |sim: &mut ScriptSim, index: INT| -> Result<(), Box<EvalAltResult>> {
+                let index = sim.star_index(index)?;
+                let mut state = sim.0.borrow_mut();
+                if index == 0 && state.has_black_hole {
+                    return Err("The supermassive black hole can't be removed".into());
+                }
+                state.stars.retain(|i| i != index);
+                Ok(())
+            })
+            .register_fn("set_position", |sim: &mut ScriptSim, index: INT, x: f64, y: f64| -> Result<(), Box<EvalAltResult>> {
+                let index = sim.star_index(index)?;
+                if let Some(mut position) = sim.0.borrow_mut().stars.component_mut::<Position>(index) {
+                    position.0 = Vec2d::new(x, y);
+                }
+                Ok(())
+            })
+            .register_fn("set_velocity", |sim: &mut ScriptSim, index: INT, vx: f64, vy: f64| -> Result<(), Box<EvalAltResult>> {
+                let index = sim.star_index(index)?;
+                if let Some(mut velocity) = sim.0.borrow_mut().stars.component_mut::<Velocity>(index) {
+                    velocity.0 = Vec2d::new(vx, vy);
+                }
+                Ok(())
+            })
+            .register_fn("set_mass", |sim: &mut ScriptSim, index: INT, mass: f64| -> Result<(), Box<EvalAltResult>> {
+                let index = sim.star_index(index)?;
+                if let Some(mut star_mass) = sim.0.borrow_mut().stars.component_mut::<Mass>(index) {
+                    star_mass.0 = SolarMass(mass);
+                }
+                Ok(())
+            });
+
+        engine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::rng::RngStreams;
+
+    #[test]
+    fn scripts_read_and_write_masses() {
+        let path = std::env::temp_dir().join(format!("galaxy-scenario-{}.rhai", std::process::id()));
+        std::fs::write(&path, "fn init(sim) { sim.set_mass(1, sim.star(1).mass * 2.0); }").unwrap();
+        let mut scenario = Scenario::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let config = Config::default();
+        let mut sim = GalaxySim::new(&config, &RngStreams::new(config.generation.seed)).unwrap();
+        let mass = sim.stars().component::<Mass>(1).unwrap().0;
+        scenario.init(&mut sim).unwrap();
+        assert_eq!(sim.stars().component::<Mass>(1).unwrap().0, SolarMass(mass.0 * 2.0));
+    }
+}
@@ -0,0 +1,234 @@
+//! Publishing snapshots of the simulation to a file for external tools to memory-map, so they can
+//! read the live state without copying it or talking to the viewer. The file is meant to be on a
+//! memory-backed filesystem such as `/dev/shm`, where it's just shared memory.
+
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::sync::atomic::{self, AtomicU64, Ordering};
+
+use memmap2::MmapMut;
+
+use crate::snapshot::SimSnapshot;
+
+/// The magic bytes at the start of the file.
+pub const MAGIC: &[u8; 8] = b"GALAXYSM";
+
+/// The version of the layout, which is increased whenever it changes.
+pub const VERSION: u32 = 1;
+
+/// The size of the header, which the star arrays follow.
+pub const HEADER_SIZE: usize = 64;
+
+/// The offset of the sequence number in the header, which is 8 byte aligned so it can be updated
+/// atomically.
+const SEQUENCE_OFFSET: usize = 16;
+
+/// The size of each star across the arrays, in bytes: its position, velocity and mass.
+const STAR_SIZE: usize = 5 * std::mem::size_of::<f32>();
+
+/// Writes snapshots into a shared file, which it maps into memory. All values are little-endian,
+/// and the file is laid out as a 64 byte header:
+///
+/// * `[u8; 8]` magic, `GALAXYSM`
+/// * `u32` layout version, then `u32` header size
+/// * `u64` sequence number, odd while a snapshot is being written
+/// * `u64` step number
+/// * `f64` elapsed simulated time in Myr
+/// * `u64` star count
+/// * `u64` capacity, the number of stars there's room for in each array
+///
+/// followed by three arrays, each `capacity` long, of which the first `star count` entries are
+/// valid: `[f32; 2]` positions in parsecs, `[f32; 2]` velocities in km/s, and `f32` masses in
+/// solar masses.
+///
+/// The sequence number is a seqlock. It's stored atomically, made odd with a release fence after
+/// it before the rest of the header and the arrays are written, and made even with a release store
+/// after them. Readers should load the sequence number with acquire ordering, read the data, issue
+/// an acquire fence, and load the sequence number again, retrying if it changed or was odd, as the
+/// data may have been torn by a write in between. The file only grows, when there are more stars
+/// than its capacity, after which readers need to map it again.
+pub struct SharedSnapshotWriter {
+    file: File,
+    map: MmapMut,
+    sequence: u64,
+    capacity: usize,
+}
+
+impl SharedSnapshotWriter {
+    /// Create the shared file at the given path, replacing any that's there.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        log::info!("Sharing snapshots in {}", path.as_ref().display());
+
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        file.set_len(HEADER_SIZE as u64)?;
+        let map = Self::map(&file)?;
+
+        let mut writer = Self { file, map, sequence: 0, capacity: 0 };
+        writer.map[..8].copy_from_slice(MAGIC);
+        writer.map[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        writer.map[12..16].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        writer.write_header(&SimSnapshot::default());
+        writer.map.flush()?;
+
+        Ok(writer)
+    }
+
+    /// Map the whole of the shared file into memory.
+    fn map(file: &File) -> Result<MmapMut, Box<dyn Error>> {
+        // Safety: other processes may change the file underneath the map, but it's only ever
+        // accessed as plain bytes, apart from the aligned sequence number which is atomic.
+        Ok(unsafe { MmapMut::map_mut(file)? })
+    }
+
+    /// Write a snapshot into the file, replacing the previous one.
+    pub fn publish(&mut self, snapshot: &SimSnapshot) -> Result<(), Box<dyn Error>> {
+        let star_count = snapshot.star_count();
+        if star_count > self.capacity {
+            // Leave room to grow so the file doesn't need to be resized, and remapped by readers,
+            // every time a star is added.
+            self.capacity = star_count + star_count / 4;
+            self.file.set_len((HEADER_SIZE + self.capacity * STAR_SIZE) as u64)?;
+            self.map = Self::map(&self.file)?;
+        }
+
+        // Mark the snapshot as being written before touching it, and as finished after. The fence
+        // keeps the writes to the data from being reordered before the odd sequence number.
+        self.sequence += 1;
+        self.sequence_number().store(self.sequence.to_le(), Ordering::Relaxed);
+        atomic::fence(Ordering::Release);
+
+        self.write_header(snapshot);
+
+        let offset = self.positions_offset();
+        let positions = self.map[offset..offset + star_count * 8].chunks_exact_mut(8);
+        for (bytes, position) in positions.zip(&snapshot.positions) {
+            bytes[..4].copy_from_slice(&(position.x as f32).to_le_bytes());
+            bytes[4..].copy_from_slice(&(position.y as f32).to_le_bytes());
+        }
+
+        let offset = self.velocities_offset();
+        let velocities = self.map[offset..offset + star_count * 8].chunks_exact_mut(8);
+        for (bytes, velocity) in velocities.zip(&snapshot.velocities) {
+            bytes[..4].copy_from_slice(&(velocity.x as f32).to_le_bytes());
+            bytes[4..].copy_from_slice(&(velocity.y as f32).to_le_bytes());
+        }
+
+        let offset = self.masses_offset();
+        let masses = self.map[offset..offset + star_count * 4].chunks_exact_mut(4);
+        for (bytes, mass) in masses.zip(&snapshot.masses) {
+            bytes.copy_from_slice(&(*mass as f32).to_le_bytes());
+        }
+
+        self.sequence += 1;
+        self.sequence_number().store(self.sequence.to_le(), Ordering::Release);
+
+        Ok(())
+    }
+
+    /// The sequence number in the mapped header.
+    fn sequence_number(&self) -> &AtomicU64 {
+        // Safety: the map is page aligned and at least HEADER_SIZE long, so the sequence number is
+        // in bounds and 8 byte aligned, and it's only ever accessed atomically.
+        unsafe { &*(self.map.as_ptr().add(SEQUENCE_OFFSET) as *const AtomicU64) }
+    }
+
+    fn positions_offset(&self) -> usize {
+        HEADER_SIZE
+    }
+
+    fn velocities_offset(&self) -> usize {
+        HEADER_SIZE + self.capacity * 8
+    }
+
+    fn masses_offset(&self) -> usize {
+        HEADER_SIZE + self.capacity * 16
+    }
+
+    /// Write the fields of the header after the sequence number for a snapshot.
+    fn write_header(&mut self, snapshot: &SimSnapshot) {
+        let mut fields = Vec::with_capacity(HEADER_SIZE - SEQUENCE_OFFSET - 8);
+        fields.extend(snapshot.step.to_le_bytes());
+        fields.extend(snapshot.elapsed_myr.to_le_bytes());
+        fields.extend((snapshot.star_count() as u64).to_le_bytes());
+        fields.extend((self.capacity as u64).to_le_bytes());
+
+        let offset = SEQUENCE_OFFSET + 8;
+        self.map[offset..offset + fields.len()].copy_from_slice(&fields);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Vec2d;
+
+    fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn f32_at(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Read a snapshot back from the shared file as an external reader would.
+    fn read(path: &Path) -> (u64, SimSnapshot, usize) {
+        let bytes = std::fs::read(path).unwrap();
+        assert_eq!(&bytes[..8], MAGIC);
+        assert_eq!(u32::from_le_bytes(bytes[8..12].try_into().unwrap()), VERSION);
+        assert_eq!(u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize, HEADER_SIZE);
+
+        let sequence = u64_at(&bytes, 16);
+        let star_count = u64_at(&bytes, 40) as usize;
+        let capacity = u64_at(&bytes, 48) as usize;
+        assert_eq!(bytes.len(), HEADER_SIZE + capacity * STAR_SIZE);
+
+        let vec2 = |offset: usize| Vec2d::new(f32_at(&bytes, offset) as f64, f32_at(&bytes, offset + 4) as f64);
+        let snapshot = SimSnapshot {
+            step: u64_at(&bytes, 24),
+            elapsed_myr: f64::from_le_bytes(bytes[32..40].try_into().unwrap()),
+            positions: (0..star_count).map(|i| vec2(HEADER_SIZE + i * 8)).collect(),
+            velocities: (0..star_count).map(|i| vec2(HEADER_SIZE + capacity * 8 + i * 8)).collect(),
+            masses: (0..star_count).map(|i| f32_at(&bytes, HEADER_SIZE + capacity * 16 + i * 4) as f64).collect(),
+            components: Vec::new(),
+        };
+        (sequence, snapshot, capacity)
+    }
+
+    fn snapshot(step: u64, star_count: usize) -> SimSnapshot {
+        SimSnapshot {
+            step,
+            elapsed_myr: step as f64 * 0.5,
+            positions: (0..star_count).map(|i| Vec2d::new(i as f64, -(i as f64) * 2.0)).collect(),
+            velocities: (0..star_count).map(|i| Vec2d::new(100.0 + i as f64, 0.25)).collect(),
+            masses: (0..star_count).map(|i| 1.0 + i as f64).collect(),
+            components: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        let path = std::env::temp_dir().join(format!("galaxy-shared-snapshot-{}", std::process::id()));
+        let mut writer = SharedSnapshotWriter::create(&path).unwrap();
+        let (sequence, empty, capacity) = read(&path);
+        assert_eq!((sequence, empty.star_count(), capacity), (0, 0, 0));
+
+        // The second snapshot is smaller, so it reuses the first's capacity, and the third is
+        // larger, so the file grows.
+        for (i, star_count) in [10, 4, 40].into_iter().enumerate() {
+            let expected = snapshot(i as u64 + 1, star_count);
+            writer.publish(&expected).unwrap();
+
+            let (sequence, actual, capacity) = read(&path);
+            assert_eq!(sequence, 2 * (i as u64 + 1));
+            assert!(capacity >= star_count);
+            assert_eq!((actual.step, actual.elapsed_myr), (expected.step, expected.elapsed_myr));
+            assert_eq!(actual.positions, expected.positions);
+            assert_eq!(actual.velocities, expected.velocities);
+            assert_eq!(actual.masses, expected.masses);
+        }
+
+        drop(writer);
+        std::fs::remove_file(path).unwrap();
+    }
+}
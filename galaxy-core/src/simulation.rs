@@ -0,0 +1,1391 @@
+use std::error::Error;
+use std::f64::consts::PI;
+
+use bevy_ecs::component::Component;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+use crate::config::{Config, SimulationConfig, SpatialBackend};
+use crate::evolution;
+use crate::generation;
+use crate::hilbert::HilbertIndex;
+use crate::{profile_scope, profile_span};
+use crate::kepler;
+use crate::three_body;
+use crate::types::Vec2d;
+use crate::units::{KmPerSec, Myr, Parsec, SolarMass};
+use crate::rng::RngStreams;
+use crate::quadtree::{Quadtree, Spatial, QuadtreeNode};
+use crate::spatial::{SpatialAccel, SpatialGrid};
+use crate::star_world::{Age, Mass, Position, Species, StarHandle, StarId, StarWorld, Velocity};
+
+/// The fraction of stars, those moving or accelerating fastest relative to the distance to their
+/// neighbours or their speed, that are ignored when limiting how far stars can move in a substep
+/// and measuring whether steps are too large, so that a few stars passing very close to each other
+/// or to the black hole don't force every step to be split as finely as possible.
+const OUTLIER_FRACTION: f64 = 0.01;
+
+/// The age of the sun in Gyr, given to stars whose age isn't known.
+pub const SOLAR_AGE: f64 = 4.6;
+
+/// A single star in our galaxy, as it's saved, loaded and added to a simulation. While it's being
+/// simulated a star is an entity in the simulation's StarWorld, with a component for each field.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Star {
+    /// A number identifying the star, which unlike its index stays the same as other stars are
+    /// added and removed. It's 0 until the star is added to a simulation, which assigns it one.
+    #[serde(default)]
+    pub id: u64,
+
+    pub position: Vec2d,
+    pub velocity: Vec2d,
+    pub mass: SolarMass,
+
+    /// The age of the star in Gyr.
+    #[serde(default)]
+    pub age: f64,
+
+    /// The metallicity of the star, [Fe/H] in dex relative to the sun.
+    #[serde(default)]
+    pub metallicity: f64,
+
+    /// The component of the galaxy the star was generated in.
+    #[serde(default)]
+    pub component: StarComponent,
+
+    /// The star's companion if it's a binary, in which case its position, velocity and mass are
+    /// those of the pair as a whole.
+    #[serde(default)]
+    pub binary: Option<Binary>,
+
+    /// The tag the user has given the star to keep track of it, which moves with it and is saved
+    /// along with it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<Box<StarTag>>,
+}
+
+/// A label, colour and note the user has tagged a star with. Tagged stars are drawn in their tag's
+/// colour and listed so they can be found again.
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StarTag {
+    pub label: String,
+
+    /// The colour the star is drawn in, as RGB from 0 to 1.
+    pub color: [f32; 3],
+
+    #[serde(default)]
+    pub note: String,
+}
+
+impl Default for StarTag {
+    fn default() -> Self {
+        Self {
+            label: String::new(),
+            color: [1.0, 0.3, 0.8],
+            note: String::new(),
+        }
+    }
+}
+
+/// The orbit of a binary star's companion. A binary is simulated as a single particle at its center
+/// of mass carrying the pair's total mass, as the two orbit each other far too quickly and closely
+/// to integrate separately, and their orbit around each other is advanced analytically instead.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Binary {
+    /// The fraction of the pair's mass in the companion.
+    pub companion_fraction: f64,
+
+    /// The position of the companion relative to the primary, in parsecs.
+    pub separation: Vec2d,
+
+    /// The velocity of the companion relative to the primary, in km/s.
+    pub relative_velocity: Vec2d,
+}
+
+impl Binary {
+    /// The positions of the primary and the companion, given the pair's center of mass.
+    pub fn positions(&self, center_of_mass: Vec2d) -> (Vec2d, Vec2d) {
+        (center_of_mass - self.separation * self.companion_fraction,
+         center_of_mass + self.separation * (1.0 - self.companion_fraction))
+    }
+
+    /// Advance the pair's orbit around each other by a time in simulation time units, given their
+    /// total mass.
+    fn advance(&mut self, gravitational_constant: f64, mass: SolarMass, time_delta: f64) {
+        (self.separation, self.relative_velocity) =
+            kepler::advance(gravitational_constant * mass.0, self.separation, self.relative_velocity, time_delta);
+    }
+}
+
+/// The component of a galaxy a star was generated in, so that components can be measured
+/// separately as they evolve.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StarComponent {
+    /// The main body of the galaxy, which is its disk apart from for ellipticals and the cluster
+    /// models. Stars from catalogues and scripts are also counted as part of it.
+    #[default]
+    Disk,
+    Bulge,
+    /// The particles of a live dark matter halo, which gravitate like stars but aren't drawn as
+    /// them.
+    Halo,
+    GlobularCluster,
+    Satellite,
+    /// The supermassive black hole, the marker at the barycenter of a cluster of galaxies, or one
+    /// of the two bodies of the restricted three-body mode.
+    BlackHole,
+    /// Massless tracer particles, which are moved by gravity but exert none, to show how the
+    /// galaxy flows.
+    Tracer,
+}
+
+impl StarComponent {
+    /// Every component, in order.
+    pub const ALL: [StarComponent; 7] = [
+        StarComponent::Disk,
+        StarComponent::Bulge,
+        StarComponent::Halo,
+        StarComponent::GlobularCluster,
+        StarComponent::Satellite,
+        StarComponent::BlackHole,
+        StarComponent::Tracer,
+    ];
+
+    /// The name of the component for display.
+    pub fn name(self) -> &'static str {
+        match self {
+            StarComponent::Disk => "Disk",
+            StarComponent::Bulge => "Bulge",
+            StarComponent::Halo => "Dark matter",
+            StarComponent::GlobularCluster => "Globular clusters",
+            StarComponent::Satellite => "Satellites",
+            StarComponent::BlackHole => "Black hole",
+            StarComponent::Tracer => "Tracers",
+        }
+    }
+
+    /// Whether particles of this component are actually stars, rather than black holes, dark
+    /// matter or tracers.
+    pub fn is_star(self) -> bool {
+        !matches!(self, StarComponent::BlackHole | StarComponent::Halo | StarComponent::Tracer)
+    }
+
+    /// The mass a particle of this component and the given mass gravitates with, which is none
+    /// for tracers, even if they've been given a mass.
+    pub fn gravitating_mass(self, mass: SolarMass) -> SolarMass {
+        match self {
+            StarComponent::Tracer => SolarMass(0.0),
+            _ => mass,
+        }
+    }
+}
+
+impl Star {
+    /// Whether this is actually a star, rather than a black hole, dark matter or a tracer.
+    pub fn is_star(&self) -> bool {
+        self.component.is_star()
+    }
+
+    /// The mass the star's gravity acts on other stars with, which is none for tracers, even if
+    /// they've been given a mass.
+    pub fn gravitating_mass(&self) -> SolarMass {
+        self.component.gravitating_mass(self.mass)
+    }
+}
+
+impl Spatial for Star {
+    fn xy(&self) -> &Vec2d {
+        &self.position
+    }
+}
+
+/// A region in our galaxy, in the quadtree. We use this to accelerate n-body calculations.
+pub struct Region {
+    center_of_mass: Vec2d,
+    mass: SolarMass,
+}
+
+impl Region {
+    /// The center of mass of the stars in the region.
+    pub fn center_of_mass(&self) -> Vec2d {
+        self.center_of_mass
+    }
+
+    /// The total mass of the stars in the region.
+    pub fn mass(&self) -> SolarMass {
+        self.mass
+    }
+}
+
+impl SpatialAccel for Quadtree<StarHandle, Region> {
+    fn insert(&mut self, star: StarHandle) {
+        GalaxySim::add_to_quadtree(self, star);
+    }
+
+    fn rebuild(&mut self) {
+        let mut quadtree = Quadtree::with_max_depth(self.min, self.max, self.max_depth())
+            .expect("Quadtree's own maximum depth is invalid");
+        for star in std::mem::take(&mut self.items) {
+            GalaxySim::add_to_quadtree(&mut quadtree, star);
+        }
+        GalaxySim::update_mass_distribution(&mut quadtree);
+
+        *self = quadtree;
+    }
+
+    fn stars(&self) -> &[StarHandle] {
+        &self.items
+    }
+
+    fn items_in_radius(&self, center: Vec2d, radius: f64, f: &mut dyn FnMut(usize, &StarHandle)) {
+        Quadtree::items_in_radius(self, center, radius, f);
+    }
+
+    fn acceleration_at_point(&self, config: &Config, point: Vec2d) -> Vec2d {
+        GalaxySim::acceleration_at_point_inner(self, config, point, HilbertIndex(0, 0))
+    }
+}
+
+/// How long each phase of the last step took, in milliseconds.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StepTimings {
+    pub quadtree_ms: f64,
+    pub mass_distribution_ms: f64,
+    pub integrate_ms: f64,
+
+    /// How long building the spatial hash grid took, which is zero unless it's the selected
+    /// spatial backend.
+    pub grid_ms: f64,
+}
+
+/// How large the substeps of the last step were relative to the stars' motion, to tell whether the
+/// simulation is running too fast for the integration to be reliable.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StepSafety {
+    /// The furthest any star moved in a substep.
+    pub max_displacement: Parsec,
+
+    /// The largest change in any star's velocity in a substep, i.e. acceleration times the time
+    /// delta.
+    pub max_velocity_change: KmPerSec,
+
+    /// How far stars moved in a substep as a fraction of the distance to their neighbours,
+    /// ignoring the fastest few.
+    pub displacement_fraction: f64,
+
+    /// How much stars' velocities changed in a substep as a fraction of their speed, ignoring the
+    /// fastest few.
+    pub velocity_change_fraction: f64,
+}
+
+impl StepSafety {
+    /// Whether stars moved too far relative to their neighbours for the integration to be reliable.
+    pub fn displacement_too_large(&self, config: &SimulationConfig) -> bool {
+        config.warn_displacement_fraction > 0.0 && self.displacement_fraction > config.warn_displacement_fraction
+    }
+
+    /// Whether stars' velocities changed too much for the integration to be reliable.
+    pub fn velocity_change_too_large(&self, config: &SimulationConfig) -> bool {
+        config.warn_velocity_change_fraction > 0.0 && self.velocity_change_fraction > config.warn_velocity_change_fraction
+    }
+
+    /// Whether the integration in the last step was reliable.
+    pub fn is_reliable(&self, config: &SimulationConfig) -> bool {
+        !self.displacement_too_large(config) && !self.velocity_change_too_large(config)
+    }
+}
+
+/// A star torn apart by a black hole's tides and accreted onto it.
+#[derive(Clone, Copy, Debug)]
+pub struct TidalDisruption {
+    /// Where the star was when it was disrupted.
+    pub position: Vec2d,
+
+    /// The mass of the star, which was added to the black hole.
+    pub mass: SolarMass,
+
+    /// The simulation time the star was disrupted at, in Myr.
+    pub elapsed_myr: f64,
+}
+
+/// The simulation of a galaxy: its stars, the quadtree used to accelerate the n-body calculations,
+/// and the integration. This has no rendering dependencies so that it can be used headlessly.
+pub struct GalaxySim {
+    /// The rate the simulation runs at, in Myr of simulated time per second of real time.
+    pub myr_per_second: f64,
+
+    /// The number of substeps the last step was split into.
+    pub substep_count: usize,
+
+    /// How large the substeps of the last step were relative to the stars' motion.
+    pub safety: StepSafety,
+
+    /// The total simulation time elapsed since the galaxy was generated, in simulation time units.
+    pub elapsed_time: f64,
+
+    /// The config the galaxy was generated with, which also contains the simulation constants.
+    config: Config,
+
+    /// The galaxy's stars, as entities with a component for each of their attributes.
+    stars: StarWorld,
+
+    /// The galaxy's quadtree. We store handles to the stars as leaf nodes in the octree, and have
+    /// an additional type Region for the internal nodes, which we use to accelerate n-body
+    /// lookups. It's rebuilt from the stars at the start of each substep, after which its items are
+    /// in the same order as the stars.
+    pub quadtree: Quadtree<StarHandle, Region>,
+
+    /// The number of stars that have left the bounds of the quadtree and been discarded.
+    pub escaped_count: usize,
+
+    /// The number of stars that have been torn apart by black holes.
+    pub disrupted_count: usize,
+
+    /// The stars torn apart by black holes during the last step.
+    pub tidal_disruptions: Vec<TidalDisruption>,
+
+    /// The number of stars that have gone supernova.
+    pub supernova_count: usize,
+
+    /// The number of stars that have been added to the galaxy since it was generated, by scenario
+    /// scripts.
+    pub formed_count: usize,
+
+    /// How long each phase of the last step took.
+    pub timings: StepTimings,
+
+    /// The ID the next star added to the galaxy is given.
+    next_star_id: u64,
+
+    /// The spatial hash grid, which is built from the stars every substep instead of being used
+    /// from the quadtree when it's the selected spatial backend.
+    grid: Option<SpatialGrid>,
+}
+
+impl GalaxySim {
+    /// Generate a new galaxy from the given random number streams.
+    pub fn new(config: &Config, streams: &RngStreams) -> Result<Self, Box<dyn Error>> {
+        let stars = generation::generate_stars(config, streams);
+        Self::from_stars(config, stars)
+    }
+
+    /// Create a galaxy from an existing list of stars. The first star is assumed to be the
+    /// supermassive black hole, if the config has one.
+    pub fn from_stars(config: &Config, stars: Vec<Star>) -> Result<Self, Box<dyn Error>> {
+        // Create quadtree, and check the grid can be created, so that neither can fail to be
+        // rebuilt each substep.
+        let quadtree = Self::create_quadtree(config)?;
+        SpatialGrid::new(config.simulation.grid_cell_size)?;
+
+        let mut sim = Self {
+            myr_per_second: config.simulation.initial_myr_per_second,
+            substep_count: 0,
+            safety: StepSafety::default(),
+            elapsed_time: 0.0,
+            config: config.clone(),
+            stars: stars.into_iter().collect(),
+            quadtree,
+            escaped_count: 0,
+            disrupted_count: 0,
+            tidal_disruptions: Vec::new(),
+            supernova_count: 0,
+            formed_count: 0,
+            timings: StepTimings::default(),
+            grid: None,
+            next_star_id: 1,
+        }.with_star_ids();
+
+        // Stars outside the quadtree's bounds are discarded, as they are when it's rebuilt.
+        sim.rebuild_quadtree();
+        sim.escaped_count = 0;
+        Ok(sim)
+    }
+
+    /// Assign IDs to the stars that don't have one yet, after those of the stars that do.
+    fn with_star_ids(mut self) -> Self {
+        let max_id = self.stars.query::<&StarId>().map(|id| id.0).max().unwrap_or(0);
+        self.next_star_id = u64::max(self.next_star_id, max_id + 1);
+        self.assign_star_ids();
+        self
+    }
+
+    /// Assign IDs to stars that have been added without one, e.g. by scenario scripts.
+    pub fn assign_star_ids(&mut self) {
+        let mut next_star_id = self.next_star_id;
+        self.stars.for_each_mut::<&mut StarId, _>(|_, mut id| {
+            if id.0 == 0 {
+                id.0 = next_star_id;
+                next_star_id += 1;
+            }
+        });
+        self.next_star_id = next_star_id;
+    }
+
+    /// Get the stars in the galaxy.
+    pub fn stars(&self) -> &StarWorld {
+        &self.stars
+    }
+
+    /// Get the stars mutably, e.g. to add or remove stars. The quadtree is rebuilt from the stars at
+    /// the start of each step, so until then it may refer to stars that no longer exist. Stars that
+    /// are added should be given IDs with assign_star_ids.
+    pub fn stars_mut(&mut self) -> &mut StarWorld {
+        &mut self.stars
+    }
+
+    /// Get the config the galaxy was created with.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Set the Barnes-Hut opening angle used from the next step onwards.
+    pub fn set_opening_angle(&mut self, opening_angle: f64) {
+        self.config.simulation.opening_angle = opening_angle;
+    }
+
+    /// Set the spatial acceleration structure used from the next step onwards.
+    pub fn set_spatial_backend(&mut self, spatial_backend: SpatialBackend) {
+        self.config.simulation.spatial_backend = spatial_backend;
+    }
+
+    /// The spatial acceleration structure used to calculate gravity in the last substep.
+    fn accel(&self) -> &dyn SpatialAccel {
+        match &self.grid {
+            Some(grid) => grid,
+            None => &self.quadtree,
+        }
+    }
+
+    /// Set whether the stars are moved into the center of momentum frame at the start of each step.
+    pub fn set_recenter(&mut self, recenter: bool) {
+        self.config.simulation.recenter = recenter;
+    }
+
+    /// Move the stars into the center of momentum frame, by subtracting the mass weighted mean
+    /// velocity of the stars from each of them, and moving them so that their barycenter is at the
+    /// origin. The quadtree is rebuilt from the stars at the start of each step, so until then its
+    /// mass distribution is out of date.
+    pub fn recenter(&mut self) {
+        let mut mass = 0.0;
+        let mut barycenter = Vec2d::new(0.0, 0.0);
+        let mut momentum = Vec2d::new(0.0, 0.0);
+        for (position, velocity, star_mass) in self.stars.query::<(&Position, &Velocity, &Mass)>() {
+            mass += star_mass.0.0;
+            barycenter = barycenter + position.0 * star_mass.0.0;
+            momentum = momentum + velocity.0 * star_mass.0.0;
+        }
+
+        if mass == 0.0 {
+            return;
+        }
+
+        let (barycenter, velocity) = (barycenter / mass, momentum / mass);
+        self.stars.for_each_mut::<(&mut Position, &mut Velocity), _>(|_, (mut position, mut star_velocity)| {
+            position.0 = position.0 - barycenter;
+            star_velocity.0 = star_velocity.0 - velocity;
+        });
+    }
+
+    /// Put a star on a circular orbit around the central black hole, or the barycenter if there
+    /// isn't one, through where it is and in the direction it's already going around it, or
+    /// anticlockwise if it isn't. The speed balances the inward pull of the rest of the galaxy and
+    /// the halo at the star, as the quadtree's mass distribution last saw them. The star is left as
+    /// it is if it's a black hole, or if nothing pulls it inward.
+    pub fn circularize_orbit(&mut self, index: usize) {
+        let (position, star_velocity) = match self.stars.component::<Species>(index) {
+            Some(species) if species.0 != StarComponent::BlackHole => {
+                (self.stars.component::<Position>(index).unwrap().0, self.stars.component::<Velocity>(index).unwrap().0)
+            },
+            _ => return,
+        };
+
+        let (center_position, center_velocity) = self.orbit_center();
+        let offset = position - center_position;
+        let radius = offset.x.hypot(offset.y);
+        if radius == 0.0 {
+            return;
+        }
+
+        let acceleration = Self::acceleration_at_point(&self.quadtree, &self.config, position);
+        let inward_acceleration = -(acceleration.x * offset.x + acceleration.y * offset.y) / radius;
+        if inward_acceleration <= 0.0 {
+            return;
+        }
+
+        // Keep the sign of the star's angular momentum around the black hole.
+        let relative_velocity = star_velocity - center_velocity;
+        let direction = match offset.x * relative_velocity.y - offset.y * relative_velocity.x < 0.0 {
+            true => -1.0,
+            false => 1.0,
+        };
+        let tangent = Vec2d::new(-offset.y, offset.x) / radius;
+        let velocity = center_velocity + tangent * (direction * f64::sqrt(inward_acceleration * radius));
+
+        if let Some(mut star_velocity) = self.stars.component_mut::<Velocity>(index) {
+            star_velocity.0 = velocity;
+        }
+    }
+
+    /// Add a random velocity to every star except the black holes, with each component drawn from
+    /// a normal distribution with the given standard deviation in km/s. Perturbing a state by a
+    /// tiny amount and comparing it with the unperturbed one shows how quickly they diverge.
+    pub fn perturb<R: Rng>(&mut self, rng: &mut R, speed: f64) {
+        let distribution = match Normal::new(0.0, speed) {
+            Ok(distribution) => distribution,
+            Err(_) => return,
+        };
+
+        self.stars.for_each_mut::<(&Species, &mut Velocity), _>(|_, (species, mut velocity)| {
+            if species.0 != StarComponent::BlackHole {
+                velocity.0 = velocity.0 + Vec2d::new(distribution.sample(rng), distribution.sample(rng));
+            }
+        });
+    }
+
+    /// The position and velocity of what stars orbit: the first black hole, or the barycenter and
+    /// its velocity if there are no black holes.
+    fn orbit_center(&self) -> (Vec2d, Vec2d) {
+        let stars = || self.stars.query::<(&Position, &Velocity, &Mass, &Species)>();
+        if let Some((position, velocity, _, _)) = stars().find(|(_, _, _, species)| species.0 == StarComponent::BlackHole) {
+            return (position.0, velocity.0);
+        }
+
+        let mut mass = 0.0;
+        let mut barycenter = Vec2d::new(0.0, 0.0);
+        let mut momentum = Vec2d::new(0.0, 0.0);
+        for (position, velocity, star_mass, _) in stars() {
+            mass += star_mass.0.0;
+            barycenter = barycenter + position.0 * star_mass.0.0;
+            momentum = momentum + velocity.0 * star_mass.0.0;
+        }
+
+        match mass > 0.0 {
+            true => (barycenter / mass, momentum / mass),
+            false => (Vec2d::new(0.0, 0.0), Vec2d::new(0.0, 0.0)),
+        }
+    }
+
+    /// The total simulation time elapsed since the galaxy was generated, in Myr.
+    pub fn elapsed_myr(&self) -> f64 {
+        Myr::from_time_units(self.elapsed_time).0
+    }
+
+    /// Create an empty quadtree with bounds large enough to contain the galaxy.
+    fn create_quadtree(config: &Config) -> Result<Quadtree<StarHandle, Region>, Box<dyn Error>> {
+        let extent_radius = config.generation.extent_radius();
+        Quadtree::with_max_depth(Vec2d::new(-extent_radius*2.0, -extent_radius*2.0),
+                                 Vec2d::new(extent_radius*2.0, extent_radius*2.0),
+                                 config.simulation.max_tree_depth)
+    }
+
+    pub fn update_mass_distribution(quadtree: &mut Quadtree<StarHandle, Region>) {
+        // Update mass distributions recursively. We only need to do this if the root node is an
+        // internal node. If it's a leaf node then nothing needs doing, if it's empty then nothing
+        // needs doing.
+        let root_index = HilbertIndex(0, 0);
+        if let Some(root_node) = quadtree.get(root_index) {
+            if root_node.is_internal() {
+                Self::update_mass_distribution_inner(quadtree, root_index);
+            }
+        }
+    }
+
+    fn update_mass_distribution_inner(quadtree: &mut Quadtree<StarHandle, Region>,
+                                      index: HilbertIndex)
+    {
+        // Update all children recursively, and then sum up their masses and produce a weighted
+        // center of mess.
+        let mut mass = SolarMass(0.0);
+        let mut center_of_mass = Vec2d::new(0.0, 0.0);
+
+        for child_index in index.children() {
+            let child_node = quadtree.get(child_index);
+            if child_node.is_none() {
+                continue;
+            }
+            let child_node = child_node.unwrap();
+
+            // Update our mass and weighted center of mass.
+            match child_node {
+                &QuadtreeNode::Internal(region_index) => {
+                    // If the child node is itself an internal node, we need to recurse deeper and update
+                    // the children first.
+                    Self::update_mass_distribution_inner(quadtree, child_index);
+
+                    // All child regions should be initialised now due to recursion.
+                    let region = quadtree.get_internal(region_index)
+                        .expect(&format!("Internal error: child region {region_index:?} not initialised"));
+                    mass += region.mass;
+                    center_of_mass.x += region.mass.0 * region.center_of_mass.x;
+                    center_of_mass.y += region.mass.0 * region.center_of_mass.y;
+                },
+                &QuadtreeNode::Leaf(first_item) => {
+                    for item_index in quadtree.leaf_items(first_item) {
+                        let star = quadtree.get_item(item_index)
+                            .expect("Internal error: failed to get star from leaf node");
+                        mass += star.mass;
+                        center_of_mass.x += star.mass.0 * star.position.x;
+                        center_of_mass.y += star.mass.0 * star.position.y;
+                    }
+                }
+            }
+        }
+
+        // Calculate our weighted center of mass and store it.
+        if mass.0 != 0.0 {
+            center_of_mass.x /= mass.0;
+            center_of_mass.y /= mass.0;
+        }
+
+        // Update region data for this internal node.
+        match quadtree.get(index) {
+            Some(&QuadtreeNode::Internal(region_index)) => {
+                let region = Region { mass, center_of_mass };
+                quadtree.set_internal(region_index, Some(region));
+            },
+            _ => panic!("Found non-internal node when updating mass distribution")
+        }
+    }
+
+    /// Calculate the forces on an object of a given mass at a given point. To save an unnecessary
+    /// multiplication followed by an inevitable division when calculating the acceleration, we omit
+    /// the mass of the body since it cancels out anyway:
+    ///   Fgravity = (mass a * mass b * gravitation constant) / distance^2
+    ///   acceleration = force / mass (from F = ma)
+    pub fn acceleration_at_point(quadtree: &Quadtree<StarHandle, Region>, config: &Config, point: Vec2d) -> Vec2d {
+        Self::acceleration_at_point_inner(quadtree, config, point, HilbertIndex(0, 0))
+            + Self::halo_acceleration_at_point(config, point)
+    }
+
+    /// Calculate the acceleration at a point by summing the gravity of every star directly, rather
+    /// than approximating distant stars with the quadtree. This is O(n) per point, so it's only
+    /// useful as a reference to measure the error of the approximation against.
+    pub fn direct_acceleration_at_point(stars: &StarWorld, config: &Config, point: Vec2d) -> Vec2d {
+        let SimulationConfig { gravitational_constant, min_gravity_distance_squared, .. } = config.simulation;
+
+        let mut acceleration = Self::halo_acceleration_at_point(config, point);
+        for star in stars.handles() {
+            // As in acceleration_at_point, a star at the point itself is ignored.
+            let diff = star.position - point;
+            let d_squared = f64::max(min_gravity_distance_squared, diff.x * diff.x + diff.y * diff.y);
+
+            if d_squared > 0.0 {
+                let dist = f64::sqrt(d_squared);
+                acceleration = acceleration + diff / dist * star.mass.acceleration_at(gravitational_constant, d_squared);
+            }
+        }
+
+        acceleration
+    }
+
+    /// Calculate the acceleration towards the center due to the dark matter halo, which only
+    /// depends on the mass of the halo closer to the center than the point.
+    fn halo_acceleration_at_point(config: &Config, point: Vec2d) -> Vec2d {
+        let halo = &config.simulation.halo;
+        let dist = f64::sqrt(point.x * point.x + point.y * point.y);
+        if halo.mass == 0.0 || dist == 0.0 {
+            return Vec2d::new(0.0, 0.0);
+        }
+
+        let force_of_gravity = halo.enclosed_mass(dist) * config.simulation.gravitational_constant / (dist * dist);
+        point * (-force_of_gravity / dist)
+    }
+
+    /// Calculate the forces on an object from a particular tree node, recursively.
+    fn acceleration_at_point_inner(quadtree: &Quadtree<StarHandle, Region>,
+                                   config: &Config,
+                                   point: Vec2d,
+                                   index: HilbertIndex) -> Vec2d
+    {
+        let SimulationConfig { gravitational_constant, opening_angle, min_gravity_distance_squared, .. } =
+            config.simulation;
+
+        let mut force = Vec2d::new(0.0, 0.0);
+
+        match quadtree.get(index) {
+            Some(&QuadtreeNode::Leaf(first_item)) => {
+                for item_index in quadtree.leaf_items(first_item) {
+                    let star = quadtree.get_item(item_index)
+                        .expect("Failed to get star");
+
+                    // If the star is at the same position as the point, we should ignore it as it's
+                    // probably the object itself, and otherwise we'll end up dividing by zero anyway.
+                    let diff = star.position - point;
+                    let d_squared = f64::max(min_gravity_distance_squared,
+                                             diff.x * diff.x + diff.y * diff.y);
+
+                    if d_squared > 0.0 {
+                        let dist = f64::sqrt(d_squared);
+                        let dir = diff / dist;
+                        let force_of_star_gravity = star.mass.acceleration_at(gravitational_constant, d_squared);
+
+                        force = force + dir * force_of_star_gravity;
+                    }
+                }
+            },
+            Some(&QuadtreeNode::Internal(region_index)) => {
+                let region = quadtree.get_internal(region_index)
+                    .expect(&format!("Region {index:?} uninitialised when calculating forces"));
+
+                let diff = region.center_of_mass - point;
+                let dist_squared = diff.x * diff.x + diff.y * diff.y;
+                let dist = f64::sqrt(dist_squared);
+                let node_size = index.node_size(quadtree.min, quadtree.max).x;
+                let dir = diff / dist;
+
+                // Barnes-Hut criterion: if the region is small enough relative to its distance,
+                // approximate it by its center of mass, otherwise recurse into its children.
+                if dist != 0.0 && node_size / dist < opening_angle {
+                    let force_of_gravity = region.mass.acceleration_at(gravitational_constant, dist_squared);
+                    force = force + dir * force_of_gravity;
+                }
+                else {
+                    for child_index in index.children() {
+                        force = force + Self::acceleration_at_point_inner(quadtree, config, point, child_index);
+                    }
+                }
+            },
+            _ => {},
+        }
+
+        force
+    }
+
+    /// Calculate the gravitational potential per unit mass at a given point, in km^2/s^2.
+    pub fn potential_at_point(quadtree: &Quadtree<StarHandle, Region>, config: &Config, point: Vec2d) -> f64 {
+        // The potential of a Hernquist halo is -GM / (r + a).
+        let halo = &config.simulation.halo;
+        let dist = f64::sqrt(point.x * point.x + point.y * point.y);
+        let halo_potential = if halo.mass != 0.0 {
+            -halo.mass * config.simulation.gravitational_constant / (dist + halo.scale_radius)
+        }
+        else {
+            0.0
+        };
+
+        Self::potential_at_point_inner(quadtree, config, point, HilbertIndex(0, 0)) + halo_potential
+    }
+
+    /// Calculate the potential at a point from a particular tree node, recursively. This uses the
+    /// same approximations as acceleration_at_point.
+    fn potential_at_point_inner(quadtree: &Quadtree<StarHandle, Region>,
+                                config: &Config,
+                                point: Vec2d,
+                                index: HilbertIndex) -> f64
+    {
+        let SimulationConfig { gravitational_constant, opening_angle, min_gravity_distance_squared, .. } =
+            config.simulation;
+
+        match quadtree.get(index) {
+            Some(&QuadtreeNode::Leaf(first_item)) => {
+                quadtree.leaf_items(first_item)
+                    .map(|item_index| {
+                        let star = quadtree.get_item(item_index)
+                            .expect("Failed to get star");
+
+                        // As with the acceleration, a star at the point itself is ignored.
+                        let diff = star.position - point;
+                        let d_squared = f64::max(min_gravity_distance_squared,
+                                                 diff.x * diff.x + diff.y * diff.y);
+
+                        if d_squared > 0.0 {
+                            star.mass.potential_at(gravitational_constant, f64::sqrt(d_squared))
+                        }
+                        else {
+                            0.0
+                        }
+                    })
+                    .sum()
+            },
+            Some(&QuadtreeNode::Internal(region_index)) => {
+                let region = quadtree.get_internal(region_index)
+                    .unwrap_or_else(|| panic!("Region {index:?} uninitialised when calculating potential"));
+
+                let diff = region.center_of_mass - point;
+                let dist = f64::sqrt(diff.x * diff.x + diff.y * diff.y);
+                let node_size = index.node_size(quadtree.min, quadtree.max).x;
+
+                if dist != 0.0 && node_size / dist < opening_angle {
+                    region.mass.potential_at(gravitational_constant, dist)
+                }
+                else {
+                    index.children().into_iter()
+                        .map(|child_index| Self::potential_at_point_inner(quadtree, config, point, child_index))
+                        .sum()
+                }
+            },
+            _ => 0.0,
+        }
+    }
+
+    /// The change in the velocity of the star with the given index over a substep due to dynamical
+    /// friction, which slows it relative to the stars and dark matter around it. This is zero
+    /// unless dynamical friction is enabled and the star is massive enough to feel it.
+    fn dynamical_friction(accel: &dyn SpatialAccel, stars: &StarWorld, config: &Config, index: usize, time_delta: f64) -> Vec2d {
+        let SimulationConfig { gravitational_constant, ref dynamical_friction, ref halo, .. } = config.simulation;
+        let body = &accel.stars()[index];
+        if dynamical_friction.coulomb_logarithm <= 0.0 || body.mass.0 < dynamical_friction.min_mass {
+            return Vec2d::new(0.0, 0.0);
+        }
+
+        let world = stars.world();
+        let velocity_of = |entity| world.get::<Velocity>(entity).map_or(Vec2d::new(0.0, 0.0), |velocity| velocity.0);
+        let body_velocity = velocity_of(body.entity);
+        let body_mass = world.get::<Mass>(body.entity).map_or(body.mass, |mass| mass.0);
+
+        // Measure the mass, mean velocity and velocity dispersion of the stars around the body.
+        // Stars bound to the body move along with it rather than forming a wake behind it, such
+        // as those of the satellite whose core it is, so they're left out.
+        let mut mass = 0.0;
+        let mut momentum = Vec2d::new(0.0, 0.0);
+        let mut speed_squared_sum = 0.0;
+        accel.items_in_radius(body.position, dynamical_friction.sample_radius, &mut |_, star| {
+            let velocity = velocity_of(star.entity);
+            let offset = star.position - body.position;
+            let relative_velocity = velocity - body_velocity;
+            let relative_speed_squared = relative_velocity.x * relative_velocity.x + relative_velocity.y * relative_velocity.y;
+            let escape_speed_squared = -2.0 * body_mass.potential_at(gravitational_constant, offset.x.hypot(offset.y));
+
+            if star.entity != body.entity && relative_speed_squared > escape_speed_squared {
+                mass += star.mass.0;
+                momentum = momentum + velocity * star.mass.0;
+                speed_squared_sum += star.mass.0 * (velocity.x * velocity.x + velocity.y * velocity.y);
+            }
+        });
+
+        let friction = ChandrasekharFriction {
+            gravitational_constant,
+            coulomb_logarithm: dynamical_friction.coulomb_logarithm,
+            body_mass: body_mass.0,
+            time_delta,
+        };
+
+        // Chandrasekhar's formula is for a three dimensional distribution, so the stars are treated
+        // as if they were spread through a sphere of the sample radius. The velocities are two
+        // dimensional, so the one dimensional dispersion is half of their total variance.
+        let star_friction = match mass > 0.0 {
+            true => {
+                let mean_velocity = momentum / mass;
+                let mean_speed_squared = mean_velocity.x * mean_velocity.x + mean_velocity.y * mean_velocity.y;
+                let density = mass / (4.0 / 3.0 * PI * dynamical_friction.sample_radius.powi(3));
+                let dispersion_squared = f64::max(0.0, (speed_squared_sum / mass - mean_speed_squared) / 2.0);
+                friction.velocity_change(density, dispersion_squared, body_velocity - mean_velocity)
+            },
+            false => Vec2d::new(0.0, 0.0),
+        };
+
+        // The static halo isn't made of particles, so its density is known exactly. It doesn't
+        // rotate, and it's treated as isothermal at the local circular speed.
+        let radius = body.position.x.hypot(body.position.y);
+        let halo_friction = match halo.mass > 0.0 && radius > 0.0 {
+            true => {
+                let circular_speed_squared = halo.enclosed_mass(radius) * gravitational_constant / radius;
+                friction.velocity_change(halo.density(radius), circular_speed_squared / 2.0, body_velocity)
+            },
+            false => Vec2d::new(0.0, 0.0),
+        };
+
+        star_friction + halo_friction
+    }
+
+    /// Step the simulation forward by `time_delta` seconds of real time at its rate. The step is
+    /// split into as many substeps as it takes for none of them to cover more than the configured
+    /// maximum, or to move stars too far relative to their neighbours, up to a limit, beyond which
+    /// the simulation runs slower than its rate rather than less accurately.
+    pub fn step(&mut self, time_delta: f64) {
+        profile_scope!("Step");
+
+        let step_myr = self.myr_per_second * time_delta;
+        let crossing_myr = self.crossing_myr();
+        let max_displacement_fraction = self.config.simulation.max_displacement_fraction;
+        let max_displacement_myr = match max_displacement_fraction > 0.0 {
+            true => crossing_myr * max_displacement_fraction,
+            false => f64::INFINITY,
+        };
+
+        let max_substep_myr = f64::min(self.max_step_myr(), max_displacement_myr);
+        let max_substeps = usize::max(self.config.simulation.max_substeps, 1);
+        let (substep_count, substep_myr) = match max_substep_myr.is_finite() && max_substep_myr > 0.0 {
+            true => {
+                let substep_count = usize::clamp((step_myr / max_substep_myr).ceil() as usize, 1, max_substeps);
+                (substep_count, f64::min(step_myr / substep_count as f64, max_substep_myr))
+            },
+            false => (1, step_myr),
+        };
+
+        self.safety = StepSafety {
+            displacement_fraction: substep_myr / crossing_myr,
+            ..Default::default()
+        };
+        self.tidal_disruptions.clear();
+
+        for _ in 0..substep_count {
+            self.substep(Myr(substep_myr).to_time_units());
+        }
+        self.substep_count = substep_count;
+
+        // The stars have aged, so their colours may have changed.
+        self.stars.update_render_colors();
+    }
+
+    /// The most simulated time a substep can cover, in Myr, or infinity for no limit.
+    fn max_step_myr(&self) -> f64 {
+        match self.config.simulation.max_step_myr > 0.0 {
+            true => self.config.simulation.max_step_myr,
+            false => f64::INFINITY,
+        }
+    }
+
+    /// How long it takes stars to move the distance to their neighbours, in Myr, or infinity if
+    /// none are moving. The distance to a star's neighbours is approximated by the size of its leaf
+    /// in the quadtree built in the last step, and the fastest few stars relative to it are
+    /// ignored.
+    fn crossing_myr(&self) -> f64 {
+        // How long each star takes to cross its leaf node.
+        let mut crossing_times = Vec::new();
+        self.quadtree.walk_nodes(|index, node| {
+            if let &QuadtreeNode::Leaf(first_star) = node {
+                for item_index in self.quadtree.leaf_items(first_star) {
+                    let velocity = self.quadtree.get_item(item_index)
+                        .and_then(|star| self.stars.world().get::<Velocity>(star.entity));
+                    if let Some(&Velocity(velocity)) = velocity {
+                        let speed = velocity.x.hypot(velocity.y);
+                        if speed > 0.0 {
+                            let (min, max) = index.bounds(self.quadtree.min, self.quadtree.max);
+                            crossing_times.push(Parsec(max.x - min.x) / KmPerSec(speed));
+                        }
+                    }
+                }
+            }
+        });
+
+        if crossing_times.is_empty() {
+            return f64::INFINITY;
+        }
+
+        let outlier_count = (crossing_times.len() as f64 * OUTLIER_FRACTION) as usize;
+        let (_, &mut crossing_time, _) = crossing_times.select_nth_unstable_by(outlier_count, |a, b| a.0.total_cmp(&b.0));
+        crossing_time.0
+    }
+
+    /// Step the simulation forward by the given time in simulation time units.
+    fn substep(&mut self, time_delta: f64) {
+        if self.config.simulation.recenter {
+            self.recenter();
+        }
+
+        self.disrupt_stars();
+
+        let tree_build = profile_span!("Tree build");
+        self.rebuild_quadtree();
+        match self.age_stars(time_delta) {
+            true => self.rebuild_quadtree(),
+            false => self.refresh_quadtree_masses(),
+        }
+        self.timings.quadtree_ms = tree_build.finish();
+
+        // Update cached mass distribution and integrate.
+        let mass_distribution = profile_span!("Mass distribution");
+        Self::update_mass_distribution(&mut self.quadtree);
+        self.timings.mass_distribution_ms = mass_distribution.finish();
+
+        // Build the grid from the quadtree's handles if it's the selected backend. The forces on all
+        // of the stars are calculated before any of them move, so the handles don't go stale.
+        let grid_build = profile_span!("Grid build");
+        self.grid = match self.config.simulation.spatial_backend {
+            SpatialBackend::Quadtree => None,
+            SpatialBackend::Grid => {
+                let mut grid = SpatialGrid::new(self.config.simulation.grid_cell_size)
+                    .expect("Grid cell size was checked when the simulation was created");
+                for &star in &self.quadtree.items {
+                    grid.insert(star);
+                }
+                Some(grid)
+            },
+        };
+        self.timings.grid_ms = grid_build.finish();
+
+        let force_eval = profile_span!("Force eval");
+        self.integrate(time_delta);
+        self.timings.integrate_ms = force_eval.finish();
+
+        self.elapsed_time += time_delta;
+    }
+
+    /// Rebuild the quadtree from the stars.
+    fn rebuild_quadtree(&mut self) {
+        // Lets just make a new quadtree every time...
+        self.quadtree = Self::create_quadtree(&self.config).unwrap();
+
+        let mut escaped = Vec::new();
+        for (i, star) in self.stars.handles().enumerate() {
+            let item_count = self.quadtree.items.len();
+            Self::add_to_quadtree(&mut self.quadtree, star);
+            if self.quadtree.items.len() == item_count {
+                escaped.push(i);
+            }
+        }
+
+        // Stars outside the quadtree's bounds are discarded when it's rebuilt, so that its items
+        // stay in the same order as the stars.
+        if !escaped.is_empty() {
+            let mut escaped_stars = escaped.iter().peekable();
+            self.stars.retain(|i| escaped_stars.next_if_eq(&&i).is_none());
+            self.escaped_count += escaped.len();
+        }
+    }
+
+    /// Update the masses of the quadtree's handles from the stars, after aging has changed them
+    /// without the stars being removed, so that the mass distribution is worked out from their new
+    /// masses without rebuilding the tree.
+    fn refresh_quadtree_masses(&mut self) {
+        for (item, star) in self.quadtree.items.iter_mut().zip(self.stars.handles()) {
+            item.mass = star.mass;
+        }
+    }
+
+    /// Add a star to the quadtree. Tracers are added without being placed in the tree, so that
+    /// they don't change its shape or mass distribution, and so can't change how the stars move.
+    fn add_to_quadtree(quadtree: &mut Quadtree<StarHandle, Region>, star: StarHandle) {
+        match star.species {
+            StarComponent::Tracer => quadtree.add_unindexed(star),
+            _ => quadtree.add(star),
+        }
+    }
+
+    /// Age the stars by the given time in simulation time units, shedding the mass they lose as
+    /// they evolve, and explode those massive enough to go supernova that reach the end of their
+    /// lives, kicking the stars around them away and leaving a remnant behind. The quadtree is used
+    /// to find the stars to kick, so it must have just been rebuilt, and its handles' masses and
+    /// mass distribution updated afterwards. Returns whether any stars were removed, in which case
+    /// it needs rebuilding again instead, which is rare enough not to be worth avoiding.
+    fn age_stars(&mut self, time_delta: f64) -> bool {
+        let age_change = Myr::from_time_units(time_delta).0 / 1000.0;
+        let mass_loss = &self.config.simulation.mass_loss;
+        let config = &self.config.simulation.supernovae;
+
+        let mut progenitors = Vec::new();
+        self.stars.for_each_mut::<(&mut Age, &mut Mass, &Species), _>(|i, (mut age, mut mass, species)| {
+            let previous_age = age.0;
+            age.0 += age_change;
+
+            // Only stars lose mass as they age.
+            if species.0.is_star() {
+                mass.0 = mass.0 * evolution::remaining_mass_fraction(mass_loss, previous_age, age.0);
+            }
+
+            if config.kick_speed > 0.0 && mass.0.0 >= config.min_mass
+                && species.0 != StarComponent::BlackHole
+                && evolution::dies_between(mass.0, previous_age, age.0)
+            {
+                progenitors.push(i);
+            }
+        });
+
+        // Kick the stars around each supernova away from it, less the further away they are.
+        // Black holes are too massive to be moved by them.
+        for &progenitor in &progenitors {
+            let progenitor = self.quadtree.items[progenitor];
+            let center = progenitor.position;
+            let mut kicks = Vec::new();
+            self.quadtree.items_in_radius(center, config.kick_radius, |_, star| {
+                let offset = star.position - center;
+                let distance = offset.x.hypot(offset.y);
+                if star.entity != progenitor.entity && distance > 0.0 && star.species != StarComponent::BlackHole {
+                    let speed = config.kick_speed * (1.0 - distance / config.kick_radius);
+                    kicks.push((star.entity, offset * (speed / distance)));
+                }
+            });
+
+            for (entity, kick) in kicks {
+                if let Some(mut velocity) = self.stars.world_mut().get_mut::<Velocity>(entity) {
+                    velocity.0 = velocity.0 + kick;
+                }
+            }
+        }
+        self.supernova_count += progenitors.len();
+
+        match config.remnant_mass > 0.0 {
+            true => {
+                for &progenitor in &progenitors {
+                    if let Some(mut mass) = self.stars.component_mut::<Mass>(progenitor) {
+                        mass.0 = SolarMass(config.remnant_mass);
+                    }
+                }
+                false
+            },
+            false => {
+                let mut exploded = progenitors.iter().peekable();
+                self.stars.retain(|i| exploded.next_if_eq(&&i).is_none());
+                !progenitors.is_empty()
+            },
+        }
+    }
+
+    /// Tear apart the stars within the tidal disruption radius of a black hole, adding their mass
+    /// and momentum to it. This is done before the quadtree is rebuilt, so that the stars removed
+    /// are gone by the time it is. Black holes are never disrupted by each other.
+    fn disrupt_stars(&mut self) {
+        let radius = self.config.simulation.tidal_disruption_radius;
+        if radius <= 0.0 {
+            return;
+        }
+
+        let black_holes: Vec<usize> = self.stars.query::<(&Species, &Mass)>()
+            .enumerate()
+            .filter(|(_, (species, mass))| species.0 == StarComponent::BlackHole && mass.0.0 > 0.0)
+            .map(|(i, _)| i)
+            .collect();
+
+        let radius_squared = radius * radius;
+        let mut disrupted = vec![false; self.stars.len()];
+        for &black_hole in &black_holes {
+            let mut accreted_mass = SolarMass(0.0);
+            let mut accreted_momentum = Vec2d::new(0.0, 0.0);
+            let center = self.stars.component::<Position>(black_hole).unwrap().0;
+            let stars = self.stars.query::<(&Position, &Velocity, &Mass, &Species)>();
+            for (i, (position, velocity, mass, species)) in stars.enumerate() {
+                let offset = position.0 - center;
+                if disrupted[i] || species.0 == StarComponent::BlackHole
+                    || offset.x * offset.x + offset.y * offset.y > radius_squared
+                {
+                    continue;
+                }
+
+                let gravitating_mass = species.0.gravitating_mass(mass.0);
+                disrupted[i] = true;
+                accreted_mass += gravitating_mass;
+                accreted_momentum = accreted_momentum + velocity.0 * gravitating_mass.0;
+                self.tidal_disruptions.push(TidalDisruption {
+                    position: position.0,
+                    mass: mass.0,
+                    elapsed_myr: Myr::from_time_units(self.elapsed_time).0,
+                });
+            }
+
+            if accreted_mass.0 > 0.0 {
+                let entity = self.stars.entity(black_hole).unwrap();
+                let world = self.stars.world_mut();
+                let (velocity, mass) = (world.get::<Velocity>(entity).unwrap().0, world.get::<Mass>(entity).unwrap().0);
+                let momentum = velocity * mass.0 + accreted_momentum;
+                let mass = mass + accreted_mass;
+                world.entity_mut(entity).insert((Mass(mass), Velocity(momentum / mass.0)));
+            }
+        }
+
+        let star_count = self.stars.len();
+        self.stars.retain(|i| !disrupted[i]);
+        self.disrupted_count += star_count - self.stars.len();
+    }
+
+    /// Integrate stars.
+    fn integrate(&mut self, time_delta: f64) {
+        if self.config.generation.three_body.enabled {
+            self.integrate_three_body(time_delta);
+            return;
+        }
+
+        // Integrate all star velocities and positions. Binaries move as a single particle, and
+        // their orbits around each other are advanced analytically. The central black hole, which
+        // is the first one and what orbit_center has stars orbit, is pinned where it is, wherever
+        // it is in the list of stars. Any other black holes, such as those of the galaxies in a
+        // cluster, move like the rest.
+        // TODO: integrating the central black hole breaks it and makes it disappear, it's not
+        // really necessary but it would be nice to work out why :)
+        // The forces on every star are calculated before any of them move, so that stars moved
+        // earlier in the loop don't pull on the rest from where they've moved to.
+        let central_black_hole = self.stars.query::<&Species>()
+            .position(|species| species.0 == StarComponent::BlackHole);
+        let moving: Vec<usize> = (0..self.stars.len())
+            .filter(|&i| Some(i) != central_black_hole)
+            .collect();
+        let accel = self.accel();
+        let mut velocity_changes: Vec<Option<Vec2d>> = vec![None; self.stars.len()];
+        for &i in &moving {
+            let position = self.stars.component::<Position>(i).unwrap().0;
+            let acceleration = accel.acceleration_at_point(&self.config, position)
+                + Self::halo_acceleration_at_point(&self.config, position);
+            let friction = Self::dynamical_friction(accel, &self.stars, &self.config, i, time_delta);
+            velocity_changes[i] = Some(acceleration * time_delta + friction);
+        }
+
+        let gravitational_constant = self.config.simulation.gravitational_constant;
+        let safety = &mut self.safety;
+        let mut velocity_change_fractions = Vec::with_capacity(moving.len());
+        self.stars.for_each_mut::<(&mut Position, &mut Velocity, &Mass, &Species, Option<&mut Binary>), _>(
+            |i, (mut position, mut velocity, mass, species, binary)| {
+                let velocity_change = match velocity_changes[i] {
+                    Some(velocity_change) => velocity_change,
+                    None => return,
+                };
+
+                let speed = velocity.0.x.hypot(velocity.0.y);
+                velocity.0 = velocity.0 + velocity_change;
+                position.0 = position.0 + velocity.0 * time_delta;
+                if let Some(mut binary) = binary {
+                    binary.advance(gravitational_constant, mass.0, time_delta);
+                }
+
+                // Track how large the step was relative to the star's motion. Tracers don't affect
+                // the stars, so neither do their steps.
+                if species.0 == StarComponent::Tracer {
+                    return;
+                }
+                let velocity_change = KmPerSec(velocity_change.x.hypot(velocity_change.y));
+                let displacement = KmPerSec(velocity.0.x.hypot(velocity.0.y)) * Myr::from_time_units(time_delta);
+                safety.max_displacement = Parsec(f64::max(safety.max_displacement.0, displacement.0));
+                safety.max_velocity_change = KmPerSec(f64::max(safety.max_velocity_change.0, velocity_change.0));
+                if speed > 0.0 {
+                    velocity_change_fractions.push(velocity_change.0 / speed);
+                }
+            });
+
+        // Ignore the stars whose velocity changed the most, as with their displacement.
+        if !velocity_change_fractions.is_empty() {
+            let outlier_count = (velocity_change_fractions.len() as f64 * OUTLIER_FRACTION) as usize;
+            let index = velocity_change_fractions.len() - 1 - outlier_count;
+            let (_, &mut velocity_change_fraction, _) = velocity_change_fractions.select_nth_unstable_by(index, f64::total_cmp);
+            self.safety.velocity_change_fraction = f64::max(self.safety.velocity_change_fraction, velocity_change_fraction);
+        }
+    }
+
+    /// Integrate the restricted three-body mode. The first two stars are the massive bodies, whose
+    /// orbit around each other is advanced analytically, and the rest are test particles which
+    /// are integrated in the bodies' field directly rather than through the quadtree.
+    fn integrate_three_body(&mut self, time_delta: f64) {
+        let SimulationConfig { gravitational_constant, min_gravity_distance_squared, .. } = self.config.simulation;
+        let (mut primary, mut secondary) = match (self.stars.get(0), self.stars.get(1)) {
+            (Some(primary), Some(secondary)) => (primary, secondary),
+            _ => return,
+        };
+
+        self.stars.for_each_mut::<(&mut Position, &mut Velocity), _>(|i, (mut position, mut velocity)| {
+            if i < 2 {
+                return;
+            }
+
+            let acceleration = three_body::acceleration_at_point([&primary, &secondary],
+                                                                 gravitational_constant,
+                                                                 min_gravity_distance_squared,
+                                                                 position.0);
+            velocity.0 = velocity.0 + acceleration * time_delta;
+            position.0 = position.0 + velocity.0 * time_delta;
+        });
+
+        three_body::advance_bodies(gravitational_constant, &mut primary, &mut secondary, time_delta);
+        for (index, body) in [primary, secondary].into_iter().enumerate() {
+            if let Some(mut star) = self.stars.entity_mut(index) {
+                star.insert((Position(body.position), Velocity(body.velocity)));
+            }
+        }
+    }
+}
+
+/// The error function, using the approximation from Abramowitz and Stegun 7.1.26, which is
+/// accurate to 1.5e-7.
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    (1.0 - polynomial * f64::exp(-x * x)).copysign(x)
+}
+
+/// Chandrasekhar's formula for the dynamical friction on a body moving through a background with a
+/// Maxwellian distribution of velocities.
+struct ChandrasekharFriction {
+    gravitational_constant: f64,
+    coulomb_logarithm: f64,
+    body_mass: f64,
+    time_delta: f64,
+}
+
+impl ChandrasekharFriction {
+    /// The change in the body's velocity over the time delta, given the background's density, its
+    /// one dimensional velocity dispersion squared, and the body's velocity relative to its mean.
+    /// The drag can't do more than stop the body relative to the background.
+    fn velocity_change(&self, density: f64, dispersion_squared: f64, relative_velocity: Vec2d) -> Vec2d {
+        let speed = relative_velocity.x.hypot(relative_velocity.y);
+        if speed == 0.0 || density <= 0.0 {
+            return Vec2d::new(0.0, 0.0);
+        }
+
+        // Only the background moving slower than the body contributes to the drag, and for a
+        // Maxwellian distribution this is the fraction of it that is.
+        let slower_fraction = match dispersion_squared > 0.0 {
+            true => {
+                let x = speed / f64::sqrt(2.0 * dispersion_squared);
+                erf(x) - 2.0 * x / PI.sqrt() * f64::exp(-x * x)
+            },
+            false => 1.0,
+        };
+
+        let deceleration = 4.0 * PI * self.gravitational_constant * self.gravitational_constant * self.body_mass
+            * density * self.coulomb_logarithm * slower_fraction / (speed * speed);
+        let speed_change = f64::min(deceleration * self.time_delta, speed);
+        relative_velocity * (-speed_change / speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_star_moves_without_black_hole() {
+        let mut config = Config::default();
+        config.generation.star_count = 200;
+        config.generation.supermassive_black_hole_mass = 0.0;
+        let mut sim = GalaxySim::new(&config, &RngStreams::new(config.generation.seed)).unwrap();
+        let start = sim.stars().get(0).unwrap();
+        assert_ne!(start.component, StarComponent::BlackHole);
+
+        sim.myr_per_second = 1.0;
+        sim.step(1.0);
+        let end = sim.stars().get(0).unwrap();
+        assert_ne!(end.position, start.position);
+        assert_ne!(end.velocity, start.velocity);
+    }
+
+    #[test]
+    fn quadtree_masses_follow_aging() {
+        let mut config = Config::default();
+        config.generation.star_count = 200;
+        config.simulation.mass_loss.rate = 0.1;
+        let mut sim = GalaxySim::new(&config, &RngStreams::new(config.generation.seed)).unwrap();
+
+        // Step far enough for the stars to lose mass.
+        sim.myr_per_second = 100.0;
+        sim.step(1.0);
+        assert_eq!(sim.quadtree.items.len(), sim.stars().len());
+        for (item, star) in sim.quadtree.items.iter().zip(sim.stars().handles()) {
+            assert_eq!(item.mass, star.mass);
+        }
+    }
+
+    #[test]
+    fn cluster_black_holes_move() {
+        let mut config = Config::default();
+        config.generation.star_count = 100;
+        config.generation.cluster.galaxy_count = 3;
+        let mut sim = GalaxySim::new(&config, &RngStreams::new(config.generation.seed)).unwrap();
+        let black_holes = |sim: &GalaxySim| -> Vec<Vec2d> {
+            sim.stars().query::<(&Position, &Species)>()
+                .filter(|(_, species)| species.0 == StarComponent::BlackHole)
+                .map(|(position, _)| position.0)
+                .collect()
+        };
+
+        // The first is the cluster's massless marker, followed by each galaxy's own black hole.
+        let start = black_holes(&sim);
+        assert_eq!(start.len(), 4);
+
+        sim.myr_per_second = 1.0;
+        sim.step(1.0);
+        let end = black_holes(&sim);
+        assert_eq!(end.len(), 4);
+        assert_eq!(end[0], start[0]);
+        for (start, end) in start.iter().zip(&end).skip(1) {
+            assert_ne!(start, end);
+        }
+    }
+}
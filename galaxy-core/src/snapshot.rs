@@ -0,0 +1,119 @@
+//! Double-buffered snapshots of the simulation's state, which are published after each step so
+//! that other threads and UI panels can read a consistent copy of the stars without borrowing the
+//! live simulation while it's being stepped.
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::simulation::{GalaxySim, StarComponent};
+use crate::star_world::{Mass, Position, Species, Velocity};
+use crate::types::Vec2d;
+
+/// A copy of the state of the stars at the end of a step. The star lists are parallel, indexed by
+/// star.
+#[derive(Clone, Debug, Default)]
+pub struct SimSnapshot {
+    /// The step the snapshot was taken after.
+    pub step: u64,
+
+    /// The total simulation time elapsed when the snapshot was taken, in Myr.
+    pub elapsed_myr: f64,
+
+    /// The positions of the stars, in parsecs.
+    pub positions: Vec<Vec2d>,
+
+    /// The velocities of the stars, in km/s.
+    pub velocities: Vec<Vec2d>,
+
+    /// The masses of the stars, in solar masses.
+    pub masses: Vec<f64>,
+
+    /// Which component of the galaxy each star belongs to.
+    pub components: Vec<StarComponent>,
+}
+
+impl SimSnapshot {
+    /// The number of stars in the snapshot.
+    pub fn star_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Copy the state of a simulation into the snapshot, reusing its allocations.
+    fn capture(&mut self, sim: &GalaxySim, step: u64) {
+        let stars = sim.stars();
+
+        self.step = step;
+        self.elapsed_myr = sim.elapsed_myr();
+
+        self.positions.clear();
+        self.positions.extend(stars.query::<&Position>().map(|position| position.0));
+        self.velocities.clear();
+        self.velocities.extend(stars.query::<&Velocity>().map(|velocity| velocity.0));
+        self.masses.clear();
+        self.masses.extend(stars.query::<&Mass>().map(|mass| mass.0.0));
+        self.components.clear();
+        self.components.extend(stars.query::<&Species>().map(|species| species.0));
+    }
+}
+
+/// Publishes snapshots of a simulation. There are two buffers: the published snapshot, which
+/// readers hold on to for as long as they need it, and a spare one that the next snapshot is
+/// captured into before the two are swapped. If a reader is still holding the old snapshot when
+/// they're swapped it keeps it, and a new spare is allocated.
+pub struct SnapshotPublisher {
+    published: Arc<Mutex<Arc<SimSnapshot>>>,
+    spare: Option<SimSnapshot>,
+}
+
+impl SnapshotPublisher {
+    /// Create a publisher, with an empty snapshot published until the first step.
+    pub fn new() -> Self {
+        Self {
+            published: Arc::new(Mutex::new(Arc::new(SimSnapshot::default()))),
+            spare: None,
+        }
+    }
+
+    /// Capture the state of a simulation after a step and publish it, replacing the previous
+    /// snapshot.
+    pub fn publish(&mut self, sim: &GalaxySim, step: u64) {
+        let mut snapshot = self.spare.take().unwrap_or_default();
+        snapshot.capture(sim, step);
+
+        let mut published = self.published.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = std::mem::replace(&mut *published, Arc::new(snapshot));
+        drop(published);
+        self.spare = Arc::try_unwrap(previous).ok();
+    }
+
+    /// The most recently published snapshot.
+    pub fn latest(&self) -> Arc<SimSnapshot> {
+        self.published.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Create a reader of the published snapshots, which can be sent to other threads.
+    pub fn reader(&self) -> SnapshotReader {
+        SnapshotReader { published: self.published.clone() }
+    }
+}
+
+impl Default for SnapshotPublisher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads the snapshots published by a SnapshotPublisher. The lock is only held while the
+/// snapshot's reference count is incremented, so reading never waits for a step to finish. It's
+/// never held while a snapshot is captured either, so if a thread panics while holding it the
+/// snapshot inside is still whole, and the poisoning is ignored rather than panicking the readers.
+#[derive(Clone)]
+pub struct SnapshotReader {
+    published: Arc<Mutex<Arc<SimSnapshot>>>,
+}
+
+impl SnapshotReader {
+    /// The most recently published snapshot.
+    pub fn latest(&self) -> Arc<SimSnapshot> {
+        self.published.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
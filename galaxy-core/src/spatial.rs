@@ -0,0 +1,284 @@
+//! Spatial acceleration structures, which find the stars near a point and approximate the gravity
+//! of distant ones, so that the simulation doesn't have to consider every pair of stars. The
+//! quadtree and a uniform spatial hash grid both implement the same trait, so the simulation can
+//! switch between them at runtime and they can be benchmarked against each other.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error;
+use std::hash::BuildHasherDefault;
+
+use crate::config::{Config, SimulationConfig};
+use crate::simulation::StarComponent;
+use crate::star_world::StarHandle;
+use crate::types::Vec2d;
+use crate::units::SolarMass;
+
+/// A spatial acceleration structure over a list of handles to stars, which it owns. Queries refer
+/// to stars by their index in the list.
+pub trait SpatialAccel {
+    /// Add a star. Tracers are kept in the list but aren't placed in the structure, so they don't
+    /// pull on anything or turn up in queries.
+    fn insert(&mut self, star: StarHandle);
+
+    /// Rebuild the structure from its stars where they are now, e.g. after they've moved, and
+    /// update its mass distribution.
+    fn rebuild(&mut self);
+
+    /// The stars in the structure.
+    fn stars(&self) -> &[StarHandle];
+
+    /// Call the callback with the index of every star within the given radius of a point.
+    fn items_in_radius(&self, center: Vec2d, radius: f64, f: &mut dyn FnMut(usize, &StarHandle));
+
+    /// The acceleration at a point due to the gravity of the stars, approximating distant ones
+    /// according to the opening angle in the config. This doesn't include the static halo.
+    fn acceleration_at_point(&self, config: &Config, point: Vec2d) -> Vec2d;
+}
+
+/// The map type for the grid's cells, which uses a hasher with fixed keys for the same reason as
+/// the quadtree's nodes.
+type CellMap = HashMap<(i64, i64), GridCell, BuildHasherDefault<DefaultHasher>>;
+
+/// A cell of the grid, with the stars in it and their total mass and center of mass.
+#[derive(Default)]
+struct GridCell {
+    items: Vec<usize>,
+    mass: SolarMass,
+    center_of_mass: Vec2d,
+}
+
+/// A uniform spatial hash grid. Only the cells containing stars are stored, so it's unbounded. It's
+/// cheaper to build than the quadtree, but every cell has to be visited to calculate gravity, so
+/// whether it's faster depends on how many stars there are and how they're spread out.
+pub struct SpatialGrid {
+    cell_size: f64,
+    stars: Vec<StarHandle>,
+    cells: CellMap,
+}
+
+impl SpatialGrid {
+    /// Create an empty grid with cells of the given size, in parsecs, which must be positive and
+    /// finite.
+    pub fn new(cell_size: f64) -> Result<Self, Box<dyn Error>> {
+        if !(cell_size > 0.0 && cell_size.is_finite()) {
+            return Err(format!("Spatial grid cell size of {cell_size} pc isn't positive and finite").into());
+        }
+
+        Ok(Self {
+            cell_size,
+            stars: Vec::new(),
+            cells: CellMap::default(),
+        })
+    }
+
+    /// The number of cells containing stars.
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// The coordinates of the cell containing a point.
+    fn cell_of(&self, point: Vec2d) -> (i64, i64) {
+        ((point.x / self.cell_size).floor() as i64, (point.y / self.cell_size).floor() as i64)
+    }
+
+    /// Place a star that's already in the list into its cell.
+    fn place(&mut self, index: usize) {
+        let star = &self.stars[index];
+        if star.species == StarComponent::Tracer {
+            return;
+        }
+
+        let cell_coords = self.cell_of(star.position);
+        let cell = self.cells.entry(cell_coords).or_default();
+        cell.items.push(index);
+
+        // Keep the cell's center of mass up to date as stars are added.
+        let mass = cell.mass + star.mass;
+        if mass.0 != 0.0 {
+            cell.center_of_mass = (cell.center_of_mass * cell.mass.0 + star.position * star.mass.0) / mass.0;
+        }
+        cell.mass = mass;
+    }
+}
+
+impl SpatialAccel for SpatialGrid {
+    fn insert(&mut self, star: StarHandle) {
+        self.stars.push(star);
+        self.place(self.stars.len() - 1);
+    }
+
+    fn rebuild(&mut self) {
+        self.cells.clear();
+        for index in 0..self.stars.len() {
+            self.place(index);
+        }
+    }
+
+    fn stars(&self) -> &[StarHandle] {
+        &self.stars
+    }
+
+    fn items_in_radius(&self, center: Vec2d, radius: f64, f: &mut dyn FnMut(usize, &StarHandle)) {
+        let radius_squared = radius * radius;
+        let (min_x, min_y) = self.cell_of(center - Vec2d::new(radius, radius));
+        let (max_x, max_y) = self.cell_of(center + Vec2d::new(radius, radius));
+
+        // Visit whichever is fewer of the cells covering the circle and the cells there are.
+        let cells_across = |min: i64, max: i64| max.saturating_sub(min).saturating_add(1);
+        let covered_cells = cells_across(min_x, max_x).saturating_mul(cells_across(min_y, max_y));
+        let mut visit = |cell: &GridCell| {
+            for &index in &cell.items {
+                let star = &self.stars[index];
+                let diff = star.position - center;
+                if diff.x * diff.x + diff.y * diff.y <= radius_squared {
+                    f(index, star);
+                }
+            }
+        };
+        if covered_cells > self.cells.len() as i64 {
+            for (&(x, y), cell) in &self.cells {
+                if (min_x..=max_x).contains(&x) && (min_y..=max_y).contains(&y) {
+                    visit(cell);
+                }
+            }
+        }
+        else {
+            for x in min_x..=max_x {
+                for y in min_y..=max_y {
+                    if let Some(cell) = self.cells.get(&(x, y)) {
+                        visit(cell);
+                    }
+                }
+            }
+        }
+    }
+
+    fn acceleration_at_point(&self, config: &Config, point: Vec2d) -> Vec2d {
+        let SimulationConfig { gravitational_constant, opening_angle, min_gravity_distance_squared, .. } =
+            config.simulation;
+
+        let mut acceleration = Vec2d::new(0.0, 0.0);
+        for cell in self.cells.values() {
+            let diff = cell.center_of_mass - point;
+            let dist_squared = diff.x * diff.x + diff.y * diff.y;
+            let dist = f64::sqrt(dist_squared);
+
+            // The same criterion as Barnes-Hut, but with every cell the same size.
+            if dist != 0.0 && self.cell_size / dist < opening_angle {
+                acceleration = acceleration + diff / dist * cell.mass.acceleration_at(gravitational_constant, dist_squared);
+                continue;
+            }
+
+            for &index in &cell.items {
+                // As with the quadtree, a star at the point itself is ignored.
+                let star = &self.stars[index];
+                let diff = star.position - point;
+                let d_squared = f64::max(min_gravity_distance_squared, diff.x * diff.x + diff.y * diff.y);
+                if d_squared > 0.0 {
+                    let dist = f64::sqrt(d_squared);
+                    acceleration = acceleration + diff / dist * star.mass.acceleration_at(gravitational_constant, d_squared);
+                }
+            }
+        }
+
+        acceleration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::simulation::{GalaxySim, Star};
+    use crate::star_world::StarWorld;
+
+    /// Random stars from -100 to 100 pc, with a few tracers among them.
+    fn stars() -> StarWorld {
+        let mut rng = StdRng::seed_from_u64(3);
+        (0..500).map(|i| Star {
+            position: Vec2d::new(rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0)),
+            mass: SolarMass(rng.gen_range(0.1..10.0)),
+            component: match i % 50 {
+                0 => StarComponent::Tracer,
+                _ => StarComponent::Disk,
+            },
+            ..Default::default()
+        }).collect()
+    }
+
+    /// Build a grid with cells of the given size from the stars.
+    fn build(stars: &StarWorld, cell_size: f64) -> SpatialGrid {
+        let mut grid = SpatialGrid::new(cell_size).unwrap();
+        for star in stars.handles() {
+            grid.insert(star);
+        }
+        grid
+    }
+
+    #[test]
+    fn cell_size_must_be_positive() {
+        for cell_size in [0.0, -10.0, f64::NAN, f64::INFINITY] {
+            assert!(SpatialGrid::new(cell_size).is_err(), "Cell size {cell_size} was accepted");
+        }
+    }
+
+    #[test]
+    fn searches_match_brute_force() {
+        let stars = stars();
+        let handles: Vec<StarHandle> = stars.handles().collect();
+        let distance_squared = |a: Vec2d, b: Vec2d| (a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y);
+
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut queries: Vec<Vec2d> = (0..50)
+            .map(|_| Vec2d::new(rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0)))
+            .collect();
+        queries.extend([Vec2d::new(0.0, 0.0), Vec2d::new(20.0, -40.0), Vec2d::new(-150.0, 0.0), Vec2d::new(0.0, -1000.0)]);
+
+        // Small cells, so large radii visit every cell there is rather than every cell covered,
+        // and large ones.
+        for cell_size in [3.0, 20.0, 500.0] {
+            let grid = build(&stars, cell_size);
+            for &query in &queries {
+                for radius in [0.0, 5.0, 30.0, 300.0] {
+                    let mut found = Vec::new();
+                    grid.items_in_radius(query, radius, &mut |item, _| found.push(item));
+                    found.sort();
+                    let expected: Vec<usize> = (0..handles.len())
+                        .filter(|&i| handles[i].species != StarComponent::Tracer)
+                        .filter(|&i| distance_squared(handles[i].position, query) <= radius * radius)
+                        .collect();
+                    assert_eq!(found, expected, "Wrong items within {radius} of {query:?} with {cell_size} pc cells");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn acceleration_matches_brute_force() {
+        let stars = stars();
+        let grid = build(&stars, 20.0);
+        let mut config = Config::default();
+        config.simulation.halo.mass = 0.0;
+        let relative_error = |config: &Config, point: Vec2d| {
+            let expected = GalaxySim::direct_acceleration_at_point(&stars, config, point);
+            let error = grid.acceleration_at_point(config, point) - expected;
+            error.x.hypot(error.y) / expected.x.hypot(expected.y)
+        };
+
+        // With an opening angle of zero no cell is approximated, so the grid sums every star.
+        config.simulation.opening_angle = 0.0;
+        for star in stars.handles() {
+            let error = relative_error(&config, star.position);
+            assert!(error < 1e-9, "Acceleration at {:?} was off by {:.2}%", star.position, error * 100.0);
+        }
+
+        // Otherwise distant cells are approximated by their centers of mass, which is close on
+        // average.
+        config.simulation.opening_angle = 0.5;
+        let mean_error = stars.handles().map(|star| relative_error(&config, star.position)).sum::<f64>() / stars.len() as f64;
+        assert!(mean_error < 0.05, "Mean acceleration error of {:.2}%", mean_error * 100.0);
+    }
+}
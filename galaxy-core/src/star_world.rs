@@ -0,0 +1,389 @@
+//! The stars of a simulation, stored in an ECS world as an entity per star with a component for
+//! each of its attributes, so that new attributes and the systems that use them can be added as
+//! components and queries of their own rather than by growing one struct that everything shares.
+//! Stars keep an order, as they did when they were a list, so that they can still be referred to by
+//! index, e.g. by the viewer's selection.
+
+use bevy_ecs::component::{Component, Mutable};
+use bevy_ecs::entity::Entity;
+use bevy_ecs::query::{QueryData, ROQueryItem, ReadOnlyQueryData};
+use bevy_ecs::world::{EntityWorldMut, Mut, World};
+
+use crate::quadtree::Spatial;
+use crate::simulation::{Binary, Star, StarComponent, StarTag};
+use crate::types::Vec2d;
+use crate::units::SolarMass;
+
+/// The colours of the youngest, most metal poor stars and the oldest, most metal rich, which stars
+/// are coloured between.
+const YOUNG_STAR_COLOR: [f32; 3] = [0.65, 0.75, 1.0];
+const OLD_STAR_COLOR: [f32; 3] = [1.0, 0.7, 0.45];
+
+/// The age in Gyr at which stars are fully the old colour.
+const OLD_STAR_AGE: f64 = 13.0;
+
+/// How much redder stars get per dex of metallicity, as a fraction of the range between the young
+/// and old colours.
+const METALLICITY_REDDENING: f64 = 0.3;
+
+/// The colour of tracer particles.
+const TRACER_COLOR: [f32; 3] = [0.3, 1.0, 0.9];
+
+/// The colour of dark matter particles.
+const DARK_MATTER_COLOR: [f32; 3] = [0.6, 0.4, 1.0];
+
+/// The star's ID, as in Star.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StarId(pub u64);
+
+/// The star's position, in parsecs.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position(pub Vec2d);
+
+/// The star's velocity, in km/s.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Velocity(pub Vec2d);
+
+/// The star's mass, which for a binary is that of the pair.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mass(pub SolarMass);
+
+/// The star's age, in Gyr.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Age(pub f64);
+
+/// The star's metallicity, [Fe/H] in dex relative to the sun.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Metallicity(pub f64);
+
+/// What kind of particle the star is: which component of the galaxy it's a star of, or whether
+/// it's a black hole, dark matter or a tracer.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Species(pub StarComponent);
+
+/// The colour the star is drawn in, as RGB from 0 to 1, before the renderer's colour mode is
+/// applied. This is kept up to date by StarWorld::update_render_colors.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderColor(pub [f32; 3]);
+
+impl RenderColor {
+    /// The colour of a star: its tag's colour if it's tagged, or otherwise its species' colour, or
+    /// for stars, between the young and old colours by age and metallicity.
+    pub fn of(species: StarComponent, age: f64, metallicity: f64, tag: Option<&StarTag>) -> Self {
+        if let Some(tag) = tag {
+            return Self(tag.color);
+        }
+
+        match species {
+            StarComponent::Tracer => Self(TRACER_COLOR),
+            StarComponent::Halo => Self(DARK_MATTER_COLOR),
+            _ => {
+                let redness = (age / OLD_STAR_AGE + metallicity * METALLICITY_REDDENING).clamp(0.0, 1.0) as f32;
+                Self(std::array::from_fn(|i| YOUNG_STAR_COLOR[i] + (OLD_STAR_COLOR[i] - YOUNG_STAR_COLOR[i]) * redness))
+            },
+        }
+    }
+}
+
+/// A star's entry in the quadtree and the spatial grid: its entity, along with a copy of the
+/// components they need to place it and calculate its gravity, taken when they were built.
+#[derive(Clone, Copy, Debug)]
+pub struct StarHandle {
+    pub entity: Entity,
+    pub position: Vec2d,
+
+    /// The mass the star's gravity acts on other stars with, which is none for tracers.
+    pub mass: SolarMass,
+
+    pub species: StarComponent,
+}
+
+impl Spatial for StarHandle {
+    fn xy(&self) -> &Vec2d {
+        &self.position
+    }
+}
+
+/// The stars of a simulation, as entities in a world, in order.
+pub struct StarWorld {
+    world: World,
+    entities: Vec<Entity>,
+}
+
+impl Default for StarWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StarWorld {
+    /// Create a world with no stars in it. The components every star has are registered up front,
+    /// so that they can be queried without mutable access to the world.
+    pub fn new() -> Self {
+        let mut world = World::new();
+        world.register_component::<StarId>();
+        world.register_component::<Position>();
+        world.register_component::<Velocity>();
+        world.register_component::<Mass>();
+        world.register_component::<Age>();
+        world.register_component::<Metallicity>();
+        world.register_component::<Species>();
+        world.register_component::<RenderColor>();
+        world.register_component::<Binary>();
+        world.register_component::<StarTag>();
+
+        Self { world, entities: Vec::new() }
+    }
+
+    /// The number of stars.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Whether there are no stars.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// The stars' entities, in order.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// The entity of the star with the given index.
+    pub fn entity(&self, index: usize) -> Option<Entity> {
+        self.entities.get(index).copied()
+    }
+
+    /// The world the stars are entities in, e.g. to run queries over other components.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// The world the stars are entities in, mutably, e.g. to add components to them. Stars must be
+    /// added and removed through the StarWorld rather than the world, so that their order is kept.
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Add a star after the others, returning its entity.
+    pub fn push(&mut self, star: Star) -> Entity {
+        let color = RenderColor::of(star.component, star.age, star.metallicity, star.tag.as_deref());
+        let mut entity = self.world.spawn((
+            StarId(star.id),
+            Position(star.position),
+            Velocity(star.velocity),
+            Mass(star.mass),
+            Age(star.age),
+            Metallicity(star.metallicity),
+            Species(star.component),
+            color,
+        ));
+        if let Some(binary) = star.binary {
+            entity.insert(binary);
+        }
+        if let Some(tag) = star.tag {
+            entity.insert(*tag);
+        }
+
+        let entity = entity.id();
+        self.entities.push(entity);
+        entity
+    }
+
+    /// Replace the stars with a list of them.
+    pub fn replace(&mut self, stars: Vec<Star>) {
+        self.retain(|_| false);
+        self.extend(stars);
+    }
+
+    /// Remove every star, returning them as a list.
+    pub fn take(&mut self) -> Vec<Star> {
+        let stars = self.to_vec();
+        self.retain(|_| false);
+        stars
+    }
+
+    /// Keep only the stars for whose index `keep` returns true, keeping their order.
+    pub fn retain<F>(&mut self, mut keep: F)
+        where F: FnMut(usize) -> bool
+    {
+        let world = &mut self.world;
+        let mut index = 0;
+        self.entities.retain(|&entity| {
+            let kept = keep(index);
+            index += 1;
+            if !kept {
+                world.despawn(entity);
+            }
+            kept
+        });
+    }
+
+    /// The star with the given index, gathered from its components.
+    pub fn get(&self, index: usize) -> Option<Star> {
+        self.entity(index).and_then(|entity| self.star(entity))
+    }
+
+    /// The star of the given entity, gathered from its components.
+    pub fn star(&self, entity: Entity) -> Option<Star> {
+        let entity = self.world.get_entity(entity).ok()?;
+        Some(Star {
+            id: entity.get::<StarId>()?.0,
+            position: entity.get::<Position>()?.0,
+            velocity: entity.get::<Velocity>()?.0,
+            mass: entity.get::<Mass>()?.0,
+            age: entity.get::<Age>()?.0,
+            metallicity: entity.get::<Metallicity>()?.0,
+            component: entity.get::<Species>()?.0,
+            binary: entity.get::<Binary>().copied(),
+            tag: entity.get::<StarTag>().cloned().map(Box::new),
+        })
+    }
+
+    /// The stars in order, gathered from their components. Systems that only need a few of the
+    /// components should query them instead.
+    pub fn iter(&self) -> impl Iterator<Item = Star> + '_ {
+        self.entities.iter().filter_map(|&entity| self.star(entity))
+    }
+
+    /// The stars in order as a list, e.g. to save them.
+    pub fn to_vec(&self) -> Vec<Star> {
+        self.iter().collect()
+    }
+
+    /// A component of the star with the given index.
+    pub fn component<C: Component>(&self, index: usize) -> Option<&C> {
+        self.world.get::<C>(self.entity(index)?)
+    }
+
+    /// A component of the star with the given index, mutably.
+    pub fn component_mut<C>(&mut self, index: usize) -> Option<Mut<'_, C>>
+        where C: Component<Mutability = Mutable>
+    {
+        let entity = self.entity(index)?;
+        self.world.get_mut::<C>(entity)
+    }
+
+    /// The star with the given index, to add and remove components.
+    pub fn entity_mut(&mut self, index: usize) -> Option<EntityWorldMut<'_>> {
+        let entity = self.entity(index)?;
+        self.world.get_entity_mut(entity).ok()
+    }
+
+    /// Query the stars in order, yielding the query's item for each star that matches it.
+    pub fn query<D: ReadOnlyQueryData + 'static>(&self) -> impl Iterator<Item = ROQueryItem<'_, D>> + '_ {
+        let world = &self.world;
+        let mut state = world.try_query::<D>();
+        self.entities.iter().filter_map(move |&entity| state.as_mut()?.get(world, entity).ok())
+    }
+
+    /// Query the stars mutably in order, calling `f` with the index and the query's item for each
+    /// star that matches it.
+    pub fn for_each_mut<D, F>(&mut self, mut f: F)
+        where D: QueryData,
+              F: FnMut(usize, D::Item<'_>)
+    {
+        let mut state = self.world.query::<D>();
+        for (index, &entity) in self.entities.iter().enumerate() {
+            if let Ok(item) = state.get_mut(&mut self.world, entity) {
+                f(index, item);
+            }
+        }
+    }
+
+    /// The handle to put the star with the given index in the quadtree or the spatial grid with.
+    pub fn handle(&self, index: usize) -> Option<StarHandle> {
+        let entity = self.entity(index)?;
+        let (position, mass, species) = self.world.entity(entity).get_components::<(&Position, &Mass, &Species)>()?;
+        Some(StarHandle { entity, position: position.0, mass: species.0.gravitating_mass(mass.0), species: species.0 })
+    }
+
+    /// The handles of the stars, in order.
+    pub fn handles(&self) -> impl Iterator<Item = StarHandle> + '_ {
+        (0..self.len()).filter_map(|index| self.handle(index))
+    }
+
+    /// Recolour the stars from their species, age, metallicity and tags, after they've changed.
+    pub fn update_render_colors(&mut self) {
+        self.for_each_mut::<(&Species, &Age, &Metallicity, Option<&StarTag>, &mut RenderColor), _>(
+            |_, (species, age, metallicity, tag, mut color)| {
+                let new_color = RenderColor::of(species.0, age.0, metallicity.0, tag);
+                if *color != new_color {
+                    *color = new_color;
+                }
+            });
+    }
+}
+
+impl Extend<Star> for StarWorld {
+    fn extend<I: IntoIterator<Item = Star>>(&mut self, stars: I) {
+        for star in stars {
+            self.push(star);
+        }
+    }
+}
+
+impl FromIterator<Star> for StarWorld {
+    fn from_iter<I: IntoIterator<Item = Star>>(stars: I) -> Self {
+        let mut world = Self::new();
+        world.extend(stars);
+        world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn star(id: u64, x: f64) -> Star {
+        Star {
+            id,
+            position: Vec2d::new(x, 0.0),
+            mass: SolarMass(1.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn stars_round_trip_through_components() {
+        let tagged = Star {
+            velocity: Vec2d::new(1.0, 2.0),
+            age: 3.0,
+            metallicity: -0.5,
+            component: StarComponent::Bulge,
+            binary: Some(Binary {
+                companion_fraction: 0.25,
+                separation: Vec2d::new(0.1, 0.0),
+                relative_velocity: Vec2d::new(0.0, 5.0),
+            }),
+            tag: Some(Box::new(StarTag::default())),
+            ..star(7, 4.0)
+        };
+        let stars: StarWorld = [star(1, 1.0), tagged].into_iter().collect();
+
+        let star = stars.get(1).unwrap();
+        assert_eq!((star.id, star.position, star.velocity), (7, Vec2d::new(4.0, 0.0), Vec2d::new(1.0, 2.0)));
+        assert_eq!((star.age, star.metallicity, star.component), (3.0, -0.5, StarComponent::Bulge));
+        assert_eq!(star.binary.map(|binary| binary.companion_fraction), Some(0.25));
+        assert_eq!(star.tag.as_deref(), Some(&StarTag::default()));
+        assert_eq!(stars.component::<RenderColor>(1).unwrap().0, StarTag::default().color);
+        assert!(stars.get(0).unwrap().tag.is_none());
+    }
+
+    #[test]
+    fn retain_keeps_order() {
+        let mut stars: StarWorld = (0..10).map(|i| star(i, i as f64)).collect();
+        stars.retain(|i| i % 3 != 0);
+
+        let ids: Vec<u64> = stars.query::<&StarId>().map(|id| id.0).collect();
+        assert_eq!(ids, [1, 2, 4, 5, 7, 8]);
+        assert_eq!(stars.world().entities().len(), 6);
+
+        let handles: Vec<f64> = stars.handles().map(|handle| handle.position.x).collect();
+        assert_eq!(handles, [1.0, 2.0, 4.0, 5.0, 7.0, 8.0]);
+
+        stars.for_each_mut::<&mut Position, _>(|i, mut position| position.0.y = i as f64);
+        assert_eq!(stars.component::<Position>(5).unwrap().0, Vec2d::new(8.0, 5.0));
+    }
+}
@@ -0,0 +1,138 @@
+//! Exports of a view of the simulation as SVG, with the stars as circles sized by their mass and
+//! optionally the quadtree's cells outlined, for figures that need to stay sharp at any scale
+//! rather than screenshots.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::simulation::GalaxySim;
+use crate::star_world::{Mass, Position, RenderColor};
+use crate::units::SolarMass;
+use crate::types::Vec2d;
+
+/// The radius of a star is the scale times the square root of its mass in solar masses, so that
+/// the area of its circle is proportional to its mass, clamped to this range in pixels so that the
+/// lightest stars stay visible and black holes don't cover the figure.
+const MIN_STAR_RADIUS: f64 = 0.2;
+const MAX_STAR_RADIUS: f64 = 4.0;
+
+/// The colour of the quadtree's cell outlines, and their width in pixels.
+const CELL_COLOR: [f64; 3] = [0.3, 0.6, 0.3];
+const CELL_STROKE_WIDTH: f64 = 0.5;
+
+/// A rectangular view of the simulation to export as SVG.
+#[derive(Clone, Debug)]
+pub struct SvgExport {
+    /// The bottom left corner of the view and its size, in parsecs.
+    pub min: Vec2d,
+    pub size: Vec2d,
+
+    /// The width of the image in pixels. Its height follows from the view's aspect ratio.
+    pub width: f64,
+
+    /// The radius in pixels of a star of one solar mass.
+    pub star_scale: f64,
+
+    /// Whether to outline the quadtree's cells.
+    pub quadtree_cells: bool,
+
+    /// The colour to fill the background with, or None to leave it transparent.
+    pub background: Option<[f64; 3]>,
+}
+
+impl SvgExport {
+    /// Export the view with the given bounds, as returned by the renderer's view_bounds, with
+    /// stars drawn at the default scale on a black background.
+    pub fn new(min: Vec2d, size: Vec2d, width: f64) -> Self {
+        Self {
+            min,
+            size,
+            width,
+            star_scale: 0.5,
+            quadtree_cells: false,
+            background: Some([0.0, 0.0, 0.0]),
+        }
+    }
+
+    /// The height of the image in pixels.
+    pub fn height(&self) -> f64 {
+        self.width * self.size.y / self.size.x
+    }
+
+    /// Convert a position in parsecs to pixels in the image. SVG's y axis points down, so it's
+    /// flipped to keep the top of the view at the top of the image.
+    fn to_pixels(&self, position: Vec2d) -> (f64, f64) {
+        let x = (position.x - self.min.x) / self.size.x * self.width;
+        let y = (1.0 - (position.y - self.min.y) / self.size.y) * self.height();
+        (x, y)
+    }
+
+    /// The radius of the circle of a star of the given mass, in pixels.
+    fn star_radius(&self, mass: SolarMass) -> f64 {
+        (self.star_scale * mass.0.max(0.0).sqrt()).clamp(MIN_STAR_RADIUS, MAX_STAR_RADIUS)
+    }
+
+    /// Format a colour with components from 0 to 1 as an SVG hex colour.
+    fn hex_color(color: [f64; 3]) -> String {
+        let [r, g, b] = color.map(|component| (component.clamp(0.0, 1.0) * 255.0).round() as u8);
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+
+    /// Write the view of a simulation as SVG, colouring each star in its render colour. Stars and
+    /// cells outside of the view are left out.
+    pub fn write<W: Write>(&self, sim: &GalaxySim, mut writer: W) -> Result<(), Box<dyn Error>> {
+        let (width, height) = (self.width, self.height());
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(writer, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.0}" height="{height:.0}" viewBox="0 0 {width} {height}">"#)?;
+        writeln!(writer, "<!-- {:.1} Myr, view ({:.1}, {:.1}) to ({:.1}, {:.1}) pc -->",
+                 sim.elapsed_myr(), self.min.x, self.min.y, self.min.x + self.size.x, self.min.y + self.size.y)?;
+
+        if let Some(background) = self.background {
+            writeln!(writer, r#"<rect width="100%" height="100%" fill="{}"/>"#, Self::hex_color(background))?;
+        }
+
+        if self.quadtree_cells {
+            let quadtree = &sim.quadtree;
+            writeln!(writer, r#"<g fill="none" stroke="{}" stroke-width="{CELL_STROKE_WIDTH}">"#,
+                     Self::hex_color(CELL_COLOR))?;
+            let mut result = Ok(());
+            quadtree.walk_nodes(|index, _| {
+                let (min, max) = index.bounds(quadtree.min, quadtree.max);
+                let (left, bottom) = self.to_pixels(min);
+                let (right, top) = self.to_pixels(max);
+                if result.is_ok() && right >= 0.0 && left <= width && bottom >= 0.0 && top <= height {
+                    result = writeln!(writer, r#"<rect x="{left:.2}" y="{top:.2}" width="{:.2}" height="{:.2}"/>"#,
+                                      right - left, bottom - top);
+                }
+            });
+            result?;
+            writeln!(writer, "</g>")?;
+        }
+
+        writeln!(writer, "<g>")?;
+        for (position, mass, color) in sim.stars().query::<(&Position, &Mass, &RenderColor)>() {
+            let (x, y) = self.to_pixels(position.0);
+            let radius = self.star_radius(mass.0);
+            if x + radius >= 0.0 && x - radius <= width && y + radius >= 0.0 && y - radius <= height {
+                writeln!(writer, r#"<circle cx="{x:.2}" cy="{y:.2}" r="{radius:.2}" fill="{}"/>"#,
+                         Self::hex_color(color.0.map(f64::from)))?;
+            }
+        }
+        writeln!(writer, "</g>")?;
+        writeln!(writer, "</svg>")?;
+
+        Ok(())
+    }
+
+    /// Write the view of a simulation to an SVG file.
+    pub fn save<P: AsRef<Path>>(&self, sim: &GalaxySim, path: P) -> Result<(), Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(sim, &mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
@@ -0,0 +1,253 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::Instant;
+
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::metrics::{self, StepMetrics};
+use crate::rng::RngStreams;
+use crate::simulation::{GalaxySim, Star, StarComponent};
+
+/// The fractions of the mass whose Lagrange radii are reported for each run.
+const LAGRANGE_FRACTIONS: [f64; 3] = [0.1, 0.5, 0.9];
+
+/// Step a simulation until it reaches a simulated time in Myr, with each step covering `step_myr`,
+/// returning the number of steps taken.
+pub fn run_until(sim: &mut GalaxySim, end_myr: f64, step_myr: f64) -> u64 {
+    if step_myr <= 0.0 {
+        return 0;
+    }
+
+    // Allow for rounding error in the elapsed time, so it doesn't take an extra step.
+    sim.myr_per_second = step_myr;
+    let mut steps = 0;
+    while sim.elapsed_myr() < end_myr - step_myr * 1e-6 {
+        sim.step(1.0);
+        steps += 1;
+    }
+    steps
+}
+
+/// A config value to sweep over, and the values to try. Parsed from strings like
+/// `simulation.opening_angle=0.5,0.7,1.0`, where the name is the path of the value in the config
+/// file and each value is parsed as JSON if possible and used as a string otherwise.
+#[derive(Clone, Debug)]
+pub struct SweepParameter {
+    pub name: String,
+    pub values: Vec<Value>,
+}
+
+impl SweepParameter {
+    /// Set the parameter to a value in a config.
+    pub fn apply(&self, config: &Config, value: &Value) -> Result<Config, Box<dyn Error>> {
+        let mut json = serde_json::to_value(config)?;
+        let pointer = format!("/{}", self.name.replace('.', "/"));
+        match json.pointer_mut(&pointer) {
+            Some(field) => *field = value.clone(),
+            None => return Err(format!("No config value named {}", self.name).into()),
+        }
+        Ok(serde_json::from_value(json)?)
+    }
+}
+
+impl FromStr for SweepParameter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, values) = s.split_once('=')
+            .ok_or_else(|| format!("Expected NAME=VALUE,VALUE,..., got {s}"))?;
+
+        let values = values.split(',')
+            .map(|value| serde_json::from_str(value.trim()).unwrap_or_else(|_| Value::String(value.trim().to_string())))
+            .collect();
+
+        Ok(Self { name: name.trim().to_string(), values })
+    }
+}
+
+/// One combination of seed and parameter values in a sweep.
+#[derive(Clone, Debug)]
+pub struct SweepRun {
+    pub seed: u64,
+
+    /// The value of each parameter, in the order of the sweep's parameters.
+    pub values: Vec<Value>,
+}
+
+/// Summary metrics of a finished run.
+#[derive(Clone, Debug)]
+pub struct RunSummary {
+    pub run: SweepRun,
+    pub steps: u64,
+    pub elapsed_myr: f64,
+
+    /// The change in total energy over the run, as a fraction of the initial energy.
+    pub energy_error: f64,
+
+    /// The Lagrange radii of the stars at the end of the run, for each of `LAGRANGE_FRACTIONS`,
+    /// in parsecs.
+    pub lagrange_radii: Vec<f64>,
+
+    /// The fraction of stars that escaped the simulation's bounds or are unbound at the end.
+    pub escape_fraction: f64,
+
+    /// How long the run took, in seconds of real time.
+    pub wall_seconds: f64,
+}
+
+/// A grid of seeds and config parameter values to run headlessly for a fixed simulated time each,
+/// for comparing how the parameters affect the outcome offline.
+pub struct Sweep {
+    /// The config the parameters are applied to.
+    pub config: Config,
+    pub seeds: Vec<u64>,
+    pub parameters: Vec<SweepParameter>,
+
+    /// How long to simulate each run for, in Myr.
+    pub duration_myr: f64,
+
+    /// How much simulated time each step covers, in Myr.
+    pub step_myr: f64,
+}
+
+impl Sweep {
+    /// Every combination of seed and parameter values in the sweep.
+    pub fn runs(&self) -> Vec<SweepRun> {
+        let mut runs: Vec<SweepRun> = self.seeds.iter()
+            .map(|&seed| SweepRun { seed, values: Vec::new() })
+            .collect();
+
+        for parameter in &self.parameters {
+            runs = runs.into_iter()
+                .flat_map(|run| parameter.values.iter().map(move |value| {
+                    let mut run = run.clone();
+                    run.values.push(value.clone());
+                    run
+                }))
+                .collect();
+        }
+
+        runs
+    }
+
+    /// Run every combination on `jobs` threads, writing the summary of each to a CSV file as it
+    /// finishes. Runs that fail are logged and left out.
+    pub fn run<P: AsRef<Path>>(&self, path: P, jobs: usize) -> Result<(), Box<dyn Error>> {
+        let runs = self.runs();
+        log::info!("Running {} combinations on {} threads", runs.len(), jobs);
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", self.csv_header())?;
+
+        let next_run = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        std::thread::scope(|scope| -> Result<(), Box<dyn Error>> {
+            for _ in 0..usize::max(jobs, 1) {
+                let sender = sender.clone();
+                let (runs, next_run) = (&runs, &next_run);
+                scope.spawn(move || {
+                    while let Some(run) = runs.get(next_run.fetch_add(1, Ordering::Relaxed)) {
+                        let result = self.run_one(run).map_err(|err| err.to_string());
+                        if sender.send((run.clone(), result)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            for (finished, (run, result)) in receiver.into_iter().enumerate() {
+                match result {
+                    Ok(summary) => {
+                        writeln!(writer, "{}", Self::csv_row(&summary))?;
+                        writer.flush()?;
+                        log::info!("Finished run {}/{}: seed {} {:?}", finished + 1, runs.len(), run.seed, run.values);
+                    },
+                    Err(err) => log::error!("Run with seed {} {:?} failed: {err}", run.seed, run.values),
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Run one combination to the end and summarise it.
+    pub fn run_one(&self, run: &SweepRun) -> Result<RunSummary, Box<dyn Error>> {
+        if self.step_myr <= 0.0 {
+            return Err("The time each step covers must be positive".into());
+        }
+
+        let start = Instant::now();
+
+        let mut config = self.config.clone();
+        for (parameter, value) in self.parameters.iter().zip(&run.values) {
+            config = parameter.apply(&config, value)?;
+        }
+
+        let mut sim = GalaxySim::new(&config, &RngStreams::new(run.seed))?;
+        let is_counted = |star: &Star| star.component != StarComponent::BlackHole;
+        let initial_star_count = sim.stars().iter().filter(is_counted).count();
+
+        // An empty step first builds the quadtree's mass distribution so the initial energy can be
+        // measured.
+        sim.step(0.0);
+        let initial_energy = StepMetrics::measure(&sim, 0).total_energy;
+
+        let steps = run_until(&mut sim, self.duration_myr, self.step_myr);
+
+        let final_energy = StepMetrics::measure(&sim, steps).total_energy;
+        let energy_error = match initial_energy != 0.0 {
+            true => (final_energy - initial_energy) / initial_energy.abs(),
+            false => 0.0,
+        };
+
+        // Black holes aren't counted, wherever they are in the list of stars, as they would
+        // dominate the mass and never escape.
+        let stars: Vec<Star> = sim.stars().iter().filter(is_counted).collect();
+        let unbound_count = stars.iter()
+            .filter(|star| {
+                let kinetic_energy = 0.5 * (star.velocity.x * star.velocity.x + star.velocity.y * star.velocity.y);
+                kinetic_energy + GalaxySim::potential_at_point(&sim.quadtree, sim.config(), star.position) > 0.0
+            })
+            .count();
+
+        Ok(RunSummary {
+            run: run.clone(),
+            steps,
+            elapsed_myr: sim.elapsed_myr(),
+            energy_error,
+            lagrange_radii: metrics::lagrange_radii(&stars, &LAGRANGE_FRACTIONS),
+            escape_fraction: (sim.escaped_count + unbound_count) as f64 / usize::max(initial_star_count, 1) as f64,
+            wall_seconds: start.elapsed().as_secs_f64(),
+        })
+    }
+
+    /// The CSV header, with a column for each parameter.
+    fn csv_header(&self) -> String {
+        let mut columns = vec!["seed".to_string()];
+        columns.extend(self.parameters.iter().map(|parameter| parameter.name.clone()));
+        columns.extend(["steps", "elapsed_myr", "energy_error"].map(String::from));
+        columns.extend(LAGRANGE_FRACTIONS.iter().map(|fraction| format!("lagrange_radius_{:.0}", fraction * 100.0)));
+        columns.extend(["escape_fraction", "wall_seconds"].map(String::from));
+        columns.join(",")
+    }
+
+    /// Format a run summary as a CSV row.
+    fn csv_row(summary: &RunSummary) -> String {
+        let mut columns = vec![summary.run.seed.to_string()];
+        columns.extend(summary.run.values.iter().map(|value| match value {
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        }));
+        columns.extend([summary.steps as f64, summary.elapsed_myr, summary.energy_error].map(|value| value.to_string()));
+        columns.extend(summary.lagrange_radii.iter().map(|radius| radius.to_string()));
+        columns.extend([summary.escape_fraction, summary.wall_seconds].map(|value| value.to_string()));
+        columns.join(",")
+    }
+}
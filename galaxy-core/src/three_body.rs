@@ -0,0 +1,121 @@
+//! The restricted three-body problem, in which massless test particles move in the field of two
+//! massive bodies orbiting each other. The bodies' orbit is advanced analytically, and the
+//! particles feel the two bodies directly rather than through the quadtree, so it's a check on the
+//! integration that doesn't depend on the Barnes-Hut approximation.
+
+use crate::kepler;
+use crate::simulation::Star;
+use crate::types::Vec2d;
+
+/// The most iterations of bisection to take when finding a collinear Lagrange point.
+const MAX_ITERATIONS: usize = 100;
+
+/// The acceleration at a point due to the two bodies, in the same units as
+/// `GalaxySim::acceleration_at_point`. Distances are softened by the minimum gravity distance as
+/// for the stars.
+pub fn acceleration_at_point(bodies: [&Star; 2],
+                             gravitational_constant: f64,
+                             min_gravity_distance_squared: f64,
+                             point: Vec2d) -> Vec2d
+{
+    let mut acceleration = Vec2d::new(0.0, 0.0);
+    for body in bodies {
+        let diff = body.position - point;
+        let d_squared = f64::max(min_gravity_distance_squared, diff.x * diff.x + diff.y * diff.y);
+        if d_squared > 0.0 {
+            let dist = f64::sqrt(d_squared);
+            acceleration = acceleration + diff / dist * body.mass.acceleration_at(gravitational_constant, d_squared);
+        }
+    }
+
+    acceleration
+}
+
+/// Advance the two bodies along their Kepler orbit around each other by a time in simulation time
+/// units. Their barycenter moves in a straight line.
+pub fn advance_bodies(gravitational_constant: f64, primary: &mut Star, secondary: &mut Star, time_delta: f64) {
+    let total_mass = primary.mass.0 + secondary.mass.0;
+    if total_mass <= 0.0 {
+        return;
+    }
+
+    let secondary_fraction = secondary.mass.0 / total_mass;
+    let barycenter = primary.position + (secondary.position - primary.position) * secondary_fraction;
+    let barycenter_velocity = primary.velocity + (secondary.velocity - primary.velocity) * secondary_fraction;
+
+    let (separation, relative_velocity) = kepler::advance(gravitational_constant * total_mass,
+                                                          secondary.position - primary.position,
+                                                          secondary.velocity - primary.velocity,
+                                                          time_delta);
+
+    let barycenter = barycenter + barycenter_velocity * time_delta;
+    primary.position = barycenter - separation * secondary_fraction;
+    primary.velocity = barycenter_velocity - relative_velocity * secondary_fraction;
+    secondary.position = barycenter + separation * (1.0 - secondary_fraction);
+    secondary.velocity = barycenter_velocity + relative_velocity * (1.0 - secondary_fraction);
+}
+
+/// The positions of the five Lagrange points L1 to L5 of the two bodies where they are now. These
+/// are exact for a circular orbit, and for an eccentric one they're where they would be if the
+/// bodies were on a circular orbit at their current separation. L1 is between the bodies, L2 is
+/// beyond the secondary and L3 beyond the primary, and L4 leads the secondary by 60 degrees and L5
+/// trails it.
+pub fn lagrange_points(primary: &Star, secondary: &Star) -> [Vec2d; 5] {
+    let total_mass = primary.mass.0 + secondary.mass.0;
+    let separation = secondary.position - primary.position;
+    let distance = separation.x.hypot(separation.y);
+    if total_mass <= 0.0 || distance == 0.0 {
+        return [primary.position; 5];
+    }
+
+    // Work in units of the separation about the barycenter, with the primary at -mu and the
+    // secondary at 1 - mu on the x axis, and L4 on the side the secondary is moving towards.
+    let mu = secondary.mass.0 / total_mass;
+    let axis = separation / distance;
+    let relative_velocity = secondary.velocity - primary.velocity;
+    let leading = match separation.x * relative_velocity.y - separation.y * relative_velocity.x < 0.0 {
+        true => Vec2d::new(axis.y, -axis.x),
+        false => Vec2d::new(-axis.y, axis.x),
+    };
+    let barycenter = primary.position + separation * mu;
+    let to_world = |x: f64, y: f64| barycenter + (axis * x + leading * y) * distance;
+
+    // The collinear points are where the gravity of the two bodies balances the centrifugal force
+    // in the rotating frame, with one between each pair of singularities of the net force.
+    let net_force = |x: f64| {
+        let to_primary = x + mu;
+        let to_secondary = x - 1.0 + mu;
+        x - (1.0 - mu) * to_primary / to_primary.abs().powi(3) - mu * to_secondary / to_secondary.abs().powi(3)
+    };
+    let l1 = bisect(net_force, -mu, 1.0 - mu);
+    let l2 = bisect(net_force, 1.0 - mu, 2.0);
+    let l3 = bisect(net_force, -2.0, -mu);
+
+    // The triangular points form equilateral triangles with the two bodies.
+    let triangle_height = f64::sqrt(3.0) / 2.0;
+
+    [
+        to_world(l1, 0.0),
+        to_world(l2, 0.0),
+        to_world(l3, 0.0),
+        to_world(0.5 - mu, triangle_height),
+        to_world(0.5 - mu, -triangle_height),
+    ]
+}
+
+/// Find the root of a function between two points where it goes from negative to positive, or the
+/// reverse, by bisection. Neither end is evaluated, so they can be singularities.
+fn bisect<F: Fn(f64) -> f64>(function: F, mut low: f64, mut high: f64) -> f64 {
+    let rising = function(low + (high - low) * 1e-9) < 0.0;
+    for _ in 0..MAX_ITERATIONS {
+        let middle = 0.5 * (low + high);
+        if (function(middle) < 0.0) == rising {
+            low = middle;
+        }
+        else {
+            high = middle;
+        }
+    }
+
+    0.5 * (low + high)
+}
@@ -0,0 +1,171 @@
+//! Trajectories: series of snapshots of a simulation written as it runs, e.g. by an expensive
+//! headless run, that can be played back in the viewer afterwards without running the physics.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::arrow_export::ArrowExporter;
+use crate::config::Config;
+use crate::rng::RngStreams;
+use crate::simulation::{GalaxySim, Star};
+use crate::sweep::run_until;
+use crate::units::Myr;
+
+/// The header of a trajectory file, describing the run the frames were recorded from.
+#[derive(Serialize, Deserialize)]
+pub struct TrajectoryHeader {
+    pub seed: u64,
+    pub config: Config,
+}
+
+/// A snapshot of the stars at one point in a trajectory.
+#[derive(Serialize, Deserialize)]
+pub struct TrajectoryFrame {
+    /// The simulation time elapsed when the frame was recorded, in simulation time units.
+    pub elapsed_time: f64,
+
+    /// All stars in the galaxy.
+    pub stars: Vec<Star>,
+}
+
+impl TrajectoryFrame {
+    /// Record the current state of a simulation.
+    pub fn from_sim(sim: &GalaxySim) -> Self {
+        Self {
+            elapsed_time: sim.elapsed_time,
+            stars: sim.stars().to_vec(),
+        }
+    }
+
+    /// Recreate the simulation in this state, with the given config.
+    pub fn to_sim(&self, config: &Config) -> Result<GalaxySim, Box<dyn Error>> {
+        let mut sim = GalaxySim::from_stars(config, self.stars.clone())?;
+        sim.elapsed_time = self.elapsed_time;
+        Ok(sim)
+    }
+
+    /// The simulation time elapsed when the frame was recorded, in Myr.
+    pub fn elapsed_myr(&self) -> f64 {
+        Myr::from_time_units(self.elapsed_time).0
+    }
+}
+
+/// Writes a trajectory to a file. The file is in JSON lines format, the first line is the
+/// TrajectoryHeader and every following line is a TrajectoryFrame.
+pub struct TrajectoryWriter {
+    writer: BufWriter<File>,
+}
+
+impl TrajectoryWriter {
+    /// Create a new trajectory file at the given path and write the header to it.
+    pub fn create<P: AsRef<Path>>(path: P, header: &TrajectoryHeader) -> Result<Self, Box<dyn Error>> {
+        log::info!("Recording trajectory to {}", path.as_ref().display());
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, header)?;
+        writer.write_all(b"\n")?;
+
+        Ok(Self { writer })
+    }
+
+    /// Write a frame of the current state of a simulation.
+    pub fn write(&mut self, sim: &GalaxySim) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer(&mut self.writer, &TrajectoryFrame::from_sim(sim))?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Flush any buffered frames to the file.
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A trajectory loaded from a file for playing back.
+pub struct Trajectory {
+    pub header: TrajectoryHeader,
+    pub frames: Vec<TrajectoryFrame>,
+}
+
+impl Trajectory {
+    /// Load a trajectory file written by TrajectoryWriter.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        log::info!("Loading trajectory from {}", path.as_ref().display());
+
+        let mut lines = BufReader::new(File::open(path)?).lines();
+
+        let header = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => return Err("Trajectory file is empty".into()),
+        };
+
+        let mut frames = Vec::new();
+        for line in lines {
+            let line = line?;
+            if !line.trim().is_empty() {
+                frames.push(serde_json::from_str(&line)?);
+            }
+        }
+
+        if frames.is_empty() {
+            return Err("Trajectory file has no frames".into());
+        }
+
+        Ok(Self { header, frames })
+    }
+}
+
+/// Generate a galaxy and simulate it headlessly for `duration_myr`, with each step covering
+/// `step_myr`, writing a frame of the trajectory to a file every `interval_myr`, starting with the
+/// initial state. The frames are also exported as Arrow to `arrow_path`, if it's given.
+pub fn record<P: AsRef<Path>>(config: &Config,
+                              seed: u64,
+                              duration_myr: f64,
+                              step_myr: f64,
+                              interval_myr: f64,
+                              path: P,
+                              arrow_path: Option<&Path>) -> Result<(), Box<dyn Error>>
+{
+    if step_myr <= 0.0 || interval_myr <= 0.0 {
+        return Err("The time each step and frame covers must be positive".into());
+    }
+
+    let mut sim = GalaxySim::new(config, &RngStreams::new(seed))?;
+    let mut writer = TrajectoryWriter::create(path, &TrajectoryHeader { seed, config: config.clone() })?;
+    let mut arrow_exporter = match arrow_path {
+        Some(arrow_path) => Some(ArrowExporter::create(arrow_path)?),
+        None => None,
+    };
+    let mut steps = 0;
+    writer.write(&sim)?;
+    if let Some(arrow_exporter) = &mut arrow_exporter {
+        arrow_exporter.write(&sim, steps)?;
+    }
+
+    // Stop once there's no time left to step through.
+    let mut frame_count = 1;
+    loop {
+        let end_myr = f64::min(sim.elapsed_myr() + interval_myr, duration_myr);
+        match run_until(&mut sim, end_myr, f64::min(step_myr, interval_myr)) {
+            0 => break,
+            frame_steps => steps += frame_steps,
+        }
+        writer.write(&sim)?;
+        if let Some(arrow_exporter) = &mut arrow_exporter {
+            arrow_exporter.write(&sim, steps)?;
+        }
+
+        frame_count += 1;
+        log::info!("Recorded frame {frame_count} at {:.1} Myr", sim.elapsed_myr());
+    }
+
+    if let Some(arrow_exporter) = &mut arrow_exporter {
+        arrow_exporter.finish()?;
+    }
+    writer.flush()
+}
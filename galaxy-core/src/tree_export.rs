@@ -0,0 +1,152 @@
+//! Debug exports of the structure of the simulation's quadtree, as Graphviz DOT or JSON, so that
+//! structural problems such as unbalanced or overly deep trees can be examined offline with
+//! standard tooling.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::hilbert::HilbertIndex;
+use crate::quadtree::{Quadtree, QuadtreeNode};
+use crate::simulation::Region;
+use crate::star_world::StarHandle;
+use crate::types::Vec2d;
+
+/// A node of the quadtree as exported. Internal nodes have the mass and center of mass of their
+/// region as of the last mass distribution update, and leaf nodes those of their items.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportedNode {
+    /// An identifier for the node that's unique within the tree, made from its depth and Hilbert
+    /// index.
+    pub id: String,
+
+    /// The identifier of the node's parent, or None for the root.
+    pub parent: Option<String>,
+
+    pub depth: u8,
+    pub index: u32,
+    pub leaf: bool,
+
+    /// The bounds of the node, in parsecs.
+    pub min: Vec2d,
+    pub max: Vec2d,
+
+    /// The mass in the node in solar masses, and its center of mass.
+    pub mass: f64,
+    pub center_of_mass: Vec2d,
+
+    /// The indices of the items in a leaf node, which is empty for internal nodes.
+    pub items: Vec<usize>,
+}
+
+/// The structure of a quadtree, as a flat list of nodes in depth-first order.
+#[derive(Clone, Debug, Serialize)]
+pub struct TreeExport {
+    /// The bounds of the root node, in parsecs.
+    pub min: Vec2d,
+    pub max: Vec2d,
+
+    pub nodes: Vec<ExportedNode>,
+}
+
+impl TreeExport {
+    /// Export the structure of a quadtree.
+    pub fn new(quadtree: &Quadtree<StarHandle, Region>) -> Self {
+        let mut nodes = Vec::new();
+        quadtree.walk_nodes(|index, node| {
+            let (min, max) = index.bounds(quadtree.min, quadtree.max);
+            let parent = match index.depth() {
+                0 => None,
+                depth => Some(Self::node_id(HilbertIndex(index.index() / 4, depth - 1))),
+            };
+
+            let (leaf, mass, center_of_mass, items) = match *node {
+                QuadtreeNode::Internal(region_index) => match quadtree.get_internal(region_index) {
+                    Some(region) => (false, region.mass().0, region.center_of_mass(), Vec::new()),
+                    None => (false, 0.0, Vec2d::new(0.0, 0.0), Vec::new()),
+                },
+                QuadtreeNode::Leaf(first_item) => {
+                    let items: Vec<usize> = quadtree.leaf_items(first_item).collect();
+                    let mass: f64 = items.iter().map(|&item| quadtree.items[item].mass.0).sum();
+                    let mut center_of_mass = Vec2d::new(0.0, 0.0);
+                    if mass != 0.0 {
+                        for &item in &items {
+                            let star = &quadtree.items[item];
+                            center_of_mass = center_of_mass + star.position * (star.mass.0 / mass);
+                        }
+                    }
+                    (true, mass, center_of_mass, items)
+                },
+            };
+
+            nodes.push(ExportedNode {
+                id: Self::node_id(index),
+                parent,
+                depth: index.depth(),
+                index: index.index(),
+                leaf,
+                min,
+                max,
+                mass,
+                center_of_mass,
+                items,
+            });
+        });
+
+        Self {
+            min: quadtree.min,
+            max: quadtree.max,
+            nodes,
+        }
+    }
+
+    /// The identifier of the node at a Hilbert index.
+    fn node_id(index: HilbertIndex) -> String {
+        format!("n{}_{}", index.depth(), index.index())
+    }
+
+    /// Write the tree as JSON.
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<(), Box<dyn Error>> {
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+
+    /// Write the tree as a Graphviz DOT digraph, with an edge from each node to its children.
+    /// Internal nodes are drawn as ellipses and leaf nodes as boxes listing their items.
+    pub fn write_dot<W: Write>(&self, mut writer: W) -> Result<(), Box<dyn Error>> {
+        writeln!(writer, "digraph quadtree {{")?;
+        for node in &self.nodes {
+            let shape = if node.leaf { "box" } else { "ellipse" };
+            let mut label = format!("{} {}\\n({:.1}, {:.1}) - ({:.1}, {:.1})\\nmass {:.3e}",
+                                    node.depth, node.index, node.min.x, node.min.y, node.max.x, node.max.y,
+                                    node.mass);
+            if node.leaf {
+                let items: Vec<String> = node.items.iter().map(usize::to_string).collect();
+                label += &format!("\\nitems {}", items.join(", "));
+            }
+
+            writeln!(writer, "    {} [shape={shape}, label=\"{label}\"];", node.id)?;
+            if let Some(parent) = &node.parent {
+                writeln!(writer, "    {parent} -> {};", node.id)?;
+            }
+        }
+        writeln!(writer, "}}")?;
+
+        Ok(())
+    }
+
+    /// Write the tree to a file, as DOT if it ends in .dot or .gv and JSON otherwise.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut writer = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("dot" | "gv") => self.write_dot(&mut writer)?,
+            _ => self.write_json(&mut writer)?,
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+}
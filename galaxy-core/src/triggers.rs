@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::config::TriggerConfig;
+use crate::metrics::StepMetrics;
+use crate::simulation::GalaxySim;
+use crate::star_world::{Position, Velocity};
+
+/// An event that met one of the trigger conditions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriggerEvent {
+    /// Two stars came closer than the close encounter distance, in parsecs.
+    CloseEncounter { stars: (usize, usize), distance: f64 },
+
+    /// A star that was bound reached escape velocity, in km/s.
+    Escape { star: usize, speed: f64, escape_speed: f64 },
+
+    /// The total energy drifted by more than the allowed percentage.
+    EnergyDrift { percent: f64 },
+}
+
+impl TriggerEvent {
+    /// The star the event happened to, if it happened to one.
+    pub fn star(&self) -> Option<usize> {
+        match *self {
+            TriggerEvent::CloseEncounter { stars: (star, _), .. } => Some(star),
+            TriggerEvent::Escape { star, .. } => Some(star),
+            TriggerEvent::EnergyDrift { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for TriggerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TriggerEvent::CloseEncounter { stars: (a, b), distance } =>
+                write!(f, "Stars {a} and {b} passed within {distance:.2} pc"),
+            TriggerEvent::Escape { star, speed, escape_speed } =>
+                write!(f, "Star {star} escaped at {speed:.1} km/s (escape velocity {escape_speed:.1} km/s)"),
+            TriggerEvent::EnergyDrift { percent } =>
+                write!(f, "Total energy drifted by {percent:.2}%"),
+        }
+    }
+}
+
+/// Watches the simulation for the conditions in a trigger config, which should pause it so that
+/// rare events can be caught as they happen. Each event is only reported once: a pair of stars
+/// isn't reported again while they stay close, a star isn't reported again until it's bound
+/// again, and the energy drift is measured from when it was last reported.
+pub struct Triggers {
+    /// The conditions to watch for, which can be changed at any time.
+    pub config: TriggerConfig,
+
+    /// The pairs of stars that are currently closer than the close encounter distance.
+    close_pairs: HashSet<(usize, usize)>,
+
+    /// Whether each star was bound when the conditions were last checked.
+    bound: Vec<bool>,
+
+    /// The total energy drift is measured relative to, or None if it hasn't been measured yet.
+    reference_energy: Option<f64>,
+
+    /// The number of steps since the conditions were last checked, or None if they should be
+    /// checked in the next step.
+    steps_since_check: Option<u64>,
+}
+
+impl Triggers {
+    /// Create triggers watching for the given conditions.
+    pub fn new(config: &TriggerConfig) -> Self {
+        Self {
+            config: config.clone(),
+            close_pairs: HashSet::new(),
+            bound: Vec::new(),
+            reference_energy: None,
+            steps_since_check: None,
+        }
+    }
+
+    /// Forget everything measured so far, e.g. when the galaxy is replaced.
+    pub fn reset(&mut self) {
+        self.close_pairs.clear();
+        self.bound.clear();
+        self.reference_energy = None;
+        self.steps_since_check = None;
+    }
+
+    /// Check the conditions after a step, if it's time to, using the quadtree built in the step.
+    /// Returns the events that met them since they were last checked.
+    pub fn check(&mut self, sim: &GalaxySim) -> Vec<TriggerEvent> {
+        match self.steps_since_check {
+            Some(steps) if steps + 1 < u64::max(self.config.interval_steps, 1) => {
+                self.steps_since_check = Some(steps + 1);
+                return Vec::new();
+            },
+            _ => self.steps_since_check = Some(0),
+        }
+
+        // Which stars are which doesn't mean anything if they've been added or removed.
+        if self.bound.len() != sim.stars().len() {
+            self.close_pairs.clear();
+            self.bound.clear();
+        }
+
+        let mut events = Vec::new();
+        events.extend(self.check_close_encounters(sim));
+        events.extend(self.check_escapes(sim));
+        events.extend(self.check_energy_drift(sim));
+        events
+    }
+
+    /// Find the pairs of stars that have come closer than the close encounter distance since the
+    /// last check.
+    fn check_close_encounters(&mut self, sim: &GalaxySim) -> Vec<TriggerEvent> {
+        let max_distance = self.config.close_encounter_pc;
+        if max_distance <= 0.0 {
+            self.close_pairs.clear();
+            return Vec::new();
+        }
+
+        let mut close_pairs = HashSet::new();
+        let mut events = Vec::new();
+        for (i, position) in sim.stars().query::<&Position>().enumerate() {
+            sim.quadtree.items_in_radius(position.0, max_distance, |j, other| {
+                if j <= i {
+                    return;
+                }
+
+                close_pairs.insert((i, j));
+                if !self.close_pairs.contains(&(i, j)) {
+                    let diff = other.position - position.0;
+                    events.push(TriggerEvent::CloseEncounter {
+                        stars: (i, j),
+                        distance: diff.x.hypot(diff.y),
+                    });
+                }
+            });
+        }
+
+        self.close_pairs = close_pairs;
+        events
+    }
+
+    /// Find the stars that were bound at the last check but have reached escape velocity since.
+    fn check_escapes(&mut self, sim: &GalaxySim) -> Vec<TriggerEvent> {
+        if !self.config.escape {
+            self.bound.clear();
+            return Vec::new();
+        }
+
+        let bound: Vec<(bool, f64, f64)> = sim.stars().query::<(&Position, &Velocity)>().map(|(position, velocity)| {
+            let potential = GalaxySim::potential_at_point(&sim.quadtree, sim.config(), position.0);
+            let speed = velocity.0.x.hypot(velocity.0.y);
+            let escape_speed = f64::sqrt(f64::max(-2.0 * potential, 0.0));
+            (speed < escape_speed, speed, escape_speed)
+        }).collect();
+
+        // Stars that have never been seen bound, such as those already escaping when the check
+        // was enabled, aren't reported.
+        let events = bound.iter().zip(&self.bound).enumerate()
+            .filter(|&(_, (&(is_bound, _, _), &was_bound))| was_bound && !is_bound)
+            .map(|(star, (&(_, speed, escape_speed), _))| TriggerEvent::Escape { star, speed, escape_speed })
+            .collect();
+
+        self.bound = bound.into_iter().map(|(is_bound, _, _)| is_bound).collect();
+        events
+    }
+
+    /// Check whether the total energy has drifted too far since it was last reported.
+    fn check_energy_drift(&mut self, sim: &GalaxySim) -> Option<TriggerEvent> {
+        if self.config.energy_drift_percent <= 0.0 {
+            self.reference_energy = None;
+            return None;
+        }
+
+        let energy = StepMetrics::measure(sim, 0).total_energy;
+        let reference_energy = *self.reference_energy.get_or_insert(energy);
+        if reference_energy == 0.0 {
+            return None;
+        }
+
+        let percent = (energy - reference_energy).abs() / reference_energy.abs() * 100.0;
+        match percent > self.config.energy_drift_percent {
+            true => {
+                self.reference_energy = Some(energy);
+                Some(TriggerEvent::EnergyDrift { percent })
+            },
+            false => None,
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use std::ops;
 
+use serde::{Deserialize, Serialize};
+
 /// A Vec2 type for uploading to opengl, and also basic vector operations.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
@@ -48,7 +50,7 @@ impl ops::Div<f32> for Vec2 {
 
 /// A Vec2d (double) type for uploading to opengl, and also basic vector operations.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Vec2d {
     pub x: f64,
     pub y: f64,
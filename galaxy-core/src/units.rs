@@ -0,0 +1,180 @@
+//! Newtypes for the physical quantities the simulation works in, so that mixing up units is a
+//! compile error rather than a subtly wrong galaxy. Each wraps an f64 in the unit it's named
+//! after. They serialize as plain numbers, so files written before they existed still load.
+//!
+//! Positions and velocities are still `Vec2d`s, in parsecs and km/s respectively.
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use serde::{Deserialize, Serialize};
+
+/// The number of Myr in one unit of simulation time. Positions are in parsecs and velocities are in
+/// km/s, so the unit of time is one pc / (km/s), which is about 0.978 Myr.
+pub const MYR_PER_TIME_UNIT: f64 = 0.9778;
+
+/// Define a newtype for a quantity, with the arithmetic that keeps it in the same unit.
+macro_rules! quantity {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(pub f64);
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self {
+                Self(self.0 + rhs.0)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, rhs: Self) {
+                self.0 += rhs.0;
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self {
+                Self(self.0 - rhs.0)
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, rhs: Self) {
+                self.0 -= rhs.0;
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self(-self.0)
+            }
+        }
+
+        impl Mul<f64> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: f64) -> Self {
+                Self(self.0 * rhs)
+            }
+        }
+
+        impl Div<f64> for $name {
+            type Output = Self;
+
+            fn div(self, rhs: f64) -> Self {
+                Self(self.0 / rhs)
+            }
+        }
+
+        /// The ratio of two quantities in the same unit is dimensionless.
+        impl Div for $name {
+            type Output = f64;
+
+            fn div(self, rhs: Self) -> f64 {
+                self.0 / rhs.0
+            }
+        }
+
+        impl Sum for $name {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                Self(iter.map(|quantity| quantity.0).sum())
+            }
+        }
+
+        impl<'a> Sum<&'a $name> for $name {
+            fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+                Self(iter.map(|quantity| quantity.0).sum())
+            }
+        }
+
+        /// Formats the number alone, respecting the precision, so the unit can be written after
+        /// it as appropriate.
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+    };
+}
+
+quantity! {
+    /// A distance in parsecs.
+    Parsec
+}
+
+quantity! {
+    /// A mass in solar masses.
+    SolarMass
+}
+
+quantity! {
+    /// A speed in km/s.
+    KmPerSec
+}
+
+quantity! {
+    /// A duration or time in millions of years.
+    Myr
+}
+
+impl SolarMass {
+    /// The magnitude of the gravitational acceleration this mass causes at a distance, in km/s per
+    /// unit of simulation time, given the gravitational constant in pc (km/s)^2 / Msun. The
+    /// distance is given squared, as it usually already is when calculating forces.
+    pub fn acceleration_at(self, gravitational_constant: f64, distance_squared: f64) -> f64 {
+        self.0 * gravitational_constant / distance_squared
+    }
+
+    /// The gravitational potential per unit mass this mass causes at a distance, in (km/s)^2,
+    /// given the gravitational constant in pc (km/s)^2 / Msun.
+    pub fn potential_at(self, gravitational_constant: f64, distance: f64) -> f64 {
+        -self.0 * gravitational_constant / distance
+    }
+}
+
+impl Myr {
+    /// Convert a time in units of simulation time, pc / (km/s), to Myr.
+    pub fn from_time_units(time: f64) -> Self {
+        Self(time * MYR_PER_TIME_UNIT)
+    }
+
+    /// Convert to units of simulation time, pc / (km/s).
+    pub fn to_time_units(self) -> f64 {
+        self.0 / MYR_PER_TIME_UNIT
+    }
+}
+
+/// The distance covered at a speed over a time.
+impl Mul<Myr> for KmPerSec {
+    type Output = Parsec;
+
+    fn mul(self, rhs: Myr) -> Parsec {
+        Parsec(self.0 * rhs.to_time_units())
+    }
+}
+
+/// The time taken to cover a distance at a speed.
+impl Div<KmPerSec> for Parsec {
+    type Output = Myr;
+
+    fn div(self, rhs: KmPerSec) -> Myr {
+        Myr::from_time_units(self.0 / rhs.0)
+    }
+}
+
+/// The speed needed to cover a distance in a time.
+impl Div<Myr> for Parsec {
+    type Output = KmPerSec;
+
+    fn div(self, rhs: Myr) -> KmPerSec {
+        KmPerSec(self.0 / rhs.to_time_units())
+    }
+}
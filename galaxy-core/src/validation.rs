@@ -0,0 +1,173 @@
+//! Validation scenarios, which set the simulation up with a problem whose solution is known
+//! analytically and measure how far the integrated result drifts from it, to check the integration
+//! both in the viewer and in tests.
+
+use std::error::Error;
+use std::f64::consts::PI;
+
+use crate::config::{Config, GenerationConfig};
+use crate::kepler;
+use crate::simulation::{GalaxySim, Star, StarComponent};
+use crate::star_world::Position;
+use crate::types::Vec2d;
+use crate::units::{Myr, SolarMass};
+
+/// The masses of the two bodies, in solar masses.
+const PRIMARY_MASS: f64 = 1e10;
+const SECONDARY_MASS: f64 = 5e9;
+
+/// The semi-major axis of the bodies' orbit around each other in parsecs, and its eccentricity.
+const SEMI_MAJOR_AXIS: f64 = 10000.0;
+const ECCENTRICITY: f64 = 0.5;
+
+/// The longest substep the scenario is integrated with, in Myr, which is short enough for the
+/// orbit to be followed closely.
+const MAX_STEP_MYR: f64 = 1.0;
+
+/// The largest deviation from the analytic orbit over one period that's considered correct, as a
+/// fraction of the semi-major axis. The integration is only first order, so the bodies drift a few
+/// percent of the semi-major axis from where they should be over an orbit.
+pub const TOLERANCE: f64 = 0.05;
+
+/// Two bodies on a known elliptical orbit around each other, which are integrated by the simulation
+/// like any other stars and compared against the analytic Kepler solution. The first star is a
+/// massless marker at the barycenter as for a cluster of galaxies, which is held still because it's
+/// the first black hole, as the central one is, and the bodies are the second and third stars.
+pub struct TwoBodyValidation {
+    /// The gravitational parameter `G (M + m)` of the pair, in pc (km/s)^2.
+    gravitational_parameter: f64,
+
+    /// The position and velocity of the secondary relative to the primary at the start.
+    initial_separation: Vec2d,
+    initial_relative_velocity: Vec2d,
+
+    /// The distance between the integrated and analytic positions of the secondary relative to the
+    /// primary at the last check, and the largest so far, in parsecs.
+    pub deviation: f64,
+    pub max_deviation: f64,
+}
+
+impl TwoBodyValidation {
+    /// Create the scenario's simulation, with the rest of its config taken from `base`, and the
+    /// validation that checks it. Everything that would disturb the orbit, such as the static halo
+    /// and dynamical friction, is turned off.
+    pub fn create(base: &Config) -> Result<(GalaxySim, Self), Box<dyn Error>> {
+        let mut config = base.clone();
+        config.generation = GenerationConfig {
+            galaxy_diameter: 2.0 * SEMI_MAJOR_AXIS * (1.0 + ECCENTRICITY),
+            supermassive_black_hole_mass: 0.0,
+            ..Default::default()
+        };
+        let simulation = &mut config.simulation;
+        simulation.min_gravity_distance_squared = 0.0;
+        simulation.max_step_myr = MAX_STEP_MYR;
+        simulation.max_substeps = usize::MAX;
+        simulation.recenter = false;
+        simulation.tidal_disruption_radius = 0.0;
+        simulation.halo.mass = 0.0;
+        simulation.dynamical_friction.coulomb_logarithm = 0.0;
+        simulation.supernovae.kick_speed = 0.0;
+        simulation.mass_loss.rate = 0.0;
+
+        // Start the bodies at pericenter, where their relative speed is sqrt(G M (1 + e) / r),
+        // about their barycenter at the origin.
+        let total_mass = PRIMARY_MASS + SECONDARY_MASS;
+        let gravitational_parameter = config.simulation.gravitational_constant * total_mass;
+        let pericenter = SEMI_MAJOR_AXIS * (1.0 - ECCENTRICITY);
+        let initial_separation = Vec2d::new(pericenter, 0.0);
+        let initial_relative_velocity =
+            Vec2d::new(0.0, f64::sqrt(gravitational_parameter * (1.0 + ECCENTRICITY) / pericenter));
+
+        let secondary_fraction = SECONDARY_MASS / total_mass;
+        let stars = vec![
+            Star { component: StarComponent::BlackHole, ..Default::default() },
+            Star {
+                position: initial_separation * -secondary_fraction,
+                velocity: initial_relative_velocity * -secondary_fraction,
+                mass: SolarMass(PRIMARY_MASS),
+                ..Default::default()
+            },
+            Star {
+                position: initial_separation * (1.0 - secondary_fraction),
+                velocity: initial_relative_velocity * (1.0 - secondary_fraction),
+                mass: SolarMass(SECONDARY_MASS),
+                ..Default::default()
+            },
+        ];
+
+        let validation = Self {
+            gravitational_parameter,
+            initial_separation,
+            initial_relative_velocity,
+            deviation: 0.0,
+            max_deviation: 0.0,
+        };
+
+        Ok((GalaxySim::from_stars(&config, stars)?, validation))
+    }
+
+    /// The period of the orbit, in Myr.
+    pub fn period_myr(&self) -> f64 {
+        Myr::from_time_units(2.0 * PI * f64::sqrt(SEMI_MAJOR_AXIS.powi(3) / self.gravitational_parameter)).0
+    }
+
+    /// The analytic position of the secondary relative to the primary after a time in simulation
+    /// time units.
+    pub fn expected_separation(&self, elapsed_time: f64) -> Vec2d {
+        kepler::advance(self.gravitational_parameter,
+                        self.initial_separation,
+                        self.initial_relative_velocity,
+                        elapsed_time).0
+    }
+
+    /// Compare the integrated positions of the bodies against the analytic orbit at the
+    /// simulation's current time, updating the deviations. Returns the deviation in parsecs, or
+    /// None if either body has been removed.
+    pub fn check(&mut self, sim: &GalaxySim) -> Option<f64> {
+        let stars = sim.stars();
+        let (primary, secondary) = match (stars.len(), stars.component::<Position>(1), stars.component::<Position>(2)) {
+            (3, Some(primary), Some(secondary)) => (primary.0, secondary.0),
+            _ => return None,
+        };
+
+        let error = secondary - primary - self.expected_separation(sim.elapsed_time);
+        self.deviation = error.x.hypot(error.y);
+        self.max_deviation = f64::max(self.max_deviation, self.deviation);
+        Some(self.deviation)
+    }
+
+    /// The largest deviation so far as a fraction of the semi-major axis.
+    pub fn max_relative_deviation(&self) -> f64 {
+        self.max_deviation / SEMI_MAJOR_AXIS
+    }
+
+    /// Check that the largest deviation so far is within a tolerance, as a fraction of the
+    /// semi-major axis.
+    pub fn assert_within(&self, tolerance: f64) -> Result<(), String> {
+        match self.max_relative_deviation() <= tolerance {
+            true => Ok(()),
+            false => Err(format!("Two-body orbit deviated by {:.1} pc, {:.2}% of its semi-major axis, more than {:.2}%",
+                                 self.max_deviation, self.max_relative_deviation() * 100.0, tolerance * 100.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_body_orbit_matches_kepler() {
+        let (mut sim, mut validation) = TwoBodyValidation::create(&Config::default()).unwrap();
+
+        // Step a Myr at a time for a whole orbit.
+        sim.myr_per_second = 1.0;
+        let step_count = validation.period_myr().ceil() as usize;
+        for _ in 0..step_count {
+            sim.step(1.0);
+            assert!(validation.check(&sim).is_some());
+        }
+
+        validation.assert_within(TOLERANCE).unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use galaxy_core::analysis::acceleration_field::AccelerationField;
+use galaxy_core::config::Config;
+use miniquad::*;
+
+use crate::drawable::*;
+use crate::galaxy::Galaxy;
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+/// The size of the texture the acceleration is sampled on. It's drawn with linear filtering, and
+/// sampling the tree is much slower than the kernel density estimate, so it's coarse.
+const TEXTURE_SIZE: usize = 96;
+
+/// How many updates there are between each sampling.
+const UPDATE_INTERVAL: u64 = 5;
+
+/// The colours of the weakest and strongest acceleration, and the one halfway between them.
+const WEAK_COLOR: [f64; 3] = [0.05, 0.0, 0.2];
+const MID_COLOR: [f64; 3] = [0.8, 0.2, 0.1];
+const STRONG_COLOR: [f64; 3] = [1.0, 1.0, 0.6];
+
+/// A layer that colours the view by the magnitude of the gravitational acceleration, sampled on a
+/// grid from the quadtree, on a logarithmic scale below the strongest acceleration in view. This
+/// shows the supermassive black hole's sphere of influence and the wakes of satellites.
+pub struct AccelerationLayer {
+    textured_quad: TexturedQuad,
+    galaxy: Rc<RefCell<Galaxy>>,
+    config: Config,
+
+    field: AccelerationField,
+
+    /// The number of updates since the field was last sampled, or None if it needs to be.
+    updates_since_sample: Option<u64>,
+
+    /// How many orders of magnitude below the strongest acceleration in view are coloured.
+    dynamic_range: f32,
+
+    /// How opaque the layer is, between 0 and 1.
+    opacity: f32,
+}
+
+impl AccelerationLayer {
+    /// Create an acceleration heatmap layer for the given galaxy.
+    pub fn new(ctx: &mut Context, config: &Config, galaxy: Rc<RefCell<Galaxy>>) -> Result<Self, Box<dyn Error>> {
+        let blend = BlendState::new(Equation::Add,
+                                    BlendFactor::Value(BlendValue::SourceAlpha),
+                                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha));
+        let textured_quad = TexturedQuad::with_blend(ctx, TEXTURE_SIZE, TEXTURE_SIZE, Some(blend))?;
+        textured_quad.texture.set_filter(ctx, FilterMode::Linear);
+
+        Ok(Self {
+            textured_quad,
+            galaxy,
+            config: config.clone(),
+            field: AccelerationField::default(),
+            updates_since_sample: None,
+            dynamic_range: 3.0,
+            opacity: 0.6,
+        })
+    }
+
+    /// The colour of an acceleration as a fraction of the strongest in view, mapped
+    /// logarithmically over the dynamic range.
+    fn acceleration_color(&self, fraction: f64) -> [f64; 3] {
+        if fraction <= 0.0 {
+            return WEAK_COLOR;
+        }
+
+        let dynamic_range = self.dynamic_range as f64;
+        let t = ((fraction.log10() + dynamic_range) / dynamic_range).clamp(0.0, 1.0);
+        let (from, to, t) = match t < 0.5 {
+            true => (WEAK_COLOR, MID_COLOR, t * 2.0),
+            false => (MID_COLOR, STRONG_COLOR, t * 2.0 - 1.0),
+        };
+        std::array::from_fn(|i| from[i] + (to[i] - from[i]) * t)
+    }
+
+    /// Sample the acceleration in the current view and draw it into the texture.
+    fn update_texture(&mut self, ctx: &mut Context) {
+        {
+            let galaxy = self.galaxy.borrow();
+            let (view_offset, view_size) = galaxy.renderer.view_bounds();
+            self.field = AccelerationField::sample(&galaxy.sim.quadtree,
+                                                   &self.config,
+                                                   view_offset,
+                                                   view_size,
+                                                   TEXTURE_SIZE,
+                                                   TEXTURE_SIZE);
+        }
+
+        let max = self.field.max;
+        let alpha = (self.opacity * 255.0) as u8;
+        let data = self.field.values.iter().flat_map(|&acceleration| {
+            let fraction = if max > 0.0 { acceleration / max } else { 0.0 };
+            let [r, g, b] = self.acceleration_color(fraction).map(|channel| (channel * 255.0) as u8);
+            [r, g, b, alpha]
+        }).collect::<Vec<u8>>();
+
+        self.textured_quad.texture.update(ctx, &data);
+    }
+}
+
+impl Drawable for AccelerationLayer {
+    /// Sample the acceleration again if it's time to.
+    fn fixed_update(&mut self,
+                    ctx: &mut Context,
+                    _input_state: &InputState,
+                    _viewport: &Viewport,
+                    _time_delta: f64)
+    {
+        match self.updates_since_sample {
+            Some(updates) if updates + 1 < UPDATE_INTERVAL => self.updates_since_sample = Some(updates + 1),
+            _ => {
+                self.update_texture(ctx);
+                self.updates_since_sample = Some(0);
+            },
+        }
+    }
+
+    /// Build the "Acceleration" window.
+    fn ui(&mut self, _ctx: &mut Context, ui: &imgui::Ui) {
+        ui.window("Acceleration")
+            .size([250.0, 110.0], imgui::Condition::FirstUseEver)
+            .position([370.0, 530.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.slider("Dynamic range", 1.0, 6.0, &mut self.dynamic_range);
+                ui.slider("Opacity", 0.0, 1.0, &mut self.opacity);
+                ui.label_text("Peak |a|", format!("{:.3e}", self.field.max));
+            });
+    }
+
+    /// Draw the acceleration heatmap.
+    fn render(&mut self, ctx: &mut Context, _alpha: f64) {
+        self.textured_quad.draw(ctx);
+    }
+}
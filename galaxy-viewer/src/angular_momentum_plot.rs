@@ -0,0 +1,85 @@
+use std::collections::VecDeque;
+
+use galaxy_core::metrics::AngularMomentum;
+use galaxy_core::simulation::{GalaxySim, StarComponent};
+
+/// The number of samples of angular momentum kept for the plot.
+const HISTORY_LENGTH: usize = 500;
+
+/// Plots the angular momentum of the galaxy and each of its components over time, as the fraction
+/// it's changed by since the galaxy was generated. The total should be conserved, so any drift
+/// exposes errors from the integrator or the tree, while the components can exchange angular
+/// momentum, e.g. as satellites are stripped.
+pub struct AngularMomentumPlot {
+    /// The angular momentum when the first sample was taken.
+    initial: Option<AngularMomentum>,
+
+    history: VecDeque<AngularMomentum>,
+}
+
+impl AngularMomentumPlot {
+    /// Create an empty plot.
+    pub fn new() -> Self {
+        Self {
+            initial: None,
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+        }
+    }
+
+    /// Clear the plot, e.g. when the galaxy is replaced.
+    pub fn reset(&mut self) {
+        self.initial = None;
+        self.history.clear();
+    }
+
+    /// Take a sample of the simulation's angular momentum.
+    pub fn record(&mut self, sim: &GalaxySim) {
+        let angular_momentum = AngularMomentum::measure(&sim.stars().to_vec());
+        self.initial.get_or_insert(angular_momentum);
+
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(angular_momentum);
+    }
+
+    /// Build the angular momentum window.
+    pub fn ui(&self, ui: &imgui::Ui) {
+        ui.window("Angular momentum")
+            .size([300.0, 250.0], imgui::Condition::FirstUseEver)
+            .position([630.0, 10.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let initial = match &self.initial {
+                    Some(initial) => initial,
+                    None => return,
+                };
+
+                self.plot(ui, "Total", initial.total, |angular_momentum| angular_momentum.total);
+
+                // Only plot the components the galaxy has.
+                for component in StarComponent::ALL {
+                    let initial_component = initial.component(component);
+                    if initial_component != 0.0 {
+                        self.plot(ui, component.name(), initial_component, |angular_momentum| {
+                            angular_momentum.component(component)
+                        });
+                    }
+                }
+            });
+    }
+
+    /// Plot the relative change of one of the angular momenta.
+    fn plot<F>(&self, ui: &imgui::Ui, label: &str, initial: f64, value: F)
+        where F: Fn(&AngularMomentum) -> f64
+    {
+        let changes: Vec<f32> = self.history.iter()
+            .map(|angular_momentum| ((value(angular_momentum) - initial) / initial.abs()) as f32)
+            .collect();
+        let latest = changes.last().copied().unwrap_or(0.0);
+
+        ui.plot_lines(label, &changes)
+            .graph_size([0.0, 40.0])
+            .overlay_text(format!("{:+.3}%", latest * 100.0))
+            .build();
+    }
+}
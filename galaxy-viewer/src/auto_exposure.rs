@@ -0,0 +1,92 @@
+use crate::drawable::StarSprite;
+
+/// The number of cells in each dimension of the grid the brightness of the stars in the view is
+/// accumulated on.
+const GRID_SIZE: usize = 64;
+
+/// The range of the histogram's bins, as log2 of the total brightness of a cell, and how many bins
+/// it's divided into.
+const MIN_LOG2: f64 = -8.0;
+const MAX_LOG2: f64 = 8.0;
+const BIN_COUNT: usize = 32;
+
+/// The range the exposure is kept within.
+const MIN_EXPOSURE: f64 = 0.01;
+const MAX_EXPOSURE: f64 = 100.0;
+
+/// How far the exposure moves towards its target each frame, in log space so that it brightens
+/// and darkens equally smoothly.
+const ADAPTATION_RATE: f64 = 0.05;
+
+/// Automatic exposure for the stars. Each frame, the brightness of the stars in the view is
+/// accumulated on a coarse grid, and the exposure is moved towards the one that draws a chosen
+/// percentile of the non-empty cells at a chosen brightness. Dense regions fill many bright cells
+/// and sparse ones a few faint cells, so the exposure follows what's in view as the camera zooms.
+pub struct AutoExposure {
+    grid: Vec<f32>,
+
+    /// The number of non-empty cells in each bin, from the faintest.
+    histogram: Vec<f32>,
+
+    exposure: f64,
+}
+
+impl AutoExposure {
+    /// Create automatic exposure starting at the given exposure.
+    pub fn new(exposure: f64) -> Self {
+        Self {
+            grid: vec![0.0; GRID_SIZE * GRID_SIZE],
+            histogram: vec![0.0; BIN_COUNT],
+            exposure: exposure.clamp(MIN_EXPOSURE, MAX_EXPOSURE),
+        }
+    }
+
+    /// The current exposure.
+    pub fn exposure(&self) -> f64 {
+        self.exposure
+    }
+
+    /// The histogram of the total brightness of the cells from the last update, with bins spaced
+    /// logarithmically from the faintest.
+    pub fn histogram(&self) -> &[f32] {
+        &self.histogram
+    }
+
+    /// Accumulate the brightness of the sprites to be drawn this frame, and move the exposure
+    /// towards the one that draws the given percentile of the cells at the key brightness. The
+    /// exposure stays where it is while the view is empty.
+    pub fn update(&mut self, sprites: &[StarSprite], key: f64, percentile: f64) {
+        self.grid.iter_mut().for_each(|cell| *cell = 0.0);
+        for sprite in sprites {
+            let to_cell = |ndc: f32| ((ndc + 1.0) * 0.5 * GRID_SIZE as f32).floor();
+            let (x, y) = (to_cell(sprite.center.x), to_cell(sprite.center.y));
+            if (0.0..GRID_SIZE as f32).contains(&x) && (0.0..GRID_SIZE as f32).contains(&y) {
+                self.grid[y as usize * GRID_SIZE + x as usize] += sprite.brightness;
+            }
+        }
+
+        self.histogram.iter_mut().for_each(|bin| *bin = 0.0);
+        let bin_width = (MAX_LOG2 - MIN_LOG2) / BIN_COUNT as f64;
+        let mut total = 0.0;
+        for &cell in self.grid.iter().filter(|&&cell| cell > 0.0) {
+            let bin = ((f64::log2(cell as f64) - MIN_LOG2) / bin_width).clamp(0.0, BIN_COUNT as f64 - 1.0);
+            self.histogram[bin as usize] += 1.0;
+            total += 1.0;
+        }
+        if total == 0.0 {
+            return;
+        }
+
+        // Find the bin the percentile falls in, and take the brightness at its center.
+        let threshold = total * percentile.clamp(0.0, 1.0);
+        let mut cumulative = 0.0;
+        let bin = self.histogram.iter().position(|&count| {
+            cumulative += count;
+            cumulative >= threshold
+        }).unwrap_or(BIN_COUNT - 1);
+        let brightness = f64::exp2(MIN_LOG2 + (bin as f64 + 0.5) * bin_width);
+
+        let target = (key / brightness).clamp(MIN_EXPOSURE, MAX_EXPOSURE);
+        self.exposure = f64::exp(self.exposure.ln() + (target.ln() - self.exposure.ln()) * ADAPTATION_RATE);
+    }
+}
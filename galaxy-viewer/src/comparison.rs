@@ -0,0 +1,87 @@
+use std::error::Error;
+
+use miniquad::Context;
+
+use galaxy_core::config::Config;
+use galaxy_core::rng::RngStreams;
+use galaxy_core::simulation::GalaxySim;
+
+use crate::galaxy::{Galaxy, WARNING_COLOR};
+
+/// How far apart in simulated time the two galaxies can be before they're considered out of sync,
+/// in Myr.
+const SYNC_TOLERANCE_MYR: f64 = 1e-6;
+
+/// A second galaxy run alongside the main one for A/B comparisons, e.g. with a different seed or
+/// different simulation parameters. It steps at the same rate as the main galaxy, pauses with it,
+/// and is drawn with the same view, so that the two can be compared side by side. Scenario scripts
+/// only run on the main galaxy.
+pub struct Comparison {
+    pub galaxy: Galaxy,
+
+    /// What's different about the comparison galaxy, shown in the UI.
+    pub label: String,
+
+    config: Config,
+
+    /// The seed to generate the comparison galaxy with, or None to use the main galaxy's seed.
+    seed: Option<u64>,
+}
+
+impl Comparison {
+    /// Generate the comparison galaxy from its config, and its own seed or the main galaxy's.
+    pub fn new(ctx: &mut Context,
+               config: Config,
+               seed: Option<u64>,
+               main_seed: u64,
+               label: String) -> Result<Self, Box<dyn Error>>
+    {
+        let seed_used = seed.unwrap_or(main_seed);
+        log::info!("Generating comparison galaxy ({label}) with seed {seed_used}");
+
+        let galaxy = Galaxy::new(ctx, &config, &RngStreams::new(seed_used))?;
+
+        Ok(Self { galaxy, label, config, seed })
+    }
+
+    /// Regenerate the comparison galaxy from the start, e.g. when the main galaxy is regenerated.
+    pub fn restart(&mut self, main_seed: u64) -> Result<(), Box<dyn Error>> {
+        let seed = self.seed.unwrap_or(main_seed);
+        log::info!("Regenerating comparison galaxy with seed {seed}");
+
+        self.galaxy.sim = GalaxySim::new(&self.config, &RngStreams::new(seed))?;
+        Ok(())
+    }
+
+    /// Match the main galaxy's view, rate and pause state, and step alongside it.
+    pub fn update(&mut self, main: &Galaxy, time_delta: f64) {
+        self.galaxy.follow(main);
+        self.galaxy.step(time_delta);
+    }
+
+    /// Build the comparison window, showing how the two galaxies differ.
+    pub fn ui(&mut self, ui: &imgui::Ui, main: &Galaxy, main_seed: u64) {
+        ui.window("Comparison")
+            .size([300.0, 150.0], imgui::Condition::FirstUseEver)
+            .position([630.0, 700.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.text(format!("Left: main, right: {}", self.label));
+                ui.label_text("Stars", format!("{} / {}", main.sim.stars().len(), self.galaxy.sim.stars().len()));
+                ui.label_text("Elapsed", format!("{:.0} / {:.0} Myr",
+                                                 main.sim.elapsed_myr(), self.galaxy.sim.elapsed_myr()));
+                ui.label_text("Substeps", format!("{} / {}", main.sim.substep_count, self.galaxy.sim.substep_count));
+
+                // Rewinding or resuming the main galaxy moves it to a different time.
+                let offset = self.galaxy.sim.elapsed_myr() - main.sim.elapsed_myr();
+                if offset.abs() > SYNC_TOLERANCE_MYR {
+                    ui.text_colored(WARNING_COLOR, format!("Out of sync by {offset:.0} Myr"));
+                }
+
+                if ui.button("Restart comparison") {
+                    if let Err(err) = self.restart(main_seed) {
+                        log::error!("Failed to regenerate comparison galaxy: {err}");
+                    }
+                }
+            });
+    }
+}
@@ -0,0 +1,134 @@
+use std::error::Error;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// A command received by the control server, to be handled by the main thread.
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    SetRate(f64),
+    SetOpeningAngle(f64),
+    Snapshot,
+    Status,
+}
+
+/// The response to a control command.
+pub struct ControlResponse {
+    pub status: u16,
+    pub body: serde_json::Value,
+}
+
+impl ControlResponse {
+    /// A successful response with the given JSON body.
+    pub fn ok(body: serde_json::Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    /// An error response with the given status code and message.
+    pub fn error(status: u16, message: &str) -> Self {
+        Self { status, body: json!({ "error": message }) }
+    }
+}
+
+/// A control command along with the channel to send its response on.
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    responder: Sender<ControlResponse>,
+}
+
+impl ControlRequest {
+    /// Send the response back to the HTTP client.
+    pub fn respond(self, response: ControlResponse) {
+        // The server thread only goes away if the client disconnected, in which case there's
+        // nobody to respond to anyway.
+        let _ = self.responder.send(response);
+    }
+}
+
+/// A small HTTP server for monitoring and steering the simulation remotely. Requests are handled
+/// on a background thread and forwarded to the main thread, which should call `poll` regularly.
+///
+/// Endpoints, all of which respond with JSON:
+///
+/// * `GET /status` - simulation diagnostics
+/// * `POST /pause` and `POST /resume`
+/// * `POST /rate` - set the rate in Myr per second to the number in the request body
+/// * `POST /opening_angle` - set the Barnes-Hut opening angle to the number in the request body
+/// * `POST /snapshot` - write a checkpoint of the current state
+pub struct ControlServer {
+    requests: Receiver<ControlRequest>,
+}
+
+impl ControlServer {
+    /// Start listening for HTTP requests on the given address.
+    pub fn new(address: &str) -> Result<Self, Box<dyn Error>> {
+        let server = Server::http(address).map_err(|err| err.to_string())?;
+        log::info!("Control API listening on http://{address}");
+
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                Self::handle_request(request, &sender);
+            }
+        });
+
+        Ok(Self { requests })
+    }
+
+    /// Take all control requests received since the last poll.
+    pub fn poll(&self) -> Vec<ControlRequest> {
+        self.requests.try_iter().collect()
+    }
+
+    /// Parse an HTTP request, forward it to the main thread and wait for the response.
+    fn handle_request(mut request: Request, sender: &Sender<ControlRequest>) {
+        let response = match Self::parse_command(&mut request) {
+            Ok(command) => {
+                let (responder, response) = mpsc::channel();
+                match sender.send(ControlRequest { command, responder }) {
+                    Ok(()) => response.recv()
+                        .unwrap_or_else(|_| ControlResponse::error(503, "Simulation is shutting down")),
+                    Err(_) => ControlResponse::error(503, "Simulation is shutting down"),
+                }
+            },
+            Err(response) => response,
+        };
+
+        let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+        let http_response = Response::from_string(response.body.to_string())
+            .with_status_code(response.status)
+            .with_header(content_type);
+
+        if let Err(err) = request.respond(http_response) {
+            log::warn!("Failed to send control API response: {err}");
+        }
+    }
+
+    /// Convert an HTTP request to a control command.
+    fn parse_command(request: &mut Request) -> Result<ControlCommand, ControlResponse> {
+        match (request.method(), request.url()) {
+            (Method::Get, "/status") => Ok(ControlCommand::Status),
+            (Method::Post, "/pause") => Ok(ControlCommand::Pause),
+            (Method::Post, "/resume") => Ok(ControlCommand::Resume),
+            (Method::Post, "/snapshot") => Ok(ControlCommand::Snapshot),
+            (Method::Post, "/rate") => Ok(ControlCommand::SetRate(Self::parse_number(request)?)),
+            (Method::Post, "/opening_angle") => Ok(ControlCommand::SetOpeningAngle(Self::parse_number(request)?)),
+            _ => Err(ControlResponse::error(404, "Unknown endpoint")),
+        }
+    }
+
+    /// Parse the body of a request as a single non-negative number.
+    fn parse_number(request: &mut Request) -> Result<f64, ControlResponse> {
+        let mut body = String::new();
+        request.as_reader().read_to_string(&mut body)
+            .map_err(|_| ControlResponse::error(400, "Failed to read request body"))?;
+
+        match body.trim().parse::<f64>() {
+            Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+            _ => Err(ControlResponse::error(400, "Expected a non-negative number in the request body")),
+        }
+    }
+}
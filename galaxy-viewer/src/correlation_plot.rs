@@ -0,0 +1,90 @@
+use galaxy_core::analysis::correlation::CorrelationFunction;
+use galaxy_core::simulation::GalaxySim;
+
+/// The range of radii the correlation function is measured over, in parsecs.
+const MIN_RADIUS: f64 = 10.0;
+const MAX_RADIUS: f64 = 5000.0;
+
+/// The number of radial bins.
+const BIN_COUNT: usize = 24;
+
+/// The number of stars whose neighbours are counted, which keeps the cost down in large galaxies.
+const SAMPLE_COUNT: usize = 1000;
+
+/// How many steps there are between each measurement.
+const UPDATE_INTERVAL: u64 = 30;
+
+/// Plots the two-point correlation function of the stars, comparing it to the one measured when
+/// the galaxy was generated so that the growth of clustering can be seen over time.
+pub struct CorrelationPlot {
+    initial: Option<CorrelationFunction>,
+    latest: Option<CorrelationFunction>,
+
+    /// The number of steps since the last measurement.
+    steps_since_update: u64,
+}
+
+impl CorrelationPlot {
+    /// Create an empty plot.
+    pub fn new() -> Self {
+        Self {
+            initial: None,
+            latest: None,
+            steps_since_update: 0,
+        }
+    }
+
+    /// Clear the plot, e.g. when the galaxy is replaced.
+    pub fn reset(&mut self) {
+        self.initial = None;
+        self.latest = None;
+        self.steps_since_update = 0;
+    }
+
+    /// Measure the correlation function, if it's time to.
+    pub fn record(&mut self, sim: &GalaxySim) {
+        if self.latest.is_some() && self.steps_since_update + 1 < UPDATE_INTERVAL {
+            self.steps_since_update += 1;
+            return;
+        }
+        self.steps_since_update = 0;
+
+        let correlation = CorrelationFunction::measure(&sim.quadtree, MIN_RADIUS, MAX_RADIUS, BIN_COUNT, SAMPLE_COUNT);
+        self.initial.get_or_insert_with(|| correlation.clone());
+        self.latest = Some(correlation);
+    }
+
+    /// Build the correlation function window.
+    pub fn ui(&self, ui: &imgui::Ui) {
+        ui.window("Correlation function")
+            .size([300.0, 220.0], imgui::Condition::FirstUseEver)
+            .position([630.0, 270.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let (initial, latest) = match (&self.initial, &self.latest) {
+                    (Some(initial), Some(latest)) => (initial, latest),
+                    _ => return,
+                };
+
+                ui.text(format!("log10(1 + xi) from {MIN_RADIUS} to {MAX_RADIUS} pc"));
+                Self::plot(ui, "Now", latest);
+                Self::plot(ui, "Initial", initial);
+            });
+    }
+
+    /// Plot a correlation function against the logarithmically spaced bins. It's plotted as
+    /// log10(1 + ξ) as it spans orders of magnitude on small scales.
+    fn plot(ui: &imgui::Ui, label: &str, correlation: &CorrelationFunction) {
+        if correlation.values.is_empty() {
+            return;
+        }
+
+        let values: Vec<f32> = correlation.values.iter()
+            .map(|value| f64::log10(f64::max(1.0 + value, 1e-3)) as f32)
+            .collect();
+
+        ui.plot_lines(label, &values)
+            .graph_size([0.0, 60.0])
+            .overlay_text(format!("xi({:.0} pc) = {:.2}", correlation.bin_radius(0), correlation.values[0]))
+            .build();
+    }
+}
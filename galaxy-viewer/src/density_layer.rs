@@ -0,0 +1,131 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use galaxy_core::analysis::density_field::DensityField;
+use miniquad::*;
+
+use crate::drawable::*;
+use crate::galaxy::Galaxy;
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+/// The size of the texture the density field is estimated on. It's drawn with linear filtering, so
+/// it can be much coarser than the stars.
+const TEXTURE_SIZE: usize = 128;
+
+/// The colours of the faintest and densest parts of the field, and of the field halfway between
+/// them, which give it the look of glowing gas.
+const FAINT_COLOR: [f64; 3] = [0.05, 0.02, 0.15];
+const MID_COLOR: [f64; 3] = [0.55, 0.15, 0.45];
+const DENSE_COLOR: [f64; 3] = [1.0, 0.85, 0.7];
+
+/// A layer that estimates the continuous surface density of the stars in the current view by
+/// kernel density estimation and draws it as a smooth, nebulous image on top of them. The kernel's
+/// bandwidth is in texels, so the field is equally smooth at every zoom level.
+pub struct DensityLayer {
+    textured_quad: TexturedQuad,
+    galaxy: Rc<RefCell<Galaxy>>,
+
+    field: DensityField,
+
+    /// The radius of the kernel, in texels.
+    bandwidth: f32,
+
+    /// How many orders of magnitude below the densest part of the view are drawn.
+    dynamic_range: f32,
+
+    /// How bright the layer is, between 0 and 1.
+    opacity: f32,
+}
+
+impl DensityLayer {
+    /// Create a density field layer for the given galaxy.
+    pub fn new(ctx: &mut Context, galaxy: Rc<RefCell<Galaxy>>) -> Result<Self, Box<dyn Error>> {
+        let blend = BlendState::new(Equation::Add, BlendFactor::One, BlendFactor::One);
+        let textured_quad = TexturedQuad::with_blend(ctx, TEXTURE_SIZE, TEXTURE_SIZE, Some(blend))?;
+        textured_quad.texture.set_filter(ctx, FilterMode::Linear);
+
+        Ok(Self {
+            textured_quad,
+            galaxy,
+            field: DensityField::default(),
+            bandwidth: 3.0,
+            dynamic_range: 3.0,
+            opacity: 1.0,
+        })
+    }
+
+    /// The colour of a density as a fraction of the densest part of the view, mapped
+    /// logarithmically over the dynamic range.
+    fn density_color(&self, fraction: f64) -> [f64; 3] {
+        if fraction <= 0.0 {
+            return [0.0; 3];
+        }
+
+        let dynamic_range = self.dynamic_range as f64;
+        let t = ((fraction.log10() + dynamic_range) / dynamic_range).clamp(0.0, 1.0);
+        let (from, to, t) = match t < 0.5 {
+            true => (FAINT_COLOR, MID_COLOR, t * 2.0),
+            false => (MID_COLOR, DENSE_COLOR, t * 2.0 - 1.0),
+        };
+
+        // Fade in from black so that the edge of the faintest part of the field isn't visible.
+        let fade = (t * 4.0).min(1.0);
+        std::array::from_fn(|i| (from[i] + (to[i] - from[i]) * t) * fade * self.opacity as f64)
+    }
+
+    /// Estimate the density field in the current view and draw it into the texture.
+    fn update_texture(&mut self, ctx: &mut Context) {
+        {
+            let galaxy = self.galaxy.borrow();
+            let (view_offset, view_size) = galaxy.renderer.view_bounds();
+            let bandwidth = self.bandwidth as f64 * view_size.x / TEXTURE_SIZE as f64;
+            self.field = DensityField::estimate(&galaxy.sim.quadtree,
+                                                view_offset,
+                                                view_size,
+                                                TEXTURE_SIZE,
+                                                TEXTURE_SIZE,
+                                                bandwidth);
+        }
+
+        let max = self.field.max;
+        let data = self.field.values.iter().flat_map(|&density| {
+            let fraction = if max > 0.0 { density / max } else { 0.0 };
+            let [r, g, b] = self.density_color(fraction).map(|channel| (channel.min(1.0) * 255.0) as u8);
+            [r, g, b, 0xFF]
+        }).collect::<Vec<u8>>();
+
+        self.textured_quad.texture.update(ctx, &data);
+    }
+}
+
+impl Drawable for DensityLayer {
+    /// Re-estimate the density field.
+    fn fixed_update(&mut self,
+                    ctx: &mut Context,
+                    _input_state: &InputState,
+                    _viewport: &Viewport,
+                    _time_delta: f64)
+    {
+        self.update_texture(ctx);
+    }
+
+    /// Build the "Density field" window.
+    fn ui(&mut self, _ctx: &mut Context, ui: &imgui::Ui) {
+        ui.window("Density field")
+            .size([250.0, 130.0], imgui::Condition::FirstUseEver)
+            .position([370.0, 450.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.slider("Bandwidth (texels)", 1.0, 10.0, &mut self.bandwidth);
+                ui.slider("Dynamic range", 1.0, 6.0, &mut self.dynamic_range);
+                ui.slider("Opacity", 0.0, 1.0, &mut self.opacity);
+                ui.label_text("Peak density", format!("{:.1} Msun/pc^2", self.field.max));
+            });
+    }
+
+    /// Draw the density field.
+    fn render(&mut self, ctx: &mut Context, _alpha: f64) {
+        self.textured_quad.draw(ctx);
+    }
+}
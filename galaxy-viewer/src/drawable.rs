@@ -0,0 +1,44 @@
+use miniquad::Context;
+
+mod textured_quad;
+mod star_sprites;
+mod supersampler;
+mod trail_accumulator;
+mod wireframe_quad;
+mod imgui;
+mod quadtree_debug;
+
+pub use textured_quad::*;
+pub use star_sprites::*;
+pub use supersampler::*;
+pub use trail_accumulator::*;
+pub use wireframe_quad::*;
+pub use quadtree_debug::*;
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+pub use self::imgui::*;
+
+/// Something drawn in the viewer. Its state only changes in fixed-size steps, in `fixed_update`,
+/// while `ui` and `render` are called once per frame, and `render` may fall between two steps, so
+/// it draws the state interpolated between them rather than changing it.
+pub trait Drawable {
+    /// Update for one fixed timestep. `viewport` is the area of the window the drawable is drawn
+    /// in, for mapping the mouse into it.
+    fn fixed_update(&mut self,
+                    ctx: &mut Context,
+                    input_state: &InputState,
+                    viewport: &Viewport,
+                    time_delta: f64);
+
+    /// Build the drawable's windows for this frame, if it has any.
+    fn ui(&mut self, _ctx: &mut Context, _ui: &::imgui::Ui) {}
+
+    /// Draw the current state. `alpha` is how far real time is between the last two fixed updates,
+    /// from 0 to 1, for drawables that interpolate between them.
+    fn render(&mut self, ctx: &mut Context, alpha: f64);
+
+    /// Draw anything that's rendered into an offscreen render target, before the frame's render
+    /// pass begins and `render` is called.
+    fn render_offscreen(&mut self, _ctx: &mut Context, _alpha: f64) {}
+}
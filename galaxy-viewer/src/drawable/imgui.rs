@@ -1,18 +1,16 @@
 // Based on https://github.com/not-fl3/imgui-miniquad-render.
 use miniquad::*;
 use imgui::{DrawCmd, DrawCmdParams, DrawVert};
-use std::{rc::Rc, cell::RefCell};
-use owning_ref::OwningRefMut;
 use crate::shaders::imgui as shader;
 
 const MAX_VERTICES: usize = 30000;
 const MAX_INDICES: usize = 50000;
 
-/// An ImguiRenderer, which owns an instance of imgui and responds to miniquad events for input and rendering.
+/// An ImguiRenderer, which owns an instance of imgui, feeds it miniquad's input events, and builds
+/// and renders a frame of UI each frame. The UI is built by a callback passed to `frame`, so
+/// nothing else needs to hold on to imgui between frames.
 pub struct ImguiRenderer {
-    // This really annoying type is just so we can store the Ui instance we get from
-    // Context::new_frame() and share it.
-    imgui: Rc<RefCell<OwningRefMut<Box<imgui::Context>, imgui::Ui>>>,
+    imgui: imgui::Context,
     last_frame: std::time::Instant,
     pipeline: Pipeline,
     font_texture: Texture,
@@ -97,10 +95,6 @@ impl ImguiRenderer {
             )
         };
 
-        // Wrap the imgui instance and a Ui instance up in an OwningRef so we can store and share it.
-        let imgui = Rc::new(RefCell::new(OwningRefMut::new(Box::new(imgui)).map_mut(|imgui| imgui.new_frame())));
-
-
         Self {
             imgui,
             pipeline,
@@ -110,22 +104,13 @@ impl ImguiRenderer {
         }
     }
 
-    /// Get the shared reference to the imgui context/ui for making windows etc.
-    pub fn ui(&mut self) -> Rc<RefCell<OwningRefMut<Box<imgui::Context>, imgui::Ui>>> {
-        self.imgui.clone()
-    }
-}
-
-impl EventHandler for ImguiRenderer {
-    fn resize_event(&mut self, _ctx: &mut miniquad::Context, width: f32, height: f32) {
-        let mut imgui = self.imgui.borrow_mut();
-        let mut io = imgui.as_owner_mut().io_mut();
+    pub fn resize_event(&mut self, _ctx: &mut miniquad::Context, width: f32, height: f32) {
+        let io = self.imgui.io_mut();
         io.display_size = [width, height];
     }
 
-    fn char_event(&mut self, _ctx: &mut miniquad::Context, character: char, mods: KeyMods, _: bool) {
-        let mut imgui = self.imgui.borrow_mut();
-        let io = imgui.as_owner_mut().io_mut();
+    pub fn char_event(&mut self, _ctx: &mut miniquad::Context, character: char, mods: KeyMods, _: bool) {
+        let io = self.imgui.io_mut();
 
         io.key_ctrl = mods.ctrl;
         io.key_alt = mods.alt;
@@ -134,9 +119,8 @@ impl EventHandler for ImguiRenderer {
         io.add_input_character(character);
     }
 
-    fn key_down_event(&mut self, _ctx: &mut miniquad::Context, keycode: KeyCode, mods: KeyMods, _: bool) {
-        let mut imgui = self.imgui.borrow_mut();
-        let mut io = imgui.as_owner_mut().io_mut();
+    pub fn key_down_event(&mut self, _ctx: &mut miniquad::Context, keycode: KeyCode, mods: KeyMods, _: bool) {
+        let io = self.imgui.io_mut();
 
         // when the keycode is the modifier itself - mods.MODIFIER is false yet, however the modifier button is just pressed and is actually true
         io.key_ctrl = mods.ctrl;
@@ -146,9 +130,8 @@ impl EventHandler for ImguiRenderer {
         io.keys_down[keycode as usize] = true;
     }
 
-    fn key_up_event(&mut self, _ctx: &mut miniquad::Context, keycode: KeyCode, mods: KeyMods) {
-        let mut imgui = self.imgui.borrow_mut();
-        let mut io = imgui.as_owner_mut().io_mut();
+    pub fn key_up_event(&mut self, _ctx: &mut miniquad::Context, keycode: KeyCode, mods: KeyMods) {
+        let io = self.imgui.io_mut();
 
         // when the keycode is the modifier itself - mods.MODIFIER is true, however the modifier is actually released
         io.key_ctrl =
@@ -160,54 +143,45 @@ impl EventHandler for ImguiRenderer {
         io.keys_down[keycode as usize] = false;
     }
 
-    fn mouse_motion_event(&mut self, _ctx: &mut miniquad::Context, x: f32, y: f32) {
-        let mut imgui = self.imgui.borrow_mut();
-        let mut io = imgui.as_owner_mut().io_mut();
+    pub fn mouse_motion_event(&mut self, _ctx: &mut miniquad::Context, x: f32, y: f32) {
+        let io = self.imgui.io_mut();
         io.mouse_pos = [x, y];
     }
-    fn mouse_wheel_event(&mut self, _ctx: &mut miniquad::Context, _x: f32, y: f32) {
-        let mut imgui = self.imgui.borrow_mut();
-        let mut io = imgui.as_owner_mut().io_mut();
+    pub fn mouse_wheel_event(&mut self, _ctx: &mut miniquad::Context, _x: f32, y: f32) {
+        let io = self.imgui.io_mut();
         io.mouse_wheel = y;
     }
-    fn mouse_button_down_event(
+    pub fn mouse_button_down_event(
         &mut self,
         _ctx: &mut miniquad::Context,
         button: MouseButton,
         _x: f32,
         _y: f32,
     ) {
-        let mut imgui = self.imgui.borrow_mut();
-        let mut io = imgui.as_owner_mut().io_mut();
+        let io = self.imgui.io_mut();
         let mouse_left = button == MouseButton::Left;
         let mouse_right = button == MouseButton::Right;
         io.mouse_down = [mouse_left, mouse_right, false, false, false];
     }
-    fn mouse_button_up_event(
+    pub fn mouse_button_up_event(
         &mut self,
         _ctx: &mut miniquad::Context,
         _button: MouseButton,
         _x: f32,
         _y: f32,
     ) {
-        let mut imgui = self.imgui.borrow_mut();
-        let mut io = imgui.as_owner_mut().io_mut();
+        let io = self.imgui.io_mut();
         io.mouse_down = [false, false, false, false, false];
     }
 
-    /// Unused
-    fn update(&mut self, _ctx: &mut miniquad::Context) {}
-
-    /// Render imgui.
-    fn draw(&mut self, ctx: &mut miniquad::Context) {
-        let mut imgui = self.imgui.borrow_mut();
-        let draw_data = {
-            let io = imgui.as_owner_mut().io_mut();
-            let now = std::time::Instant::now();
-            io.update_delta_time(now.duration_since(self.last_frame));
-            self.last_frame = now;
-            imgui.as_owner_mut().render()
-        };
+    /// Build a frame of UI with `build_ui`, and render it over whatever has been drawn this frame.
+    pub fn frame(&mut self, ctx: &mut miniquad::Context, build_ui: impl FnOnce(&mut miniquad::Context, &imgui::Ui)) {
+        let now = std::time::Instant::now();
+        self.imgui.io_mut().update_delta_time(now.duration_since(self.last_frame));
+        self.last_frame = now;
+
+        build_ui(ctx, self.imgui.new_frame());
+        let draw_data = self.imgui.render();
 
         let (width, height) = ctx.screen_size();
         let projection = glam::Mat4::orthographic_rh_gl(0., width, height, 0., -1., 1.);
@@ -302,8 +276,5 @@ impl EventHandler for ImguiRenderer {
         ctx.end_render_pass();
 
         ctx.commit_frame();
-
-        // After rendering, start a new frame for next frame.
-        imgui.as_owner_mut().new_frame();
     }
 }
@@ -0,0 +1,31 @@
+use galaxy_core::quadtree::{Quadtree, Spatial};
+
+use super::WireframeQuad;
+
+/// Draws the outlines of a quadtree's cells for debugging. The wireframe quad is created the first
+/// time it's drawn and then kept, as miniquad doesn't free its buffers and pipelines when dropped.
+pub struct QuadtreeDebug {
+    wireframe_quad: Option<WireframeQuad>,
+}
+
+impl QuadtreeDebug {
+    /// Create a quadtree debug drawer. Nothing is allocated until it's first drawn.
+    pub fn new() -> Self {
+        Self {
+            wireframe_quad: None,
+        }
+    }
+
+    /// Draw the outline of every node in the quadtree.
+    pub fn draw<T: Spatial, Internal>(&mut self, ctx: &mut miniquad::Context, quadtree: &Quadtree<T, Internal>) {
+        let wireframe_quad = self.wireframe_quad.get_or_insert_with(|| WireframeQuad::new(ctx).unwrap());
+
+        quadtree.walk_nodes(|index, node| {
+            if node.is_internal() || node.is_leaf() {
+                let (cell_min, cell_max) = index.bounds(quadtree.min, quadtree.max);
+
+                wireframe_quad.draw(ctx, &cell_min.into(), &cell_max.into());
+            }
+        });
+    }
+}
@@ -0,0 +1,110 @@
+use std::error::Error;
+
+use miniquad::*;
+use galaxy_core::types::*;
+use crate::shaders::*;
+
+/// A star to draw as a sprite.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct StarSprite {
+    /// The star's position, in normalized device coordinates.
+    pub center: Vec2,
+
+    /// The radius of the star's glow, in texels.
+    pub point_size: f32,
+
+    /// The star's colour, with each channel between 0 and 1.
+    pub color: [f32; 3],
+
+    /// The star's brightness, between 0 and 1.
+    pub brightness: f32,
+}
+
+/// Draws stars as glowing sprites, by instancing a quad once per star. The sprites are added to
+/// what's already been drawn, so overlapping glows build up.
+pub struct StarSprites {
+    pipeline: Pipeline,
+    bindings: Bindings,
+
+    /// How many sprites the instance buffer has room for.
+    capacity: usize,
+}
+
+impl StarSprites {
+    pub fn new(ctx: &mut Context) -> Result<Self, Box<dyn Error>> {
+        let corners: [Vec2; 4] = [
+            Vec2::new(-1.0, -1.0),
+            Vec2::new( 1.0, -1.0),
+            Vec2::new( 1.0,  1.0),
+            Vec2::new(-1.0,  1.0),
+        ];
+        let corner_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, &corners);
+
+        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+        let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
+
+        let capacity = 1024;
+        let instance_buffer = Self::create_instance_buffer(ctx, capacity);
+
+        let bindings = Bindings {
+            vertex_buffers: vec![corner_buffer, instance_buffer],
+            images: Vec::new(),
+            index_buffer,
+        };
+
+        let shader = Shader::new(ctx, stars::VERTEX, stars::FRAGMENT, stars::meta())?;
+
+        let pipeline = Pipeline::with_params(
+            ctx,
+            &[
+                BufferLayout::default(),
+                BufferLayout {
+                    step_func: VertexStep::PerInstance,
+                    ..Default::default()
+                },
+            ],
+            &[
+                VertexAttribute::with_buffer("corner", VertexFormat::Float2, 0),
+                VertexAttribute::with_buffer("center", VertexFormat::Float2, 1),
+                VertexAttribute::with_buffer("point_size", VertexFormat::Float1, 1),
+                VertexAttribute::with_buffer("color", VertexFormat::Float3, 1),
+                VertexAttribute::with_buffer("brightness", VertexFormat::Float1, 1),
+            ],
+            shader,
+            PipelineParams {
+                color_blend: Some(BlendState::new(Equation::Add, BlendFactor::One, BlendFactor::One)),
+                ..Default::default()
+            },
+        );
+
+        Ok(Self {
+            pipeline,
+            bindings,
+            capacity,
+        })
+    }
+
+    fn create_instance_buffer(ctx: &mut Context, capacity: usize) -> Buffer {
+        Buffer::stream(ctx, BufferType::VertexBuffer, capacity * std::mem::size_of::<StarSprite>())
+    }
+
+    /// Draw the sprites, growing the instance buffer first if they don't fit in it.
+    pub fn draw(&mut self, ctx: &mut Context, sprites: &[StarSprite], uniforms: &stars::Uniforms) {
+        if sprites.is_empty() {
+            return;
+        }
+
+        if sprites.len() > self.capacity {
+            self.capacity = sprites.len().next_power_of_two();
+            self.bindings.vertex_buffers[1].delete();
+            self.bindings.vertex_buffers[1] = Self::create_instance_buffer(ctx, self.capacity);
+        }
+        self.bindings.vertex_buffers[1].update(ctx, sprites);
+
+        ctx.apply_pipeline(&self.pipeline);
+        ctx.apply_bindings(&self.bindings);
+        ctx.apply_uniforms(uniforms);
+        ctx.draw(0, 6, sprites.len() as i32);
+    }
+}
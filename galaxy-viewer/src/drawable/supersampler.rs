@@ -0,0 +1,120 @@
+use std::error::Error;
+
+use miniquad::*;
+use galaxy_core::types::*;
+use crate::shaders::*;
+
+/// Renders into an offscreen target several times larger than the output in each dimension, and
+/// downsamples it to the output size by averaging, which smooths out anything smaller than a pixel
+/// of the output. Drawing happens between `begin` and `end`, outside of any other render pass, and
+/// `draw` then draws the downsampled result.
+pub struct Supersampler {
+    scale: usize,
+
+    /// The large render target that's drawn into.
+    pass: RenderPass,
+
+    /// The output size render target it's downsampled into, and how.
+    downsampled_pass: RenderPass,
+    downsample_pipeline: Pipeline,
+    downsample_bindings: Bindings,
+
+    /// Draws the downsampled result.
+    output: TexturedQuad,
+}
+
+impl Supersampler {
+    /// Create a supersampler with an output of the given size, rendering at the given scale, which
+    /// must be between 2 and downsample::MAX_SCALE.
+    pub fn new(ctx: &mut Context, width: usize, height: usize, scale: usize) -> Result<Self, Box<dyn Error>> {
+        if !(2..=downsample::MAX_SCALE).contains(&scale) {
+            return Err(format!("Render scale must be between 2 and {}", downsample::MAX_SCALE).into());
+        }
+
+        let params = |width: usize, height: usize| TextureParams {
+            width: width as u32,
+            height: height as u32,
+            format: TextureFormat::RGBA8,
+            wrap: TextureWrap::Clamp,
+            filter: FilterMode::Nearest,
+        };
+        let texture = Texture::new_render_texture(ctx, params(width * scale, height * scale));
+        let pass = RenderPass::new(ctx, texture, None);
+        let downsampled_texture = Texture::new_render_texture(ctx, params(width, height));
+        let downsampled_pass = RenderPass::new(ctx, downsampled_texture, None);
+
+        let vertices: [Vertex; 4] = [
+            Vertex { pos: Vec2::new(-1.0, -1.0), uv: Vec2::new(0.0, 0.0) },
+            Vertex { pos: Vec2::new( 1.0, -1.0), uv: Vec2::new(1.0, 0.0) },
+            Vertex { pos: Vec2::new( 1.0,  1.0), uv: Vec2::new(1.0, 1.0) },
+            Vertex { pos: Vec2::new(-1.0,  1.0), uv: Vec2::new(0.0, 1.0) },
+        ];
+        let vertex_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, &vertices);
+
+        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+        let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
+
+        let downsample_bindings = Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            images: vec![texture],
+            index_buffer,
+        };
+
+        let shader = Shader::new(ctx, downsample::VERTEX, downsample::FRAGMENT, downsample::meta())?;
+        let downsample_pipeline = Pipeline::new(
+            ctx,
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("pos", VertexFormat::Float2),
+                VertexAttribute::new("uv", VertexFormat::Float2),
+            ],
+            shader,
+        );
+
+        Ok(Self {
+            scale,
+            pass,
+            downsampled_pass,
+            downsample_pipeline,
+            downsample_bindings,
+            output: TexturedQuad::with_texture(ctx, downsampled_texture, None),
+        })
+    }
+
+    /// How many times larger than the output the render target is in each dimension.
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    /// Start drawing into the large render target, clearing it.
+    pub fn begin(&self, ctx: &mut Context) {
+        ctx.begin_pass(self.pass, PassAction::clear_color(0.0, 0.0, 0.0, 1.0));
+    }
+
+    /// Finish drawing into the large render target, and downsample it.
+    pub fn end(&self, ctx: &mut Context) {
+        ctx.end_render_pass();
+
+        let source = self.pass.texture(ctx);
+        ctx.begin_pass(self.downsampled_pass, PassAction::Nothing);
+        ctx.apply_pipeline(&self.downsample_pipeline);
+        ctx.apply_bindings(&self.downsample_bindings);
+        ctx.apply_uniforms(&downsample::Uniforms {
+            texel_size: (1.0 / source.width as f32, 1.0 / source.height as f32),
+            scale: self.scale as f32,
+        });
+        ctx.draw(0, 6, 1);
+        ctx.end_render_pass();
+    }
+
+    /// Draw the downsampled result.
+    pub fn draw(&self, ctx: &mut Context) {
+        self.output.draw(ctx);
+    }
+
+    /// Delete the render targets.
+    pub fn delete(&self, ctx: &mut Context) {
+        self.pass.delete(ctx);
+        self.downsampled_pass.delete(ctx);
+    }
+}
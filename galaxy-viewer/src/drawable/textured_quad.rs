@@ -1,7 +1,7 @@
 use std::error::Error;
 
 use miniquad::*;
-use crate::types::*;
+use galaxy_core::types::*;
 use crate::shaders::*;
 
 pub struct TexturedQuad {
@@ -14,18 +14,16 @@ pub struct TexturedQuad {
 
 impl TexturedQuad {
     pub fn new(ctx: &mut Context, width: usize, height: usize) -> Result<Self, Box<dyn Error>> {
-        let vertices: [Vertex; 4] = [
-            Vertex { pos: Vec2::new(-1.0, -1.0), uv: Vec2::new(0.0, 0.0) },
-            Vertex { pos: Vec2::new( 1.0, -1.0), uv: Vec2::new(1.0, 0.0) },
-            Vertex { pos: Vec2::new( 1.0,  1.0), uv: Vec2::new(1.0, 1.0) },
-            Vertex { pos: Vec2::new(-1.0,  1.0), uv: Vec2::new(0.0, 1.0) },
-        ];
-
-        let vertex_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, &vertices);
-
-        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
-        let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
+        Self::with_blend(ctx, width, height, None)
+    }
 
+    /// Create a textured quad that's blended with what's already been drawn using the given blend
+    /// state, rather than replacing it.
+    pub fn with_blend(ctx: &mut Context,
+                      width: usize,
+                      height: usize,
+                      blend: Option<BlendState>) -> Result<Self, Box<dyn Error>>
+    {
         let texture_size = usize::try_from(width * height * 4).unwrap();
         let pixels = vec![0x00; texture_size];
         let texture = Texture::from_data_and_format(
@@ -39,18 +37,53 @@ impl TexturedQuad {
                 filter: FilterMode::Nearest,
             });
 
+        Ok(Self::with_texture(ctx, texture, blend))
+    }
+
+    /// Create a quad that draws an existing texture, such as a render target, blended with what's
+    /// already been drawn using the given blend state.
+    pub fn with_texture(ctx: &mut Context, texture: Texture, blend: Option<BlendState>) -> Self {
+        let vertices: [Vertex; 4] = [
+            Vertex { pos: Vec2::new(-1.0, -1.0), uv: Vec2::new(0.0, 0.0) },
+            Vertex { pos: Vec2::new( 1.0, -1.0), uv: Vec2::new(1.0, 0.0) },
+            Vertex { pos: Vec2::new( 1.0,  1.0), uv: Vec2::new(1.0, 1.0) },
+            Vertex { pos: Vec2::new(-1.0,  1.0), uv: Vec2::new(0.0, 1.0) },
+        ];
+
+        let vertex_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, &vertices);
+
+        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+        let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
+
         let bindings = Bindings {
             vertex_buffers: vec![vertex_buffer],
             images: vec![texture],
             index_buffer,
         };
 
+        let pipeline = Self::create_pipeline(ctx, blend);
+
+        Self {
+            pipeline,
+            bindings,
+            texture,
+            width: texture.width as usize,
+            height: texture.height as usize,
+        }
+    }
+
+    /// Change how the quad is blended with what's already been drawn.
+    pub fn set_blend(&mut self, ctx: &mut Context, blend: Option<BlendState>) {
+        self.pipeline = Self::create_pipeline(ctx, blend);
+    }
+
+    fn create_pipeline(ctx: &mut Context, blend: Option<BlendState>) -> Pipeline {
         let shader = Shader::new(ctx,
             basic_textured::VERTEX,
             basic_textured::FRAGMENT,
             basic_textured::meta()).unwrap();
 
-        let pipeline = Pipeline::new(
+        Pipeline::with_params(
             ctx,
             &[BufferLayout::default()],
             &[
@@ -58,15 +91,11 @@ impl TexturedQuad {
                 VertexAttribute::new("uv", VertexFormat::Float2),
             ],
             shader,
-        );
-
-        Ok(Self {
-            pipeline,
-            bindings,
-            texture,
-            width,
-            height,
-        })
+            PipelineParams {
+                color_blend: blend,
+                ..Default::default()
+            },
+        )
     }
 
     pub fn draw(&self, ctx: &mut Context) {
@@ -0,0 +1,132 @@
+use std::error::Error;
+
+use miniquad::*;
+use galaxy_core::types::*;
+use crate::shaders::*;
+
+/// Leaves trails behind whatever's drawn, by blending each frame with a faded copy of the frames
+/// before it. Frames are drawn into a render target between `begin` and `end`, outside of any other
+/// render pass, and `end` combines them with the trails so far in one of two accumulation targets,
+/// which are swapped each frame so that the previous one can be read while the next is written.
+/// `draw` then draws the result.
+pub struct TrailAccumulator {
+    /// The render target the current frame is drawn into.
+    frame_pass: RenderPass,
+
+    /// The accumulation targets, the bindings that read the frame and the other target while
+    /// writing to each of them, and the quads that draw them.
+    accumulated_passes: [RenderPass; 2],
+    bindings: [Bindings; 2],
+    outputs: [TexturedQuad; 2],
+    pipeline: Pipeline,
+
+    /// The index of the accumulation target that was written last.
+    current: usize,
+
+    /// Whether the trails so far should be discarded at the next frame.
+    cleared: bool,
+}
+
+impl TrailAccumulator {
+    /// Create a trail accumulator with render targets of the given size.
+    pub fn new(ctx: &mut Context, width: usize, height: usize) -> Result<Self, Box<dyn Error>> {
+        let params = TextureParams {
+            width: width as u32,
+            height: height as u32,
+            format: TextureFormat::RGBA8,
+            wrap: TextureWrap::Clamp,
+            filter: FilterMode::Nearest,
+        };
+        let frame_texture = Texture::new_render_texture(ctx, params);
+        let frame_pass = RenderPass::new(ctx, frame_texture, None);
+        let accumulated_textures = [
+            Texture::new_render_texture(ctx, params),
+            Texture::new_render_texture(ctx, params),
+        ];
+        let accumulated_passes = accumulated_textures.map(|texture| RenderPass::new(ctx, texture, None));
+
+        let vertices: [Vertex; 4] = [
+            Vertex { pos: Vec2::new(-1.0, -1.0), uv: Vec2::new(0.0, 0.0) },
+            Vertex { pos: Vec2::new( 1.0, -1.0), uv: Vec2::new(1.0, 0.0) },
+            Vertex { pos: Vec2::new( 1.0,  1.0), uv: Vec2::new(1.0, 1.0) },
+            Vertex { pos: Vec2::new(-1.0,  1.0), uv: Vec2::new(0.0, 1.0) },
+        ];
+        let vertex_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, &vertices);
+
+        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+        let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
+
+        // Writing to each target reads the previous frame's trails from the other one.
+        let bindings = [1, 0].map(|previous| Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            images: vec![frame_texture, accumulated_textures[previous]],
+            index_buffer,
+        });
+
+        let shader = Shader::new(ctx, trails::VERTEX, trails::FRAGMENT, trails::meta())?;
+        let pipeline = Pipeline::new(
+            ctx,
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("pos", VertexFormat::Float2),
+                VertexAttribute::new("uv", VertexFormat::Float2),
+            ],
+            shader,
+        );
+
+        Ok(Self {
+            frame_pass,
+            accumulated_passes,
+            bindings,
+            outputs: accumulated_textures.map(|texture| TexturedQuad::with_texture(ctx, texture, None)),
+            pipeline,
+            current: 0,
+            cleared: true,
+        })
+    }
+
+    /// Discard the trails so far, such as when they're turned back on after being off.
+    pub fn clear(&mut self) {
+        self.cleared = true;
+    }
+
+    /// Start drawing the current frame, clearing it.
+    pub fn begin(&self, ctx: &mut Context) {
+        ctx.begin_pass(self.frame_pass, PassAction::clear_color(0.0, 0.0, 0.0, 1.0));
+    }
+
+    /// Finish drawing the current frame, and combine it with the trails so far, keeping the given
+    /// fraction of their brightness.
+    pub fn end(&mut self, ctx: &mut Context, decay: f32) {
+        ctx.end_render_pass();
+
+        if self.cleared {
+            ctx.begin_pass(self.accumulated_passes[self.current], PassAction::clear_color(0.0, 0.0, 0.0, 1.0));
+            ctx.end_render_pass();
+            self.cleared = false;
+        }
+
+        self.current = 1 - self.current;
+        ctx.begin_pass(self.accumulated_passes[self.current], PassAction::Nothing);
+        ctx.apply_pipeline(&self.pipeline);
+        ctx.apply_bindings(&self.bindings[self.current]);
+        ctx.apply_uniforms(&trails::Uniforms {
+            decay: decay.clamp(0.0, 1.0),
+        });
+        ctx.draw(0, 6, 1);
+        ctx.end_render_pass();
+    }
+
+    /// Draw the current frame with its trails.
+    pub fn draw(&self, ctx: &mut Context) {
+        self.outputs[self.current].draw(ctx);
+    }
+
+    /// Delete the render targets.
+    pub fn delete(&self, ctx: &mut Context) {
+        self.frame_pass.delete(ctx);
+        for pass in &self.accumulated_passes {
+            pass.delete(ctx);
+        }
+    }
+}
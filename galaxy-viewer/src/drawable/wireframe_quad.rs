@@ -1,7 +1,7 @@
 use std::error::Error;
 
 use miniquad::*;
-use crate::types::*;
+use galaxy_core::types::*;
 use crate::shaders::*;
 
 pub struct WireframeQuad {
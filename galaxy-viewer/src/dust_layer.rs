@@ -0,0 +1,168 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use galaxy_core::config::Config;
+use galaxy_core::dust::DustField;
+use galaxy_core::types::Vec2d;
+use miniquad::*;
+
+use crate::drawable::*;
+use crate::galaxy::Galaxy;
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+/// The size of the dust map sampled from the dust field, which covers the galaxy.
+const MAP_SIZE: usize = 512;
+
+/// The size of the texture the dust in the current view is drawn into.
+const TEXTURE_SIZE: usize = 256;
+
+/// The colour dust multiplies the stars behind it by when fully opaque, reddening as well as
+/// dimming them.
+const DUST_COLOR: [f64; 3] = [0.25, 0.18, 0.12];
+
+/// The colour of glowing nebulae when fully opaque.
+const NEBULA_COLOR: [f64; 3] = [0.8, 0.25, 0.4];
+
+/// How the dust is combined with the stars.
+#[derive(Clone, Copy, PartialEq)]
+enum DustMode {
+    /// Dark dust lanes that absorb the light of the stars behind them.
+    Absorption,
+
+    /// Glowing nebulae added to the light of the stars, as if drawn underneath them.
+    Emission,
+}
+
+impl DustMode {
+    const ALL: [DustMode; 2] = [DustMode::Absorption, DustMode::Emission];
+
+    fn name(self) -> &'static str {
+        match self {
+            DustMode::Absorption => "Dust lanes",
+            DustMode::Emission => "Nebulae",
+        }
+    }
+
+    /// The blend state that combines the layer with the stars already drawn.
+    fn blend(self) -> BlendState {
+        match self {
+            DustMode::Absorption => BlendState::new(Equation::Add, BlendFactor::Zero, BlendFactor::Value(BlendValue::SourceColor)),
+            DustMode::Emission => BlendState::new(Equation::Add, BlendFactor::One, BlendFactor::One),
+        }
+    }
+}
+
+/// A layer of dust lanes or nebulae drawn over the galaxy, following its camera. The dust field is
+/// sampled once into a map covering the galaxy, which is then resampled for the current view each
+/// update.
+pub struct DustLayer {
+    textured_quad: TexturedQuad,
+    galaxy: Rc<RefCell<Galaxy>>,
+
+    /// The density of dust sampled over the square the galaxy is generated in.
+    dust_map: Vec<f32>,
+
+    /// The radius in parsecs of the area covered by the dust map.
+    map_radius: f64,
+
+    mode: DustMode,
+    opacity: f32,
+}
+
+impl DustLayer {
+    /// Create a dust layer for the galaxy generated from the given config.
+    pub fn new(ctx: &mut Context, config: &Config, galaxy: Rc<RefCell<Galaxy>>) -> Result<Self, Box<dyn Error>> {
+        let mode = DustMode::Absorption;
+        let textured_quad = TexturedQuad::with_blend(ctx, TEXTURE_SIZE, TEXTURE_SIZE, Some(mode.blend()))?;
+
+        let dust = DustField::new(&config.generation, &config.rendering.dust);
+        let map_radius = config.generation.galaxy_radius();
+
+        let dust_map = (0..MAP_SIZE).flat_map(|y| (0..MAP_SIZE).map(move |x| (x, y))).map(|(x, y)| {
+            let position = Vec2d::new(((x as f64 + 0.5) / MAP_SIZE as f64 * 2.0 - 1.0) * map_radius,
+                                      ((y as f64 + 0.5) / MAP_SIZE as f64 * 2.0 - 1.0) * map_radius);
+            dust.sample(position) as f32
+        }).collect();
+
+        Ok(Self {
+            textured_quad,
+            galaxy,
+            dust_map,
+            map_radius,
+            mode,
+            opacity: config.rendering.dust.opacity as f32,
+        })
+    }
+
+    /// The density of dust at a position in parsecs, from the dust map.
+    fn density_at(&self, position: Vec2d) -> f32 {
+        let to_map = |coordinate: f64| (coordinate / self.map_radius * 0.5 + 0.5) * MAP_SIZE as f64;
+        let (x, y) = (to_map(position.x), to_map(position.y));
+        match (0.0..MAP_SIZE as f64).contains(&x) && (0.0..MAP_SIZE as f64).contains(&y) {
+            true => self.dust_map[y as usize * MAP_SIZE + x as usize],
+            false => 0.0,
+        }
+    }
+
+    /// Draw the dust in the current view into the texture.
+    fn update_texture(&mut self, ctx: &mut Context) {
+        let (view_offset, view_size) = self.galaxy.borrow().renderer.view_bounds();
+
+        let data = (0..TEXTURE_SIZE).flat_map(|y| (0..TEXTURE_SIZE).map(move |x| (x, y))).flat_map(|(x, y)| {
+            let position = view_offset + Vec2d::new((x as f64 + 0.5) / TEXTURE_SIZE as f64 * view_size.x,
+                                                    (y as f64 + 0.5) / TEXTURE_SIZE as f64 * view_size.y);
+            let amount = (self.density_at(position) * self.opacity) as f64;
+
+            // Absorption multiplies the stars by the texture, so it fades from white to the dust
+            // colour, while emission is added to them.
+            let color = match self.mode {
+                DustMode::Absorption => DUST_COLOR.map(|channel| 1.0 - amount * (1.0 - channel)),
+                DustMode::Emission => NEBULA_COLOR.map(|channel| amount * channel),
+            };
+            let [r, g, b] = color.map(|channel| (channel * 255.0) as u8);
+            [r, g, b, 0xFF]
+        }).collect::<Vec<u8>>();
+
+        self.textured_quad.texture.update(ctx, &data);
+    }
+}
+
+impl Drawable for DustLayer {
+    /// Update the dust layer.
+    fn fixed_update(&mut self,
+                    ctx: &mut Context,
+                    _input_state: &InputState,
+                    _viewport: &Viewport,
+                    _time_delta: f64)
+    {
+        self.update_texture(ctx);
+    }
+
+    /// Build the "Dust" window, switching the blend state if the mode is changed.
+    fn ui(&mut self, ctx: &mut Context, ui: &imgui::Ui) {
+        let previous_mode = self.mode;
+
+        ui.window("Dust")
+            .size([250.0, 80.0], imgui::Condition::FirstUseEver)
+            .position([370.0, 370.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let names = DustMode::ALL.map(DustMode::name);
+                let mut selected_mode = DustMode::ALL.iter().position(|&mode| mode == self.mode).unwrap_or(0);
+                ui.combo_simple_string("Mode", &mut selected_mode, &names);
+                self.mode = DustMode::ALL[selected_mode];
+
+                ui.slider("Opacity", 0.0, 1.0, &mut self.opacity);
+            });
+
+        if self.mode != previous_mode {
+            self.textured_quad.set_blend(ctx, Some(self.mode.blend()));
+        }
+    }
+
+    /// Draw the dust layer.
+    fn render(&mut self, ctx: &mut Context, _alpha: f64) {
+        self.textured_quad.draw(ctx);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+
+use galaxy_core::metrics::Energy;
+use galaxy_core::simulation::{GalaxySim, StarComponent};
+
+/// The number of samples of the energy kept for the plot.
+const HISTORY_LENGTH: usize = 300;
+
+/// How many steps there are between each sample. Measuring the potential energy walks the tree for
+/// every star, so it's sampled less often than the other plots.
+const UPDATE_INTERVAL: u64 = 20;
+
+/// The height of the plot, in pixels.
+const PLOT_HEIGHT: f32 = 150.0;
+
+/// The colours of the kinetic and potential energy when they aren't split by component.
+const KINETIC_COLOR: [f32; 4] = [0.9, 0.5, 0.2, 1.0];
+const POTENTIAL_COLOR: [f32; 4] = [0.3, 0.5, 0.9, 1.0];
+
+/// The colour of each component, in the order of `StarComponent::ALL`, when they're split.
+const COMPONENT_COLORS: [[f32; 4]; StarComponent::ALL.len()] = [
+    [0.4, 0.6, 0.9, 1.0],
+    [0.9, 0.7, 0.3, 1.0],
+    [0.5, 0.4, 0.7, 1.0],
+    [0.9, 0.4, 0.4, 1.0],
+    [0.4, 0.8, 0.5, 1.0],
+    [0.6, 0.6, 0.6, 1.0],
+    [0.3, 0.8, 0.8, 1.0],
+];
+
+/// The colour of the line drawn for the total energy.
+const TOTAL_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// Plots the kinetic and potential energy of the galaxy over time as areas stacked either side of
+/// zero, kinetic above and potential below, optionally split by the component of the galaxy they
+/// belong to, with the total energy drawn over them as a line. This shows where the energy that's
+/// conserved overall is flowing, e.g. into a satellite's orbit as it's stripped.
+pub struct EnergyPlot {
+    history: VecDeque<Energy>,
+
+    /// Whether to split the areas by component.
+    split_components: bool,
+
+    /// The number of steps since the last sample, or None if there hasn't been one.
+    steps_since_update: Option<u64>,
+}
+
+impl EnergyPlot {
+    /// Create an empty plot.
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+            split_components: false,
+            steps_since_update: None,
+        }
+    }
+
+    /// Clear the plot, e.g. when the galaxy is replaced.
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.steps_since_update = None;
+    }
+
+    /// Measure the simulation's energy, if it's time to.
+    pub fn record(&mut self, sim: &GalaxySim) {
+        self.steps_since_update = match self.steps_since_update {
+            Some(steps) if steps + 1 < UPDATE_INTERVAL => Some(steps + 1),
+            _ => {
+                if self.history.len() == HISTORY_LENGTH {
+                    self.history.pop_front();
+                }
+                self.history.push_back(Energy::measure(sim));
+                Some(0)
+            },
+        };
+    }
+
+    /// Build the energy window.
+    pub fn ui(&mut self, ui: &imgui::Ui) {
+        ui.window("Energy")
+            .size([300.0, 300.0], imgui::Condition::FirstUseEver)
+            .position([940.0, 590.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let latest = match self.history.back() {
+                    Some(latest) => *latest,
+                    None => return,
+                };
+
+                ui.checkbox("Split by component", &mut self.split_components);
+                ui.text_colored(KINETIC_COLOR, format!("Kinetic: {:.4e}", latest.kinetic()));
+                ui.text_colored(POTENTIAL_COLOR, format!("Potential: {:.4e}", latest.potential()));
+                ui.text_colored(TOTAL_COLOR, format!("Total: {:.4e}", latest.total()));
+
+                self.plot(ui);
+
+                // Only list the components the galaxy has.
+                if self.split_components {
+                    for (i, component) in StarComponent::ALL.iter().enumerate() {
+                        if latest.kinetic[i] != 0.0 || latest.potential[i] != 0.0 {
+                            ui.text_colored(COMPONENT_COLORS[i], component.name());
+                        }
+                    }
+                }
+            });
+    }
+
+    /// The areas stacked on each side of zero for a sample, from zero outwards, as the energy in
+    /// each and its colour.
+    fn areas(&self, energy: &Energy) -> (Vec<(f64, [f32; 4])>, Vec<(f64, [f32; 4])>) {
+        match self.split_components {
+            true => (energy.kinetic.iter().copied().zip(COMPONENT_COLORS).collect(),
+                     energy.potential.iter().copied().zip(COMPONENT_COLORS).collect()),
+            false => (vec![(energy.kinetic(), KINETIC_COLOR)], vec![(energy.potential(), POTENTIAL_COLOR)]),
+        }
+    }
+
+    /// Draw the stacked areas, with a column for each sample, and the total energy over them.
+    fn plot(&self, ui: &imgui::Ui) {
+        // Scale the plot so that the largest stack on either side fits.
+        let extent = self.history.iter()
+            .map(|energy| f64::max(energy.kinetic.iter().map(|kinetic| kinetic.abs()).sum(),
+                                   energy.potential.iter().map(|potential| potential.abs()).sum()))
+            .fold(0.0, f64::max);
+        if extent == 0.0 {
+            return;
+        }
+
+        // Reserve the space for the plot and draw it there.
+        let [left, top] = ui.cursor_screen_pos();
+        let width = ui.content_region_avail()[0];
+        ui.dummy([width, PLOT_HEIGHT]);
+
+        let draw_list = ui.get_window_draw_list();
+        let column_width = width / self.history.len() as f32;
+        let zero = top + PLOT_HEIGHT * 0.5;
+        let to_y = |energy: f64| zero - (energy / extent) as f32 * PLOT_HEIGHT * 0.5;
+        for (column, energy) in self.history.iter().enumerate() {
+            let x = left + column as f32 * column_width;
+            let (kinetic_areas, potential_areas) = self.areas(energy);
+
+            // Kinetic energy is positive and stacked upwards, potential energy negative and
+            // stacked downwards.
+            for areas in [kinetic_areas, potential_areas] {
+                let mut stacked = 0.0;
+                for (area, color) in areas {
+                    let base = to_y(stacked);
+                    stacked += area;
+                    let end = to_y(stacked);
+                    if base != end {
+                        draw_list.add_rect([x, f32::min(base, end)], [x + column_width, f32::max(base, end)], color)
+                            .filled(true)
+                            .build();
+                    }
+                }
+            }
+        }
+
+        let totals: Vec<[f32; 2]> = self.history.iter().enumerate()
+            .map(|(column, energy)| [left + (column as f32 + 0.5) * column_width, to_y(energy.total())])
+            .collect();
+        draw_list.add_polyline(totals, TOTAL_COLOR).thickness(1.5).build();
+        draw_list.add_line([left, zero], [left + width, zero], ui.style_color(imgui::StyleColor::Border))
+            .build();
+        draw_list.add_rect([left, top], [left + width, top + PLOT_HEIGHT], ui.style_color(imgui::StyleColor::Border))
+            .build();
+    }
+}
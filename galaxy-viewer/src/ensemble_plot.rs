@@ -0,0 +1,197 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use galaxy_core::analysis::radial_profile::RadialProfile;
+use galaxy_core::config::Config;
+use galaxy_core::ensemble::{Ensemble, EnsembleCurve};
+
+/// The number of rings the profiles are measured in.
+const BIN_COUNT: usize = 30;
+
+/// The height of each plot, in pixels.
+const PLOT_HEIGHT: f32 = 80.0;
+
+/// The colours of each member's curve, the mean, and the spread about it.
+const MEMBER_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 0.5];
+const MEAN_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+const SPREAD_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 0.5];
+
+/// The lowest surface density plotted, in solar masses per square parsec, so that empty rings
+/// don't stretch the logarithmic scale.
+const MIN_SURFACE_DENSITY: f64 = 1e-6;
+
+/// Runs an ensemble on a worker thread, so that the UI keeps responding while it's simulated.
+struct EnsembleRunner {
+    /// The fraction of the seeds finished so far, stored as the bits of an f32 so it can be shared
+    /// with the worker thread.
+    progress: Arc<AtomicU32>,
+
+    result: Receiver<Result<Ensemble, String>>,
+}
+
+impl EnsembleRunner {
+    /// Start running the seeds to a simulated time.
+    fn start(config: &Config, seeds: Vec<u64>, end_myr: f64, step_myr: f64) -> Self {
+        log::info!("Running an ensemble of {} seeds to {end_myr:.0} Myr in the background", seeds.len());
+
+        let progress = Arc::new(AtomicU32::new(0.0f32.to_bits()));
+        let (sender, result) = mpsc::channel();
+
+        let config = config.clone();
+        let thread_progress = progress.clone();
+        thread::spawn(move || {
+            let max_radius = config.generation.galaxy_radius();
+            let ensemble = Ensemble::run(&config, &seeds, end_myr, step_myr, max_radius, BIN_COUNT, &mut |fraction| {
+                thread_progress.store((fraction as f32).to_bits(), Ordering::Relaxed);
+            });
+
+            // Errors can't be sent between threads, so send the message instead.
+            let _ = sender.send(ensemble.map_err(|err| err.to_string()));
+        });
+
+        Self { progress, result }
+    }
+
+    /// The fraction of the seeds finished so far, between 0 and 1.
+    fn progress(&self) -> f32 {
+        f32::from_bits(self.progress.load(Ordering::Relaxed))
+    }
+
+    /// Take the ensemble if it's finished.
+    fn try_finish(&self) -> Option<Result<Ensemble, Box<dyn Error>>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result.map_err(Into::into)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("The ensemble thread panicked".into())),
+        }
+    }
+}
+
+/// Overlays the rotation curves and surface density profiles of several seeds of the same
+/// parameters, with their mean and spread, to tell real trends apart from the noise of one seed.
+/// The seeds are either run in the background from the current config, or loaded from
+/// checkpoints.
+pub struct EnsemblePlot {
+    ensemble: Option<Ensemble>,
+
+    /// The number of seeds to run, edited in the UI.
+    seed_count: i32,
+
+    runner: Option<EnsembleRunner>,
+}
+
+impl EnsemblePlot {
+    /// Create an empty plot.
+    pub fn new() -> Self {
+        Self {
+            ensemble: None,
+            seed_count: 4,
+            runner: None,
+        }
+    }
+
+    /// Load the ensemble from checkpoints, e.g. of several seeds run separately.
+    pub fn load_checkpoints(&mut self, paths: &[PathBuf], config: &Config) -> Result<(), Box<dyn Error>> {
+        log::info!("Loading an ensemble of {} checkpoints", paths.len());
+        self.ensemble = Some(Ensemble::from_checkpoints(paths, config.generation.galaxy_radius(), BIN_COUNT)?);
+        Ok(())
+    }
+
+    /// Build the ensemble window. Runs start from `seed` and go to the simulated time `end_myr`,
+    /// with each step covering `step_myr`.
+    pub fn ui(&mut self, ui: &imgui::Ui, config: &Config, seed: u64, end_myr: f64, step_myr: f64) {
+        // Take the ensemble once it's finished running.
+        if let Some(result) = self.runner.as_ref().and_then(EnsembleRunner::try_finish) {
+            self.runner = None;
+            match result {
+                Ok(ensemble) => self.ensemble = Some(ensemble),
+                Err(err) => log::error!("Failed to run ensemble: {err}"),
+            }
+        }
+
+        ui.window("Ensemble")
+            .size([300.0, 300.0], imgui::Condition::FirstUseEver)
+            .position([940.0, 580.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                match &self.runner {
+                    Some(runner) => {
+                        imgui::ProgressBar::new(runner.progress())
+                            .overlay_text("Running seeds...")
+                            .build(ui);
+                    },
+                    None => {
+                        ui.input_int("Seeds", &mut self.seed_count).build();
+                        self.seed_count = self.seed_count.max(1);
+
+                        if ui.button(format!("Run to {end_myr:.0} Myr")) {
+                            let seeds = (0..self.seed_count as u64).map(|i| seed + i).collect();
+                            self.runner = Some(EnsembleRunner::start(config, seeds, end_myr, step_myr));
+                        }
+                    },
+                }
+
+                let ensemble = match &self.ensemble {
+                    Some(ensemble) if !ensemble.members.is_empty() => ensemble,
+                    _ => return,
+                };
+
+                let max_radius = ensemble.members[0].profile.max_radius;
+                ui.text(format!("{} members, 0 to {max_radius:.0} pc", ensemble.members.len()));
+
+                ui.text("Rotation velocity (km/s)");
+                Self::plot(ui, ensemble, |profile| profile.rotation_velocity.clone());
+
+                ui.text("log10 surface density (Msun/pc^2)");
+                Self::plot(ui, ensemble, |profile| {
+                    profile.surface_density.iter()
+                        .map(|&density| f64::max(density, MIN_SURFACE_DENSITY).log10())
+                        .collect()
+                });
+            });
+    }
+
+    /// Plot a curve of each member, with the mean and the spread about it drawn over them.
+    fn plot<F>(ui: &imgui::Ui, ensemble: &Ensemble, f: F)
+        where F: Fn(&RadialProfile) -> Vec<f64>
+    {
+        let curves: Vec<Vec<f64>> = ensemble.members.iter().map(|member| f(&member.profile)).collect();
+        let EnsembleCurve { mean, spread } = EnsembleCurve::of(&curves);
+
+        let lower: Vec<f64> = mean.iter().zip(&spread).map(|(mean, spread)| mean - spread).collect();
+        let upper: Vec<f64> = mean.iter().zip(&spread).map(|(mean, spread)| mean + spread).collect();
+
+        let (min, max) = curves.iter().chain([&lower, &upper])
+            .flatten()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &value| (min.min(value), max.max(value)));
+        if min >= max {
+            return;
+        }
+
+        // Reserve the space for the plot and draw it there.
+        let [left, top] = ui.cursor_screen_pos();
+        let width = ui.content_region_avail()[0];
+        ui.dummy([width, PLOT_HEIGHT]);
+
+        let draw_list = ui.get_window_draw_list();
+        draw_list.add_rect([left, top], [left + width, top + PLOT_HEIGHT], ui.style_color(imgui::StyleColor::Border))
+            .build();
+
+        let points = |values: &[f64]| -> Vec<[f32; 2]> {
+            values.iter().enumerate().map(|(bin, &value)| {
+                [left + (bin as f32 + 0.5) / values.len() as f32 * width,
+                 top + PLOT_HEIGHT - ((value - min) / (max - min)) as f32 * PLOT_HEIGHT]
+            }).collect()
+        };
+
+        for values in &curves {
+            draw_list.add_polyline(points(values), MEMBER_COLOR).build();
+        }
+        draw_list.add_polyline(points(&lower), SPREAD_COLOR).build();
+        draw_list.add_polyline(points(&upper), SPREAD_COLOR).build();
+        draw_list.add_polyline(points(&mean), MEAN_COLOR).thickness(2.0).build();
+    }
+}
@@ -0,0 +1,122 @@
+use galaxy_core::profiler::{self, Span};
+
+/// The height of each row of the chart, in pixels.
+const ROW_HEIGHT: f32 = 18.0;
+
+/// The colours spans are drawn in, picked by their names so that each keeps its colour.
+const SPAN_COLORS: [[f32; 4]; 6] = [
+    [0.85, 0.45, 0.2, 1.0],
+    [0.9, 0.65, 0.2, 1.0],
+    [0.7, 0.3, 0.25, 1.0],
+    [0.55, 0.6, 0.25, 1.0],
+    [0.3, 0.55, 0.6, 1.0],
+    [0.5, 0.4, 0.65, 1.0],
+];
+
+/// The colour of the names drawn over the spans.
+const TEXT_COLOR: [f32; 4] = [0.05, 0.05, 0.05, 1.0];
+
+/// Shows the profiler's spans from the last frame as a flame chart, with time along the x axis and
+/// nested spans below the spans they're in, and the total time spent in each named span.
+pub struct FlameChart {
+    spans: Vec<Span>,
+
+    /// Whether to keep showing the same frame rather than the latest one.
+    frozen: bool,
+}
+
+impl FlameChart {
+    /// Create an empty chart.
+    pub fn new() -> Self {
+        Self {
+            spans: Vec::new(),
+            frozen: false,
+        }
+    }
+
+    /// Build the profiler window.
+    pub fn ui(&mut self, ui: &imgui::Ui) {
+        if !self.frozen {
+            self.spans = profiler::last_frame();
+        }
+
+        ui.window("Profiler")
+            .size([500.0, 250.0], imgui::Condition::FirstUseEver)
+            .position([370.0, 650.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let frame_ms = self.spans.iter()
+                    .map(|span| span.start_ms + span.duration_ms)
+                    .fold(0.0, f64::max);
+                ui.checkbox("Freeze", &mut self.frozen);
+                ui.same_line();
+                ui.text(format!("Frame: {frame_ms:.2} ms"));
+
+                if frame_ms > 0.0 {
+                    self.chart(ui, frame_ms);
+                }
+
+                ui.separator();
+                for (name, count, total_ms) in self.totals() {
+                    ui.text(format!("{name}: {total_ms:.2} ms ({count}x)"));
+                }
+            });
+    }
+
+    /// Draw the spans across the width of the window, with the one under the mouse described in a
+    /// tooltip.
+    fn chart(&self, ui: &imgui::Ui, frame_ms: f64) {
+        let rows = self.spans.iter().map(|span| span.depth + 1).max().unwrap_or(0);
+
+        // Reserve the space for the chart and draw it there.
+        let [left, top] = ui.cursor_screen_pos();
+        let width = ui.content_region_avail()[0];
+        ui.dummy([width, rows as f32 * ROW_HEIGHT]);
+
+        let draw_list = ui.get_window_draw_list();
+        let mouse = ui.io().mouse_pos;
+        let mut hovered = None;
+        for span in &self.spans {
+            let min = [left + (span.start_ms / frame_ms) as f32 * width,
+                       top + span.depth as f32 * ROW_HEIGHT];
+            let max = [f32::max(min[0] + ((span.duration_ms / frame_ms) as f32 * width), min[0] + 1.0),
+                       min[1] + ROW_HEIGHT - 1.0];
+            draw_list.add_rect(min, max, Self::color(span.name)).filled(true).build();
+
+            // Only label the spans that are wide enough for their names.
+            if ui.calc_text_size(span.name)[0] + 4.0 < max[0] - min[0] {
+                draw_list.add_text([min[0] + 2.0, min[1] + 1.0], TEXT_COLOR, span.name);
+            }
+
+            if ui.is_window_hovered() && (min[0]..max[0]).contains(&mouse[0]) && (min[1]..max[1]).contains(&mouse[1]) {
+                hovered = Some(span);
+            }
+        }
+
+        if let Some(span) = hovered {
+            ui.tooltip_text(format!("{}: {:.3} ms", span.name, span.duration_ms));
+        }
+    }
+
+    /// The number of times each named span was opened in the frame and the total time spent in
+    /// it, from the most time.
+    fn totals(&self) -> Vec<(&'static str, usize, f64)> {
+        let mut totals: Vec<(&'static str, usize, f64)> = Vec::new();
+        for span in &self.spans {
+            match totals.iter_mut().find(|(name, _, _)| *name == span.name) {
+                Some((_, count, total_ms)) => {
+                    *count += 1;
+                    *total_ms += span.duration_ms;
+                },
+                None => totals.push((span.name, 1, span.duration_ms)),
+            }
+        }
+        totals.sort_by(|a, b| b.2.total_cmp(&a.2));
+        totals
+    }
+
+    /// The colour to draw the spans with a name in.
+    fn color(name: &str) -> [f32; 4] {
+        let hash = name.bytes().fold(0usize, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as usize));
+        SPAN_COLORS[hash % SPAN_COLORS.len()]
+    }
+}
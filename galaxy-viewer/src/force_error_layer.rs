@@ -0,0 +1,167 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use galaxy_core::analysis::force_error::ForceErrors;
+use galaxy_core::config::Config;
+use galaxy_core::star_world::Position;
+use miniquad::*;
+
+use crate::drawable::*;
+use crate::galaxy::Galaxy;
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+/// How many updates there are between each measurement, as summing the forces directly is slow.
+const UPDATE_INTERVAL: u64 = 60;
+
+/// The range of log10 of the relative error covered by the colour map and histogram.
+const MIN_LOG_ERROR: f64 = -5.0;
+const MAX_LOG_ERROR: f64 = 0.0;
+
+/// The number of bins in the histogram.
+const HISTOGRAM_BINS: usize = 25;
+
+/// The colours of the smallest and largest errors, and the one halfway between them.
+const LOW_ERROR_COLOR: [f64; 3] = [0.1, 0.2, 1.0];
+const MID_ERROR_COLOR: [f64; 3] = [0.1, 1.0, 0.2];
+const HIGH_ERROR_COLOR: [f64; 3] = [1.0, 0.1, 0.1];
+
+/// The size of the square drawn for each sampled star, in pixels, so they stand out from the stars.
+const SAMPLE_SIZE: i64 = 3;
+
+/// A diagnostic layer that compares the Barnes-Hut acceleration of a sample of the stars against
+/// summing the forces directly, and colours them by the relative error, with a histogram of the
+/// errors in its window. This is useful for choosing the opening angle.
+pub struct ForceErrorLayer {
+    textured_quad: TexturedQuad,
+    galaxy: Rc<RefCell<Galaxy>>,
+
+    errors: ForceErrors,
+
+    /// The number of stars to measure the error of.
+    sample_count: i32,
+
+    /// The number of updates since the errors were last measured, or None if they need to be.
+    updates_since_measure: Option<u64>,
+}
+
+impl ForceErrorLayer {
+    /// Create a force error layer for the given galaxy.
+    pub fn new(ctx: &mut Context, config: &Config, galaxy: Rc<RefCell<Galaxy>>) -> Result<Self, Box<dyn Error>> {
+        let blend = BlendState::new(Equation::Add,
+                                    BlendFactor::Value(BlendValue::SourceAlpha),
+                                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha));
+        let textured_quad = TexturedQuad::with_blend(ctx,
+                                                     config.rendering.texture_width,
+                                                     config.rendering.texture_height,
+                                                     Some(blend))?;
+
+        Ok(Self {
+            textured_quad,
+            galaxy,
+            errors: ForceErrors::default(),
+            sample_count: 500,
+            updates_since_measure: None,
+        })
+    }
+
+    /// The colour of a relative error, from blue for small errors through green to red.
+    fn error_color(relative_error: f64) -> [u8; 3] {
+        let log_error = relative_error.max(f64::MIN_POSITIVE).log10();
+        let t = ((log_error - MIN_LOG_ERROR) / (MAX_LOG_ERROR - MIN_LOG_ERROR)).clamp(0.0, 1.0) * 2.0;
+        let (from, to, t) = match t < 1.0 {
+            true => (LOW_ERROR_COLOR, MID_ERROR_COLOR, t),
+            false => (MID_ERROR_COLOR, HIGH_ERROR_COLOR, t - 1.0),
+        };
+        std::array::from_fn(|i| ((from[i] + (to[i] - from[i]) * t) * 255.0) as u8)
+    }
+
+    /// Draw the sampled stars in the current view into the texture, coloured by their error.
+    fn update_texture(&mut self, ctx: &mut Context) {
+        let (width, height) = (self.textured_quad.width, self.textured_quad.height);
+        let mut bytes = vec![0; 4 * width * height];
+
+        let galaxy = self.galaxy.borrow();
+        let (view_offset, view_size) = galaxy.renderer.view_bounds();
+
+        // Stars can be removed by scenario scripts between measurements.
+        for sample in &self.errors.samples {
+            let position = match galaxy.sim.stars().component::<Position>(sample.star) {
+                Some(position) => position.0,
+                None => continue,
+            };
+
+            let pos = position - view_offset;
+            let center_x = (pos.x / view_size.x * width as f64) as i64;
+            let center_y = (pos.y / view_size.y * height as f64) as i64;
+            let [r, g, b] = Self::error_color(sample.relative_error);
+
+            for y in center_y - SAMPLE_SIZE / 2..=center_y + SAMPLE_SIZE / 2 {
+                for x in center_x - SAMPLE_SIZE / 2..=center_x + SAMPLE_SIZE / 2 {
+                    if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                        let idx = 4 * (y as usize * width + x as usize);
+                        bytes[idx..idx+4].copy_from_slice(&[r, g, b, 0xFF]);
+                    }
+                }
+            }
+        }
+
+        self.textured_quad.texture.update(ctx, &bytes);
+    }
+}
+
+impl Drawable for ForceErrorLayer {
+    /// Update the force error layer, measuring the errors again if it's time to.
+    fn fixed_update(&mut self,
+                    ctx: &mut Context,
+                    _input_state: &InputState,
+                    _viewport: &Viewport,
+                    _time_delta: f64)
+    {
+        self.updates_since_measure = match self.updates_since_measure {
+            Some(updates) if updates + 1 < UPDATE_INTERVAL => Some(updates + 1),
+            _ => {
+                self.errors = ForceErrors::measure(&self.galaxy.borrow().sim, self.sample_count as usize);
+                Some(0)
+            },
+        };
+
+        self.update_texture(ctx);
+    }
+
+    /// Build the "Force error" window.
+    fn ui(&mut self, _ctx: &mut Context, ui: &imgui::Ui) {
+        ui.window("Force error")
+            .size([300.0, 200.0], imgui::Condition::FirstUseEver)
+            .position([630.0, 500.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.label_text("Opening angle", format!("{:.2}", self.galaxy.borrow().sim.config().simulation.opening_angle));
+                if ui.slider("Samples", 10, 5000, &mut self.sample_count) {
+                    self.updates_since_measure = None;
+                }
+                if ui.button("Measure now") {
+                    self.updates_since_measure = None;
+                }
+
+                ui.text(format!("Median {:.2e}, 90% {:.2e}, 99% {:.2e}",
+                                self.errors.percentile(0.5),
+                                self.errors.percentile(0.9),
+                                self.errors.percentile(0.99)));
+
+                let histogram: Vec<f32> = self.errors.log_histogram(MIN_LOG_ERROR, MAX_LOG_ERROR, HISTOGRAM_BINS)
+                    .into_iter()
+                    .map(|count| count as f32)
+                    .collect();
+                ui.plot_histogram("##histogram", &histogram)
+                    .graph_size([0.0, 60.0])
+                    .overlay_text(format!("log10 error, {MIN_LOG_ERROR} to {MAX_LOG_ERROR}"))
+                    .build();
+            });
+    }
+
+    /// Draw the force error layer.
+    fn render(&mut self, ctx: &mut Context, _alpha: f64) {
+        self.textured_quad.draw(ctx);
+    }
+}
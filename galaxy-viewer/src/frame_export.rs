@@ -0,0 +1,81 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::galaxy::Galaxy;
+
+/// Exports the rendered galaxy as a numbered sequence of PNG images, one per fixed interval of
+/// simulated time, so that videos can be assembled offline.
+pub struct FrameExporter {
+    /// The directory to write frames to.
+    directory: PathBuf,
+
+    /// The interval between frames, in Myr of simulated time.
+    interval_myr: f64,
+
+    /// The simulated time in Myr at which the next frame should be written.
+    next_frame_myr: f64,
+
+    /// The number of the next frame.
+    frame_index: usize,
+}
+
+impl FrameExporter {
+    /// Create a new frame exporter writing to the given directory. The first frame is written
+    /// immediately.
+    pub fn new<P: AsRef<Path>>(directory: P, interval_myr: f64, elapsed_myr: f64) -> Result<Self, Box<dyn Error>> {
+        if interval_myr <= 0.0 {
+            return Err("Frame export interval must be greater than zero".into());
+        }
+
+        let directory = directory.as_ref().to_path_buf();
+        std::fs::create_dir_all(&directory)?;
+        log::info!("Exporting a frame every {interval_myr} Myr to {}", directory.display());
+
+        Ok(Self {
+            directory,
+            interval_myr,
+            next_frame_myr: elapsed_myr,
+            frame_index: 0,
+        })
+    }
+
+    /// The number of frames written so far.
+    pub fn frame_count(&self) -> usize {
+        self.frame_index
+    }
+
+    /// Write a frame if the galaxy's simulated time has reached the next frame time.
+    pub fn update(&mut self, galaxy: &Galaxy) -> Result<(), Box<dyn Error>> {
+        if galaxy.sim.elapsed_myr() < self.next_frame_myr {
+            return Ok(());
+        }
+
+        let config = &galaxy.sim.config().rendering;
+        let path = self.directory.join(format!("frame_{:06}.png", self.frame_index));
+        log::debug!("Writing frame at {:.1} Myr to {}", galaxy.sim.elapsed_myr(), path.display());
+        Self::write_png(&path, config.texture_width, config.texture_height, &galaxy.renderer.render_stars(&galaxy.sim, false))?;
+
+        self.frame_index += 1;
+        self.next_frame_myr += self.interval_myr;
+
+        Ok(())
+    }
+
+    /// Write an RGBA buffer to a PNG file. The buffer's first row is the bottom of the image, as in
+    /// an OpenGL texture, so the rows are flipped.
+    fn write_png(path: &Path, width: usize, height: usize, rgba: &[u8]) -> Result<(), Box<dyn Error>> {
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width as u32, height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let row_size = width * 4;
+        let flipped: Vec<u8> = rgba.chunks_exact(row_size).rev().flatten().copied().collect();
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&flipped)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+use std::error::Error;
+
+use imgui::{SliderFlags, TreeNodeFlags};
+use miniquad::*;
+use galaxy_core::config::Config;
+use galaxy_core::rng::RngStreams;
+use galaxy_core::simulation::{GalaxySim, StarTag};
+use galaxy_core::star_world::{Mass, Position, Velocity};
+use galaxy_core::types::Vec2d;
+use galaxy_core::units::SolarMass;
+use crate::drawable::*;
+use crate::galaxy_renderer::GalaxyRenderer;
+use crate::input::InputState;
+use crate::session::{InputEvent, StarEdit};
+use crate::viewport::Viewport;
+
+/// The colour of warnings in the UI.
+pub const WARNING_COLOR: [f32; 4] = [1.0, 0.6, 0.2, 1.0];
+
+/// The render scales that can be chosen in the UI, from 1 up to the largest the supersampler
+/// supports.
+const RENDER_SCALES: [&str; 4] = ["1x", "2x", "3x", "4x"];
+
+/// A galaxy in the viewer: its simulation, and the renderer that draws it. The simulation is pure
+/// physics and knows nothing about rendering, and the renderer only reads it, so the simulation
+/// can also be stepped headlessly or on another thread. This ties the two together as a layer,
+/// stepping the simulation in fixed_update and drawing it in render.
+pub struct Galaxy {
+    /// The simulation of the galaxy's stars.
+    pub sim: GalaxySim,
+
+    /// Draws the simulation's stars, and owns the camera they're viewed through.
+    pub renderer: GalaxyRenderer,
+
+    /// A second view of the same simulation through its own camera, drawn beside the main one in
+    /// split screen, if it's enabled. It starts as a close-up of the locked star.
+    pub split_view: Option<GalaxyRenderer>,
+
+    /// A close-up of the star the main view is locked onto, drawn as an inset in its corner while
+    /// it's locked onto one, if `show_inset` is set.
+    inset: Option<GalaxyRenderer>,
+    pub show_inset: bool,
+
+    /// Whether the simulation is paused. The galaxy is still drawn and can be navigated while
+    /// paused.
+    pub paused: bool,
+
+    /// Whether to slow the simulation down automatically while its integration is unreliable.
+    pub auto_slowdown: bool,
+
+    /// An edit to a star made in the inspector, which is applied as an input event in the next
+    /// step so that it's recorded.
+    pub star_edit: Option<InputEvent>,
+}
+
+impl Galaxy {
+    /// Create a new galaxy that renders via the given miniquad context.
+    pub fn new(ctx: &mut Context, config: &Config, streams: &RngStreams) -> Result<Self, Box<dyn Error>> {
+        Self::from_sim(ctx, GalaxySim::new(config, streams)?)
+    }
+
+    /// Create a galaxy that renders an existing simulation.
+    pub fn from_sim(ctx: &mut Context, sim: GalaxySim) -> Result<Self, Box<dyn Error>> {
+        let renderer = GalaxyRenderer::new(ctx, &sim.config().rendering)?;
+        let auto_slowdown = sim.config().simulation.auto_slowdown;
+
+        Ok(Self {
+            sim,
+            renderer,
+            split_view: None,
+            inset: None,
+            show_inset: true,
+            paused: false,
+            auto_slowdown,
+            star_edit: None,
+        })
+    }
+
+    /// Highlight a star and lock the camera onto it.
+    pub fn focus_on_star(&mut self, star: usize) {
+        self.renderer.focus_on_star(&self.sim, star);
+    }
+
+    /// Apply an edit made to a star in the inspector.
+    pub fn edit_star(&mut self, index: usize, edit: StarEdit) {
+        match edit {
+            StarEdit::Set { mass, position, velocity } => {
+                if let Some(mut star) = self.sim.stars_mut().entity_mut(index) {
+                    star.insert((Mass(SolarMass(mass)), Position(position), Velocity(velocity)));
+                }
+            },
+            StarEdit::ZeroVelocity => {
+                if let Some(mut velocity) = self.sim.stars_mut().component_mut::<Velocity>(index) {
+                    velocity.0 = Vec2d::new(0.0, 0.0);
+                }
+            },
+            StarEdit::CircularizeOrbit => self.sim.circularize_orbit(index),
+            StarEdit::SetTag(tag) => {
+                if let Some(mut star) = self.sim.stars_mut().entity_mut(index) {
+                    match tag {
+                        Some(tag) => star.insert(tag),
+                        None => star.remove::<StarTag>(),
+                    };
+                }
+                self.sim.stars_mut().update_render_colors();
+            },
+        }
+    }
+
+    /// Step the simulation unless it's paused. Every view of it remembers where the stars were.
+    pub fn step(&mut self, time_delta: f64) {
+        let paused = self.paused;
+        let sim = &self.sim;
+        for renderer in std::iter::once(&mut self.renderer).chain(self.split_view.as_mut()).chain(self.inset.as_mut()) {
+            renderer.remember_positions((!paused).then_some(sim));
+        }
+
+        if !paused {
+            self.sim.step(time_delta);
+
+            let disruptions = &self.sim.tidal_disruptions;
+            for renderer in std::iter::once(&mut self.renderer).chain(self.split_view.as_mut()).chain(self.inset.as_mut()) {
+                renderer.add_flashes(disruptions);
+            }
+        }
+    }
+
+    /// Create a renderer for another view of the galaxy, at the main view's render scale.
+    fn new_view(&self, ctx: &mut Context) -> Result<GalaxyRenderer, Box<dyn Error>> {
+        GalaxyRenderer::with_render_scale(ctx, &self.sim.config().rendering, self.renderer.render_scale())
+    }
+
+    /// Change the render scale of every view of the galaxy.
+    pub fn set_render_scale(&mut self, ctx: &mut Context, render_scale: usize) -> Result<(), Box<dyn Error>> {
+        let config = &self.sim.config().rendering;
+        for renderer in std::iter::once(&mut self.renderer).chain(self.split_view.as_mut()).chain(self.inset.as_mut()) {
+            renderer.set_render_scale(ctx, config, render_scale)?;
+        }
+
+        Ok(())
+    }
+
+    /// Open or close the split screen view. It opens as a close-up of the star the main view is
+    /// locked onto or highlighting.
+    pub fn set_split_view(&mut self, ctx: &mut Context, enabled: bool) -> Result<(), Box<dyn Error>> {
+        self.split_view = match enabled {
+            true => {
+                let mut split_view = self.new_view(ctx)?;
+                split_view.close_up_of(&self.renderer, &self.sim);
+                Some(split_view)
+            },
+            false => None,
+        };
+
+        Ok(())
+    }
+
+    /// Update the split screen view's camera, for it drawn in the given viewport. It's only
+    /// controlled by the mouse while the mouse is over it.
+    pub fn update_split_view(&mut self, input_state: &InputState, viewport: &Viewport) {
+        if let Some(split_view) = &mut self.split_view {
+            split_view.update_camera(&self.sim, input_state, viewport, viewport.contains(input_state.mouse_pos));
+        }
+    }
+
+    /// Draw the split screen view, if it's open.
+    pub fn render_split_view(&mut self, ctx: &mut Context, alpha: f64) {
+        if let Some(split_view) = &mut self.split_view {
+            split_view.set_interpolation(alpha);
+            split_view.draw(ctx, &self.sim);
+        }
+    }
+
+    /// Show the inset close-up while the main view is locked onto a star, zooming it in on the
+    /// star whenever the main view locks onto a different one, and drop it otherwise. The inset
+    /// isn't controlled by the mouse.
+    fn update_inset(&mut self, ctx: &mut Context, input_state: &InputState, viewport: &Viewport) {
+        let locked_star = match (self.show_inset, self.renderer.locked_star()) {
+            (true, Some(locked_star)) => locked_star,
+            _ => {
+                self.inset = None;
+                return;
+            },
+        };
+
+        if self.inset.is_none() {
+            match self.new_view(ctx) {
+                Ok(inset) => self.inset = Some(inset),
+                Err(err) => {
+                    log::error!("Failed to create locked star inset: {err}");
+                    return;
+                },
+            }
+        }
+
+        if let Some(inset) = &mut self.inset {
+            if inset.locked_star() != Some(locked_star) {
+                inset.close_up_of(&self.renderer, &self.sim);
+            }
+            inset.update_camera(&self.sim, input_state, viewport, false);
+        }
+    }
+
+    /// Draw the inset close-up of the locked star in the corner of the given viewport, which the
+    /// main view is drawn in, if it's shown.
+    pub fn render_inset(&mut self, ctx: &mut Context, alpha: f64, viewport: &Viewport) {
+        if let Some(inset) = &mut self.inset {
+            viewport.inset().apply(ctx);
+            inset.set_interpolation(alpha);
+            inset.draw(ctx, &self.sim);
+        }
+    }
+
+    /// Match another galaxy's view, rate and pause state, so that the two can be compared side by
+    /// side.
+    pub fn follow(&mut self, other: &Galaxy) {
+        self.renderer.follow(&other.renderer);
+        self.sim.myr_per_second = other.sim.myr_per_second;
+        self.paused = other.paused;
+    }
+
+    /// Show warnings if the last step was too large for the integration to be reliable.
+    fn step_safety_ui(&self, ui: &imgui::Ui) {
+        let config = &self.sim.config().simulation;
+        let safety = &self.sim.safety;
+        if safety.displacement_too_large(config) {
+            ui.text_colored(WARNING_COLOR, format!("Stars moving {:.1}x their spacing per step (max {:.0} pc)",
+                                                   safety.displacement_fraction, safety.max_displacement));
+        }
+        if safety.velocity_change_too_large(config) {
+            ui.text_colored(WARNING_COLOR, format!("Velocities changing {:.0}% per step (max {:.0} km/s)",
+                                                   safety.velocity_change_fraction * 100.0, safety.max_velocity_change));
+        }
+    }
+}
+
+impl Drawable for Galaxy {
+    /// Update the camera and step the galaxy.
+    fn fixed_update(&mut self,
+                    ctx: &mut Context,
+                    input_state: &InputState,
+                    viewport: &Viewport,
+                    time_delta: f64)
+    {
+        // With a split screen view, the main view is only controlled by the mouse while it's over
+        // it, and never while it's over the inset.
+        let inset_viewport = viewport.inset();
+        let focused = (self.split_view.is_none() || viewport.contains(input_state.mouse_pos))
+            && !(self.inset.is_some() && inset_viewport.contains(input_state.mouse_pos));
+        self.renderer.update_camera(&self.sim, input_state, viewport, focused);
+        self.update_inset(ctx, input_state, &inset_viewport);
+        self.step(time_delta);
+    }
+
+    /// Build the "Galaxy" window.
+    fn ui(&mut self, ctx: &mut Context, ui: &imgui::Ui) {
+        ui.window("Galaxy")
+            .size([350.0, 300.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.collapsing_header("Simulation", TreeNodeFlags::all())
+                    .then(|| {
+                        ui.checkbox("Paused", &mut self.paused);
+                        ui.slider_config("Myr per second", 0.0, 50_000.0)
+                            .flags(SliderFlags::LOGARITHMIC)
+                            .display_format("%.0f")
+                            .build(&mut self.sim.myr_per_second);
+                        ui.label_text("Substeps", self.sim.substep_count.to_string());
+                        ui.checkbox("Auto slowdown", &mut self.auto_slowdown);
+                        self.step_safety_ui(ui);
+                    });
+
+                if let Some(event) = self.renderer.ui(ui, &self.sim, self.paused) {
+                    self.star_edit = Some(event);
+                }
+
+                ui.checkbox("Locked star inset", &mut self.show_inset);
+
+                let mut render_scale = self.renderer.render_scale() - 1;
+                if ui.combo_simple_string("Render scale", &mut render_scale, &RENDER_SCALES) {
+                    if let Err(err) = self.set_render_scale(ctx, render_scale + 1) {
+                        log::error!("Failed to change render scale: {err}");
+                    }
+                }
+
+                let mut split_view = self.split_view.is_some();
+                if ui.checkbox("Split view", &mut split_view) {
+                    if let Err(err) = self.set_split_view(ctx, split_view) {
+                        log::error!("Failed to open split view: {err}");
+                    }
+                }
+            });
+    }
+
+    /// Render the stars of every view that's supersampled into its render target.
+    fn render_offscreen(&mut self, ctx: &mut Context, alpha: f64) {
+        let sim = &self.sim;
+        for renderer in std::iter::once(&mut self.renderer).chain(self.split_view.as_mut()).chain(self.inset.as_mut()) {
+            renderer.set_interpolation(alpha);
+            renderer.render_offscreen(ctx, sim);
+        }
+    }
+
+    /// Draw the galaxy, with its stars placed between their positions before and after the last
+    /// step by alpha.
+    fn render(&mut self, ctx: &mut Context, alpha: f64) {
+        self.renderer.set_interpolation(alpha);
+        self.renderer.draw(ctx, &self.sim);
+    }
+}
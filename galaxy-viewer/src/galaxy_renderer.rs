@@ -0,0 +1,950 @@
+use std::error::Error;
+use std::time::Instant;
+
+use imgui::{SliderFlags, TreeNodeFlags};
+use miniquad::*;
+use galaxy_core::analysis::extremes::Extreme;
+use galaxy_core::config::{GlowConfig, RenderingConfig, StarColorMode, TrailsConfig};
+use galaxy_core::profile_scope;
+use galaxy_core::simulation::{GalaxySim, StarComponent, StarTag, TidalDisruption};
+use galaxy_core::star_world::{Mass, Position, RenderColor, Species, StarId};
+use galaxy_core::units::SolarMass;
+use galaxy_core::types::{Vec2, Vec2d};
+use crate::auto_exposure::AutoExposure;
+use crate::drawable::*;
+use crate::input::InputState;
+use crate::selection::Selection;
+use crate::session::{InputEvent, StarEdit};
+use crate::shaders::stars;
+use crate::viewport::Viewport;
+
+/// The view bounds (min, max), in parsecs, about the galaxy's origin.
+const VIEW_BOUNDS: (Vec2d, Vec2d) = (Vec2d::new(-25_000.0, -25_000.0),
+                                     Vec2d::new(25_000.0, 25_000.0));
+
+/// Whether to draw the debug overlay for the quadtree.
+const DEBUG_DRAW_QUADTREE: bool = false;
+
+/// How many stars to highlight in red for debugging purposes.
+const HIGHLIGHT_RED_STAR_COUNT: usize = 0;
+
+/// How long the flash drawn where a star is torn apart by a black hole lasts, in seconds of real
+/// time.
+const FLASH_SECONDS: f64 = 1.0;
+
+/// The radius a tidal disruption flash grows to before it fades out, in pixels.
+const FLASH_RADIUS: f64 = 8.0;
+
+/// The colour of tidal disruption flashes.
+const FLASH_COLOR: [f64; 3] = [1.0, 0.95, 0.8];
+
+/// The radius of the ring drawn around tagged stars, in pixels.
+const TAG_MARKER_RADIUS: f64 = 4.0;
+
+/// The radius and colour of the ring drawn around selected stars, inside that of tagged stars so
+/// that both can be seen.
+const SELECTION_MARKER_RADIUS: f64 = 2.5;
+const SELECTION_COLOR: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+
+/// The brightness of tracer particles, which are massless so can't be drawn by their mass like
+/// stars.
+const TRACER_BRIGHTNESS: u8 = 0xC0;
+
+/// The brightness of dark matter particles, which are far more massive than stars so would be
+/// drawn at full brightness by their mass.
+const DARK_MATTER_BRIGHTNESS: u8 = 0x50;
+
+/// How fast the camera zooms (per mouse wheel click, which probably isn't consistent between
+/// mousewheels but oh well.)
+const CAMERA_ZOOM_SPEED: f64 = 1.0 / 200.0;
+
+/// The zoom level of a close-up view of a star.
+const CLOSE_UP_ZOOM_LEVEL: f64 = 4.0;
+
+/// A simple "camera" (just a position, default viewport width and height, and zoom level).
+struct Camera {
+    position: Vec2d,
+    viewport_dimensions: Vec2d,
+    zoom_level: f64,
+    locked_star: Option<usize>,
+    highlighted_star: usize,
+    right_mouse_down_prev: bool,
+}
+
+impl Camera {
+    fn new() -> Self {
+        Self {
+            position: VIEW_BOUNDS.0 * 0.5 + VIEW_BOUNDS.1 * 0.5,
+            viewport_dimensions: VIEW_BOUNDS.1 - VIEW_BOUNDS.0,
+            zoom_level: 0.0,
+            locked_star: None,
+            highlighted_star: 0,
+            right_mouse_down_prev: false,
+        }
+    }
+}
+
+/// A flash drawn where a star was torn apart by a black hole.
+struct Flash {
+    position: Vec2d,
+    started: Instant,
+}
+
+/// Draws the stars of a GalaxySim, and owns the camera they're viewed through. The renderer only
+/// ever reads the simulation, which is passed in to each method, so the simulation itself doesn't
+/// depend on anything to do with rendering.
+pub struct GalaxyRenderer {
+    /// The star texture, which is render_scale times larger than the configured size in each
+    /// dimension.
+    textured_quad: TexturedQuad,
+    texture_dirty: bool,
+
+    /// Renders the stars at a larger scale and downsamples them to the star texture's size, if the
+    /// render scale is more than 1.
+    supersampler: Option<Supersampler>,
+
+    /// Draws the stars as glowing sprites over the texture, if that's enabled. The texture then
+    /// only has the highlighted star and the tidal disruption flashes in it.
+    star_sprites: StarSprites,
+    glow: GlowConfig,
+
+    /// Blends each frame of the stars with the faded frames before it, if trails are enabled.
+    trails: TrailAccumulator,
+    trails_config: TrailsConfig,
+
+    /// Adjusts the sprites' exposure to what's in view, unless it's overridden by the exposure in
+    /// the glow config.
+    auto_exposure: AutoExposure,
+
+    /// The positions of the stars before the last step, which they're drawn interpolated from.
+    /// Empty if the simulation didn't step, in which case the stars are drawn where they are.
+    previous_positions: Vec<Vec2d>,
+
+    /// How far between the previous and current positions to draw the stars, from 0 to 1.
+    interpolation: f64,
+
+    /// The flashes where stars were recently torn apart by black holes.
+    flashes: Vec<Flash>,
+
+    /// The tag being edited in the inspector, and the index of the star it was started from. It's
+    /// only applied to the star when it's saved, rather than on every keystroke.
+    tag_draft: StarTag,
+    tag_draft_star: Option<usize>,
+
+    /// The stars the user has selected, and the selections they've saved.
+    selection: Selection,
+
+    /// Draws the quadtree's cells over the stars, if DEBUG_DRAW_QUADTREE is set.
+    quadtree_debug: QuadtreeDebug,
+
+    /// The simple "camera" containing the parameters to render the galaxy (such as viewport
+    /// position).
+    camera: Camera,
+}
+
+impl GalaxyRenderer {
+    /// Create a renderer that draws stars into a texture of the configured size.
+    pub fn new(ctx: &mut Context, config: &RenderingConfig) -> Result<Self, Box<dyn Error>> {
+        Self::with_render_scale(ctx, config, config.render_scale)
+    }
+
+    /// Create a renderer that draws stars into a texture of the configured size, rendering them at
+    /// the given scale rather than the configured one.
+    pub fn with_render_scale(ctx: &mut Context,
+                             config: &RenderingConfig,
+                             render_scale: usize) -> Result<Self, Box<dyn Error>>
+    {
+        // Create textured quad for drawing stars.
+        let (textured_quad, supersampler) = Self::create_targets(ctx, config, render_scale)?;
+        let star_sprites = StarSprites::new(ctx)?;
+        let trails = TrailAccumulator::new(ctx, config.texture_width, config.texture_height)?;
+
+        Ok(Self {
+            textured_quad,
+            texture_dirty: true,
+            supersampler,
+            star_sprites,
+            glow: config.glow.clone(),
+            trails,
+            trails_config: config.trails.clone(),
+            auto_exposure: AutoExposure::new(config.glow.exposure),
+            previous_positions: Vec::new(),
+            interpolation: 1.0,
+            flashes: Vec::new(),
+            tag_draft: StarTag::default(),
+            tag_draft_star: None,
+            selection: Selection::new(),
+            quadtree_debug: QuadtreeDebug::new(),
+            camera: Camera::new(),
+        })
+    }
+
+    /// Create the star texture and the supersampler for a render scale. There's no supersampler
+    /// at a scale of 1.
+    fn create_targets(ctx: &mut Context,
+                      config: &RenderingConfig,
+                      render_scale: usize) -> Result<(TexturedQuad, Option<Supersampler>), Box<dyn Error>>
+    {
+        let (width, height) = (config.texture_width, config.texture_height);
+        let supersampler = match render_scale {
+            0 | 1 => None,
+            _ => Some(Supersampler::new(ctx, width, height, render_scale)?),
+        };
+
+        let render_scale = supersampler.as_ref().map_or(1, Supersampler::scale);
+        let textured_quad = TexturedQuad::new(ctx, width * render_scale, height * render_scale)?;
+
+        Ok((textured_quad, supersampler))
+    }
+
+    /// How many times larger than the configured star texture size the stars are rendered.
+    pub fn render_scale(&self) -> usize {
+        self.supersampler.as_ref().map_or(1, Supersampler::scale)
+    }
+
+    /// Change the render scale, recreating the star texture and the supersampler.
+    pub fn set_render_scale(&mut self,
+                            ctx: &mut Context,
+                            config: &RenderingConfig,
+                            render_scale: usize) -> Result<(), Box<dyn Error>>
+    {
+        let (textured_quad, supersampler) = Self::create_targets(ctx, config, render_scale)?;
+
+        self.textured_quad.texture.delete();
+        if let Some(supersampler) = &self.supersampler {
+            supersampler.delete(ctx);
+        }
+        self.textured_quad = textured_quad;
+        self.supersampler = supersampler;
+        self.texture_dirty = true;
+
+        Ok(())
+    }
+
+    /// Update the texture if the dirty flag is set.
+    pub fn update_texture(&mut self, ctx: &mut Context, sim: &GalaxySim) {
+        if self.texture_dirty {
+            profile_scope!("Texture update");
+            log::debug!("Updating star texture");
+
+            self.texture_dirty = false;
+
+            // Update texture.
+            let render_scale = self.render_scale();
+            let mut bytes = match self.glow.enabled {
+                true => self.render_highlight(sim, render_scale),
+                false => self.render_stars_scaled(sim, true, render_scale),
+            };
+            self.render_flashes(sim, &mut bytes, render_scale);
+            self.render_markers(sim, &mut bytes, render_scale);
+            self.textured_quad.texture.update(ctx, &bytes);
+        }
+    }
+
+    /// Remember where the stars are before the simulation steps, so they can be drawn moving
+    /// smoothly to where they end up. `None` if the simulation isn't about to step, in which case
+    /// the stars are drawn where they are.
+    pub fn remember_positions(&mut self, sim: Option<&GalaxySim>) {
+        self.previous_positions.clear();
+        if let Some(sim) = sim {
+            self.previous_positions.extend(sim.stars().query::<&Position>().map(|position| position.0));
+        }
+        self.texture_dirty = true;
+    }
+
+    /// Set how far between their positions before and after the last step to draw the stars, from
+    /// 0 to 1, so that their motion is smooth when frames are drawn more often than the simulation
+    /// steps.
+    pub fn set_interpolation(&mut self, alpha: f64) {
+        if alpha != self.interpolation {
+            self.interpolation = alpha;
+            self.texture_dirty |= !self.previous_positions.is_empty();
+        }
+    }
+
+    /// The position to draw a star at, interpolated between its positions before and after the
+    /// last step. Stars are drawn where they are if they've been added or removed since.
+    fn interpolated_position(&self, sim: &GalaxySim, index: usize, position: Vec2d) -> Vec2d {
+        match self.previous_positions.len() == sim.stars().len() {
+            true => {
+                let previous = self.previous_positions[index];
+                previous + (position - previous) * self.interpolation
+            },
+            false => position,
+        }
+    }
+
+    /// Flash where stars were torn apart by black holes in the last step.
+    pub fn add_flashes(&mut self, disruptions: &[TidalDisruption]) {
+        let started = Instant::now();
+        self.flashes.extend(disruptions.iter().map(|disruption| Flash {
+            position: disruption.position,
+            started,
+        }));
+    }
+
+    /// Draw the tidal disruption flashes over the stars in an RGBA buffer from render_stars_scaled
+    /// at the given scale. Each flash is a disc that grows and fades out over FLASH_SECONDS, and
+    /// those that have faded out are forgotten.
+    fn render_flashes(&mut self, sim: &GalaxySim, bytes: &mut [u8], render_scale: usize) {
+        self.flashes.retain(|flash| flash.started.elapsed().as_secs_f64() < FLASH_SECONDS);
+
+        let tex_width = sim.config().rendering.texture_width * render_scale;
+        let tex_height = sim.config().rendering.texture_height * render_scale;
+        let (view_offset, view_size) = self.view_bounds();
+
+        for flash in &self.flashes {
+            let progress = flash.started.elapsed().as_secs_f64() / FLASH_SECONDS;
+            let radius = FLASH_RADIUS * render_scale as f64 * progress.sqrt();
+            let brightness = 1.0 - progress;
+
+            let pos = flash.position - view_offset;
+            let center_x = pos.x / view_size.x * tex_width as f64;
+            let center_y = pos.y / view_size.y * tex_height as f64;
+
+            let min_x = f64::max(center_x - radius, 0.0) as usize;
+            let min_y = f64::max(center_y - radius, 0.0) as usize;
+            let max_x = f64::min(center_x + radius, tex_width as f64 - 1.0);
+            let max_y = f64::min(center_y + radius, tex_height as f64 - 1.0);
+            if max_x < 0.0 || max_y < 0.0 {
+                continue;
+            }
+
+            for y in min_y..=max_y as usize {
+                for x in min_x..=max_x as usize {
+                    let distance = (x as f64 - center_x).hypot(y as f64 - center_y);
+                    if distance > radius {
+                        continue;
+                    }
+
+                    // Brightest in the middle, fading out towards the edge.
+                    let intensity = brightness * (1.0 - distance / f64::max(radius, 1.0));
+                    let idx = 4 * (y * tex_width + x);
+                    let pixel = &mut bytes[idx..idx+4];
+                    for channel in 0..3 {
+                        let value = pixel[channel] as f64 + intensity * FLASH_COLOR[channel] * 255.0;
+                        pixel[channel] = f64::min(value, 255.0) as u8;
+                    }
+                    pixel[3] = 0xFF;
+                }
+            }
+        }
+
+        // Keep redrawing until the flashes have faded out.
+        self.texture_dirty |= !self.flashes.is_empty();
+    }
+
+    /// Draw a ring in each tagged star's colour around it, and a white ring around each selected
+    /// star, in an RGBA buffer from render_stars_scaled at the given scale, so that they stand out
+    /// however faint they are.
+    fn render_markers(&self, sim: &GalaxySim, bytes: &mut [u8], render_scale: usize) {
+        let (view_offset, view_size) = self.drawn_view_bounds(sim);
+        for (i, (position, id, tag)) in sim.stars().query::<(&Position, &StarId, Option<&StarTag>)>().enumerate() {
+            let pos = self.interpolated_position(sim, i, position.0) - view_offset;
+            let pos = Vec2d::new(pos.x / view_size.x, pos.y / view_size.y);
+
+            if let Some(tag) = tag {
+                let rgba = [tag.color[0], tag.color[1], tag.color[2], 1.0].map(|channel| (channel * 255.0) as u8);
+                Self::render_ring(sim, bytes, render_scale, pos, TAG_MARKER_RADIUS, rgba);
+            }
+            if self.selection.contains(id.0) {
+                Self::render_ring(sim, bytes, render_scale, pos, SELECTION_MARKER_RADIUS, SELECTION_COLOR);
+            }
+        }
+    }
+
+    /// Draw a ring one texel thick in an RGBA buffer from render_stars_scaled at the given scale,
+    /// around a position normalized to the view and with a radius in texels.
+    fn render_ring(sim: &GalaxySim, bytes: &mut [u8], render_scale: usize, pos: Vec2d, radius: f64, rgba: [u8; 4]) {
+        let tex_width = sim.config().rendering.texture_width * render_scale;
+        let tex_height = sim.config().rendering.texture_height * render_scale;
+        let radius = radius * render_scale as f64;
+        let thickness = render_scale as f64;
+
+        let center_x = pos.x * tex_width as f64;
+        let center_y = pos.y * tex_height as f64;
+
+        let min_x = f64::max(center_x - radius - thickness, 0.0) as usize;
+        let min_y = f64::max(center_y - radius - thickness, 0.0) as usize;
+        let max_x = f64::min(center_x + radius + thickness, tex_width as f64 - 1.0);
+        let max_y = f64::min(center_y + radius + thickness, tex_height as f64 - 1.0);
+        if max_x < 0.0 || max_y < 0.0 {
+            return;
+        }
+
+        for y in min_y..=max_y as usize {
+            for x in min_x..=max_x as usize {
+                let distance = (x as f64 - center_x).hypot(y as f64 - center_y);
+                if (distance - radius).abs() <= thickness * 0.5 {
+                    let idx = 4 * (y * tex_width + x);
+                    bytes[idx..idx+4].copy_from_slice(&rgba);
+                }
+            }
+        }
+    }
+
+    /// Highlight a star and lock the camera onto it.
+    pub fn focus_on_star(&mut self, sim: &GalaxySim, star: usize) {
+        if let Some(position) = sim.stars().component::<Position>(star) {
+            self.camera.position = position.0;
+            self.camera.highlighted_star = star;
+            self.camera.locked_star = Some(star);
+            self.texture_dirty = true;
+        }
+    }
+
+    /// The star the camera is locked onto, if any.
+    pub fn locked_star(&self) -> Option<usize> {
+        self.camera.locked_star
+    }
+
+    /// Zoom in closely on the star another renderer is locked onto, or its highlighted star if it
+    /// isn't locked onto one, and lock onto it.
+    pub fn close_up_of(&mut self, other: &GalaxyRenderer, sim: &GalaxySim) {
+        let star = other.camera.locked_star.unwrap_or(other.camera.highlighted_star);
+        self.focus_on_star(sim, star);
+        self.camera.zoom_level = CLOSE_UP_ZOOM_LEVEL;
+    }
+
+    /// Match another renderer's view. The highlighted star is matched by index, which is the same
+    /// star if both galaxies were generated from the same seed.
+    pub fn follow(&mut self, other: &GalaxyRenderer) {
+        self.camera.position = other.camera.position;
+        self.camera.viewport_dimensions = other.camera.viewport_dimensions;
+        self.camera.zoom_level = other.camera.zoom_level;
+        self.camera.highlighted_star = other.camera.highlighted_star;
+    }
+
+    /// How far the camera is zoomed in, as the factor the view has shrunk by.
+    pub fn zoom_scale(&self) -> f64 {
+        Self::linear_scale_to_exponential(self.camera.zoom_level)
+    }
+
+    /// The bounds of the current view in parsecs, as its bottom left corner and its size.
+    pub fn view_bounds(&self) -> (Vec2d, Vec2d) {
+        let zoom_scale = Self::linear_scale_to_exponential(self.camera.zoom_level);
+        let view_size = self.camera.viewport_dimensions / zoom_scale;
+        (self.camera.position - view_size * 0.5, view_size)
+    }
+
+    /// The bounds of the view the stars are drawn in, as view_bounds. The camera follows the locked
+    /// star's current position, so this follows where it's drawn instead to keep it still.
+    fn drawn_view_bounds(&self, sim: &GalaxySim) -> (Vec2d, Vec2d) {
+        let (mut view_offset, view_size) = self.view_bounds();
+        if let Some((locked_star, position)) = self.camera.locked_star.and_then(|i| sim.stars().component::<Position>(i).map(|position| (i, position.0))) {
+            view_offset = view_offset + self.interpolated_position(sim, locked_star, position) - position;
+        }
+        (view_offset, view_size)
+    }
+
+    /// Render the stars in the current view into an RGBA buffer the configured size of the star
+    /// texture. Row 0 is the bottom of the view, as in the texture. The highlighted star is only
+    /// drawn in green if `highlight` is set. They're rendered at the render scale and then
+    /// downsampled, as they're drawn on screen.
+    pub fn render_stars(&self, sim: &GalaxySim, highlight: bool) -> Vec<u8> {
+        let render_scale = self.render_scale();
+        let bytes = self.render_stars_scaled(sim, highlight, render_scale);
+        match render_scale {
+            1 => bytes,
+            _ => Self::downsample(&bytes,
+                                  sim.config().rendering.texture_width,
+                                  sim.config().rendering.texture_height,
+                                  render_scale),
+        }
+    }
+
+    /// Render the stars into an RGBA buffer the given number of times larger than the configured
+    /// size of the star texture in each dimension. Stars are still single texels, so they're made
+    /// brighter by the square of the scale to keep them as bright once downsampled, and the
+    /// highlighted star fills the texels that are downsampled into one.
+    fn render_stars_scaled(&self, sim: &GalaxySim, highlight: bool, render_scale: usize) -> Vec<u8> {
+        let tex_width = sim.config().rendering.texture_width * render_scale;
+        let tex_height = sim.config().rendering.texture_height * render_scale;
+        let star_mass_range = sim.config().generation.star_mass_max - sim.config().generation.star_mass_min;
+        let gain = (render_scale * render_scale) as f64;
+
+        // Create new buffer.
+        let mut bytes = vec![0; 4 * tex_width * tex_height];
+
+        // Draw all stars in buffer.
+        let mut star_count = 0;
+        let (view_offset, view_size) = self.drawn_view_bounds(sim);
+
+        let show_dark_matter = sim.config().rendering.show_dark_matter;
+        for (i, (position, mass, species, color)) in sim.stars().query::<(&Position, &Mass, &Species, &RenderColor)>().enumerate() {
+            if species.0 == StarComponent::Halo && !show_dark_matter {
+                continue;
+            }
+
+            // Normalize position to texture coordinates.
+            let mut pos = self.interpolated_position(sim, i, position.0) - view_offset;
+            pos.x /= view_size.x;
+            pos.y /= view_size.y;
+
+            // Convert to pixel coordinates in our texture.
+            let x = (pos.x * tex_width as f64) as usize;
+            let y = (pos.y * tex_height as f64) as usize;
+
+            if true || mass.0.0 < sim.config().generation.supermassive_black_hole_mass * 2.0 {
+                if x < tex_width && y < tex_height {
+                    // Get index and slice of pixel, *4 because the texture is 4 bytes per pixel.
+                    let idx = 4 * (y * tex_width + x);
+                    let pixel = &mut bytes[idx..idx+4];
+
+                    let brightness = Self::star_brightness(mass.0, species.0, star_mass_range);
+
+                    // TODO: refactor this a bit.
+                    if highlight && i == self.camera.highlighted_star {
+                        Self::fill_texel(&mut bytes, tex_width, x, y, render_scale, [0x0, 0xFF, 0x0, 0xFF]);
+                    }
+                    else if star_count > HIGHLIGHT_RED_STAR_COUNT {
+                        let color = color.0.map(f64::from);
+                        pixel[0] = f64::min(brightness as f64 * color[0] * gain, 255.0) as u8;
+                        pixel[1] = f64::min(brightness as f64 * color[1] * gain, 255.0) as u8;
+                        pixel[2] = f64::min(brightness as f64 * color[2] * gain, 255.0) as u8;
+                        pixel[3] = 0xFF;
+                    }
+                    else {
+                        pixel[0] = brightness;
+                        pixel[1] = 0x0;
+                        pixel[2] = 0x0;
+                        pixel[3] = 0xFF;
+                    }
+                }
+            }
+
+            star_count += 1;
+        }
+
+        bytes
+    }
+
+    /// Render just the highlighted star into an RGBA buffer the size of render_stars_scaled's, for
+    /// when the rest of the stars are drawn as sprites.
+    fn render_highlight(&self, sim: &GalaxySim, render_scale: usize) -> Vec<u8> {
+        let tex_width = sim.config().rendering.texture_width * render_scale;
+        let tex_height = sim.config().rendering.texture_height * render_scale;
+        let mut bytes = vec![0; 4 * tex_width * tex_height];
+
+        let (view_offset, view_size) = self.drawn_view_bounds(sim);
+        if let Some(position) = sim.stars().component::<Position>(self.camera.highlighted_star) {
+            let pos = self.interpolated_position(sim, self.camera.highlighted_star, position.0) - view_offset;
+            let x = (pos.x / view_size.x * tex_width as f64) as usize;
+            let y = (pos.y / view_size.y * tex_height as f64) as usize;
+            if x < tex_width && y < tex_height {
+                Self::fill_texel(&mut bytes, tex_width, x, y, render_scale, [0x0, 0xFF, 0x0, 0xFF]);
+            }
+        }
+
+        bytes
+    }
+
+    /// Fill the square of texels of an RGBA buffer, rendered at the given scale, that will be
+    /// downsampled into the same texel as the texel at (x, y).
+    fn fill_texel(bytes: &mut [u8], width: usize, x: usize, y: usize, render_scale: usize, rgba: [u8; 4]) {
+        let (first_x, first_y) = (x - x % render_scale, y - y % render_scale);
+        for y in first_y..first_y + render_scale {
+            for x in first_x..first_x + render_scale {
+                let idx = 4 * (y * width + x);
+                bytes[idx..idx+4].copy_from_slice(&rgba);
+            }
+        }
+    }
+
+    /// Downsample an RGBA buffer rendered at the given scale to the given size, by averaging each
+    /// square of texels.
+    fn downsample(bytes: &[u8], width: usize, height: usize, render_scale: usize) -> Vec<u8> {
+        let source_width = width * render_scale;
+        let texel_count = (render_scale * render_scale) as u32;
+
+        (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).flat_map(|(x, y)| {
+            let mut sum = [0u32; 4];
+            for source_y in y * render_scale..(y + 1) * render_scale {
+                for source_x in x * render_scale..(x + 1) * render_scale {
+                    let idx = 4 * (source_y * source_width + source_x);
+                    for channel in 0..4 {
+                        sum[channel] += bytes[idx + channel] as u32;
+                    }
+                }
+            }
+            sum.map(|channel| (channel / texel_count) as u8)
+        }).collect()
+    }
+
+    /// The stars in the current view as sprites. Stars outside the view are left out, except for
+    /// those whose glow might reach into it.
+    fn star_sprites(&self, sim: &GalaxySim) -> Vec<StarSprite> {
+        let tex_width = sim.config().rendering.texture_width;
+        let star_mass_range = sim.config().generation.star_mass_max - sim.config().generation.star_mass_min;
+        let show_dark_matter = sim.config().rendering.show_dark_matter;
+
+        let (view_offset, view_size) = self.drawn_view_bounds(sim);
+        let margin = self.glow.max_point_size / tex_width as f64 * 2.0;
+
+        sim.stars().query::<(&Position, &Mass, &Species, &RenderColor)>().enumerate().filter_map(|(i, (position, mass, species, color))| {
+            if species.0 == StarComponent::Halo && !show_dark_matter {
+                return None;
+            }
+
+            // Map the position to normalized device coordinates.
+            let pos = self.interpolated_position(sim, i, position.0) - view_offset;
+            let x = pos.x / view_size.x * 2.0 - 1.0;
+            let y = pos.y / view_size.y * 2.0 - 1.0;
+            if x.abs() > 1.0 + margin || y.abs() > 1.0 + margin {
+                return None;
+            }
+
+            Some(StarSprite {
+                center: Vec2::new(x as f32, y as f32),
+                point_size: self.point_size(mass.0, species.0) as f32,
+                color: color.0,
+                brightness: Self::star_brightness(mass.0, species.0, star_mass_range) as f32 / 255.0,
+            })
+        }).collect()
+    }
+
+    /// The radius to draw a star's glow at, in texels. It's scaled by the star's mass relative to
+    /// the reference mass and by the camera's zoom, so massive stars are larger and stars grow as
+    /// the camera zooms in, and then clamped.
+    fn point_size(&self, mass: SolarMass, species: StarComponent) -> f64 {
+        let mass_scale = match species {
+            StarComponent::Tracer | StarComponent::Halo => 1.0,
+            _ => (mass.0 / self.glow.reference_mass).powf(self.glow.mass_size_exponent),
+        };
+        let zoom_scale = Self::linear_scale_to_exponential(self.camera.zoom_level).powf(self.glow.zoom_size_exponent);
+
+        let size = self.glow.glow_radius * mass_scale * zoom_scale;
+        size.clamp(self.glow.min_point_size, f64::max(self.glow.min_point_size, self.glow.max_point_size))
+    }
+
+    /// The brightness of a star, from its mass as a fraction of the range of star masses. Tracers
+    /// and dark matter have fixed brightnesses.
+    fn star_brightness(mass: SolarMass, species: StarComponent, star_mass_range: f64) -> u8 {
+        match species {
+            StarComponent::Tracer => TRACER_BRIGHTNESS,
+            StarComponent::Halo => DARK_MATTER_BRIGHTNESS,
+            _ => f64::min(mass.0 / star_mass_range * 255.0, 255.0) as u8,
+        }
+    }
+
+    /// Build the camera, star, tag, selection, extremes and highlighted star sections of the galaxy window. The
+    /// highlighted star can be edited while the simulation is `paused`, and tagged at any time, and
+    /// the event to apply if it was is returned.
+    pub fn ui(&mut self, ui: &imgui::Ui, sim: &GalaxySim, paused: bool) -> Option<InputEvent> {
+        ui.collapsing_header("Camera", TreeNodeFlags::all())
+            .then(|| {
+                ui.label_text("Cam pos", format!("{:.2}, {:.2}",
+                                                 self.camera.position.x,
+                                                 self.camera.position.y));
+                ui.label_text("Zoom level", self.camera.zoom_level.to_string());
+            });
+
+        ui.collapsing_header("Stars", TreeNodeFlags::empty())
+            .then(|| {
+                self.texture_dirty |= ui.checkbox("Glow", &mut self.glow.enabled);
+                if self.glow.enabled {
+                    ui.slider("Core size (texels)", 0.0, 5.0, &mut self.glow.core_size);
+                    ui.slider("Glow radius (texels)", 0.5, 20.0, &mut self.glow.glow_radius);
+                    ui.slider("Mass size exponent", 0.0, 1.0, &mut self.glow.mass_size_exponent);
+                    ui.slider("Zoom size exponent", 0.0, 1.0, &mut self.glow.zoom_size_exponent);
+                    ui.slider("Min point size (texels)", 0.5, 10.0, &mut self.glow.min_point_size);
+                    ui.slider("Max point size (texels)", 0.5, 50.0, &mut self.glow.max_point_size);
+                    ui.slider("Falloff", 0.5, 8.0, &mut self.glow.falloff);
+                    ui.checkbox("Auto exposure", &mut self.glow.auto_exposure);
+                    if self.glow.auto_exposure {
+                        ui.slider("Exposure key", 0.1, 10.0, &mut self.glow.exposure_key);
+                        ui.slider("Exposure percentile", 0.0, 1.0, &mut self.glow.exposure_percentile);
+                        ui.label_text("Exposure", format!("{:.2}", self.auto_exposure.exposure()));
+                        ui.plot_histogram("##brightness", self.auto_exposure.histogram())
+                            .graph_size([0.0, 40.0])
+                            .build();
+                    }
+                    else {
+                        ui.slider_config("Exposure", 0.1, 20.0)
+                            .flags(SliderFlags::LOGARITHMIC)
+                            .build(&mut self.glow.exposure);
+                    }
+
+                    let names: Vec<&str> = StarColorMode::ALL.iter().map(|mode| mode.name()).collect();
+                    let mut selected = self.color_mode_index();
+                    if ui.combo_simple_string("Colour", &mut selected, &names) {
+                        self.glow.color_mode = StarColorMode::ALL[selected];
+                    }
+                }
+
+                // Start the trails afresh when they're turned back on, rather than from whenever
+                // they were turned off.
+                if ui.checkbox("Trails", &mut self.trails_config.enabled) && self.trails_config.enabled {
+                    self.trails.clear();
+                }
+                if self.trails_config.enabled {
+                    ui.slider("Trail decay", 0.0, 0.99, &mut self.trails_config.decay);
+                }
+            });
+
+        ui.collapsing_header("Tags", TreeNodeFlags::empty())
+            .then(|| {
+                let mut focused = None;
+                for (i, tag) in sim.stars().query::<Option<&StarTag>>().enumerate() {
+                    if let Some(tag) = tag {
+                        let color = [tag.color[0], tag.color[1], tag.color[2], 1.0];
+                        let label = match tag.label.is_empty() {
+                            true => format!("Star {i}"),
+                            false => format!("{} (star {i})", tag.label),
+                        };
+                        ui.text_colored(color, "*");
+                        ui.same_line();
+                        if ui.selectable_config(format!("{label}##tag{i}"))
+                            .selected(i == self.camera.highlighted_star)
+                            .build()
+                        {
+                            focused = Some(i);
+                        }
+                        if !tag.note.is_empty() && ui.is_item_hovered() {
+                            ui.tooltip_text(&tag.note);
+                        }
+                    }
+                }
+                if let Some(star) = focused {
+                    self.focus_on_star(sim, star);
+                }
+            });
+
+        ui.collapsing_header("Selection", TreeNodeFlags::empty())
+            .then(|| {
+                self.texture_dirty |= self.selection.ui(ui, sim);
+            });
+
+        ui.collapsing_header("Extremes", TreeNodeFlags::empty())
+            .then(|| {
+                // Highlight the star the query finds and lock the camera onto it.
+                for (i, extreme) in Extreme::ALL.into_iter().enumerate() {
+                    if i % 2 != 0 {
+                        ui.same_line();
+                    }
+                    if ui.button(extreme.name()) {
+                        match extreme.find(sim) {
+                            Some(star) => self.focus_on_star(sim, star),
+                            None => log::info!("There are no stars to find the {} of", extreme.name().to_lowercase()),
+                        }
+                    }
+                }
+            });
+
+        ui.collapsing_header("Highlighted star", TreeNodeFlags::all())
+            .then(|| {
+                let index = self.camera.highlighted_star;
+                let star = sim.stars().get(index)?;
+
+                ui.label_text("ID", star.id.to_string());
+                if ui.button(if self.selection.contains(star.id) { "Deselect" } else { "Select" }) {
+                    self.selection.toggle(star.id);
+                    self.texture_dirty = true;
+                }
+
+                // The star can only be edited while the simulation is paused, so that what's entered
+                // isn't immediately stepped away from.
+                let mut edit = None;
+                match paused {
+                    true => {
+                        let mut position = [star.position.x, star.position.y];
+                        let mut velocity = [star.velocity.x, star.velocity.y];
+                        let mut mass = star.mass.0;
+                        let changed = ui.input_scalar_n("Pos (pc)", &mut position).enter_returns_true(true).build()
+                            | ui.input_scalar_n("Velocity (km/s)", &mut velocity).enter_returns_true(true).build()
+                            | ui.input_scalar("Mass (Msun)", &mut mass).enter_returns_true(true).build();
+                        if changed {
+                            edit = Some(StarEdit::Set {
+                                mass: f64::max(mass, 0.0),
+                                position: Vec2d::new(position[0], position[1]),
+                                velocity: Vec2d::new(velocity[0], velocity[1]),
+                            });
+                        }
+
+                        if ui.button("Zero velocity") {
+                            edit = Some(StarEdit::ZeroVelocity);
+                        }
+                        ui.same_line();
+                        if ui.button("Circularize orbit") {
+                            edit = Some(StarEdit::CircularizeOrbit);
+                        }
+                    },
+                    false => {
+                        ui.label_text("Pos", format!("{:.2}, {:.2}", star.position.x, star.position.y));
+                        ui.label_text("Velocity", format!("{:.2}, {:.2}", star.velocity.x, star.velocity.y));
+                        ui.label_text("Mass", star.mass.to_string());
+                        ui.text_disabled("Pause to edit the star");
+                    },
+                }
+
+                if let Some(binary) = &star.binary {
+                    let separation = binary.separation.x.hypot(binary.separation.y);
+                    ui.label_text("Companion", format!("{:.2} Msun, {:.4} pc away",
+                                                       star.mass.0 * binary.companion_fraction, separation));
+                }
+
+                // Start editing the star's tag afresh whenever another star is highlighted.
+                ui.separator();
+                if self.tag_draft_star != Some(index) {
+                    self.tag_draft = star.tag.as_deref().cloned().unwrap_or_default();
+                    self.tag_draft_star = Some(index);
+                }
+                ui.input_text("Label", &mut self.tag_draft.label).build();
+                ui.color_edit3("Tag colour", &mut self.tag_draft.color);
+                ui.input_text_multiline("Note", &mut self.tag_draft.note, [0.0, 60.0]).build();
+                if ui.button(if star.tag.is_some() { "Update tag" } else { "Tag" }) {
+                    edit = Some(StarEdit::SetTag(Some(self.tag_draft.clone())));
+                }
+                if star.tag.is_some() {
+                    ui.same_line();
+                    if ui.button("Remove tag") {
+                        edit = Some(StarEdit::SetTag(None));
+                        self.tag_draft_star = None;
+                    }
+                }
+
+                edit.map(|edit| InputEvent::EditStar { index, edit })
+            })
+            .flatten()
+    }
+
+    /// Zoom and pan the camera, and pick the star under the mouse, for a galaxy drawn in the given
+    /// viewport. If the camera isn't `focused` it ignores the mouse, and only keeps following its
+    /// locked star, so that only one of several views of the same galaxy is controlled at once.
+    pub fn update_camera(&mut self, sim: &GalaxySim, input_state: &InputState, viewport: &Viewport, focused: bool) {
+        if focused {
+            self.handle_mouse(sim, input_state, viewport);
+        }
+        self.camera.right_mouse_down_prev = input_state.right_mouse_button_down;
+
+        // Stars can be removed by scenario scripts, in which case stop following them.
+        if let Some(locked_star) = self.camera.locked_star {
+            match sim.stars().component::<Position>(locked_star) {
+                Some(position) => self.camera.position = position.0,
+                None => self.camera.locked_star = None,
+            }
+        }
+    }
+
+    /// Zoom and pan the camera with the mouse, pick the star under it, and lock onto or unlock
+    /// from the picked star with the right mouse button.
+    fn handle_mouse(&mut self, sim: &GalaxySim, input_state: &InputState, viewport: &Viewport) {
+        // Update camera zoom using scrollwheel.
+        self.camera.zoom_level = f64::max(0.0,
+            self.camera.zoom_level + input_state.mouse_wheel_dy as f64 * CAMERA_ZOOM_SPEED);
+
+        if input_state.left_mouse_button_down {
+            // Translate pixel movement to movement at the current scale.
+            let (_, view_size) = self.view_bounds();
+            let viewport_size = viewport.size();
+            let movement_scale = Vec2d::new(view_size.x / viewport_size.x, view_size.y / viewport_size.y);
+
+            // Calculate movement.
+            let (mouse_dx, mouse_dy) = input_state.mouse_diff;
+            let movement = Vec2d::new(-mouse_dx as f64 * movement_scale.x, mouse_dy as f64 * movement_scale.y);
+            self.camera.position = self.camera.position + movement;
+        }
+
+        // Update highlighted star.
+        if self.camera.locked_star.is_none() {
+            let mouse_pos_world = self.window_to_world(viewport, input_state.mouse_pos);
+            if let Some(star) = sim.quadtree.nearest_item(mouse_pos_world) {
+                self.camera.highlighted_star = star;
+            }
+        }
+
+        // Update camera position to locked star position.
+        if input_state.right_mouse_button_down && !self.camera.right_mouse_down_prev {
+            if self.camera.locked_star.is_some() {
+                self.camera.locked_star = None;
+            }
+            else {
+                self.camera.locked_star = Some(self.camera.highlighted_star);
+            }
+        }
+    }
+
+    fn linear_scale_to_exponential(linear: f64) -> f64 {
+        f64::exp(linear)
+    }
+
+    // Project window to world coordinates.
+    fn window_to_world(&self, viewport: &Viewport, window: (f32, f32)) -> Vec2d {
+        let (view_offset, view_size) = self.view_bounds();
+
+        let pos_vp = viewport.normalize(window);
+        Vec2d::new(pos_vp.x * view_size.x, pos_vp.y * view_size.y) + view_offset
+    }
+
+    /// The index of the glow's colour mode in StarColorMode::ALL.
+    fn color_mode_index(&self) -> usize {
+        StarColorMode::ALL.iter().position(|&mode| mode == self.glow.color_mode).unwrap_or(0)
+    }
+
+    /// Render the stars into the supersampler's render target and downsample them, if the render
+    /// scale is more than 1, and then combine them with their trails, if those are enabled. This
+    /// has to happen before the frame's render pass begins.
+    pub fn render_offscreen(&mut self, ctx: &mut Context, sim: &GalaxySim) {
+        if let Some(supersampler) = self.supersampler.take() {
+            supersampler.begin(ctx);
+            self.draw_stars(ctx, sim);
+            supersampler.end(ctx);
+            self.supersampler = Some(supersampler);
+        }
+
+        if self.trails_config.enabled {
+            self.trails.begin(ctx);
+            self.draw_frame(ctx, sim);
+            self.trails.end(ctx, self.trails_config.decay as f32);
+        }
+    }
+
+    /// Draw the stars with their trails, if those are enabled.
+    pub fn draw(&mut self, ctx: &mut Context, sim: &GalaxySim) {
+        match self.trails_config.enabled {
+            true => self.trails.draw(ctx),
+            false => self.draw_frame(ctx, sim),
+        }
+        if DEBUG_DRAW_QUADTREE {
+            self.quadtree_debug.draw(ctx, &sim.quadtree);
+        }
+    }
+
+    /// Draw the stars, or the downsampled stars if they were rendered at a larger scale.
+    fn draw_frame(&mut self, ctx: &mut Context, sim: &GalaxySim) {
+        match &self.supersampler {
+            Some(supersampler) => supersampler.draw(ctx),
+            None => self.draw_stars(ctx, sim),
+        }
+    }
+
+    /// Draw the stars into the current render pass, updating the texture first if they've changed.
+    fn draw_stars(&mut self, ctx: &mut Context, sim: &GalaxySim) {
+        self.update_texture(ctx, sim);
+        self.textured_quad.draw(ctx);
+
+        if self.glow.enabled {
+            let sprites = self.star_sprites(sim);
+            let exposure = match self.glow.auto_exposure {
+                true => {
+                    self.auto_exposure.update(&sprites, self.glow.exposure_key, self.glow.exposure_percentile);
+                    self.auto_exposure.exposure()
+                },
+                false => self.glow.exposure,
+            };
+            let tex_width = sim.config().rendering.texture_width as f32;
+            let tex_height = sim.config().rendering.texture_height as f32;
+            let core_fraction = match self.glow.glow_radius > 0.0 {
+                true => (self.glow.core_size / self.glow.glow_radius) as f32,
+                false => 1.0,
+            };
+            self.star_sprites.draw(ctx, &sprites, &stars::Uniforms {
+                texel_size: (2.0 / tex_width, 2.0 / tex_height),
+                core_fraction,
+                falloff: self.glow.falloff as f32,
+                exposure: exposure as f32,
+                color_mode: self.color_mode_index() as f32,
+            });
+        }
+    }
+}
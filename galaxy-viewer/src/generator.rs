@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use galaxy_core::config::Config;
+use galaxy_core::generation;
+use galaxy_core::rng::RngStreams;
+use galaxy_core::simulation::GalaxySim;
+
+/// The fraction of the work that's generating the stars, the rest is building the quadtree.
+const STAR_GENERATION_FRACTION: f32 = 0.9;
+
+/// Generates a galaxy on a worker thread, so that the UI keeps responding while large galaxies are
+/// generated. The main thread should poll it with `try_finish` and swap the galaxy in once it's
+/// done. Dropping it abandons the generation, and the thread's result is discarded.
+pub struct GalaxyGenerator {
+    /// The seed the galaxy is being generated with.
+    pub seed: u64,
+
+    /// The fraction of the work done so far, stored as the bits of an f32 so it can be shared
+    /// with the worker thread.
+    progress: Arc<AtomicU32>,
+
+    result: Receiver<Result<GalaxySim, String>>,
+}
+
+impl GalaxyGenerator {
+    /// Start generating a galaxy from the given config and seed.
+    pub fn start(config: &Config, seed: u64) -> Self {
+        log::info!("Generating galaxy with seed {seed} in the background");
+
+        let progress = Arc::new(AtomicU32::new(0.0f32.to_bits()));
+        let (sender, result) = mpsc::channel();
+
+        let config = config.clone();
+        let thread_progress = progress.clone();
+        thread::spawn(move || {
+            let stars = generation::generate_stars_with_progress(&config, &RngStreams::new(seed), &mut |fraction| {
+                let fraction = fraction as f32 * STAR_GENERATION_FRACTION;
+                thread_progress.store(fraction.to_bits(), Ordering::Relaxed);
+            });
+
+            // Errors can't be sent between threads, so send the message instead. The receiver is
+            // gone if the generation was abandoned, in which case there's nobody to tell.
+            let sim = GalaxySim::from_stars(&config, stars).map_err(|err| err.to_string());
+            let _ = sender.send(sim);
+        });
+
+        Self { seed, progress, result }
+    }
+
+    /// The fraction of the generation done so far, between 0 and 1.
+    pub fn progress(&self) -> f32 {
+        f32::from_bits(self.progress.load(Ordering::Relaxed))
+    }
+
+    /// Take the generated galaxy if generation has finished.
+    pub fn try_finish(&self) -> Option<Result<GalaxySim, Box<dyn Error>>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result.map_err(Into::into)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("The galaxy generation thread panicked".into())),
+        }
+    }
+}
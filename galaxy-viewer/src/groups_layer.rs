@@ -0,0 +1,174 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use galaxy_core::analysis::friends_of_friends::FriendsOfFriends;
+use galaxy_core::config::Config;
+use galaxy_core::star_world::{Position, StarHandle};
+use galaxy_core::types::Vec2d;
+use miniquad::*;
+
+use crate::drawable::*;
+use crate::galaxy::Galaxy;
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+/// How many updates there are between each search for groups, as it's too slow to do every step
+/// in large galaxies.
+const UPDATE_INTERVAL: u64 = 30;
+
+/// The opacity of the outlines around each group.
+const OUTLINE_ALPHA: u8 = 0xA0;
+
+/// A layer that finds groups of stars with the friends-of-friends algorithm and draws them over
+/// the galaxy, colouring each group's stars and outlining it, so that bound clusters can be seen
+/// forming.
+pub struct GroupsLayer {
+    textured_quad: TexturedQuad,
+    galaxy: Rc<RefCell<Galaxy>>,
+
+    groups: FriendsOfFriends,
+
+    /// The linking length in parsecs, within which stars are in the same group.
+    linking_length: f32,
+
+    /// The fewest stars a group can have to be drawn.
+    min_members: i32,
+
+    /// Whether to draw the bounding boxes of the groups.
+    outline: bool,
+
+    /// The number of updates since the groups were last found, or None if they need finding.
+    updates_since_search: Option<u64>,
+}
+
+impl GroupsLayer {
+    /// Create a groups layer for the given galaxy.
+    pub fn new(ctx: &mut Context, config: &Config, galaxy: Rc<RefCell<Galaxy>>) -> Result<Self, Box<dyn Error>> {
+        let blend = BlendState::new(Equation::Add,
+                                    BlendFactor::Value(BlendValue::SourceAlpha),
+                                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha));
+        let textured_quad = TexturedQuad::with_blend(ctx,
+                                                     config.rendering.texture_width,
+                                                     config.rendering.texture_height,
+                                                     Some(blend))?;
+
+        Ok(Self {
+            textured_quad,
+            galaxy,
+            groups: FriendsOfFriends::default(),
+            linking_length: 50.0,
+            min_members: 10,
+            outline: true,
+            updates_since_search: None,
+        })
+    }
+
+    /// A distinct colour for each group, spreading their hues around the colour wheel.
+    fn group_color(group: usize) -> [u8; 3] {
+        let hue = (group as f64 * 0.618_034).fract() * 6.0;
+        let x = 1.0 - (hue % 2.0 - 1.0).abs();
+        let [r, g, b] = match hue as u32 {
+            0 => [1.0, x, 0.0],
+            1 => [x, 1.0, 0.0],
+            2 => [0.0, 1.0, x],
+            3 => [0.0, x, 1.0],
+            4 => [x, 0.0, 1.0],
+            _ => [1.0, 0.0, x],
+        };
+        [r, g, b].map(|channel: f64| (channel * 255.0) as u8)
+    }
+
+    /// Draw the groups in the current view into the texture, in the same pixels as their stars.
+    fn update_texture(&mut self, ctx: &mut Context) {
+        let (width, height) = (self.textured_quad.width, self.textured_quad.height);
+        let mut bytes = vec![0; 4 * width * height];
+
+        let galaxy = self.galaxy.borrow();
+        let (view_offset, view_size) = galaxy.renderer.view_bounds();
+        let to_pixel = |position: Vec2d| {
+            let pos = position - view_offset;
+            ((pos.x / view_size.x * width as f64) as i64, (pos.y / view_size.y * height as f64) as i64)
+        };
+        let mut set_pixel = |x: i64, y: i64, [r, g, b]: [u8; 3], alpha: u8| {
+            if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                let idx = 4 * (y as usize * width + x as usize);
+                bytes[idx..idx+4].copy_from_slice(&[r, g, b, alpha]);
+            }
+        };
+
+        for (group, members) in self.groups.groups.iter().enumerate() {
+            let color = Self::group_color(group);
+            let mut min = (i64::MAX, i64::MAX);
+            let mut max = (i64::MIN, i64::MIN);
+
+            // Stars can be removed by scenario scripts between searches.
+            for position in members.iter().filter_map(|&index| galaxy.sim.stars().component::<Position>(index)) {
+                let (x, y) = to_pixel(position.0);
+                set_pixel(x, y, color, 0xFF);
+                min = (min.0.min(x), min.1.min(y));
+                max = (max.0.max(x), max.1.max(y));
+            }
+
+            if self.outline && min.0 <= max.0 {
+                let (min, max) = ((min.0 - 2, min.1 - 2), (max.0 + 2, max.1 + 2));
+                for x in min.0..=max.0 {
+                    set_pixel(x, min.1, color, OUTLINE_ALPHA);
+                    set_pixel(x, max.1, color, OUTLINE_ALPHA);
+                }
+                for y in min.1..=max.1 {
+                    set_pixel(min.0, y, color, OUTLINE_ALPHA);
+                    set_pixel(max.0, y, color, OUTLINE_ALPHA);
+                }
+            }
+        }
+
+        self.textured_quad.texture.update(ctx, &bytes);
+    }
+}
+
+impl Drawable for GroupsLayer {
+    /// Update the groups layer, finding the groups again if it's time to.
+    fn fixed_update(&mut self,
+                    ctx: &mut Context,
+                    _input_state: &InputState,
+                    _viewport: &Viewport,
+                    _time_delta: f64)
+    {
+        self.updates_since_search = match self.updates_since_search {
+            Some(updates) if updates + 1 < UPDATE_INTERVAL => Some(updates + 1),
+            _ => {
+                let galaxy = self.galaxy.borrow();
+                self.groups = FriendsOfFriends::find(&galaxy.sim.quadtree,
+                                                     self.linking_length as f64,
+                                                     self.min_members as usize,
+                                                     |star: &StarHandle| star.species.is_star());
+                Some(0)
+            },
+        };
+
+        self.update_texture(ctx);
+    }
+
+    /// Build the "Groups" window.
+    fn ui(&mut self, _ctx: &mut Context, ui: &imgui::Ui) {
+        ui.window("Groups")
+            .size([250.0, 130.0], imgui::Condition::FirstUseEver)
+            .position([370.0, 460.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let mut changed = ui.slider("Linking length (pc)", 1.0, 1000.0, &mut self.linking_length);
+                changed |= ui.slider("Min members", 2, 500, &mut self.min_members);
+                ui.checkbox("Outline", &mut self.outline);
+                ui.text(format!("{} groups", self.groups.groups.len()));
+
+                if changed {
+                    self.updates_since_search = None;
+                }
+            });
+    }
+
+    /// Draw the groups layer.
+    fn render(&mut self, ctx: &mut Context, _alpha: f64) {
+        self.textured_quad.draw(ctx);
+    }
+}
@@ -0,0 +1,93 @@
+use miniquad::KeyCode;
+
+/// Something the user can do with a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    RegenerateGalaxy,
+    IncreaseRate,
+    DecreaseRate,
+    ToggleShortcuts,
+}
+
+impl Action {
+    /// A description of the action for display.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::RegenerateGalaxy => "Regenerate the galaxy with the next seed",
+            Action::IncreaseRate => "Speed up time by 10x",
+            Action::DecreaseRate => "Slow down time by 10x",
+            Action::ToggleShortcuts => "Show or hide this list",
+        }
+    }
+}
+
+/// The mouse controls, which aren't rebindable, as the control and what it does, for display
+/// alongside the key bindings.
+const MOUSE_CONTROLS: [(&str, &str); 4] = [
+    ("Left drag", "Pan the camera"),
+    ("Wheel", "Zoom the camera"),
+    ("Hover", "Highlight the nearest star"),
+    ("Right click", "Lock the camera onto the highlighted star, or unlock it"),
+];
+
+/// Which key triggers each action. The key handler looks actions up here, and the shortcut
+/// overlay is generated from it, so the two can't disagree.
+pub struct KeyBindings {
+    bindings: Vec<(KeyCode, Action)>,
+
+    /// Whether the shortcut overlay is shown.
+    pub show_overlay: bool,
+}
+
+impl KeyBindings {
+    /// Create the default key bindings.
+    pub fn new() -> Self {
+        Self {
+            bindings: vec![
+                (KeyCode::F1, Action::ToggleShortcuts),
+                (KeyCode::Space, Action::RegenerateGalaxy),
+                (KeyCode::M, Action::IncreaseRate),
+                (KeyCode::A, Action::DecreaseRate),
+                (KeyCode::Escape, Action::Quit),
+            ],
+            show_overlay: false,
+        }
+    }
+
+    /// The action bound to a key, if there is one.
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        self.bindings.iter().find(|(bound_key, _)| *bound_key == key).map(|&(_, action)| action)
+    }
+
+    /// Build the shortcut overlay, if it's shown, listing every key binding and mouse control in
+    /// the middle of the screen.
+    pub fn overlay_ui(&mut self, ui: &imgui::Ui) {
+        if !self.show_overlay {
+            return;
+        }
+
+        let [width, height] = ui.io().display_size;
+        ui.window("Shortcuts")
+            .position([width * 0.5, height * 0.5], imgui::Condition::Always)
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .collapsible(false)
+            .opened(&mut self.show_overlay)
+            .build(|| {
+                for (key, action) in &self.bindings {
+                    ui.text(format!("{key:?}"));
+                    ui.same_line_with_pos(100.0);
+                    ui.text(action.description());
+                }
+
+                ui.separator();
+                for (control, description) in MOUSE_CONTROLS {
+                    ui.text(control);
+                    ui.same_line_with_pos(100.0);
+                    ui.text(description);
+                }
+            });
+    }
+}
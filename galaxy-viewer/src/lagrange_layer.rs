@@ -0,0 +1,126 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use galaxy_core::config::Config;
+use galaxy_core::three_body;
+use galaxy_core::types::Vec2d;
+use miniquad::*;
+
+use crate::drawable::*;
+use crate::galaxy::Galaxy;
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+/// The colours of the collinear Lagrange points L1 to L3 and the triangular points L4 and L5.
+const COLLINEAR_COLOR: [u8; 3] = [0xFF, 0x60, 0x60];
+const TRIANGULAR_COLOR: [u8; 3] = [0x60, 0xFF, 0x60];
+
+/// The half width of the cross drawn at each point, in pixels.
+const CROSS_RADIUS: i64 = 4;
+
+/// A layer that marks the Lagrange points of the first two stars, for the restricted three-body
+/// mode, where they're the two massive bodies. Particles collect around L4 and L5 and escape
+/// through L1 and L2, so they're useful for checking the particles move as they should.
+pub struct LagrangeLayer {
+    textured_quad: TexturedQuad,
+    galaxy: Rc<RefCell<Galaxy>>,
+
+    /// The Lagrange points L1 to L5 as of the last update, or None if there aren't two bodies.
+    points: Option<[Vec2d; 5]>,
+}
+
+impl LagrangeLayer {
+    /// Create a Lagrange points layer for the given galaxy.
+    pub fn new(ctx: &mut Context, config: &Config, galaxy: Rc<RefCell<Galaxy>>) -> Result<Self, Box<dyn Error>> {
+        let blend = BlendState::new(Equation::Add,
+                                    BlendFactor::Value(BlendValue::SourceAlpha),
+                                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha));
+        let textured_quad = TexturedQuad::with_blend(ctx,
+                                                     config.rendering.texture_width,
+                                                     config.rendering.texture_height,
+                                                     Some(blend))?;
+
+        Ok(Self {
+            textured_quad,
+            galaxy,
+            points: None,
+        })
+    }
+
+    /// Draw a cross at each of the Lagrange points in the current view into the texture.
+    fn update_texture(&mut self, ctx: &mut Context) {
+        let (width, height) = (self.textured_quad.width, self.textured_quad.height);
+        let mut bytes = vec![0; 4 * width * height];
+
+        let galaxy = self.galaxy.borrow();
+        let (view_offset, view_size) = galaxy.renderer.view_bounds();
+        let to_pixel = |position: Vec2d| {
+            let pos = position - view_offset;
+            ((pos.x / view_size.x * width as f64) as i64, (pos.y / view_size.y * height as f64) as i64)
+        };
+        let mut set_pixel = |x: i64, y: i64, [r, g, b]: [u8; 3]| {
+            if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                let idx = 4 * (y as usize * width + x as usize);
+                bytes[idx..idx+4].copy_from_slice(&[r, g, b, 0xFF]);
+            }
+        };
+
+        for (i, &point) in self.points.iter().flatten().enumerate() {
+            let color = match i {
+                0..=2 => COLLINEAR_COLOR,
+                _ => TRIANGULAR_COLOR,
+            };
+            let (x, y) = to_pixel(point);
+            for offset in -CROSS_RADIUS..=CROSS_RADIUS {
+                set_pixel(x + offset, y + offset, color);
+                set_pixel(x + offset, y - offset, color);
+            }
+        }
+
+        self.textured_quad.texture.update(ctx, &bytes);
+    }
+}
+
+impl Drawable for LagrangeLayer {
+    /// Find the Lagrange points where the bodies are now.
+    fn fixed_update(&mut self,
+                    ctx: &mut Context,
+                    _input_state: &InputState,
+                    _viewport: &Viewport,
+                    _time_delta: f64)
+    {
+        let (primary, secondary) = {
+            let galaxy = self.galaxy.borrow();
+            (galaxy.sim.stars().get(0), galaxy.sim.stars().get(1))
+        };
+        self.points = match (primary, secondary) {
+            (Some(primary), Some(secondary)) => Some(three_body::lagrange_points(&primary, &secondary)),
+            _ => None,
+        };
+
+        self.update_texture(ctx);
+    }
+
+    /// Build the "Lagrange points" window.
+    fn ui(&mut self, _ctx: &mut Context, ui: &imgui::Ui) {
+        ui.window("Lagrange points")
+            .size([250.0, 130.0], imgui::Condition::FirstUseEver)
+            .position([1250.0, 800.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                match &self.points {
+                    Some(points) => {
+                        for (i, point) in points.iter().enumerate() {
+                            ui.label_text(format!("L{}", i + 1), format!("{:.2}, {:.2}", point.x, point.y));
+                        }
+                    },
+                    None => ui.text("Needs two bodies"),
+                }
+            });
+    }
+
+    /// Draw the Lagrange points layer.
+    fn render(&mut self, ctx: &mut Context, _alpha: f64) {
+        self.textured_quad.draw(ctx);
+    }
+}
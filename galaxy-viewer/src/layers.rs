@@ -0,0 +1,172 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use miniquad::Context;
+
+use crate::drawable::Drawable;
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+/// A shared handle to a drawable, so that the owner of a layer can still access it directly.
+pub type SharedDrawable = Rc<RefCell<dyn Drawable>>;
+
+/// A function that creates a new instance of a type of layer.
+pub type LayerFactory = Box<dyn Fn(&mut Context) -> Result<SharedDrawable, Box<dyn Error>>>;
+
+/// A single layer in the registry.
+struct Layer {
+    name: String,
+    drawable: SharedDrawable,
+
+    /// Hidden layers are still updated but not drawn.
+    visible: bool,
+
+    /// Whether the layer can be removed from the UI. Layers that other code depends on being
+    /// updated, such as the galaxy which steps the simulation, can only be hidden.
+    removable: bool,
+}
+
+/// A registry of Drawable layers, which are updated in order and drawn from first to last so that
+/// later layers are drawn on top. Layers can be added, removed, reordered and hidden at runtime
+/// via the "Layers" window, and new kinds of layer can be made available by registering a factory
+/// for them.
+pub struct LayerRegistry {
+    layers: Vec<Layer>,
+    factories: Vec<(String, LayerFactory)>,
+
+    /// The index of the factory selected in the UI.
+    selected_factory: usize,
+}
+
+impl LayerRegistry {
+    /// Create an empty layer registry.
+    pub fn new() -> Self {
+        Self {
+            layers: Vec::new(),
+            factories: Vec::new(),
+            selected_factory: 0,
+        }
+    }
+
+    /// Register a kind of layer that can be added from the UI.
+    pub fn register_factory(&mut self, name: &str, factory: LayerFactory) {
+        self.factories.push((name.to_owned(), factory));
+    }
+
+    /// Add a layer on top of the existing ones.
+    pub fn add(&mut self, name: &str, drawable: SharedDrawable, visible: bool) {
+        self.layers.push(Layer { name: name.to_owned(), drawable, visible, removable: true });
+    }
+
+    /// Add a layer that can be hidden but not removed.
+    pub fn add_fixed(&mut self, name: &str, drawable: SharedDrawable) {
+        self.layers.push(Layer { name: name.to_owned(), drawable, visible: true, removable: false });
+    }
+
+    /// Create a layer using the factory with the given index and add it on top.
+    fn add_from_factory(&mut self, ctx: &mut Context, index: usize) -> Result<(), Box<dyn Error>> {
+        let (name, factory) = &self.factories[index];
+        let drawable = factory(ctx)?;
+        let name = name.clone();
+
+        self.add(&name, drawable, true);
+
+        Ok(())
+    }
+
+    /// Update all layers for one fixed timestep, including hidden ones. They're all drawn in the
+    /// same viewport.
+    pub fn fixed_update(&mut self,
+                        ctx: &mut Context,
+                        input_state: &InputState,
+                        viewport: &Viewport,
+                        time_delta: f64)
+    {
+        for layer in &self.layers {
+            layer.drawable.borrow_mut().fixed_update(ctx, input_state, viewport, time_delta);
+        }
+    }
+
+    /// Render all visible layers, from bottom to top, alpha of the way between the last two fixed
+    /// updates.
+    pub fn render(&mut self, ctx: &mut Context, alpha: f64) {
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            layer.drawable.borrow_mut().render(ctx, alpha);
+        }
+    }
+
+    /// Let all visible layers render into their offscreen render targets, before the frame's render
+    /// pass begins.
+    pub fn render_offscreen(&mut self, ctx: &mut Context, alpha: f64) {
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            layer.drawable.borrow_mut().render_offscreen(ctx, alpha);
+        }
+    }
+
+    /// Build the "Layers" window, and then the windows of the layers themselves, including hidden
+    /// ones.
+    pub fn ui(&mut self, ctx: &mut Context, ui: &imgui::Ui) {
+        // Changes are applied after building the UI so the list isn't modified while iterating it.
+        let mut move_up = None;
+        let mut move_down = None;
+        let mut remove = None;
+        let mut add = false;
+
+        ui.window("Layers")
+            .size([250.0, 150.0], imgui::Condition::FirstUseEver)
+            .position([370.0, 120.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                // List the layers top first, as in most image editors.
+                let layer_count = self.layers.len();
+                for (i, layer) in self.layers.iter_mut().enumerate().rev() {
+                    let _id = ui.push_id_usize(i);
+
+                    ui.checkbox(&layer.name, &mut layer.visible);
+
+                    ui.same_line_with_pos(150.0);
+                    if ui.arrow_button("up", imgui::Direction::Up) && i + 1 < layer_count {
+                        move_up = Some(i);
+                    }
+                    ui.same_line();
+                    if ui.arrow_button("down", imgui::Direction::Down) && i > 0 {
+                        move_down = Some(i);
+                    }
+                    if layer.removable {
+                        ui.same_line();
+                        if ui.small_button("x") {
+                            remove = Some(i);
+                        }
+                    }
+                }
+
+                if !self.factories.is_empty() {
+                    ui.separator();
+
+                    let names: Vec<&str> = self.factories.iter().map(|(name, _)| name.as_str()).collect();
+                    ui.combo_simple_string("##layer_type", &mut self.selected_factory, &names);
+                    ui.same_line();
+                    add = ui.button("Add layer");
+                }
+            });
+
+        if let Some(i) = move_up {
+            self.layers.swap(i, i + 1);
+        }
+        if let Some(i) = move_down {
+            self.layers.swap(i, i - 1);
+        }
+        if let Some(i) = remove {
+            self.layers.remove(i);
+        }
+        if add {
+            if let Err(err) = self.add_from_factory(ctx, self.selected_factory) {
+                log::error!("Failed to create layer: {err}");
+            }
+        }
+
+        for layer in &self.layers {
+            layer.drawable.borrow_mut().ui(ctx, ui);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// The number of records kept, after which the oldest are dropped.
+const CAPACITY: usize = 1000;
+
+/// The least severe level that's always captured for the panel, whatever RUST_LOG is set to, so
+/// that warnings and progress are visible without running from a terminal.
+const CAPTURE_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// The levels that can be filtered to, from the most severe.
+const LEVELS: [Level; 4] = [Level::Error, Level::Warn, Level::Info, Level::Debug];
+
+/// A captured log record.
+struct LogEntry {
+    /// The time since logging was initialized, in seconds.
+    time: f64,
+    level: Level,
+    target: String,
+    message: String,
+}
+
+/// The most recent log records, shared between the logger and the panel that displays them.
+#[derive(Clone)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<LogEntry>>>);
+
+impl LogBuffer {
+    fn new() -> Self {
+        Self(Arc::new(Mutex::new(VecDeque::with_capacity(CAPACITY))))
+    }
+
+    fn push(&self, entry: LogEntry) {
+        let mut entries = self.0.lock().unwrap();
+        if entries.len() == CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// A logger that writes to the terminal as env_logger does, filtered by RUST_LOG, and also
+/// captures records into a buffer for the log panel.
+struct PanelLogger {
+    terminal: env_logger::Logger,
+    buffer: LogBuffer,
+    started: Instant,
+}
+
+impl Log for PanelLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= CAPTURE_LEVEL || self.terminal.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let shown_in_terminal = self.terminal.matches(record);
+        if shown_in_terminal {
+            self.terminal.log(record);
+        }
+
+        // Anything RUST_LOG lets through is captured too, so more detail can be turned on for the
+        // panel in the same way.
+        if record.level() <= CAPTURE_LEVEL || shown_in_terminal {
+            self.buffer.push(LogEntry {
+                time: self.started.elapsed().as_secs_f64(),
+                level: record.level(),
+                target: record.target().to_string(),
+                message: record.args().to_string(),
+            });
+        }
+    }
+
+    fn flush(&self) {
+        self.terminal.flush();
+    }
+}
+
+/// Initialize logging to the terminal, configured by RUST_LOG as with env_logger, and to a buffer
+/// for the log panel, which is returned.
+pub fn init() -> LogBuffer {
+    let terminal = env_logger::Builder::from_default_env().build();
+    let max_level = LevelFilter::max(terminal.filter(), CAPTURE_LEVEL);
+    let buffer = LogBuffer::new();
+
+    log::set_boxed_logger(Box::new(PanelLogger { terminal, buffer: buffer.clone(), started: Instant::now() }))
+        .expect("Logging was already initialized");
+    log::set_max_level(max_level);
+
+    buffer
+}
+
+/// A console window showing the captured log records, filtered by level and by text.
+pub struct LogPanel {
+    buffer: LogBuffer,
+
+    /// The index in LEVELS of the least severe level shown.
+    level: usize,
+
+    /// Only records whose message or target contain this are shown, ignoring case.
+    search: String,
+
+    /// Whether to keep scrolled to the newest record.
+    auto_scroll: bool,
+}
+
+impl LogPanel {
+    /// Create a panel that shows the records captured in a buffer.
+    pub fn new(buffer: LogBuffer) -> Self {
+        Self {
+            buffer,
+            level: 2,
+            search: String::new(),
+            auto_scroll: true,
+        }
+    }
+
+    /// Build the log window.
+    pub fn ui(&mut self, ui: &imgui::Ui) {
+        ui.window("Log")
+            .size([500.0, 200.0], imgui::Condition::FirstUseEver)
+            .position([370.0, 900.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let names: Vec<&str> = LEVELS.iter().map(|level| level.as_str()).collect();
+                ui.set_next_item_width(80.0);
+                ui.combo_simple_string("Level", &mut self.level, &names);
+                ui.same_line();
+                ui.set_next_item_width(150.0);
+                ui.input_text("Search", &mut self.search).build();
+                ui.same_line();
+                ui.checkbox("Auto-scroll", &mut self.auto_scroll);
+                ui.same_line();
+                if ui.button("Clear") {
+                    self.buffer.clear();
+                }
+
+                let max_level = LEVELS[self.level];
+                let search = self.search.to_lowercase();
+                ui.child_window("##records").build(|| {
+                    for entry in self.buffer.0.lock().unwrap().iter() {
+                        let matches = search.is_empty()
+                            || entry.message.to_lowercase().contains(&search)
+                            || entry.target.to_lowercase().contains(&search);
+                        if entry.level <= max_level && matches {
+                            ui.text_colored(Self::level_color(entry.level),
+                                            format!("{:8.2} {:5} {}: {}", entry.time, entry.level, entry.target, entry.message));
+                        }
+                    }
+
+                    if self.auto_scroll && ui.scroll_y() >= ui.scroll_max_y() {
+                        ui.set_scroll_here_y_with_ratio(1.0);
+                    }
+                });
+            });
+    }
+
+    /// The colour records of a level are shown in.
+    fn level_color(level: Level) -> [f32; 4] {
+        match level {
+            Level::Error => [1.0, 0.4, 0.4, 1.0],
+            Level::Warn => [1.0, 0.8, 0.3, 1.0],
+            Level::Info => [0.9, 0.9, 0.9, 1.0],
+            Level::Debug | Level::Trace => [0.6, 0.6, 0.6, 1.0],
+        }
+    }
+}
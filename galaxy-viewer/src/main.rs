@@ -0,0 +1,1868 @@
+mod shaders;
+mod auto_exposure;
+mod galaxy;
+mod galaxy_renderer;
+mod selection;
+mod perlin_map;
+mod dust_layer;
+mod density_layer;
+mod starfield_layer;
+mod groups_layer;
+mod tidal_tails_layer;
+mod force_error_layer;
+mod acceleration_layer;
+mod lagrange_layer;
+mod two_body_layer;
+mod state_diff_layer;
+mod drawable;
+mod ui_stage;
+mod control;
+mod input;
+mod layers;
+mod session;
+mod playback;
+mod frame_export;
+mod stream;
+mod generator;
+mod angular_momentum_plot;
+mod correlation_plot;
+mod velocity_plot;
+mod population_plot;
+mod energy_plot;
+mod log_panel;
+mod keybindings;
+mod flame_chart;
+mod power_spectrum_plot;
+mod tree_balance_panel;
+mod ensemble_plot;
+mod comparison;
+mod viewport;
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::{error::Error, iter::repeat, time::Instant};
+
+use clap::{Parser, Subcommand};
+use serde_json::json;
+use miniquad::*;
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use galaxy::Galaxy;
+use galaxy_core::analysis::state_diff::StateDiff;
+use galaxy_core::arrow_export::ArrowExporter;
+use galaxy_core::catalog;
+use galaxy_core::checkpoint::{Checkpoint, Checkpointer};
+use galaxy_core::config::{self, Config, SpatialBackend};
+use galaxy_core::hilbert::HilbertIndex;
+use galaxy_core::metrics::{MetricsWriter, StepMetrics};
+use galaxy_core::profiler;
+use galaxy_core::{profile_scope, profile_span};
+use galaxy_core::presets::Preset;
+use galaxy_core::rewind::{Bookmark, RewindBuffer};
+use galaxy_core::triggers::{TriggerEvent, Triggers};
+use galaxy_core::scenario::Scenario;
+use galaxy_core::rng::{self, RngStreams};
+use galaxy_core::simulation::GalaxySim;
+use galaxy_core::shared_snapshot::SharedSnapshotWriter;
+use galaxy_core::snapshot::SnapshotPublisher;
+use galaxy_core::sweep::{Sweep, SweepParameter};
+use galaxy_core::trajectory::{self, Trajectory};
+use galaxy_core::svg_export::SvgExport;
+use galaxy_core::tree_export::TreeExport;
+use galaxy_core::validation::TwoBodyValidation;
+use perlin_map::PerlinMap;
+use dust_layer::DustLayer;
+use density_layer::DensityLayer;
+use starfield_layer::StarfieldLayer;
+use groups_layer::GroupsLayer;
+use tidal_tails_layer::TidalTailsLayer;
+use force_error_layer::ForceErrorLayer;
+use acceleration_layer::AccelerationLayer;
+use lagrange_layer::LagrangeLayer;
+use two_body_layer::TwoBodyLayer;
+use state_diff_layer::StateDiffLayer;
+
+use crate::angular_momentum_plot::AngularMomentumPlot;
+use crate::correlation_plot::CorrelationPlot;
+use crate::velocity_plot::VelocityPlot;
+use crate::population_plot::PopulationPlot;
+use crate::energy_plot::EnergyPlot;
+use crate::log_panel::{LogBuffer, LogPanel};
+use crate::keybindings::{Action, KeyBindings};
+use crate::flame_chart::FlameChart;
+use crate::power_spectrum_plot::PowerSpectrumPlot;
+use crate::tree_balance_panel::TreeBalancePanel;
+use crate::ensemble_plot::EnsemblePlot;
+use crate::comparison::Comparison;
+use crate::frame_export::FrameExporter;
+use crate::galaxy_renderer::GalaxyRenderer;
+use crate::generator::GalaxyGenerator;
+use crate::ui_stage::{UiBuilder, UiStage};
+use crate::drawable::Drawable;
+use crate::control::{ControlCommand, ControlResponse, ControlServer};
+use crate::input::InputState;
+use crate::layers::LayerRegistry;
+use crate::playback::Playback;
+use crate::session::{Button, InputEvent, SessionHeader, SessionRecorder, SessionReplay};
+use crate::stream::StreamServer;
+use crate::viewport::Viewport;
+
+/// The window width.
+const WINDOW_WIDTH: i32 = 1024;
+
+/// The window height.
+const WINDOW_HEIGHT: i32 = 1024;
+
+/// The fixed timestep, each update will account for this many seconds of simulation.
+const FIXED_TIMESTEP: f64 = 1.0 / 60.0;
+
+/// The most fixed timesteps that can be taken in one frame to catch up with real time.
+const MAX_CATCH_UP_STEPS: usize = 5;
+
+/// How much the rate is multiplied by in each step in which the integration is unreliable, when
+/// slowing down automatically.
+const AUTO_SLOWDOWN_FACTOR: f64 = 0.9;
+
+/// Command line arguments.
+#[derive(Parser)]
+#[command(about = "A galaxy simulation")]
+pub struct Args {
+    /// The TOML config file to load simulation constants from.
+    #[arg(long, default_value = config::DEFAULT_CONFIG_PATH)]
+    pub config: PathBuf,
+
+    /// Record the session's seed, config and inputs to this file so it can be replayed.
+    #[arg(long)]
+    pub record: Option<PathBuf>,
+
+    /// Replay a session recorded with --record. The seed and config are taken from the session
+    /// file.
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
+
+    /// Play back a trajectory recorded with the record subcommand instead of running the
+    /// simulation. The seed and config are taken from the trajectory file.
+    #[arg(long)]
+    pub playback: Option<PathBuf>,
+
+    /// Resume from the latest checkpoint in the configured checkpoint directory.
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Compare two checkpoints, e.g. of the same seed run with different integrators, showing the
+    /// first paused with how far each star is from its position in the second drawn over it.
+    #[arg(long, num_args = 2, value_names = ["FIRST", "SECOND"])]
+    pub diff: Vec<PathBuf>,
+
+    /// Export a numbered PNG frame to this directory every --export-interval Myr of simulated
+    /// time. In this mode the simulation steps once per rendered frame regardless of real time.
+    #[arg(long)]
+    pub export_frames: Option<PathBuf>,
+
+    /// The interval between exported frames, in Myr of simulated time.
+    #[arg(long, default_value_t = 10.0)]
+    pub export_interval: f64,
+
+    /// Quit after exporting this many Myr of simulated time.
+    #[arg(long)]
+    pub export_duration: Option<f64>,
+
+    /// Seed the simulation with stars from a Gaia catalogue export (CSV or VOTable) instead of
+    /// generating a galaxy.
+    #[arg(long)]
+    pub catalog: Option<PathBuf>,
+
+    /// Run the two-body validation scenario instead of generating a galaxy: two bodies on a known
+    /// elliptical orbit, whose integrated positions are checked against the analytic solution.
+    #[arg(long)]
+    pub two_body: bool,
+
+    /// Stream star positions and masses to WebSocket clients every step, listening on this
+    /// address (e.g. 127.0.0.1:9001).
+    #[arg(long)]
+    pub stream: Option<String>,
+
+    /// Write the star positions, velocities and masses into this file every step for external
+    /// tools to memory-map, ideally somewhere memory-backed such as /dev/shm/galaxy.
+    #[arg(long)]
+    pub shared_memory: Option<PathBuf>,
+
+    /// Serve the HTTP control API on this address (e.g. 127.0.0.1:8080), for monitoring and
+    /// steering the simulation remotely.
+    #[arg(long)]
+    pub control: Option<String>,
+
+    /// A Rhai scenario script to set up initial conditions and trigger events as the simulation
+    /// runs.
+    #[arg(long)]
+    pub script: Option<PathBuf>,
+
+    /// Write per-step metrics (energy, momentum, escaped stars, tree depth and timings) to this
+    /// file, as CSV if it ends in .csv and JSON lines otherwise.
+    #[arg(long)]
+    pub metrics: Option<PathBuf>,
+
+    /// Write metrics every this many steps.
+    #[arg(long, default_value_t = 1)]
+    pub metrics_interval: u64,
+
+    /// Export the state of every star to this file as an Arrow IPC stream, for analysing the run
+    /// in pandas or polars.
+    #[arg(long)]
+    pub export_arrow: Option<PathBuf>,
+
+    /// Export the stars every this many steps.
+    #[arg(long, default_value_t = 10)]
+    pub export_arrow_interval: u64,
+
+    /// Allow the quadtree's structure to be exported to this file from the checkpoint window, as
+    /// Graphviz DOT if it ends in .dot or .gv and JSON otherwise, for debugging.
+    #[arg(long)]
+    pub export_tree: Option<PathBuf>,
+
+    /// Allow the current view to be exported to this file from the checkpoint window as SVG, with
+    /// the stars as circles sized by their mass, for figures.
+    #[arg(long)]
+    pub export_svg: Option<PathBuf>,
+
+    /// Generate the galaxy from a preset (milky_way, andromeda, dwarf_irregular or
+    /// compact_elliptical), overriding the generation parameters in the config file.
+    #[arg(long)]
+    pub preset: Option<Preset>,
+
+    /// Checkpoints of several seeds of the same parameters, separated by commas. Their rotation
+    /// curves and density profiles are overlaid in the ensemble window.
+    #[arg(long, value_delimiter = ',')]
+    pub ensemble: Vec<PathBuf>,
+
+    /// Run a second galaxy generated with this seed alongside the main one, drawn side by side
+    /// with the same view and time for comparison.
+    #[arg(long)]
+    pub compare_seed: Option<u64>,
+
+    /// Run a second galaxy using this config file alongside the main one, drawn side by side with
+    /// the same view and time for comparison.
+    #[arg(long)]
+    pub compare_config: Option<PathBuf>,
+
+    /// A config value to change in the comparison galaxy, e.g. simulation.opening_angle=1.0. Can be
+    /// given more than once, and runs a comparison galaxy even without --compare-seed or
+    /// --compare-config.
+    #[arg(long = "compare-param")]
+    pub compare_parameters: Vec<SweepParameter>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Subcommands that run instead of the viewer.
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run every combination of seeds and config parameter values headlessly for a fixed
+    /// simulated time, writing summary metrics of each run to a CSV file.
+    Batch(BatchArgs),
+
+    /// Simulate a galaxy headlessly for a fixed simulated time, writing its trajectory to a file to
+    /// play back in the viewer with --playback.
+    Record(RecordArgs),
+}
+
+/// Arguments for the batch subcommand.
+#[derive(clap::Args)]
+pub struct BatchArgs {
+    /// The seeds to run, separated by commas. Defaults to the seed in the config.
+    #[arg(long, value_delimiter = ',')]
+    pub seeds: Vec<u64>,
+
+    /// A config value to sweep over and the values to try, e.g.
+    /// simulation.opening_angle=0.5,0.7,1.0. Can be given more than once to sweep over every
+    /// combination.
+    #[arg(long = "param")]
+    pub parameters: Vec<SweepParameter>,
+
+    /// How long to simulate each run for, in Myr.
+    #[arg(long, default_value_t = 1000.0)]
+    pub duration: f64,
+
+    /// How much simulated time each step covers, in Myr. Defaults to one fixed timestep at the
+    /// config's initial rate, as in the viewer.
+    #[arg(long)]
+    pub step_myr: Option<f64>,
+
+    /// The CSV file to write the summary metrics to.
+    #[arg(long, default_value = "sweep.csv")]
+    pub output: PathBuf,
+
+    /// How many runs to simulate at once. Defaults to the number of CPUs.
+    #[arg(long)]
+    pub jobs: Option<usize>,
+}
+
+/// Arguments for the record subcommand.
+#[derive(clap::Args)]
+pub struct RecordArgs {
+    /// The seed to generate the galaxy with. Defaults to the seed in the config.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// How long to simulate for, in Myr.
+    #[arg(long, default_value_t = 1000.0)]
+    pub duration: f64,
+
+    /// How much simulated time each step covers, in Myr. Defaults to one fixed timestep at the
+    /// config's initial rate, as in the viewer.
+    #[arg(long)]
+    pub step_myr: Option<f64>,
+
+    /// How much simulated time there is between each frame of the trajectory, in Myr.
+    #[arg(long, default_value_t = 10.0)]
+    pub interval: f64,
+
+    /// The file to write the trajectory to.
+    #[arg(long, default_value = "trajectory.jsonl")]
+    pub output: PathBuf,
+
+    /// Also export the frames to this file as an Arrow IPC stream, for analysing the run in pandas
+    /// or polars.
+    #[arg(long)]
+    pub arrow: Option<PathBuf>,
+}
+
+/// The oddly named 'Stage', which is actually just an event handler that renders our application
+/// via miniquad.
+pub struct Stage {
+    layers: LayerRegistry,
+    galaxy: Rc<RefCell<Galaxy>>,
+    config: Config,
+    seed: u64,
+    start_time: Instant,
+    sim_time: f64,
+    input_state: InputState,
+
+    /// The number of fixed updates that have been run so far.
+    step: u64,
+
+    /// Input events received since the last fixed update, which will be applied in the next one.
+    pending_events: Vec<InputEvent>,
+
+    /// The session recorder, if the session is being recorded.
+    recorder: Option<SessionRecorder>,
+
+    /// The session being replayed, if any. Live input is ignored until the replay finishes.
+    replay: Option<SessionReplay>,
+
+    /// The trajectory being played back, if any, in which case the simulation isn't stepped.
+    playback: Option<Playback>,
+
+    /// Writes periodic checkpoints of the galaxy, if enabled.
+    checkpointer: Option<Checkpointer>,
+
+    /// Exports frames of the galaxy, if running in frame export mode.
+    frame_exporter: Option<FrameExporter>,
+
+    /// When exporting frames, the simulated time in Myr after which to quit.
+    export_end_myr: Option<f64>,
+
+    /// Streams the simulation to WebSocket clients, if enabled.
+    stream_server: Option<StreamServer>,
+
+    /// Shares the simulation with external tools through a memory-mapped file, if enabled.
+    shared_snapshot: Option<SharedSnapshotWriter>,
+
+    /// Snapshots of the simulation published after each step, for reading without borrowing it.
+    snapshots: SnapshotPublisher,
+
+    /// Serves the HTTP control API, if enabled.
+    control_server: Option<ControlServer>,
+
+    /// The scenario script, if any.
+    scenario: Option<Scenario>,
+
+    /// Writes per-step metrics, if enabled.
+    metrics_writer: Option<MetricsWriter>,
+
+    /// How many steps to write metrics every.
+    metrics_interval: u64,
+
+    /// Exports the stars as Arrow, if enabled.
+    arrow_exporter: Option<ArrowExporter>,
+
+    /// How many steps to export the stars every.
+    export_arrow_interval: u64,
+
+    /// The file to export the quadtree's structure to, if enabled.
+    export_tree: Option<PathBuf>,
+
+    /// The file to export the current view to as SVG, if enabled.
+    export_svg: Option<PathBuf>,
+
+    /// Whether to outline the quadtree's cells in the SVG export.
+    svg_quadtree_cells: bool,
+
+    /// The index of the preset selected in the UI.
+    selected_preset: usize,
+
+    /// The counter-rotating fraction being edited in the UI.
+    counter_rotating_fraction: f64,
+
+    /// The streams the randomize controls and perturbations draw from, derived from the seed the
+    /// session started with, so that replaying a session draws the same numbers.
+    ui_randomize: StdRng,
+    perturbations: StdRng,
+
+    /// The standard deviation of the velocity perturbations in km/s, as edited in the UI.
+    perturbation_speed: f64,
+
+    /// The galaxy being generated in the background, if any.
+    generator: Option<GalaxyGenerator>,
+
+    /// Recent states of the simulation, which can be scrubbed back through.
+    rewind: RewindBuffer,
+
+    /// The index of the state in the rewind buffer being shown, if scrubbing.
+    scrub_index: Option<usize>,
+
+    /// Bookmarked states of the simulation, which can be jumped back to.
+    bookmarks: Vec<Bookmark>,
+
+    /// The label for the next bookmark being edited in the UI.
+    bookmark_label: String,
+
+    /// Conditions that automatically pause the simulation.
+    triggers: Triggers,
+
+    /// The last event that paused the simulation, and when it happened in Myr.
+    last_trigger: Option<(f64, TriggerEvent)>,
+
+    /// Plots of the galaxy's angular momentum, clustering, velocities and structure over time.
+    angular_momentum_plot: AngularMomentumPlot,
+    correlation_plot: CorrelationPlot,
+    velocity_plot: VelocityPlot,
+    population_plot: PopulationPlot,
+    energy_plot: EnergyPlot,
+    log_panel: LogPanel,
+    keybindings: KeyBindings,
+    flame_chart: FlameChart,
+    power_spectrum_plot: PowerSpectrumPlot,
+    tree_balance_panel: TreeBalancePanel,
+    ensemble_plot: EnsemblePlot,
+
+    /// A second galaxy drawn beside the main one for comparison, if one was requested.
+    comparison: Option<Comparison>,
+
+    /// The whole window, kept up to date as it's resized.
+    viewport: Viewport,
+}
+
+impl Stage {
+    pub fn new(ctx: &mut Context,
+               config: Config,
+               args: Args,
+               log_buffer: LogBuffer) -> Result<Stage, Box<dyn Error>>
+    {
+        let start_time = Instant::now();
+        let (window_width, window_height) = ctx.screen_size();
+        let replay = match args.replay {
+            Some(path) => Some(SessionReplay::load(path)?),
+            None => None,
+        };
+
+        let playback = match args.playback {
+            Some(path) => Some(Playback::new(Trajectory::load(path)?)),
+            None => None,
+        };
+
+        // When replaying or playing back, the seed and config come from the recorded file.
+        let (config, seed) = match (&replay, &playback) {
+            (Some(replay), _) => (replay.header.config.clone(), replay.header.seed),
+            (None, Some(playback)) => (playback.trajectory.header.config.clone(), playback.trajectory.header.seed),
+            (None, None) => { let seed = config.generation.seed; (config, seed) },
+        };
+
+        // Start recording.
+        let recorder = match args.record {
+            Some(path) => Some(SessionRecorder::create(path, &SessionHeader { seed, config: config.clone() })?),
+            None => None,
+        };
+
+        // Create galaxy, either from the latest checkpoint or by generating a new one.
+        // When comparing two checkpoints the first is shown, with the differences over it.
+        let mut state_diff = None;
+        let checkpoint = match (args.diff.as_slice(), args.resume) {
+            ([first_path, second_path], _) => {
+                let first = Checkpoint::load(first_path)?;
+                let second = Checkpoint::load(second_path)?;
+                let diff = StateDiff::compare(&first.stars, &second.stars);
+                state_diff = Some((diff, [first_path.display().to_string(), second_path.display().to_string()]));
+                Some(first)
+            },
+            (_, true) => Self::load_latest_checkpoint(&config.checkpoint.directory)?,
+            (_, false) => None,
+        };
+        let resumed = checkpoint.is_some();
+        let mut two_body = None;
+        let playback_sim = match &playback {
+            Some(playback) => Some(playback.trajectory.frames[0].to_sim(&config)?),
+            None => None,
+        };
+        let (mut galaxy, config, seed) = match (playback_sim, checkpoint, args.catalog) {
+            (Some(sim), _, _) => {
+                let mut galaxy = Galaxy::from_sim(ctx, sim)?;
+                galaxy.paused = true;
+                (galaxy, config, seed)
+            },
+            (None, Some(checkpoint), _) => {
+                let (config, seed) = (checkpoint.config.clone(), checkpoint.seed);
+                (Self::galaxy_from_checkpoint(ctx, checkpoint)?, config, seed)
+            },
+            (None, None, Some(catalog_path)) => {
+                let stars = catalog::load_catalog(catalog_path, &config)?;
+                (Galaxy::from_sim(ctx, GalaxySim::from_stars(&config, stars)?)?, config, seed)
+            },
+            (None, None, None) if args.two_body => {
+                let (sim, validation) = TwoBodyValidation::create(&config)?;
+                two_body = Some(validation);
+                let config = sim.config().clone();
+                (Galaxy::from_sim(ctx, sim)?, config, seed)
+            },
+            (None, None, None) => (Self::generate_galaxy(ctx, &config, seed)?, config, seed),
+        };
+
+        // Load the scenario script. Its init hook has already run if we resumed from a checkpoint.
+        let scenario = match args.script {
+            Some(path) => {
+                let mut scenario = Scenario::load(path)?;
+                if !resumed {
+                    scenario.init(&mut galaxy.sim)?;
+                }
+                Some(scenario)
+            },
+            None => None,
+        };
+
+        // Create layers. The perlin map is hidden by default, it's mostly useful for debugging. The
+        // starfield is added to the galaxy's light, so it's drawn over it to show behind the stars.
+        let galaxy = Rc::new(RefCell::new(galaxy));
+        let mut layers = LayerRegistry::new();
+        let generation_config = config.generation.clone();
+        layers.register_factory("Perlin map", Box::new(move |ctx| {
+            Ok(Rc::new(RefCell::new(PerlinMap::new(ctx, &generation_config)?)))
+        }));
+        let dust_config = config.clone();
+        let dust_galaxy = galaxy.clone();
+        layers.register_factory("Dust", Box::new(move |ctx| {
+            Ok(Rc::new(RefCell::new(DustLayer::new(ctx, &dust_config, dust_galaxy.clone())?)))
+        }));
+        let density_galaxy = galaxy.clone();
+        layers.register_factory("Density field", Box::new(move |ctx| {
+            Ok(Rc::new(RefCell::new(DensityLayer::new(ctx, density_galaxy.clone())?)))
+        }));
+        let starfield_galaxy = galaxy.clone();
+        layers.register_factory("Starfield", Box::new(move |ctx| {
+            Ok(Rc::new(RefCell::new(StarfieldLayer::new(ctx, starfield_galaxy.clone())?)))
+        }));
+        let groups_config = config.clone();
+        let groups_galaxy = galaxy.clone();
+        layers.register_factory("Groups", Box::new(move |ctx| {
+            Ok(Rc::new(RefCell::new(GroupsLayer::new(ctx, &groups_config, groups_galaxy.clone())?)))
+        }));
+        let tidal_tails_config = config.clone();
+        let tidal_tails_galaxy = galaxy.clone();
+        layers.register_factory("Tidal tails", Box::new(move |ctx| {
+            Ok(Rc::new(RefCell::new(TidalTailsLayer::new(ctx, &tidal_tails_config, tidal_tails_galaxy.clone())?)))
+        }));
+        let force_error_config = config.clone();
+        let force_error_galaxy = galaxy.clone();
+        layers.register_factory("Force error", Box::new(move |ctx| {
+            Ok(Rc::new(RefCell::new(ForceErrorLayer::new(ctx, &force_error_config, force_error_galaxy.clone())?)))
+        }));
+        let acceleration_config = config.clone();
+        let acceleration_galaxy = galaxy.clone();
+        layers.register_factory("Acceleration", Box::new(move |ctx| {
+            Ok(Rc::new(RefCell::new(AccelerationLayer::new(ctx, &acceleration_config, acceleration_galaxy.clone())?)))
+        }));
+        let lagrange_config = config.clone();
+        let lagrange_galaxy = galaxy.clone();
+        layers.register_factory("Lagrange points", Box::new(move |ctx| {
+            Ok(Rc::new(RefCell::new(LagrangeLayer::new(ctx, &lagrange_config, lagrange_galaxy.clone())?)))
+        }));
+        layers.add("Perlin map", Rc::new(RefCell::new(PerlinMap::new(ctx, &config.generation)?)), false);
+        layers.add_fixed("Galaxy", galaxy.clone());
+        layers.add("Starfield", Rc::new(RefCell::new(StarfieldLayer::new(ctx, galaxy.clone())?)), true);
+
+        // The Lagrange points are shown from the start in the restricted three-body mode.
+        if config.generation.three_body.enabled {
+            layers.add("Lagrange points", Rc::new(RefCell::new(LagrangeLayer::new(ctx, &config, galaxy.clone())?)), true);
+        }
+        if let Some(validation) = two_body {
+            let layer = TwoBodyLayer::new(ctx, &config, galaxy.clone(), validation)?;
+            layers.add("Two-body validation", Rc::new(RefCell::new(layer)), true);
+        }
+        if let Some((diff, names)) = state_diff {
+            let layer = StateDiffLayer::new(ctx, &config, galaxy.clone(), diff, names)?;
+            layers.add("State diff", Rc::new(RefCell::new(layer)), true);
+            galaxy.borrow_mut().paused = true;
+        }
+        let galaxy_ref = galaxy.borrow();
+
+        let checkpointer = match config.checkpoint.enabled {
+            true => Some(Checkpointer::new(&config.checkpoint, galaxy_ref.sim.elapsed_myr())),
+            false => None,
+        };
+
+        // Start exporting frames.
+        let (frame_exporter, export_end_myr) = match args.export_frames {
+            Some(directory) => {
+                let elapsed_myr = galaxy_ref.sim.elapsed_myr();
+                (Some(FrameExporter::new(directory, args.export_interval, elapsed_myr)?),
+                 args.export_duration.map(|duration_myr| elapsed_myr + duration_myr))
+            },
+            None => (None, None),
+        };
+
+        // Start streaming.
+        let stream_server = match args.stream {
+            Some(address) => Some(StreamServer::new(address)?),
+            None => None,
+        };
+
+        // Start sharing snapshots.
+        let shared_snapshot = match args.shared_memory {
+            Some(path) => Some(SharedSnapshotWriter::create(path)?),
+            None => None,
+        };
+
+        // Start the control API.
+        let control_server = match args.control {
+            Some(address) => Some(ControlServer::new(&address)?),
+            None => None,
+        };
+
+        drop(galaxy_ref);
+
+        // Start writing metrics.
+        let metrics_writer = match args.metrics {
+            Some(path) => Some(MetricsWriter::create(path)?),
+            None => None,
+        };
+
+        // Start exporting the stars.
+        let arrow_exporter = match args.export_arrow {
+            Some(path) => Some(ArrowExporter::create(path)?),
+            None => None,
+        };
+
+        let counter_rotating_fraction = config.generation.disk.counter_rotating_fraction;
+        let rewind = RewindBuffer::new(&config.rewind);
+        let triggers = Triggers::new(&config.triggers);
+
+        // Load the ensemble to compare against.
+        let mut ensemble_plot = EnsemblePlot::new();
+        if !args.ensemble.is_empty() {
+            ensemble_plot.load_checkpoints(&args.ensemble, &config)?;
+        }
+
+        let comparison = Self::create_comparison(ctx,
+                                                 &config,
+                                                 seed,
+                                                 args.compare_seed,
+                                                 args.compare_config,
+                                                 args.compare_parameters)?;
+
+        Ok(Stage {
+            layers,
+            galaxy,
+            config,
+            seed,
+            start_time,
+            sim_time: start_time.elapsed().as_secs_f64(),
+            input_state: Default::default(),
+            step: 0,
+            pending_events: Vec::new(),
+            recorder,
+            replay,
+            playback,
+            checkpointer,
+            frame_exporter,
+            export_end_myr,
+            stream_server,
+            shared_snapshot,
+            snapshots: SnapshotPublisher::new(),
+            control_server,
+            scenario,
+            metrics_writer,
+            metrics_interval: args.metrics_interval.max(1),
+            arrow_exporter,
+            export_arrow_interval: args.export_arrow_interval.max(1),
+            export_tree: args.export_tree,
+            export_svg: args.export_svg,
+            svg_quadtree_cells: false,
+            selected_preset: 0,
+            counter_rotating_fraction,
+            ui_randomize: RngStreams::new(seed).stream(rng::UI_RANDOMIZE),
+            perturbations: RngStreams::new(seed).stream(rng::PERTURBATIONS),
+            perturbation_speed: 0.1,
+            generator: None,
+            rewind,
+            scrub_index: None,
+            bookmarks: Vec::new(),
+            bookmark_label: String::new(),
+            triggers,
+            last_trigger: None,
+            angular_momentum_plot: AngularMomentumPlot::new(),
+            correlation_plot: CorrelationPlot::new(),
+            velocity_plot: VelocityPlot::new(),
+            population_plot: PopulationPlot::new(),
+            energy_plot: EnergyPlot::new(),
+            log_panel: LogPanel::new(log_buffer),
+            keybindings: KeyBindings::new(),
+            flame_chart: FlameChart::new(),
+            power_spectrum_plot: PowerSpectrumPlot::new(),
+            tree_balance_panel: TreeBalancePanel::new(),
+            ensemble_plot,
+            comparison,
+            viewport: Viewport::new(window_width, window_height),
+        })
+    }
+
+    /// Create the comparison galaxy if any of the comparison arguments were given. It uses the
+    /// main galaxy's config and seed unless they're overridden.
+    fn create_comparison(ctx: &mut Context,
+                         config: &Config,
+                         seed: u64,
+                         compare_seed: Option<u64>,
+                         compare_config: Option<PathBuf>,
+                         parameters: Vec<SweepParameter>) -> Result<Option<Comparison>, Box<dyn Error>>
+    {
+        if compare_seed.is_none() && compare_config.is_none() && parameters.is_empty() {
+            return Ok(None);
+        }
+
+        let mut differences = Vec::new();
+        let mut config = match compare_config {
+            Some(path) => {
+                differences.push(path.display().to_string());
+                Config::load(&path)?
+            },
+            None => config.clone(),
+        };
+        for parameter in &parameters {
+            let value = match parameter.values.as_slice() {
+                [value] => value,
+                _ => return Err(format!("Expected one value for --compare-param {}", parameter.name).into()),
+            };
+            config = parameter.apply(&config, value)?;
+            differences.push(format!("{}={value}", parameter.name));
+        }
+        if let Some(compare_seed) = compare_seed {
+            differences.push(format!("seed {compare_seed}"));
+        }
+
+        Ok(Some(Comparison::new(ctx, config, compare_seed, seed, differences.join(", "))?))
+    }
+
+    /// The viewports the main galaxy and the comparison galaxy or the main galaxy's split screen
+    /// view are drawn in. Without either the main galaxy fills the window, and with one they're
+    /// side by side. A comparison takes the place of the split screen view.
+    fn galaxy_viewports(&self) -> (Viewport, Option<Viewport>) {
+        match self.comparison.is_some() || self.galaxy.borrow().split_view.is_some() {
+            true => {
+                let (left, right) = self.viewport.split_horizontally();
+                (left, Some(right))
+            },
+            false => (self.viewport, None),
+        }
+    }
+
+    /// Load the latest checkpoint from the given directory, if there is one.
+    fn load_latest_checkpoint(directory: &Path) -> Result<Option<Checkpoint>, Box<dyn Error>> {
+        match Checkpoint::latest(directory)? {
+            Some(path) => {
+                log::info!("Resuming from checkpoint {}", path.display());
+                Ok(Some(Checkpoint::load(path)?))
+            },
+            None => {
+                log::warn!("No checkpoint found in {}", directory.display());
+                Ok(None)
+            },
+        }
+    }
+
+    /// Recreate a galaxy from a checkpoint.
+    fn galaxy_from_checkpoint(ctx: &mut Context, checkpoint: Checkpoint) -> Result<Galaxy, Box<dyn Error>> {
+        Galaxy::from_sim(ctx, checkpoint.into_sim()?)
+    }
+
+    /// Replace the current galaxy with the one in the latest checkpoint, as with a newly generated
+    /// one.
+    fn resume_latest_checkpoint(&mut self, ctx: &mut Context) -> Result<(), Box<dyn Error>> {
+        if let Some(checkpoint) = Self::load_latest_checkpoint(&self.config.checkpoint.directory)? {
+            self.config = checkpoint.config.clone();
+            self.seed = checkpoint.seed;
+            let galaxy = Self::galaxy_from_checkpoint(ctx, checkpoint)?;
+
+            // Don't let a galaxy still being generated replace the resumed one.
+            self.generator = None;
+            self.install_galaxy(galaxy);
+        }
+        Ok(())
+    }
+
+    /// Build the checkpoint window, returning the event to apply if the user asked to resume from
+    /// the latest checkpoint.
+    fn checkpoint_ui(&mut self, ui: &imgui::Ui) -> Option<InputEvent> {
+        let mut event = None;
+
+        ui.window("Checkpoints")
+            .size([250.0, 100.0], imgui::Condition::FirstUseEver)
+            .position([370.0, 10.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.label_text("Simulated time", format!("{:.1} Myr", self.galaxy.borrow().sim.elapsed_myr()));
+
+                if let Some(checkpointer) = &mut self.checkpointer {
+                    if ui.button("Save checkpoint") {
+                        if let Err(err) = checkpointer.save(&self.galaxy.borrow().sim, self.seed) {
+                            log::error!("Failed to write checkpoint: {err}");
+                        }
+                    }
+                    ui.same_line();
+                }
+
+                if ui.button("Resume latest") {
+                    event = Some(InputEvent::ResumeCheckpoint);
+                }
+
+                if let Some(path) = &self.export_tree {
+                    if ui.button("Export quadtree") {
+                        match TreeExport::new(&self.galaxy.borrow().sim.quadtree).save(path) {
+                            Ok(()) => log::info!("Exported quadtree to {}", path.display()),
+                            Err(err) => log::error!("Failed to export quadtree: {err}"),
+                        }
+                    }
+                }
+
+                if let Some(path) = &self.export_svg {
+                    if ui.button("Export SVG") {
+                        let galaxy = self.galaxy.borrow();
+                        let (min, size) = galaxy.renderer.view_bounds();
+                        let mut export = SvgExport::new(min, size, self.config.rendering.texture_width as f64);
+                        export.quadtree_cells = self.svg_quadtree_cells;
+                        match export.save(&galaxy.sim, path) {
+                            Ok(()) => log::info!("Exported view to {}", path.display()),
+                            Err(err) => log::error!("Failed to export SVG: {err}"),
+                        }
+                    }
+                    ui.same_line();
+                    ui.checkbox("Quadtree cells", &mut self.svg_quadtree_cells);
+                }
+            });
+
+        event
+    }
+
+    /// Build the generation window, returning the event to apply if the user asked to generate a
+    /// galaxy from a preset or changed a generation parameter.
+    fn generation_ui(&mut self, ui: &imgui::Ui) -> Option<InputEvent> {
+        let mut event = None;
+
+        ui.window("Generation")
+            .size([250.0, 80.0], imgui::Condition::FirstUseEver)
+            .position([370.0, 280.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let names = Preset::ALL.map(Preset::name);
+                ui.combo_simple_string("##preset", &mut self.selected_preset, &names);
+                ui.same_line();
+                if ui.button("Generate") {
+                    event = Some(InputEvent::ApplyPreset(Preset::ALL[self.selected_preset]));
+                }
+                ui.same_line();
+                if ui.button("Random seed") {
+                    event = Some(InputEvent::RandomizeSeed);
+                }
+
+                if let Some(generator) = &self.generator {
+                    imgui::ProgressBar::new(generator.progress())
+                        .overlay_text(format!("Generating seed {}", generator.seed))
+                        .build(ui);
+                }
+
+                // Only regenerate once the slider is released, rather than on every change.
+                ui.slider("Counter-rotating", 0.0, 1.0, &mut self.counter_rotating_fraction);
+                if ui.is_item_deactivated_after_edit() {
+                    event = Some(InputEvent::SetCounterRotatingFraction(self.counter_rotating_fraction));
+                }
+
+                ui.input_scalar("Perturbation km/s", &mut self.perturbation_speed).build();
+                if ui.button("Perturb velocities") {
+                    event = Some(InputEvent::Perturb(self.perturbation_speed.max(0.0)));
+                }
+            });
+
+        event
+    }
+
+    /// Build the reference frame window, returning the event to apply if the user asked to
+    /// recenter the galaxy or changed whether it's recentered every step.
+    fn frame_ui(&mut self, ui: &imgui::Ui) -> Option<InputEvent> {
+        let mut event = None;
+
+        ui.window("Reference frame")
+            .size([250.0, 80.0], imgui::Condition::FirstUseEver)
+            .position([10.0, 320.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let mut recenter = self.galaxy.borrow().sim.config().simulation.recenter;
+                if ui.checkbox("Recenter every step", &mut recenter) {
+                    event = Some(InputEvent::SetRecenter(recenter));
+                }
+
+                if ui.button("Recenter now") {
+                    event = Some(InputEvent::Recenter);
+                }
+            });
+
+        event
+    }
+
+    /// Build the spatial acceleration window, showing how long the last step took to build and
+    /// use the selected structure, and returning the event to apply if the user selected another.
+    fn spatial_ui(&mut self, ui: &imgui::Ui) -> Option<InputEvent> {
+        let mut event = None;
+
+        ui.window("Spatial acceleration")
+            .size([250.0, 130.0], imgui::Condition::FirstUseEver)
+            .position([10.0, 520.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let galaxy = self.galaxy.borrow();
+                let spatial_backend = galaxy.sim.config().simulation.spatial_backend;
+
+                let names: Vec<&str> = SpatialBackend::ALL.iter().map(|backend| backend.name()).collect();
+                let mut selected = SpatialBackend::ALL.iter().position(|&backend| backend == spatial_backend).unwrap_or(0);
+                if ui.combo_simple_string("Backend", &mut selected, &names) {
+                    event = Some(InputEvent::SetSpatialBackend(SpatialBackend::ALL[selected]));
+                }
+
+                let timings = &galaxy.sim.timings;
+                let build_ms = match spatial_backend {
+                    SpatialBackend::Quadtree => timings.quadtree_ms + timings.mass_distribution_ms,
+                    SpatialBackend::Grid => timings.grid_ms,
+                };
+                ui.label_text("Build", format!("{build_ms:.2} ms"));
+                ui.label_text("Integrate", format!("{:.2} ms", timings.integrate_ms));
+            });
+
+        event
+    }
+
+    /// Build the rewind window, returning the event to apply if the user scrubbed through the
+    /// history or asked to resume from the state being shown.
+    fn rewind_ui(&mut self, ui: &imgui::Ui) -> Option<InputEvent> {
+        let mut event = None;
+
+        ui.window("Rewind")
+            .size([300.0, 100.0], imgui::Condition::FirstUseEver)
+            .position([10.0, 410.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                if self.rewind.is_empty() {
+                    ui.text("No history recorded yet");
+                    return;
+                }
+
+                // When not scrubbing, the latest state is the one being shown.
+                let last_index = self.rewind.len() - 1;
+                let current_index = self.scrub_index.unwrap_or(last_index);
+                let mut index = current_index;
+
+                if ui.arrow_button("back", imgui::Direction::Left) {
+                    index = index.saturating_sub(1);
+                }
+                ui.same_line();
+                if ui.arrow_button("forward", imgui::Direction::Right) {
+                    index = usize::min(index + 1, last_index);
+                }
+                ui.same_line();
+                let elapsed_myr = self.rewind.get(current_index).map_or(0.0, |state| state.elapsed_myr());
+                ui.slider_config("##history", 0, last_index)
+                    .display_format(format!("{elapsed_myr:.1} Myr"))
+                    .build(&mut index);
+
+                if index != current_index {
+                    event = Some(InputEvent::ScrubTo(index));
+                }
+
+                if self.scrub_index.is_some() && ui.button("Resume from here") {
+                    event = Some(InputEvent::SetPaused(false));
+                }
+
+                ui.text(format!("{} states, {:.1} MB", self.rewind.len(), self.rewind.used_bytes() as f64 / (1024.0 * 1024.0)));
+            });
+
+        event
+    }
+
+    /// Build the bookmarks window, returning the event to apply if the user added, jumped to or
+    /// removed a bookmark.
+    fn bookmarks_ui(&mut self, ui: &imgui::Ui) -> Option<InputEvent> {
+        let mut event = None;
+
+        ui.window("Bookmarks")
+            .size([300.0, 150.0], imgui::Condition::FirstUseEver)
+            .position([10.0, 660.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.input_text("##label", &mut self.bookmark_label)
+                    .hint("Label")
+                    .build();
+                ui.same_line();
+                if ui.button("Add") {
+                    event = Some(InputEvent::AddBookmark);
+                }
+
+                for (i, bookmark) in self.bookmarks.iter().enumerate() {
+                    let _id = ui.push_id_usize(i);
+                    if ui.button("Go") {
+                        event = Some(InputEvent::JumpToBookmark(i));
+                    }
+                    ui.same_line();
+                    if ui.button("X") {
+                        event = Some(InputEvent::RemoveBookmark(i));
+                    }
+                    ui.same_line();
+                    ui.text(format!("{} ({:.1} Myr)", bookmark.label, bookmark.state.elapsed_myr()));
+                }
+            });
+
+        event
+    }
+
+    /// Bookmark the current state of the simulation with the label entered in the UI.
+    fn add_bookmark(&mut self) {
+        let label = match self.bookmark_label.trim() {
+            "" => format!("Bookmark {}", self.bookmarks.len() + 1),
+            label => label.to_string(),
+        };
+        self.bookmark_label.clear();
+
+        log::info!("Bookmarking {label}");
+        self.bookmarks.push(Bookmark::new(label, &self.galaxy.borrow().sim));
+    }
+
+    /// Pause the simulation and restore a bookmarked state. The states recorded for rewinding
+    /// after it are discarded, as they won't happen once it's resumed.
+    fn jump_to_bookmark(&mut self, index: usize) {
+        let mut galaxy = self.galaxy.borrow_mut();
+        let bookmark = match self.bookmarks.get(index) {
+            Some(bookmark) => bookmark,
+            None => return,
+        };
+
+        match bookmark.state.to_sim(&galaxy.sim) {
+            Ok(sim) => {
+                galaxy.sim = sim;
+                galaxy.paused = true;
+                self.rewind.discard_after(bookmark.state.elapsed_time);
+                self.scrub_index = None;
+            },
+            Err(err) => log::error!("Failed to restore bookmark: {err}"),
+        }
+    }
+
+    /// Slow the simulation down if auto slowdown is enabled and the last step was too large for
+    /// the integration to be reliable.
+    fn auto_slowdown(&mut self) {
+        let mut galaxy = self.galaxy.borrow_mut();
+        if galaxy.auto_slowdown && !galaxy.paused && !galaxy.sim.safety.is_reliable(&galaxy.sim.config().simulation) {
+            galaxy.sim.myr_per_second *= AUTO_SLOWDOWN_FACTOR;
+        }
+    }
+
+    /// Build the window for the conditions that automatically pause the simulation.
+    fn triggers_ui(&mut self, ui: &imgui::Ui) {
+        ui.window("Pause triggers")
+            .size([300.0, 130.0], imgui::Condition::FirstUseEver)
+            .position([1250.0, 10.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let config = &mut self.triggers.config;
+                ui.input_scalar("Close encounter (pc)", &mut config.close_encounter_pc).build();
+                ui.checkbox("Escape", &mut config.escape);
+                ui.input_scalar("Energy drift (%)", &mut config.energy_drift_percent).build();
+
+                match &self.last_trigger {
+                    Some((elapsed_myr, event)) => ui.text_wrapped(format!("{elapsed_myr:.1} Myr: {event}")),
+                    None => ui.text("Not triggered yet"),
+                }
+            });
+    }
+
+    /// Check the pause triggers, pausing the simulation and focusing the camera on where the
+    /// event happened if any of them are met.
+    fn check_triggers(&mut self) {
+        let events = self.triggers.check(&self.galaxy.borrow().sim);
+        for event in &events {
+            log::info!("Pause triggered: {event}");
+        }
+
+        // Prefer events that happened somewhere the camera can focus on.
+        let event = match events.iter().find(|event| event.star().is_some()).or(events.first()) {
+            Some(&event) => event,
+            None => return,
+        };
+
+        let mut galaxy = self.galaxy.borrow_mut();
+        galaxy.paused = true;
+        if let Some(star) = event.star() {
+            galaxy.focus_on_star(star);
+        }
+        self.last_trigger = Some((galaxy.sim.elapsed_myr(), event));
+    }
+
+    /// Pause the simulation and restore a state from the rewind buffer.
+    fn scrub_to(&mut self, index: usize) {
+        let mut galaxy = self.galaxy.borrow_mut();
+        let state = match self.rewind.get(index) {
+            Some(state) => state,
+            None => return,
+        };
+
+        match state.to_sim(&galaxy.sim) {
+            Ok(sim) => {
+                galaxy.sim = sim;
+                galaxy.paused = true;
+                self.scrub_index = Some(index);
+            },
+            Err(err) => log::error!("Failed to restore state from the rewind buffer: {err}"),
+        }
+    }
+
+    /// Show a frame of the trajectory being played back.
+    fn show_playback_frame(&mut self, index: usize) {
+        let frame = match self.playback.as_ref().and_then(|playback| playback.trajectory.frames.get(index)) {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        match frame.to_sim(&self.config) {
+            Ok(sim) => {
+                let mut galaxy = self.galaxy.borrow_mut();
+                galaxy.sim = sim;
+                galaxy.paused = true;
+            },
+            Err(err) => log::error!("Failed to show trajectory frame {index}: {err}"),
+        }
+    }
+
+    /// Replace the galaxy with a newly generated one, using the current seed and config. The galaxy
+    /// is generated in the background and swapped in once it's ready, unless the session needs to
+    /// be reproducible because it's being recorded, replayed or exported, as the step it would be
+    /// swapped in at depends on how long it takes to generate.
+    fn regenerate_galaxy(&mut self, ctx: &mut Context) {
+        let reproducible = self.recorder.is_some() || self.replay.is_some() || self.frame_exporter.is_some();
+        match reproducible {
+            true => {
+                self.generator = None;
+                let galaxy = Self::generate_galaxy(ctx, &self.config, self.seed).unwrap();
+                self.install_galaxy(galaxy);
+            },
+            false => self.generator = Some(GalaxyGenerator::start(&self.config, self.seed)),
+        }
+    }
+
+    /// Swap in the galaxy being generated in the background, if it's finished.
+    fn poll_generator(&mut self, ctx: &mut Context) {
+        let result = match self.generator.as_ref().and_then(GalaxyGenerator::try_finish) {
+            Some(result) => result,
+            None => return,
+        };
+        self.generator = None;
+
+        match result.and_then(|sim| Galaxy::from_sim(ctx, sim)) {
+            Ok(galaxy) => self.install_galaxy(galaxy),
+            Err(err) => log::error!("Failed to generate galaxy: {err}"),
+        }
+    }
+
+    /// Replace the galaxy with a newly generated or resumed one, running the scenario script's init
+    /// hook on it and resetting everything that tracked the old one.
+    fn install_galaxy(&mut self, galaxy: Galaxy) {
+        *self.galaxy.borrow_mut() = galaxy;
+        self.run_scenario(Scenario::init);
+
+        // Restart the comparison too, so that the two stay in sync.
+        if let Some(comparison) = &mut self.comparison {
+            if let Err(err) = comparison.restart(self.seed) {
+                log::error!("Failed to regenerate comparison galaxy: {err}");
+            }
+        }
+        self.rewind.clear();
+        self.scrub_index = None;
+        self.bookmarks.clear();
+        self.triggers.reset();
+        self.last_trigger = None;
+        self.angular_momentum_plot.reset();
+        self.correlation_plot.reset();
+        self.velocity_plot.reset();
+        self.population_plot.reset();
+        self.energy_plot.reset();
+        self.power_spectrum_plot.reset();
+        self.tree_balance_panel.reset();
+
+        if let Some(checkpointer) = &mut self.checkpointer {
+            checkpointer.reset(self.galaxy.borrow().sim.elapsed_myr());
+        }
+    }
+
+    fn generate_galaxy(ctx: &mut Context, config: &Config, seed: u64) -> Result<Galaxy, Box<dyn Error>> {
+        log::info!("Generating galaxy with seed {seed}");
+
+        let galaxy = Galaxy::new(ctx, config, &RngStreams::new(seed))?;
+
+        // Print out quadtree for debugging.
+        galaxy.sim.quadtree.walk_nodes(|index@HilbertIndex(_, depth), node| {
+            let indentation: String = repeat(' ').take(depth as usize * 2).collect();
+            log::debug!("{indentation}{index:?} {node:?}");
+        });
+
+        Ok(galaxy)
+    }
+
+    /// Run a scenario script hook on the galaxy, disabling the script if it fails.
+    fn run_scenario<F>(&mut self, hook: F)
+        where F: FnOnce(&mut Scenario, &mut GalaxySim) -> Result<(), Box<dyn Error>>
+    {
+        if let Some(scenario) = &mut self.scenario {
+            if let Err(err) = hook(scenario, &mut self.galaxy.borrow_mut().sim) {
+                log::error!("Scenario script failed, disabling it: {err}");
+                self.scenario = None;
+            }
+        }
+    }
+
+    /// Measure the simulation and write the metrics, stopping if writing fails.
+    fn write_metrics(&mut self) {
+        if let Some(metrics_writer) = &mut self.metrics_writer {
+            let metrics = StepMetrics::measure(&self.galaxy.borrow().sim, self.step);
+            if let Err(err) = metrics_writer.write(&metrics).and_then(|_| metrics_writer.flush()) {
+                log::error!("Failed to write metrics, stopping: {err}");
+                self.metrics_writer = None;
+            }
+        }
+    }
+
+    /// Queue a live input event to be applied in the next fixed update. Live input is ignored while
+    /// a session is being replayed.
+    fn push_event(&mut self, event: InputEvent) {
+        if self.replay.is_none() {
+            self.pending_events.push(event);
+        }
+    }
+
+    /// Record an event applied in the current fixed update, if the session is being recorded.
+    fn record_event(&mut self, event: InputEvent) {
+        if let Some(recorder) = &mut self.recorder {
+            let time = self.start_time.elapsed().as_secs_f64();
+            if let Err(err) = recorder.record(self.step, time, event) {
+                log::error!("Failed to record session event, stopping recording: {err}");
+                self.recorder = None;
+            }
+        }
+    }
+
+    /// Record an event that happened after the current fixed update's step ran, such as a pause by
+    /// a trigger, if the session is being recorded. Replayed events are applied at the start of the
+    /// step they're recorded for, so it's recorded for the next one.
+    fn record_event_after_step(&mut self, event: InputEvent) {
+        if let Some(recorder) = &mut self.recorder {
+            let time = self.start_time.elapsed().as_secs_f64();
+            if let Err(err) = recorder.record_after_step(self.step, time, event) {
+                log::error!("Failed to record session event, stopping recording: {err}");
+                self.recorder = None;
+            }
+        }
+    }
+
+    /// Apply an input event to the input state or the simulation.
+    fn apply_event(&mut self, ctx: &mut Context, event: InputEvent) {
+        match event {
+            InputEvent::MouseMotion { x, y } => {
+                let (old_x, old_y) = self.input_state.mouse_pos;
+                let (cur_dx, cur_dy) = self.input_state.mouse_diff;
+
+                self.input_state.mouse_pos = (x, y);
+                self.input_state.mouse_diff = (cur_dx + (x - old_x), cur_dy + (y - old_y));
+            },
+            InputEvent::MouseWheel { dy } => {
+                self.input_state.mouse_wheel_dy += dy;
+            },
+            InputEvent::MouseButton { button, down } => {
+                let button_state = match button {
+                    Button::Left => &mut self.input_state.left_mouse_button_down,
+                    Button::Right => &mut self.input_state.right_mouse_button_down,
+                    Button::Middle => &mut self.input_state.middle_mouse_button_down,
+                };
+                *button_state = down;
+            },
+            InputEvent::RegenerateGalaxy => {
+                log::info!("Key pressed, regenerating galaxy");
+                self.seed += 1;
+                self.regenerate_galaxy(ctx);
+            },
+            InputEvent::ApplyPreset(preset) => {
+                log::info!("Generating galaxy from preset {preset}");
+                preset.apply(&mut self.config);
+                self.counter_rotating_fraction = self.config.generation.disk.counter_rotating_fraction;
+                self.regenerate_galaxy(ctx);
+            },
+            InputEvent::SetCounterRotatingFraction(fraction) => {
+                log::info!("Regenerating galaxy with counter-rotating fraction {fraction}");
+                self.config.generation.disk.counter_rotating_fraction = fraction;
+                self.counter_rotating_fraction = fraction;
+                self.regenerate_galaxy(ctx);
+            },
+            InputEvent::RandomizeSeed => {
+                self.seed = self.ui_randomize.gen();
+                log::info!("Regenerating galaxy with random seed {}", self.seed);
+                self.regenerate_galaxy(ctx);
+            },
+            InputEvent::ResumeCheckpoint => {
+                if let Err(err) = self.resume_latest_checkpoint(ctx) {
+                    log::error!("Failed to resume from checkpoint: {err}");
+                }
+            },
+            InputEvent::Perturb(speed) => {
+                log::info!("Perturbing star velocities by {speed} km/s");
+                self.galaxy.borrow_mut().sim.perturb(&mut self.perturbations, speed);
+            },
+            InputEvent::IncreaseRate => {
+                self.galaxy.borrow_mut().sim.myr_per_second *= 10.0;
+            },
+            InputEvent::DecreaseRate => {
+                self.galaxy.borrow_mut().sim.myr_per_second /= 10.0;
+            },
+            InputEvent::SetRate(myr_per_second) => {
+                self.galaxy.borrow_mut().sim.myr_per_second = myr_per_second;
+            },
+            InputEvent::SetPaused(paused) => {
+                self.galaxy.borrow_mut().paused = paused;
+            },
+            InputEvent::SetOpeningAngle(opening_angle) => {
+                self.galaxy.borrow_mut().sim.set_opening_angle(opening_angle);
+            },
+            InputEvent::SetSpatialBackend(spatial_backend) => {
+                log::info!("Calculating gravity with the {}", spatial_backend.name().to_lowercase());
+                self.galaxy.borrow_mut().sim.set_spatial_backend(spatial_backend);
+            },
+            InputEvent::Recenter => {
+                self.galaxy.borrow_mut().sim.recenter();
+            },
+            InputEvent::SetRecenter(recenter) => {
+                self.galaxy.borrow_mut().sim.set_recenter(recenter);
+            },
+            InputEvent::ScrubTo(index) => {
+                self.scrub_to(index);
+            },
+            InputEvent::AddBookmark => {
+                self.add_bookmark();
+            },
+            InputEvent::JumpToBookmark(index) => {
+                self.jump_to_bookmark(index);
+            },
+            InputEvent::RemoveBookmark(index) => {
+                if index < self.bookmarks.len() {
+                    self.bookmarks.remove(index);
+                }
+            },
+            InputEvent::EditStar { index, edit } => {
+                self.galaxy.borrow_mut().edit_star(index, edit);
+            },
+        }
+    }
+
+    /// Convert a control command to the input event that applies it, if it changes the simulation.
+    fn control_event(command: &ControlCommand) -> Option<InputEvent> {
+        match *command {
+            ControlCommand::Pause => Some(InputEvent::SetPaused(true)),
+            ControlCommand::Resume => Some(InputEvent::SetPaused(false)),
+            ControlCommand::SetRate(myr_per_second) => Some(InputEvent::SetRate(myr_per_second)),
+            ControlCommand::SetOpeningAngle(opening_angle) => Some(InputEvent::SetOpeningAngle(opening_angle)),
+            ControlCommand::Snapshot | ControlCommand::Status => None,
+        }
+    }
+
+    /// Build the response to a control command, once any input event for it has been applied.
+    fn control_response(&mut self, command: &ControlCommand, replaying: bool) -> ControlResponse {
+        match command {
+            ControlCommand::Status => ControlResponse::ok(self.status()),
+            ControlCommand::Snapshot => match self.write_snapshot() {
+                Ok(path) => ControlResponse::ok(json!({ "path": path.display().to_string() })),
+                Err(err) => ControlResponse::error(500, &err.to_string()),
+            },
+            _ if replaying => ControlResponse::error(409, "The simulation can't be controlled while replaying a session"),
+            _ => ControlResponse::ok(self.status()),
+        }
+    }
+
+    /// Diagnostics about the current state of the simulation.
+    fn status(&self) -> serde_json::Value {
+        let galaxy = self.galaxy.borrow();
+        let sim = &galaxy.sim;
+        json!({
+            "step": self.step,
+            "seed": self.seed,
+            "elapsed_myr": sim.elapsed_myr(),
+            "myr_per_second": sim.myr_per_second,
+            "opening_angle": sim.config().simulation.opening_angle,
+            "paused": galaxy.paused,
+            "star_count": sim.stars().len(),
+        })
+    }
+
+    /// Write a snapshot of the simulation to the checkpoint directory, returning its path.
+    fn write_snapshot(&self) -> Result<PathBuf, Box<dyn Error>> {
+        let directory = &self.config.checkpoint.directory;
+        std::fs::create_dir_all(directory)?;
+
+        let path = directory.join(format!("snapshot_{}.json", self.step));
+        log::info!("Writing snapshot to {}", path.display());
+        Checkpoint::from_sim(&self.galaxy.borrow().sim, self.seed).save(&path)?;
+
+        Ok(path)
+    }
+
+    /// Advance the simulation by one fixed timestep, applying input and running everything that
+    /// happens once per step. The UI is built separately, once per frame, in `build_ui`.
+    fn fixed_update(&mut self, ctx: &mut Context) {
+        profile_scope!("Fixed update");
+
+        // Gather the input events for this step, either from the replay or from live input.
+        let mut events = match &mut self.replay {
+            Some(replay) => {
+                let events = replay.events_for_step(self.step);
+                if replay.is_finished() {
+                    log::info!("Session replay finished");
+                    self.replay = None;
+                }
+                events
+            },
+            None => std::mem::take(&mut self.pending_events),
+        };
+
+        // Remote control commands are applied as input events so that they get recorded.
+        let control_requests = match &self.control_server {
+            Some(control_server) => control_server.poll(),
+            None => Vec::new(),
+        };
+        let replaying = self.replay.is_some();
+        if !replaying {
+            events.extend(control_requests.iter().filter_map(|request| Self::control_event(&request.command)));
+        }
+
+        // Apply and record input events.
+        for event in events {
+            self.record_event(event.clone());
+            self.apply_event(ctx, event);
+        }
+
+        for request in control_requests {
+            let response = self.control_response(&request.command, replaying);
+            request.respond(response);
+        }
+
+        // Swap in a galaxy generated in the background once it's ready.
+        self.poll_generator(ctx);
+
+        // When playing back a trajectory its frames are shown in turn, and the simulation is kept
+        // paused so the physics never runs.
+        if let Some(playback) = &mut self.playback {
+            if let Some(frame) = playback.advance(FIXED_TIMESTEP) {
+                self.show_playback_frame(frame);
+            }
+            self.galaxy.borrow_mut().paused = true;
+        }
+
+        // Update drawables.
+        let myr_per_second = self.galaxy.borrow().sim.myr_per_second;
+        let paused = self.galaxy.borrow().paused;
+
+        // Slow down while the integration is unreliable. This happens before the step, so the new
+        // rate is recorded for it, and recorded sessions already contain it, so it isn't done when
+        // replaying.
+        if self.replay.is_none() {
+            self.auto_slowdown();
+        }
+        let myr_per_second = {
+            let new_myr_per_second = self.galaxy.borrow().sim.myr_per_second;
+            if new_myr_per_second != myr_per_second {
+                self.record_event(InputEvent::SetRate(new_myr_per_second));
+            }
+            new_myr_per_second
+        };
+        let (galaxy_viewport, second_viewport) = self.galaxy_viewports();
+        self.layers.fixed_update(ctx, &self.input_state, &galaxy_viewport, FIXED_TIMESTEP);
+
+        // Step the comparison galaxy alongside the main one, or otherwise update the split screen
+        // view's camera.
+        match (&mut self.comparison, second_viewport) {
+            (Some(comparison), _) => comparison.update(&self.galaxy.borrow(), FIXED_TIMESTEP),
+            (None, Some(split_viewport)) => self.galaxy.borrow_mut().update_split_view(&self.input_state, &split_viewport),
+            (None, None) => {},
+        }
+
+        // Pause if any of the triggers are met. Recorded sessions already contain the pauses they
+        // caused, applied at the start of the next step, so they aren't checked when replaying.
+        if !self.galaxy.borrow().paused && self.replay.is_none() {
+            self.check_triggers();
+        }
+
+        // Run the scenario script's per-step hook.
+        if !self.galaxy.borrow().paused {
+            self.run_scenario(Scenario::on_step);
+        }
+
+        // Record the state for rewinding. If the simulation was unpaused while scrubbing, it's
+        // resumed from the state being shown, and the states after it are discarded.
+        if !self.galaxy.borrow().paused {
+            if let Some(index) = self.scrub_index.take() {
+                self.rewind.truncate(index);
+            }
+            self.rewind.record(&self.galaxy.borrow().sim);
+        }
+
+        // Track the galaxy's angular momentum, structure, velocities, population and energy.
+        if !self.galaxy.borrow().paused {
+            self.angular_momentum_plot.record(&self.galaxy.borrow().sim);
+            self.correlation_plot.record(&self.galaxy.borrow().sim);
+            self.velocity_plot.record(&self.galaxy.borrow().sim);
+            self.population_plot.record(&self.galaxy.borrow().sim);
+            self.energy_plot.record(&self.galaxy.borrow().sim);
+            self.power_spectrum_plot.record(&self.galaxy.borrow().sim);
+            self.tree_balance_panel.record(&self.galaxy.borrow().sim);
+        }
+
+        // Write metrics for the step.
+        if !self.galaxy.borrow().paused && self.step % self.metrics_interval == 0 {
+            self.write_metrics();
+        }
+
+        // Export the stars for the step.
+        if !self.galaxy.borrow().paused && self.step % self.export_arrow_interval == 0 {
+            if let Some(arrow_exporter) = &mut self.arrow_exporter {
+                if let Err(err) = arrow_exporter.write(&self.galaxy.borrow().sim, self.step) {
+                    log::error!("Failed to export stars as Arrow, stopping: {err}");
+                    self.arrow_exporter = None;
+                }
+            }
+        }
+
+        // Publish a snapshot of the new state, and stream and share it.
+        self.snapshots.publish(&self.galaxy.borrow().sim, self.step);
+        if let Some(stream_server) = &self.stream_server {
+            stream_server.publish(&self.snapshots.latest());
+        }
+        if let Some(shared_snapshot) = &mut self.shared_snapshot {
+            if let Err(err) = shared_snapshot.publish(&self.snapshots.latest()) {
+                log::error!("Failed to write shared snapshot, stopping sharing: {err}");
+                self.shared_snapshot = None;
+            }
+        }
+
+        // Write a checkpoint if it's time to.
+        if let Some(checkpointer) = &mut self.checkpointer {
+            checkpointer.update(&self.galaxy.borrow().sim, self.seed);
+        }
+
+        // Export a frame if it's time to, and quit once the export is finished.
+        if let Some(frame_exporter) = &mut self.frame_exporter {
+            if let Err(err) = frame_exporter.update(&self.galaxy.borrow()) {
+                log::error!("Failed to export frame, stopping export: {err}");
+                self.frame_exporter = None;
+            }
+            else if self.export_end_myr.is_some_and(|end_myr| self.galaxy.borrow().sim.elapsed_myr() >= end_myr) {
+                log::info!("Exported {} frames, quitting", frame_exporter.frame_count());
+                ctx.quit();
+            }
+        }
+
+        // The rate and pause state can also be changed by triggers and scenario scripts after the
+        // step has run, so make sure that gets recorded too, for the next step.
+        let (new_myr_per_second, new_paused) = {
+            let galaxy = self.galaxy.borrow();
+            (galaxy.sim.myr_per_second, galaxy.paused)
+        };
+        if new_myr_per_second != myr_per_second {
+            self.record_event_after_step(InputEvent::SetRate(new_myr_per_second));
+        }
+        if new_paused != paused {
+            self.record_event_after_step(InputEvent::SetPaused(new_paused));
+        }
+
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(err) = recorder.flush() {
+                log::error!("Failed to flush session recording: {err}");
+            }
+        }
+
+        // Clear relative moevments from input state.
+        self.input_state.mouse_diff = (0.0, 0.0);
+        self.input_state.mouse_wheel_dy = 0.0;
+
+        self.step += 1;
+    }
+
+    /// Convert a miniquad mouse button to a recordable one.
+    fn button(button: MouseButton) -> Button {
+        match button {
+            MouseButton::Left => Button::Left,
+            MouseButton::Right => Button::Right,
+            _ => Button::Middle,
+        }
+    }
+}
+
+impl UiBuilder for Stage {
+    /// Build the UI for this frame. Anything it changes that needs to be recorded is applied in the
+    /// next step as an input event.
+    fn build_ui(&mut self, ctx: &mut Context, ui: &imgui::Ui) {
+        let (myr_per_second, paused) = {
+            let galaxy = self.galaxy.borrow();
+            (galaxy.sim.myr_per_second, galaxy.paused)
+        };
+
+        self.layers.ui(ctx, ui);
+
+        // The rate and pause state can be changed in the galaxy's window.
+        let (new_myr_per_second, new_paused) = {
+            let galaxy = self.galaxy.borrow();
+            (galaxy.sim.myr_per_second, galaxy.paused)
+        };
+        if new_myr_per_second != myr_per_second {
+            self.push_event(InputEvent::SetRate(new_myr_per_second));
+        }
+        if new_paused != paused {
+            self.push_event(InputEvent::SetPaused(new_paused));
+        }
+        if let Some(event) = self.galaxy.borrow_mut().star_edit.take() {
+            self.push_event(event);
+        }
+
+        if let Some(comparison) = &mut self.comparison {
+            comparison.ui(ui, &self.galaxy.borrow(), self.seed);
+        }
+        self.angular_momentum_plot.ui(ui);
+        self.correlation_plot.ui(ui);
+        self.velocity_plot.ui(ui);
+        self.population_plot.ui(ui);
+        self.energy_plot.ui(ui);
+        self.log_panel.ui(ui);
+        self.keybindings.overlay_ui(ui);
+        self.flame_chart.ui(ui);
+        self.power_spectrum_plot.ui(ui);
+        self.tree_balance_panel.ui(ui, &self.galaxy.borrow().sim);
+        self.ensemble_plot.ui(ui,
+                              &self.config,
+                              self.seed,
+                              self.galaxy.borrow().sim.elapsed_myr(),
+                              self.config.simulation.initial_myr_per_second * FIXED_TIMESTEP);
+        self.triggers_ui(ui);
+
+        if let Some(frame) = self.playback.as_mut().and_then(|playback| playback.ui(ui)) {
+            self.show_playback_frame(frame);
+        }
+
+        // Resuming from a checkpoint and changes to generation, the reference frame, the spatial
+        // backend, the rewind buffer and bookmarks made in the UI are applied in the next step, so
+        // that they get recorded.
+        let ui_events = [self.checkpoint_ui(ui), self.generation_ui(ui), self.frame_ui(ui), self.spatial_ui(ui), self.rewind_ui(ui), self.bookmarks_ui(ui)];
+        for event in ui_events.into_iter().flatten() {
+            self.push_event(event);
+        }
+    }
+}
+
+impl<'a> EventHandler for Stage {
+    fn update(&mut self, ctx: &mut Context) {
+        // The frame's profile covers its updates and drawing.
+        profiler::begin_frame();
+
+        // Update timer.
+        let time_since_start = self.start_time.elapsed().as_secs_f64();
+
+        // When exporting frames the simulation runs independently of real time, stepping once per
+        // rendered frame.
+        if self.frame_exporter.is_some() {
+            self.sim_time = time_since_start;
+            self.fixed_update(ctx);
+            return;
+        }
+
+        // Otherwise step as many times as it takes to catch up with real time, so the simulation
+        // doesn't slow down when drawing does. If it still can't keep up, the time it's behind by
+        // is dropped rather than leaving it further and further behind.
+        let mut steps = 0;
+        while self.sim_time + FIXED_TIMESTEP < time_since_start {
+            if steps == MAX_CATCH_UP_STEPS {
+                log::debug!("Simulation fell {:.3}s behind, skipping ahead",
+                            time_since_start - self.sim_time);
+                self.sim_time = time_since_start;
+                break;
+            }
+
+            self.sim_time += FIXED_TIMESTEP;
+            self.fixed_update(ctx);
+            steps += 1;
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context) {
+        let draw = profile_span!("Draw");
+
+        // Draw the galaxy between its last two steps by how far real time is between them.
+        let alpha = match self.frame_exporter.is_some() {
+            true => 1.0,
+            false => ((self.start_time.elapsed().as_secs_f64() - self.sim_time) / FIXED_TIMESTEP).clamp(0.0, 1.0),
+        };
+
+        // Render anything supersampled offscreen before the frame's render pass begins.
+        {
+            profile_scope!("Offscreen");
+            self.layers.render_offscreen(ctx, alpha);
+            if let Some(comparison) = &mut self.comparison {
+                comparison.galaxy.render_offscreen(ctx, alpha);
+            }
+        }
+
+        ctx.begin_default_pass(Default::default());
+
+        // Draw drawables, and the comparison galaxy or the split screen view beside them if there
+        // is one.
+        let (galaxy_viewport, second_viewport) = self.galaxy_viewports();
+        galaxy_viewport.apply(ctx);
+        self.layers.render(ctx, alpha);
+
+        if let Some(second_viewport) = second_viewport {
+            second_viewport.apply(ctx);
+            match &mut self.comparison {
+                Some(comparison) => comparison.galaxy.render(ctx, alpha),
+                None => self.galaxy.borrow_mut().render_split_view(ctx, alpha),
+            }
+        }
+
+        // Draw the close-up of the locked star over the corner of the main view.
+        self.galaxy.borrow_mut().render_inset(ctx, alpha, &galaxy_viewport);
+
+        ctx.end_render_pass();
+        ctx.commit_frame();
+
+        draw.finish();
+        profiler::end_frame();
+    }
+
+    fn key_down_event(&mut self, ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods, _repeat: bool) {
+        match self.keybindings.action(keycode) {
+            Some(Action::Quit) => ctx.quit(),
+            Some(Action::RegenerateGalaxy) => self.push_event(InputEvent::RegenerateGalaxy),
+            Some(Action::IncreaseRate) => self.push_event(InputEvent::IncreaseRate),
+            Some(Action::DecreaseRate) => self.push_event(InputEvent::DecreaseRate),
+            Some(Action::ToggleShortcuts) => self.keybindings.show_overlay = !self.keybindings.show_overlay,
+            None => {},
+        }
+    }
+
+    fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) {
+        self.viewport.resize(width, height);
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        self.push_event(InputEvent::MouseWheel { dy: y });
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.push_event(InputEvent::MouseMotion { x, y });
+    }
+
+    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, _x: f32, _y: f32) {
+        self.push_event(InputEvent::MouseButton { button: Self::button(button), down: false });
+    }
+
+    fn mouse_button_down_event(&mut self, _ctx: &mut Context, button: MouseButton, _x: f32, _y: f32) {
+        self.push_event(InputEvent::MouseButton { button: Self::button(button), down: true });
+    }
+}
+
+/// Run a parameter sweep headlessly for the batch subcommand.
+fn run_batch(config: Config, args: BatchArgs) -> Result<(), Box<dyn Error>> {
+    let seeds = match args.seeds.is_empty() {
+        true => vec![config.generation.seed],
+        false => args.seeds,
+    };
+    let step_myr = args.step_myr.unwrap_or(config.simulation.initial_myr_per_second * FIXED_TIMESTEP);
+    let jobs = args.jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |jobs| jobs.get()));
+
+    let sweep = Sweep {
+        config,
+        seeds,
+        parameters: args.parameters,
+        duration_myr: args.duration,
+        step_myr,
+    };
+    sweep.run(&args.output, jobs)?;
+
+    log::info!("Wrote sweep results to {}", args.output.display());
+    Ok(())
+}
+
+/// Record a trajectory headlessly for the record subcommand.
+fn run_record(config: Config, args: RecordArgs) -> Result<(), Box<dyn Error>> {
+    let seed = args.seed.unwrap_or(config.generation.seed);
+    let step_myr = args.step_myr.unwrap_or(config.simulation.initial_myr_per_second * FIXED_TIMESTEP);
+    trajectory::record(&config, seed, args.duration, step_myr, args.interval, &args.output, args.arrow.as_deref())?;
+
+    log::info!("Wrote trajectory to {}", args.output.display());
+    Ok(())
+}
+
+fn main() {
+    // Initialize logging, to the terminal and the log panel.
+    let log_buffer = log_panel::init();
+    log::info!("Hello!");
+
+    // Parse command line arguments and load config.
+    let args = Args::parse();
+    let mut config = Config::load(&args.config).unwrap();
+    if let Some(preset) = args.preset {
+        preset.apply(&mut config);
+    }
+
+    // Subcommands run headlessly instead of opening the viewer.
+    match args.command {
+        Some(Command::Batch(batch_args)) => {
+            if let Err(err) = run_batch(config, batch_args) {
+                log::error!("Batch run failed: {err}");
+                std::process::exit(1);
+            }
+            return;
+        },
+        Some(Command::Record(record_args)) => {
+            if let Err(err) = run_record(config, record_args) {
+                log::error!("Recording failed: {err}");
+                std::process::exit(1);
+            }
+            return;
+        },
+        None => {},
+    }
+
+    // Create window config.
+    let window_config = conf::Conf {
+        window_title: "Galaxy".to_owned(),
+        window_width: WINDOW_WIDTH,
+        window_height: WINDOW_HEIGHT,
+        ..Default::default()
+    };
+
+    miniquad::start(window_config, move |mut ctx: &mut GraphicsContext| {
+        let imgui_renderer = drawable::ImguiRenderer::new(&mut ctx);
+        let stage = Stage::new(&mut ctx, config, args, log_buffer).unwrap();
+
+        Box::new(UiStage::new(stage, imgui_renderer))
+    });
+}
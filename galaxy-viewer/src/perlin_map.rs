@@ -0,0 +1,56 @@
+use std::error::Error;
+
+use galaxy_core::config::GenerationConfig;
+use galaxy_core::density::DensityField;
+use galaxy_core::types::Vec2d;
+use miniquad::Context;
+
+use crate::drawable::*;
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+/// A structure representing the rendering of the perlin noise density field used to place stars,
+/// covering the square the galaxy is generated in.
+pub struct PerlinMap {
+    textured_quad: TexturedQuad,
+}
+
+impl PerlinMap {
+    /// Create a new perlin map that renders via the given miniquad context.
+    pub fn new(ctx: &mut Context, config: &GenerationConfig) -> Result<Self, Box<dyn Error>> {
+        const WIDTH: usize = 128;
+        const HEIGHT: usize = 128;
+
+        let textured_quad = TexturedQuad::new(ctx, WIDTH, HEIGHT)?;
+
+        let density = DensityField::new(&config.density);
+        let galaxy_radius = config.galaxy_radius();
+
+        let data = (0..HEIGHT).flat_map(|y| (0..WIDTH).map(move |x| (x, y))).flat_map(|(x, y)| {
+            let position = Vec2d::new((x as f64 / WIDTH as f64 * 2.0 - 1.0) * galaxy_radius,
+                                      (y as f64 / HEIGHT as f64 * 2.0 - 1.0) * galaxy_radius);
+            let sample = (density.sample(position) * 255.0) as u8;
+            [sample, sample, sample, 0xFF]
+        }).collect::<Vec<u8>>();
+
+        textured_quad.texture.update(ctx, &data);
+
+        Ok(Self {
+            textured_quad,
+        })
+    }
+}
+
+impl Drawable for PerlinMap {
+    /// Update the perlin map.
+    fn fixed_update(&mut self,
+                    _ctx: &mut Context,
+                    input_state: &InputState,
+                    _viewport: &Viewport,
+                    _time_delta: f64) {}
+
+    /// Draw the perlin map.
+    fn render(&mut self, ctx: &mut Context, _alpha: f64) {
+        self.textured_quad.draw(ctx);
+    }
+}
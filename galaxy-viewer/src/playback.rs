@@ -0,0 +1,108 @@
+use galaxy_core::trajectory::Trajectory;
+
+/// The range of playback speeds, in frames of the trajectory per second.
+const MIN_FRAMES_PER_SECOND: f32 = 0.5;
+const MAX_FRAMES_PER_SECOND: f32 = 60.0;
+
+/// Plays back a recorded trajectory, choosing which of its frames to show as time passes or as the
+/// user scrubs through it. The physics isn't run, the frames are just shown in turn.
+pub struct Playback {
+    pub trajectory: Trajectory,
+
+    /// The index of the frame being shown.
+    frame: usize,
+
+    playing: bool,
+    frames_per_second: f32,
+
+    /// The real time since the current frame was shown, in seconds.
+    frame_time: f64,
+
+    /// Whether to start again from the first frame after the last.
+    looping: bool,
+}
+
+impl Playback {
+    /// Start playing back a trajectory from its first frame.
+    pub fn new(trajectory: Trajectory) -> Self {
+        Self {
+            trajectory,
+            frame: 0,
+            playing: true,
+            frames_per_second: 10.0,
+            frame_time: 0.0,
+            looping: false,
+        }
+    }
+
+    /// Advance playback by a time in seconds of real time, returning the frame to show if it
+    /// changed.
+    pub fn advance(&mut self, time_delta: f64) -> Option<usize> {
+        if !self.playing {
+            return None;
+        }
+
+        self.frame_time += time_delta;
+        let frame_seconds = 1.0 / self.frames_per_second as f64;
+        if self.frame_time < frame_seconds {
+            return None;
+        }
+        self.frame_time -= frame_seconds;
+
+        let last_frame = self.trajectory.frames.len() - 1;
+        match (self.frame < last_frame, self.looping) {
+            (true, _) => self.frame += 1,
+            (false, true) => self.frame = 0,
+            (false, false) => {
+                self.playing = false;
+                return None;
+            },
+        }
+        Some(self.frame)
+    }
+
+    /// Build the playback window, returning the frame to show if the user scrubbed to another one.
+    pub fn ui(&mut self, ui: &imgui::Ui) -> Option<usize> {
+        let mut frame = self.frame;
+
+        ui.window("Playback")
+            .size([300.0, 130.0], imgui::Condition::FirstUseEver)
+            .position([1250.0, 150.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let last_frame = self.trajectory.frames.len() - 1;
+
+                if ui.arrow_button("back", imgui::Direction::Left) {
+                    frame = frame.saturating_sub(1);
+                }
+                ui.same_line();
+                if ui.button(if self.playing { "Pause" } else { "Play" }) {
+                    // Playing from the end starts again from the beginning.
+                    self.playing = !self.playing;
+                    if self.playing && frame == last_frame {
+                        frame = 0;
+                    }
+                }
+                ui.same_line();
+                if ui.arrow_button("forward", imgui::Direction::Right) {
+                    frame = usize::min(frame + 1, last_frame);
+                }
+                ui.same_line();
+                ui.checkbox("Loop", &mut self.looping);
+
+                let elapsed_myr = self.trajectory.frames[self.frame].elapsed_myr();
+                ui.slider_config("##frame", 0, last_frame)
+                    .display_format(format!("{elapsed_myr:.1} Myr"))
+                    .build(&mut frame);
+                ui.slider_config("Frames/s", MIN_FRAMES_PER_SECOND, MAX_FRAMES_PER_SECOND)
+                    .flags(imgui::SliderFlags::LOGARITHMIC)
+                    .build(&mut self.frames_per_second);
+                ui.text(format!("Frame {} of {}", self.frame + 1, last_frame + 1));
+            });
+
+        (frame != self.frame).then(|| {
+            self.frame = frame;
+            self.frame_time = 0.0;
+            frame
+        })
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+
+use galaxy_core::analysis::population::Population;
+use galaxy_core::simulation::GalaxySim;
+
+/// The number of samples of the population kept for the plot.
+const HISTORY_LENGTH: usize = 500;
+
+/// How many steps there are between each sample.
+const UPDATE_INTERVAL: u64 = 10;
+
+/// The height of the plot, in pixels.
+const PLOT_HEIGHT: f32 = 120.0;
+
+/// The series that are stacked, from the bottom, with their names and colours.
+const SERIES: [(&str, [f32; 4], fn(&Population) -> usize); 4] = [
+    ("Alive", [0.4, 0.6, 0.9, 1.0], |population| population.alive),
+    ("Formed", [0.4, 0.8, 0.4, 1.0], |population| population.formed),
+    ("Merged", [0.9, 0.5, 0.2, 1.0], |population| population.merged),
+    ("Escaped", [0.7, 0.4, 0.8, 1.0], |population| population.escaped),
+];
+
+/// Plots how many stars are alive, and how many have merged with black holes, escaped, or been
+/// formed, over simulation time, stacked on top of each other so that long-run changes in the
+/// galaxy's population are visible.
+pub struct PopulationPlot {
+    history: VecDeque<Population>,
+
+    /// The number of steps since the last sample, or None if there hasn't been one.
+    steps_since_update: Option<u64>,
+}
+
+impl PopulationPlot {
+    /// Create an empty plot.
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+            steps_since_update: None,
+        }
+    }
+
+    /// Clear the plot, e.g. when the galaxy is replaced.
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.steps_since_update = None;
+    }
+
+    /// Count the simulation's stars, if it's time to.
+    pub fn record(&mut self, sim: &GalaxySim) {
+        self.steps_since_update = match self.steps_since_update {
+            Some(steps) if steps + 1 < UPDATE_INTERVAL => Some(steps + 1),
+            _ => {
+                if self.history.len() == HISTORY_LENGTH {
+                    self.history.pop_front();
+                }
+                self.history.push_back(Population::measure(sim));
+                Some(0)
+            },
+        };
+    }
+
+    /// Build the population window.
+    pub fn ui(&self, ui: &imgui::Ui) {
+        ui.window("Population")
+            .size([300.0, 230.0], imgui::Condition::FirstUseEver)
+            .position([1250.0, 560.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let (first, latest) = match (self.history.front(), self.history.back()) {
+                    (Some(first), Some(latest)) => (first, latest),
+                    _ => return,
+                };
+
+                for (name, color, count) in SERIES {
+                    ui.text_colored(color, format!("{name}: {}", count(latest)));
+                }
+                ui.text(format!("{:.0} - {:.0} Myr", first.elapsed_myr, latest.elapsed_myr));
+
+                self.plot(ui);
+            });
+    }
+
+    /// Draw the series stacked on top of each other, with a column for each sample.
+    fn plot(&self, ui: &imgui::Ui) {
+        let max_total = self.history.iter()
+            .map(|population| SERIES.iter().map(|(_, _, count)| count(population)).sum::<usize>())
+            .max()
+            .unwrap_or(0);
+        if max_total == 0 {
+            return;
+        }
+
+        // Reserve the space for the plot and draw it there.
+        let [left, top] = ui.cursor_screen_pos();
+        let width = ui.content_region_avail()[0];
+        ui.dummy([width, PLOT_HEIGHT]);
+
+        let draw_list = ui.get_window_draw_list();
+        let column_width = width / self.history.len() as f32;
+        let bottom = top + PLOT_HEIGHT;
+        for (column, population) in self.history.iter().enumerate() {
+            let x = left + column as f32 * column_width;
+            let mut stacked = 0;
+            for (_, color, count) in SERIES {
+                let base = bottom - stacked as f32 / max_total as f32 * PLOT_HEIGHT;
+                stacked += count(population);
+                let height = bottom - stacked as f32 / max_total as f32 * PLOT_HEIGHT;
+                if height < base {
+                    draw_list.add_rect([x, height], [x + column_width, base], color).filled(true).build();
+                }
+            }
+        }
+
+        draw_list.add_rect([left, top], [left + width, bottom], ui.style_color(imgui::StyleColor::Border))
+            .build();
+    }
+}
@@ -0,0 +1,90 @@
+use galaxy_core::analysis::power_spectrum::PowerSpectrum;
+use galaxy_core::simulation::GalaxySim;
+
+/// The size of the grid the stars are binned onto, which must be a power of two.
+const GRID_SIZE: usize = 128;
+
+/// How many steps there are between each measurement.
+const UPDATE_INTERVAL: u64 = 30;
+
+/// Plots the power spectrum of the surface density of the stars, comparing it to the one measured
+/// when the galaxy was generated so that the growth of spiral arms and clumps can be seen over
+/// time.
+pub struct PowerSpectrumPlot {
+    initial: Option<PowerSpectrum>,
+    latest: Option<PowerSpectrum>,
+
+    /// The number of steps since the last measurement.
+    steps_since_update: u64,
+}
+
+impl PowerSpectrumPlot {
+    /// Create an empty plot.
+    pub fn new() -> Self {
+        Self {
+            initial: None,
+            latest: None,
+            steps_since_update: 0,
+        }
+    }
+
+    /// Clear the plot, e.g. when the galaxy is replaced.
+    pub fn reset(&mut self) {
+        self.initial = None;
+        self.latest = None;
+        self.steps_since_update = 0;
+    }
+
+    /// Measure the power spectrum, if it's time to. It covers the area the galaxy was generated in.
+    pub fn record(&mut self, sim: &GalaxySim) {
+        if self.latest.is_some() && self.steps_since_update + 1 < UPDATE_INTERVAL {
+            self.steps_since_update += 1;
+            return;
+        }
+        self.steps_since_update = 0;
+
+        let half_width = sim.config().generation.galaxy_radius();
+        let spectrum = PowerSpectrum::measure(&sim.stars().to_vec(), half_width, GRID_SIZE);
+        self.initial.get_or_insert_with(|| spectrum.clone());
+        self.latest = Some(spectrum);
+    }
+
+    /// Build the power spectrum window.
+    pub fn ui(&self, ui: &imgui::Ui) {
+        ui.window("Power spectrum")
+            .size([300.0, 220.0], imgui::Condition::FirstUseEver)
+            .position([940.0, 350.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let (initial, latest) = match (&self.initial, &self.latest) {
+                    (Some(initial), Some(latest)) => (initial, latest),
+                    _ => return,
+                };
+
+                if let (Some(largest), Some(smallest)) = (latest.wavelengths.first(), latest.wavelengths.last()) {
+                    ui.text(format!("log10 power from {largest:.0} to {smallest:.0} pc"));
+                }
+                Self::plot(ui, "Now", latest);
+                Self::plot(ui, "Initial", initial);
+            });
+    }
+
+    /// Plot a power spectrum against wavenumber. It's plotted logarithmically as it spans orders
+    /// of magnitude.
+    fn plot(ui: &imgui::Ui, label: &str, spectrum: &PowerSpectrum) {
+        let values: Vec<f32> = spectrum.power.iter()
+            .map(|power| f64::log10(f64::max(*power, 1e-6)) as f32)
+            .collect();
+
+        // The scale with the most power, apart from the size of the whole galaxy.
+        let peak = spectrum.power.iter()
+            .enumerate()
+            .skip(1)
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(bin, _)| spectrum.wavelengths[bin]);
+
+        ui.plot_lines(label, &values)
+            .graph_size([0.0, 60.0])
+            .overlay_text(peak.map(|peak| format!("Peak at {peak:.0} pc")).unwrap_or_default())
+            .build();
+    }
+}
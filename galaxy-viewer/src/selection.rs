@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+
+use galaxy_core::simulation::GalaxySim;
+use galaxy_core::star_world::StarId;
+
+/// A selection saved under a name.
+struct SelectionSet {
+    name: String,
+    stars: HashSet<u64>,
+}
+
+/// The stars the user has selected, by their IDs so that the same stars stay selected as others are
+/// added and removed, and the selections they've saved to restore later.
+pub struct Selection {
+    stars: HashSet<u64>,
+    saved: Vec<SelectionSet>,
+
+    /// The name entered for the next selection to be saved.
+    name: String,
+}
+
+impl Selection {
+    /// Create an empty selection with no saved sets.
+    pub fn new() -> Self {
+        Self {
+            stars: HashSet::new(),
+            saved: Vec::new(),
+            name: String::new(),
+        }
+    }
+
+    /// Whether the star with the given ID is selected.
+    pub fn contains(&self, id: u64) -> bool {
+        self.stars.contains(&id)
+    }
+
+    /// Add a star to the selection if it isn't in it, otherwise remove it.
+    pub fn toggle(&mut self, id: u64) {
+        if !self.stars.remove(&id) {
+            self.stars.insert(id);
+        }
+    }
+
+    /// Build the selection section of the galaxy window. Returns whether the selection changed.
+    pub fn ui(&mut self, ui: &imgui::Ui, sim: &GalaxySim) -> bool {
+        let mut changed = false;
+
+        // Stars that have since been destroyed stay in the selection, in case the state they
+        // existed in is restored, but only those that still exist are counted.
+        let alive_ids: HashSet<u64> = sim.stars().query::<&StarId>().map(|id| id.0).collect();
+        let alive_count = |stars: &HashSet<u64>| stars.iter().filter(|id| alive_ids.contains(id)).count();
+
+        ui.label_text("Selected", format!("{} ({} alive)", self.stars.len(), alive_count(&self.stars)));
+        if ui.button("Clear selection") {
+            self.stars.clear();
+            changed = true;
+        }
+
+        ui.input_text("Name", &mut self.name).build();
+        ui.same_line();
+        if ui.button("Save") && !self.stars.is_empty() {
+            let name = match self.name.trim() {
+                "" => format!("Selection {}", self.saved.len() + 1),
+                name => name.to_string(),
+            };
+            self.saved.push(SelectionSet { name, stars: self.stars.clone() });
+            self.name.clear();
+        }
+
+        let mut removed = None;
+        for (i, set) in self.saved.iter().enumerate() {
+            ui.text(format!("{} ({} of {} alive)", set.name, alive_count(&set.stars), set.stars.len()));
+            ui.same_line();
+            if ui.small_button(format!("Restore##selection{i}")) {
+                self.stars = set.stars.clone();
+                changed = true;
+            }
+            ui.same_line();
+            if ui.small_button(format!("Delete##selection{i}")) {
+                removed = Some(i);
+            }
+        }
+        if let Some(i) = removed {
+            self.saved.remove(i);
+        }
+
+        changed
+    }
+}
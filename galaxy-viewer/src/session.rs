@@ -0,0 +1,208 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use galaxy_core::config::{Config, SpatialBackend};
+use galaxy_core::presets::Preset;
+use galaxy_core::simulation::StarTag;
+use galaxy_core::types::Vec2d;
+
+/// A mouse button, as recorded in a session.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+}
+
+/// An input event that affects the simulation. All input is converted to these and applied at the
+/// start of the next fixed update, so that a recorded session can be replayed exactly.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    MouseMotion { x: f32, y: f32 },
+    MouseWheel { dy: f32 },
+    MouseButton { button: Button, down: bool },
+    RegenerateGalaxy,
+    IncreaseRate,
+    DecreaseRate,
+    /// The rate in Myr per second was changed directly, e.g. via the UI.
+    SetRate(f64),
+    SetPaused(bool),
+    SetOpeningAngle(f64),
+    /// Set the spatial acceleration structure used to calculate gravity.
+    SetSpatialBackend(SpatialBackend),
+    /// Move the stars into the center of momentum frame once.
+    Recenter,
+    /// Set whether the stars are moved into the center of momentum frame every step.
+    SetRecenter(bool),
+    /// Pause and restore the state with the given index in the rewind buffer. Unpausing resumes
+    /// the simulation from it, discarding the states after it.
+    ScrubTo(usize),
+    /// Bookmark the current state with the label entered in the UI.
+    AddBookmark,
+    /// Pause and restore the bookmarked state with the given index, discarding the states
+    /// recorded for rewinding after it.
+    JumpToBookmark(usize),
+    RemoveBookmark(usize),
+    /// Apply a galaxy preset to the config and regenerate the galaxy with it.
+    ApplyPreset(Preset),
+    /// Set the fraction of disk stars that orbit retrograde and regenerate the galaxy with it.
+    SetCounterRotatingFraction(f64),
+    /// Regenerate the galaxy with a seed drawn from the UI randomize stream.
+    RandomizeSeed,
+    /// Replace the galaxy with the one in the latest checkpoint.
+    ResumeCheckpoint,
+    /// Add random velocities with the given standard deviation in km/s to the stars.
+    Perturb(f64),
+    /// Edit the star with the given index from the inspector.
+    EditStar { index: usize, edit: StarEdit },
+}
+
+/// A change made to a star in the inspector.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StarEdit {
+    /// Set its mass in solar masses, its position in parsecs, and its velocity.
+    Set { mass: f64, position: Vec2d, velocity: Vec2d },
+    /// Stop it where it is.
+    ZeroVelocity,
+    /// Put it on a circular orbit around the central black hole.
+    CircularizeOrbit,
+    /// Tag it, or remove its tag.
+    SetTag(Option<StarTag>),
+}
+
+/// The header of a session file, containing everything needed to recreate the initial state.
+#[derive(Serialize, Deserialize)]
+pub struct SessionHeader {
+    pub seed: u64,
+    pub config: Config,
+}
+
+/// A recorded input event, along with the fixed update it was applied in.
+#[derive(Serialize, Deserialize)]
+pub struct SessionEvent {
+    /// The fixed update step the event was applied at.
+    pub step: u64,
+
+    /// The wall-clock time in seconds since the start of the session, for reference only.
+    pub time: f64,
+
+    pub event: InputEvent,
+}
+
+/// Records a session to a file. The file is in JSON lines format, the first line is the
+/// SessionHeader and every following line is a SessionEvent.
+pub struct SessionRecorder {
+    writer: BufWriter<File>,
+}
+
+impl SessionRecorder {
+    /// Create a new session file at the given path and write the header to it.
+    pub fn create<P: AsRef<Path>>(path: P, header: &SessionHeader) -> Result<Self, Box<dyn Error>> {
+        log::info!("Recording session to {}", path.as_ref().display());
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, header)?;
+        writer.write_all(b"\n")?;
+
+        Ok(Self { writer })
+    }
+
+    /// Record an event.
+    pub fn record(&mut self, step: u64, time: f64, event: InputEvent) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer(&mut self.writer, &SessionEvent { step, time, event })?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Record an event that happened after the given step had run, such as a pause by a trigger.
+    /// Events are replayed at the start of the step they're recorded for, so it's recorded for the
+    /// next step, where it takes effect.
+    pub fn record_after_step(&mut self, step: u64, time: f64, event: InputEvent) -> Result<(), Box<dyn Error>> {
+        self.record(step + 1, time, event)
+    }
+
+    /// Flush any buffered events to the file.
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A session loaded from a file for replaying.
+pub struct SessionReplay {
+    pub header: SessionHeader,
+    events: VecDeque<SessionEvent>,
+}
+
+impl SessionReplay {
+    /// Load a session file written by SessionRecorder.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        log::info!("Replaying session from {}", path.as_ref().display());
+
+        let mut lines = BufReader::new(File::open(path)?).lines();
+
+        let header = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => return Err("Session file is empty".into()),
+        };
+
+        let mut events = VecDeque::new();
+        for line in lines {
+            let line = line?;
+            if !line.trim().is_empty() {
+                events.push_back(serde_json::from_str(&line)?);
+            }
+        }
+
+        Ok(Self { header, events })
+    }
+
+    /// Take all events that should be applied at the given step.
+    pub fn events_for_step(&mut self, step: u64) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        while self.events.front().is_some_and(|event| event.step <= step) {
+            events.push(self.events.pop_front().unwrap().event);
+        }
+        events
+    }
+
+    /// Whether all events have been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_round_trip() {
+        let path = std::env::temp_dir().join(format!("galaxy-session-{}.jsonl", std::process::id()));
+        let header = SessionHeader { seed: 7, config: Config::default() };
+        let mut recorder = SessionRecorder::create(&path, &header).unwrap();
+
+        // A rate change applied in step 2, and a pause by a trigger after step 5 has run.
+        recorder.record(2, 0.0, InputEvent::SetRate(5.0)).unwrap();
+        recorder.record_after_step(5, 0.0, InputEvent::SetPaused(true)).unwrap();
+        recorder.flush().unwrap();
+        drop(recorder);
+
+        let mut replay = SessionReplay::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(replay.header.seed, 7);
+        assert_eq!(replay.events_for_step(1), Vec::new());
+        assert_eq!(replay.events_for_step(2), vec![InputEvent::SetRate(5.0)]);
+
+        // Step 5 still runs when replayed, and the pause takes effect before step 6.
+        assert_eq!(replay.events_for_step(5), Vec::new());
+        assert!(!replay.is_finished());
+        assert_eq!(replay.events_for_step(6), vec![InputEvent::SetPaused(true)]);
+        assert!(replay.is_finished());
+    }
+}
@@ -1,4 +1,6 @@
 pub mod basic_textured;
 pub mod wireframe_quad;
 pub mod stars;
+pub mod downsample;
+pub mod trails;
 pub mod imgui;
@@ -0,0 +1,66 @@
+use miniquad::*;
+
+pub const VERTEX: &str = r#"
+    #version 100
+
+    attribute vec2 pos;
+    attribute vec2 uv;
+
+    varying lowp vec2 texcoord;
+
+    void main() {
+        gl_Position = vec4(pos, 0, 1);
+        texcoord = uv;
+    }
+"#;
+
+/// Averages the square of `scale` by `scale` texels of the source texture under each pixel of the
+/// target, which is `scale` times smaller. Loops in GLSL 100 need a constant bound, so the scale is
+/// at most MAX_SCALE.
+pub const FRAGMENT: &str = r#"
+    #version 100
+
+    precision mediump float;
+
+    varying lowp vec2 texcoord;
+
+    uniform sampler2D tex;
+    uniform vec2 texel_size;
+    uniform float scale;
+
+    void main() {
+        vec4 sum = vec4(0.0);
+        vec2 first = texcoord - texel_size * (scale - 1.0) * 0.5;
+        for (int y = 0; y < 4; y++) {
+            for (int x = 0; x < 4; x++) {
+                if (float(x) < scale && float(y) < scale) {
+                    sum += texture2D(tex, first + vec2(float(x), float(y)) * texel_size);
+                }
+            }
+        }
+        gl_FragColor = sum / (scale * scale);
+    }
+"#;
+
+/// The largest scale the fragment shader can downsample by.
+pub const MAX_SCALE: usize = 4;
+
+pub fn meta() -> ShaderMeta {
+    ShaderMeta {
+        images: vec!["tex".to_string()],
+        uniforms: UniformBlockLayout {
+            uniforms: vec![
+                UniformDesc::new("texel_size", UniformType::Float2),
+                UniformDesc::new("scale", UniformType::Float1),
+            ],
+        },
+    }
+}
+
+#[repr(C)]
+pub struct Uniforms {
+    /// The size of a texel of the source texture, in texture coordinates.
+    pub texel_size: (f32, f32),
+
+    pub scale: f32,
+}
@@ -0,0 +1,100 @@
+use miniquad::*;
+
+/// Draws each star as a point sprite: a quad, instanced once per star, covering its glow. The
+/// point size is the radius of the star's glow in texels.
+pub const VERTEX: &str = r#"
+    #version 100
+
+    attribute vec2 corner;
+    attribute vec2 center;
+    attribute float point_size;
+    attribute vec3 color;
+    attribute float brightness;
+
+    uniform vec2 texel_size;
+
+    varying vec2 local;
+    varying lowp vec3 star_color;
+    varying lowp float star_brightness;
+
+    void main() {
+        gl_Position = vec4(center + corner * point_size * texel_size, 0, 1);
+        local = corner;
+        star_color = color;
+        star_brightness = brightness;
+    }
+"#;
+
+/// Gives each star a solid core, surrounded by a glow that falls off as a power of the remaining
+/// distance to the edge of the sprite. The core is a fraction of the sprite's radius, so it scales
+/// with the sprite. The uniforms that are only used here are kept out of the vertex shader, as
+/// their precision would have to match between the two.
+pub const FRAGMENT: &str = r#"
+    #version 100
+
+    precision mediump float;
+
+    varying vec2 local;
+    varying lowp vec3 star_color;
+    varying lowp float star_brightness;
+
+    uniform float core_fraction;
+    uniform float falloff;
+    uniform float exposure;
+    uniform float color_mode;
+
+    void main() {
+        float radius = length(local);
+        if (radius > 1.0) {
+            discard;
+        }
+
+        float intensity = 1.0;
+        if (radius > core_fraction) {
+            float glow = 1.0 - (radius - core_fraction) / max(1.0 - core_fraction, 0.001);
+            intensity = pow(glow, falloff);
+        }
+
+        vec3 color = star_color;
+        if (color_mode > 1.5) {
+            // Heat: red, through yellow, to white as the star gets brighter.
+            float heat = star_brightness * 3.0;
+            color = clamp(vec3(heat, heat - 1.0, heat - 2.0), 0.0, 1.0);
+        }
+        else if (color_mode > 0.5) {
+            color = vec3(1.0, 1.0, 1.0);
+        }
+
+        gl_FragColor = vec4(color * star_brightness * intensity * exposure, 1.0);
+    }
+"#;
+
+pub fn meta() -> ShaderMeta {
+    ShaderMeta {
+        images: Vec::new(),
+        uniforms: UniformBlockLayout {
+            uniforms: vec![
+                UniformDesc::new("texel_size", UniformType::Float2),
+                UniformDesc::new("core_fraction", UniformType::Float1),
+                UniformDesc::new("falloff", UniformType::Float1),
+                UniformDesc::new("exposure", UniformType::Float1),
+                UniformDesc::new("color_mode", UniformType::Float1),
+            ],
+        },
+    }
+}
+
+#[repr(C)]
+pub struct Uniforms {
+    /// The size of a texel of the star texture, in normalized device coordinates.
+    pub texel_size: (f32, f32),
+
+    /// The radius of each star's core, as a fraction of the radius of its glow.
+    pub core_fraction: f32,
+
+    pub falloff: f32,
+    pub exposure: f32,
+
+    /// The colour mode's index in StarColorMode::ALL.
+    pub color_mode: f32,
+}
@@ -0,0 +1,53 @@
+use miniquad::*;
+
+pub const VERTEX: &str = r#"
+    #version 100
+
+    attribute vec2 pos;
+    attribute vec2 uv;
+
+    varying lowp vec2 texcoord;
+
+    void main() {
+        gl_Position = vec4(pos, 0, 1);
+        texcoord = uv;
+    }
+"#;
+
+/// Keeps the brighter of the current frame and the faded trails from the frames before it, so that
+/// stars that stay still keep their brightness rather than building up, and the head of a trail is
+/// as bright as the star itself.
+pub const FRAGMENT: &str = r#"
+    #version 100
+
+    precision mediump float;
+
+    varying lowp vec2 texcoord;
+
+    uniform sampler2D frame;
+    uniform sampler2D previous;
+    uniform float decay;
+
+    void main() {
+        vec4 current = texture2D(frame, texcoord);
+        vec4 trail = texture2D(previous, texcoord) * decay;
+        gl_FragColor = vec4(max(current.rgb, trail.rgb), 1.0);
+    }
+"#;
+
+pub fn meta() -> ShaderMeta {
+    ShaderMeta {
+        images: vec!["frame".to_string(), "previous".to_string()],
+        uniforms: UniformBlockLayout {
+            uniforms: vec![
+                UniformDesc::new("decay", UniformType::Float1),
+            ],
+        },
+    }
+}
+
+#[repr(C)]
+pub struct Uniforms {
+    /// How much of the previous frame's brightness is kept, from 0 to 1.
+    pub decay: f32,
+}
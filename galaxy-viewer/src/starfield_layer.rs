@@ -0,0 +1,171 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use galaxy_core::rng::{self, RngStreams};
+use galaxy_core::types::{Vec2, Vec2d};
+use miniquad::*;
+use rand::Rng;
+
+use crate::drawable::*;
+use crate::galaxy::Galaxy;
+use crate::input::InputState;
+use crate::shaders::stars;
+use crate::viewport::Viewport;
+
+/// The seed the background stars are generated from, so that the starfield is the same every run.
+const SEED: u64 = 0x5747_4152;
+
+/// A layer of background stars: how fast it scrolls relative to the galaxy, the size of the tile
+/// it repeats over in parsecs, how many stars are in each tile, and how bright and large in pixels
+/// they are. The farthest layers scroll slowest and have the most, faintest stars.
+struct LayerParams {
+    parallax: f64,
+    tile_size: f64,
+    star_count: usize,
+    brightness: f32,
+    point_size: f32,
+}
+
+const LAYERS: [LayerParams; 3] = [
+    LayerParams { parallax: 0.05, tile_size: 23_000.0, star_count: 300, brightness: 0.25, point_size: 1.0 },
+    LayerParams { parallax: 0.15, tile_size: 31_000.0, star_count: 150, brightness: 0.4, point_size: 1.3 },
+    LayerParams { parallax: 0.3, tile_size: 43_000.0, star_count: 60, brightness: 0.6, point_size: 1.7 },
+];
+
+/// The colours of the bluest and reddest background stars, which they're tinted between.
+const BLUE_TINT: [f32; 3] = [0.75, 0.85, 1.0];
+const RED_TINT: [f32; 3] = [1.0, 0.85, 0.7];
+
+/// A background star, at a position within its layer's tile from (0, 0) to (1, 1).
+struct BackgroundStar {
+    position: Vec2d,
+    color: [f32; 3],
+    brightness: f32,
+}
+
+/// A procedurally generated background starfield, made of several layers of stars that scroll and
+/// zoom slower than the galaxy as the camera moves, as if they were much farther away. Each layer
+/// is a tile of random stars repeated infinitely in every direction. The stars are added to what's
+/// already been drawn, so the layer can go on top of the galaxy.
+pub struct StarfieldLayer {
+    star_sprites: StarSprites,
+    galaxy: Rc<RefCell<Galaxy>>,
+
+    /// The stars in each layer's tile.
+    layers: Vec<Vec<BackgroundStar>>,
+
+    /// The sprites of the stars in the current view, and the size of a pixel of the viewport
+    /// they're drawn in, in normalized device coordinates.
+    sprites: Vec<StarSprite>,
+    pixel_size: (f32, f32),
+
+    /// A multiplier on how fast every layer scrolls, where 0 keeps them all still.
+    depth: f32,
+
+    /// A multiplier on the brightness of the stars.
+    brightness: f32,
+}
+
+impl StarfieldLayer {
+    /// Create a starfield layer behind the given galaxy.
+    pub fn new(ctx: &mut Context, galaxy: Rc<RefCell<Galaxy>>) -> Result<Self, Box<dyn Error>> {
+        let mut rng = RngStreams::new(SEED).stream(rng::STARFIELD);
+        let layers = LAYERS.iter().map(|params| {
+            (0..params.star_count).map(|_| {
+                let tint: f32 = rng.gen();
+                BackgroundStar {
+                    position: Vec2d::new(rng.gen(), rng.gen()),
+                    color: std::array::from_fn(|i| BLUE_TINT[i] + (RED_TINT[i] - BLUE_TINT[i]) * tint),
+                    brightness: params.brightness * rng.gen_range(0.3..1.0),
+                }
+            }).collect()
+        }).collect();
+
+        Ok(Self {
+            star_sprites: StarSprites::new(ctx)?,
+            galaxy,
+            layers,
+            sprites: Vec::new(),
+            pixel_size: (0.0, 0.0),
+            depth: 1.0,
+            brightness: 1.0,
+        })
+    }
+
+    /// Place the stars of every layer in the current view. Each layer is viewed through a camera
+    /// that's moved and zoomed by the same fraction of the galaxy camera's movement and zoom as the
+    /// layer's parallax.
+    fn update_sprites(&mut self) {
+        let (view_offset, view_size) = self.galaxy.borrow().renderer.view_bounds();
+        let zoom_scale = self.galaxy.borrow().renderer.zoom_scale();
+        let view_center = view_offset + view_size * 0.5;
+
+        self.sprites.clear();
+        for (params, stars) in LAYERS.iter().zip(&self.layers) {
+            let parallax = params.parallax * self.depth as f64;
+            let layer_size = view_size * zoom_scale / zoom_scale.powf(parallax);
+            let layer_min = view_center * parallax - layer_size * 0.5;
+            let layer_max = layer_min + layer_size;
+
+            // Draw every copy of the tile that overlaps the view.
+            let first_tile = ((layer_min.x / params.tile_size).floor() as i64, (layer_min.y / params.tile_size).floor() as i64);
+            let last_tile = ((layer_max.x / params.tile_size).floor() as i64, (layer_max.y / params.tile_size).floor() as i64);
+            for tile_y in first_tile.1..=last_tile.1 {
+                for tile_x in first_tile.0..=last_tile.0 {
+                    let tile_origin = Vec2d::new(tile_x as f64, tile_y as f64) * params.tile_size;
+                    for star in stars {
+                        let position = tile_origin + star.position * params.tile_size - layer_min;
+                        let x = position.x / layer_size.x * 2.0 - 1.0;
+                        let y = position.y / layer_size.y * 2.0 - 1.0;
+                        if x.abs() > 1.0 || y.abs() > 1.0 {
+                            continue;
+                        }
+
+                        self.sprites.push(StarSprite {
+                            center: Vec2::new(x as f32, y as f32),
+                            point_size: params.point_size,
+                            color: star.color,
+                            brightness: star.brightness,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drawable for StarfieldLayer {
+    /// Place the stars in the current view.
+    fn fixed_update(&mut self,
+                    _ctx: &mut Context,
+                    _input_state: &InputState,
+                    viewport: &Viewport,
+                    _time_delta: f64)
+    {
+        self.pixel_size = (2.0 / viewport.width, 2.0 / viewport.height);
+        self.update_sprites();
+    }
+
+    /// Build the "Starfield" window.
+    fn ui(&mut self, _ctx: &mut Context, ui: &imgui::Ui) {
+        ui.window("Starfield")
+            .size([250.0, 80.0], imgui::Condition::FirstUseEver)
+            .position([370.0, 290.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.slider("Depth", 0.0, 3.0, &mut self.depth);
+                ui.slider("Brightness", 0.0, 3.0, &mut self.brightness);
+            });
+    }
+
+    /// Draw the starfield.
+    fn render(&mut self, ctx: &mut Context, _alpha: f64) {
+        self.star_sprites.draw(ctx, &self.sprites, &stars::Uniforms {
+            texel_size: self.pixel_size,
+            core_fraction: 0.3,
+            falloff: 2.0,
+            exposure: self.brightness,
+            color_mode: 0.0,
+        });
+    }
+}
@@ -0,0 +1,173 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use galaxy_core::analysis::state_diff::StateDiff;
+use galaxy_core::config::Config;
+use galaxy_core::types::Vec2d;
+use miniquad::*;
+
+use crate::drawable::*;
+use crate::galaxy::Galaxy;
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+/// The range of log10 of the distance in parsecs covered by the colour map and histogram.
+const MIN_LOG_DISTANCE: f64 = -2.0;
+const MAX_LOG_DISTANCE: f64 = 4.0;
+
+/// The number of bins in the histogram.
+const HISTOGRAM_BINS: usize = 24;
+
+/// The colours of the smallest and largest differences, and the one halfway between them.
+const LOW_DISTANCE_COLOR: [f64; 3] = [0.1, 0.2, 1.0];
+const MID_DISTANCE_COLOR: [f64; 3] = [0.1, 1.0, 0.2];
+const HIGH_DISTANCE_COLOR: [f64; 3] = [1.0, 0.1, 0.1];
+
+/// A layer that shows how far each star is from where it is in a second saved state, as a vector
+/// from its position in the first state to its position in the second coloured by the distance,
+/// or just a coloured point at its first position, with summary statistics in its window.
+pub struct StateDiffLayer {
+    textured_quad: TexturedQuad,
+    galaxy: Rc<RefCell<Galaxy>>,
+
+    diff: StateDiff,
+
+    /// The names of the two states, e.g. the files they were loaded from.
+    names: [String; 2],
+
+    /// Whether to draw vectors between the positions rather than points.
+    vectors: bool,
+
+    /// Differences smaller than this are left out, in log10 parsecs, so the stars that diverged
+    /// the most can be picked out.
+    min_log_distance: f32,
+}
+
+impl StateDiffLayer {
+    /// Create a layer showing the differences between two states over the galaxy showing the first.
+    pub fn new(ctx: &mut Context,
+               config: &Config,
+               galaxy: Rc<RefCell<Galaxy>>,
+               diff: StateDiff,
+               names: [String; 2]) -> Result<Self, Box<dyn Error>>
+    {
+        let blend = BlendState::new(Equation::Add,
+                                    BlendFactor::Value(BlendValue::SourceAlpha),
+                                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha));
+        let textured_quad = TexturedQuad::with_blend(ctx,
+                                                     config.rendering.texture_width,
+                                                     config.rendering.texture_height,
+                                                     Some(blend))?;
+
+        Ok(Self {
+            textured_quad,
+            galaxy,
+            diff,
+            names,
+            vectors: true,
+            min_log_distance: MIN_LOG_DISTANCE as f32,
+        })
+    }
+
+    /// The colour of a distance, from blue for small differences through green to red.
+    fn distance_color(distance: f64) -> [u8; 3] {
+        let log_distance = distance.max(f64::MIN_POSITIVE).log10();
+        let t = ((log_distance - MIN_LOG_DISTANCE) / (MAX_LOG_DISTANCE - MIN_LOG_DISTANCE)).clamp(0.0, 1.0) * 2.0;
+        let (from, to, t) = match t < 1.0 {
+            true => (LOW_DISTANCE_COLOR, MID_DISTANCE_COLOR, t),
+            false => (MID_DISTANCE_COLOR, HIGH_DISTANCE_COLOR, t - 1.0),
+        };
+        std::array::from_fn(|i| ((from[i] + (to[i] - from[i]) * t) * 255.0) as u8)
+    }
+
+    /// Draw the differences in the current view into the texture. The largest are drawn last so
+    /// they end up on top.
+    fn update_texture(&mut self, ctx: &mut Context) {
+        let (width, height) = (self.textured_quad.width, self.textured_quad.height);
+        let mut bytes = vec![0; 4 * width * height];
+
+        let (view_offset, view_size) = self.galaxy.borrow().renderer.view_bounds();
+        let to_texels = |position: Vec2d| {
+            let pos = position - view_offset;
+            (pos.x / view_size.x * width as f64, pos.y / view_size.y * height as f64)
+        };
+
+        let min_distance = 10f64.powf(self.min_log_distance as f64);
+        for displacement in self.diff.displacements.iter().filter(|displacement| displacement.distance >= min_distance) {
+            let [r, g, b] = Self::distance_color(displacement.distance);
+            let (x0, y0) = to_texels(displacement.first);
+            let (x1, y1) = match self.vectors {
+                true => to_texels(displacement.second),
+                false => (x0, y0),
+            };
+
+            // Step along the vector a texel at a time, skipping those that are out of view. Long
+            // vectors are capped so that a star that's crossed the galaxy can't stall drawing.
+            let steps = f64::max((x1 - x0).abs(), (y1 - y0).abs()).ceil().min((width + height) as f64) as usize;
+            for step in 0..=steps {
+                let t = step as f64 / usize::max(steps, 1) as f64;
+                let (x, y) = (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+                if x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height {
+                    let idx = 4 * (y as usize * width + x as usize);
+                    bytes[idx..idx+4].copy_from_slice(&[r, g, b, 0xFF]);
+                }
+            }
+        }
+
+        self.textured_quad.texture.update(ctx, &bytes);
+    }
+}
+
+impl Drawable for StateDiffLayer {
+    /// Redraw the differences in case the camera moved.
+    fn fixed_update(&mut self,
+                    ctx: &mut Context,
+                    _input_state: &InputState,
+                    _viewport: &Viewport,
+                    _time_delta: f64)
+    {
+        self.update_texture(ctx);
+    }
+
+    /// Build the "State diff" window.
+    fn ui(&mut self, _ctx: &mut Context, ui: &imgui::Ui) {
+        ui.window("State diff")
+            .size([320.0, 260.0], imgui::Condition::FirstUseEver)
+            .position([1250.0, 290.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.text_wrapped(format!("From {} to {}", self.names[0], self.names[1]));
+                ui.checkbox("Vectors", &mut self.vectors);
+                ui.slider("Min log10 pc", MIN_LOG_DISTANCE as f32, MAX_LOG_DISTANCE as f32, &mut self.min_log_distance);
+
+                let diff = &self.diff;
+                ui.label_text("Matched stars", diff.displacements.len().to_string());
+                if diff.only_in_first > 0 || diff.only_in_second > 0 {
+                    ui.label_text("Unmatched", format!("{} first, {} second", diff.only_in_first, diff.only_in_second));
+                }
+                ui.label_text("Mean", format!("{:.3} pc", diff.mean_distance()));
+                ui.label_text("RMS", format!("{:.3} pc", diff.rms_distance()));
+                ui.text(format!("Median {:.2e}, 90% {:.2e}, 99% {:.2e} pc",
+                                diff.percentile(0.5),
+                                diff.percentile(0.9),
+                                diff.percentile(0.99)));
+                if let Some(largest) = diff.largest() {
+                    ui.label_text("Largest", format!("{:.3} pc (star {})", largest.distance, largest.id));
+                }
+
+                let histogram: Vec<f32> = diff.log_histogram(MIN_LOG_DISTANCE, MAX_LOG_DISTANCE, HISTOGRAM_BINS)
+                    .into_iter()
+                    .map(|count| count as f32)
+                    .collect();
+                ui.plot_histogram("##histogram", &histogram)
+                    .graph_size([0.0, 60.0])
+                    .overlay_text(format!("log10 pc, {MIN_LOG_DISTANCE} to {MAX_LOG_DISTANCE}"))
+                    .build();
+            });
+    }
+
+    /// Draw the state diff layer.
+    fn render(&mut self, ctx: &mut Context, _alpha: f64) {
+        self.textured_quad.draw(ctx);
+    }
+}
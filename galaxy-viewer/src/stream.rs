@@ -0,0 +1,110 @@
+use std::error::Error;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use galaxy_core::snapshot::SimSnapshot;
+use tungstenite::Message;
+
+/// The number of frames that can be queued for a client before new frames are dropped for it, so
+/// that a slow client can't hold up the simulation or use unbounded memory.
+const CLIENT_QUEUE_LENGTH: usize = 4;
+
+/// An encoded frame. Each client is sent its own copy, as WebSocket messages own their payload.
+type Frame = Vec<u8>;
+
+/// A WebSocket server that streams the state of the simulation to any connected clients, one binary
+/// message per step. All values are little-endian, each message is laid out as:
+///
+/// * `u64` step number
+/// * `f64` elapsed simulated time in Myr
+/// * `u32` star count
+/// * then for each star, `f32` x and y position in parsecs and `f32` mass in solar masses
+pub struct StreamServer {
+    clients: Arc<Mutex<Vec<SyncSender<Frame>>>>,
+}
+
+impl StreamServer {
+    /// Start listening for WebSocket connections on the given address. Connections are accepted
+    /// and served on background threads.
+    pub fn new<A: ToSocketAddrs>(address: A) -> Result<Self, Box<dyn Error>> {
+        let listener = TcpListener::bind(address)?;
+        log::info!("Streaming star positions on ws://{}", listener.local_addr()?);
+
+        let clients = Arc::new(Mutex::new(Vec::new()));
+
+        let accept_clients = clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let (sender, receiver) = mpsc::sync_channel(CLIENT_QUEUE_LENGTH);
+                        accept_clients.lock().unwrap().push(sender);
+                        thread::spawn(move || Self::serve_client(stream, receiver));
+                    },
+                    Err(err) => log::warn!("Failed to accept stream connection: {err}"),
+                }
+            }
+        });
+
+        Ok(Self { clients })
+    }
+
+    /// Send a snapshot of the simulation to all connected clients.
+    pub fn publish(&self, snapshot: &SimSnapshot) {
+        let mut clients = self.clients.lock().unwrap();
+        if clients.is_empty() {
+            return;
+        }
+
+        let frame = Self::encode_frame(snapshot);
+
+        // Drop clients that have disconnected, and skip frames for clients that are falling behind.
+        clients.retain(|client| match client.try_send(frame.clone()) {
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+
+    /// Encode a snapshot of the simulation as a binary frame.
+    fn encode_frame(snapshot: &SimSnapshot) -> Vec<u8> {
+        let star_count = snapshot.star_count();
+
+        let mut frame = Vec::with_capacity(20 + star_count * 12);
+        frame.extend_from_slice(&snapshot.step.to_le_bytes());
+        frame.extend_from_slice(&snapshot.elapsed_myr.to_le_bytes());
+        frame.extend_from_slice(&(star_count as u32).to_le_bytes());
+
+        for (position, mass) in snapshot.positions.iter().zip(&snapshot.masses) {
+            frame.extend_from_slice(&(position.x as f32).to_le_bytes());
+            frame.extend_from_slice(&(position.y as f32).to_le_bytes());
+            frame.extend_from_slice(&(*mass as f32).to_le_bytes());
+        }
+
+        frame
+    }
+
+    /// Perform the WebSocket handshake with a client and then forward frames to it until it
+    /// disconnects.
+    fn serve_client(stream: TcpStream, frames: Receiver<Frame>) {
+        let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+
+        let mut websocket = match tungstenite::accept(stream) {
+            Ok(websocket) => websocket,
+            Err(err) => {
+                log::warn!("WebSocket handshake with {peer} failed: {err}");
+                return;
+            },
+        };
+
+        log::info!("Stream client {peer} connected");
+
+        for frame in frames {
+            if let Err(err) = websocket.write_message(Message::Binary(frame)) {
+                log::info!("Stream client {peer} disconnected: {err}");
+                return;
+            }
+        }
+    }
+}
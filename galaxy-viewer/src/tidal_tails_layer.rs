@@ -0,0 +1,156 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use galaxy_core::analysis::tidal_tails::{TidalState, TidalTails};
+use galaxy_core::config::Config;
+use galaxy_core::star_world::Position;
+use galaxy_core::types::Vec2d;
+use miniquad::*;
+
+use crate::drawable::*;
+use crate::galaxy::Galaxy;
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+/// How many updates there are between each classification of the stars, as finding the potential
+/// of every star is too slow to do every step in large galaxies.
+const UPDATE_INTERVAL: u64 = 30;
+
+/// The colours of stars in tidal tails and stars that have recently been stripped.
+const TAIL_COLOR: [u8; 3] = [0x40, 0xE0, 0xFF];
+const STRIPPED_COLOR: [u8; 3] = [0xFF, 0x90, 0x20];
+
+/// The colour of the circle around the main body.
+const BODY_COLOR: [u8; 3] = [0xFF, 0xFF, 0xFF];
+
+/// The number of points drawn around the circle around the main body.
+const BODY_CIRCLE_POINTS: usize = 512;
+
+/// A layer that highlights the stars far outside the main body of the galaxy, colouring those that
+/// are still bound, as in tidal tails, and those that have recently been stripped. This is mostly
+/// useful in merger scenarios.
+pub struct TidalTailsLayer {
+    textured_quad: TexturedQuad,
+    galaxy: Rc<RefCell<Galaxy>>,
+
+    tidal_tails: TidalTails,
+
+    /// How many times the radius containing half the stars the main body extends to.
+    radius_factor: f32,
+
+    /// How long stars are shown as stripped for after they become unbound, in Myr.
+    stripped_myr: f32,
+
+    /// Whether to draw the edge of the main body.
+    show_body: bool,
+
+    /// The number of updates since the stars were last classified, or None if they need to be.
+    updates_since_update: Option<u64>,
+}
+
+impl TidalTailsLayer {
+    /// Create a tidal tails layer for the given galaxy.
+    pub fn new(ctx: &mut Context, config: &Config, galaxy: Rc<RefCell<Galaxy>>) -> Result<Self, Box<dyn Error>> {
+        let blend = BlendState::new(Equation::Add,
+                                    BlendFactor::Value(BlendValue::SourceAlpha),
+                                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha));
+        let textured_quad = TexturedQuad::with_blend(ctx,
+                                                     config.rendering.texture_width,
+                                                     config.rendering.texture_height,
+                                                     Some(blend))?;
+
+        Ok(Self {
+            textured_quad,
+            galaxy,
+            tidal_tails: TidalTails::default(),
+            radius_factor: 3.0,
+            stripped_myr: 500.0,
+            show_body: true,
+            updates_since_update: None,
+        })
+    }
+
+    /// Draw the highlighted stars in the current view into the texture, in the same pixels as the
+    /// stars themselves.
+    fn update_texture(&mut self, ctx: &mut Context) {
+        let (width, height) = (self.textured_quad.width, self.textured_quad.height);
+        let mut bytes = vec![0; 4 * width * height];
+
+        let galaxy = self.galaxy.borrow();
+        let (view_offset, view_size) = galaxy.renderer.view_bounds();
+        let to_pixel = |position: Vec2d| {
+            let pos = position - view_offset;
+            ((pos.x / view_size.x * width as f64) as i64, (pos.y / view_size.y * height as f64) as i64)
+        };
+        let mut set_pixel = |(x, y): (i64, i64), [r, g, b]: [u8; 3]| {
+            if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                let idx = 4 * (y as usize * width + x as usize);
+                bytes[idx..idx+4].copy_from_slice(&[r, g, b, 0xFF]);
+            }
+        };
+
+        if self.show_body {
+            for i in 0..BODY_CIRCLE_POINTS {
+                let angle = i as f64 / BODY_CIRCLE_POINTS as f64 * 2.0 * PI;
+                let offset = Vec2d::new(angle.cos(), angle.sin()) * self.tidal_tails.body_radius;
+                set_pixel(to_pixel(self.tidal_tails.center + offset), BODY_COLOR);
+            }
+        }
+
+        for (position, state) in galaxy.sim.stars().query::<&Position>().zip(&self.tidal_tails.states) {
+            match state {
+                TidalState::Tail => set_pixel(to_pixel(position.0), TAIL_COLOR),
+                TidalState::Stripped => set_pixel(to_pixel(position.0), STRIPPED_COLOR),
+                TidalState::Body | TidalState::Unbound => {},
+            }
+        }
+
+        self.textured_quad.texture.update(ctx, &bytes);
+    }
+}
+
+impl Drawable for TidalTailsLayer {
+    /// Update the tidal tails layer, classifying the stars again if it's time to.
+    fn fixed_update(&mut self,
+                    ctx: &mut Context,
+                    _input_state: &InputState,
+                    _viewport: &Viewport,
+                    _time_delta: f64)
+    {
+        self.updates_since_update = match self.updates_since_update {
+            Some(updates) if updates + 1 < UPDATE_INTERVAL => Some(updates + 1),
+            _ => {
+                self.tidal_tails.update(&self.galaxy.borrow().sim, self.radius_factor as f64, self.stripped_myr as f64);
+                Some(0)
+            },
+        };
+
+        self.update_texture(ctx);
+    }
+
+    /// Build the "Tidal tails" window.
+    fn ui(&mut self, _ctx: &mut Context, ui: &imgui::Ui) {
+        ui.window("Tidal tails")
+            .size([250.0, 150.0], imgui::Condition::FirstUseEver)
+            .position([370.0, 600.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let mut changed = ui.slider("Body radius", 1.0, 10.0, &mut self.radius_factor);
+                changed |= ui.slider("Stripped (Myr)", 0.0, 5000.0, &mut self.stripped_myr);
+                ui.checkbox("Show body", &mut self.show_body);
+                ui.text(format!("{} in tails, {} stripped",
+                                self.tidal_tails.count(TidalState::Tail),
+                                self.tidal_tails.count(TidalState::Stripped)));
+
+                if changed {
+                    self.updates_since_update = None;
+                }
+            });
+    }
+
+    /// Draw the tidal tails layer.
+    fn render(&mut self, ctx: &mut Context, _alpha: f64) {
+        self.textured_quad.draw(ctx);
+    }
+}
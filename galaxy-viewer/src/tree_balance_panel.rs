@@ -0,0 +1,91 @@
+use galaxy_core::analysis::tree_balance::{TreeBalance, MAX_COUNTED_LEAF_ITEMS};
+use galaxy_core::simulation::GalaxySim;
+
+/// How many steps there are between each measurement.
+const UPDATE_INTERVAL: u64 = 30;
+
+/// The colour of the warning shown when the tree is badly shaped.
+const WARNING_COLOR: [f32; 4] = [1.0, 0.8, 0.3, 1.0];
+
+/// Shows how balanced the simulation's quadtree is and how full its nodes are, with a warning if
+/// it's pathologically deep, to guide the choice of its maximum depth and bounds.
+pub struct TreeBalancePanel {
+    latest: Option<TreeBalance>,
+
+    /// The number of steps since the last measurement.
+    steps_since_update: u64,
+}
+
+impl TreeBalancePanel {
+    /// Create an empty panel.
+    pub fn new() -> Self {
+        Self {
+            latest: None,
+            steps_since_update: 0,
+        }
+    }
+
+    /// Clear the panel, e.g. when the galaxy is replaced.
+    pub fn reset(&mut self) {
+        self.latest = None;
+        self.steps_since_update = 0;
+    }
+
+    /// Measure the quadtree, if it's time to.
+    pub fn record(&mut self, sim: &GalaxySim) {
+        if self.latest.is_some() && self.steps_since_update + 1 < UPDATE_INTERVAL {
+            self.steps_since_update += 1;
+            return;
+        }
+        self.steps_since_update = 0;
+        self.latest = Some(TreeBalance::measure(&sim.quadtree));
+    }
+
+    /// Build the quadtree balance window.
+    pub fn ui(&self, ui: &imgui::Ui, sim: &GalaxySim) {
+        ui.window("Quadtree balance")
+            .size([300.0, 330.0], imgui::Condition::FirstUseEver)
+            .position([620.0, 590.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let balance = match &self.latest {
+                    Some(balance) => balance,
+                    None => return,
+                };
+
+                let extent = sim.quadtree.max.x - sim.quadtree.min.x;
+                ui.label_text("Bounds", format!("{extent:.0} pc across"));
+                ui.label_text("Max depth", balance.max_depth.to_string());
+                ui.label_text("Leaves", format!("{} holding {} items", balance.leaf_count(), balance.item_count));
+                ui.label_text("Leaf depth", format!("mean {:.1}, deepest {}, balanced {:.1}",
+                                                    balance.mean_leaf_depth(),
+                                                    balance.deepest_leaf(),
+                                                    balance.balanced_depth()));
+
+                let depths: Vec<f32> = balance.leaf_depths.iter().map(|&count| count as f32).collect();
+                ui.plot_histogram("##depths", &depths)
+                    .graph_size([0.0, 60.0])
+                    .overlay_text(format!("Leaves by depth, 0 to {}", balance.max_depth - 1))
+                    .build();
+
+                ui.text("Items per leaf");
+                for (i, &count) in balance.leaf_items.iter().enumerate().filter(|(_, count)| **count > 0) {
+                    let items = match i + 1 {
+                        MAX_COUNTED_LEAF_ITEMS => format!("{MAX_COUNTED_LEAF_ITEMS}+"),
+                        items => items.to_string(),
+                    };
+                    ui.bullet_text(format!("{items}: {count} leaves"));
+                }
+
+                let children: Vec<f32> = balance.internal_children.iter().map(|&count| count as f32).collect();
+                ui.plot_histogram("##children", &children)
+                    .graph_size([0.0, 40.0])
+                    .overlay_text("Internal nodes by children, 1 to 4")
+                    .build();
+
+                if let Some(warning) = balance.warning() {
+                    ui.text_colored(WARNING_COLOR, "Warning:");
+                    ui.text_wrapped(warning);
+                }
+            });
+    }
+}
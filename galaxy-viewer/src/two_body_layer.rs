@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+
+use galaxy_core::config::Config;
+use galaxy_core::star_world::Position;
+use galaxy_core::types::Vec2d;
+use galaxy_core::validation::{self, TwoBodyValidation};
+use miniquad::*;
+
+use crate::drawable::*;
+use crate::galaxy::{Galaxy, WARNING_COLOR};
+use crate::input::InputState;
+use crate::viewport::Viewport;
+
+/// The colour of the marker where the analytic orbit puts the secondary.
+const EXPECTED_COLOR: [u8; 3] = [0xFF, 0xFF, 0x40];
+
+/// The half width of the marker, in pixels.
+const MARKER_RADIUS: i64 = 4;
+
+/// A layer for the two-body validation scenario, which compares the integrated orbit against the
+/// analytic one every update, marks where the secondary should be, and shows how far it has
+/// strayed. The first time the deviation exceeds the tolerance it's logged as an error.
+pub struct TwoBodyLayer {
+    textured_quad: TexturedQuad,
+    galaxy: Rc<RefCell<Galaxy>>,
+
+    validation: TwoBodyValidation,
+
+    /// Where the analytic orbit puts the secondary as of the last update, or None if either body
+    /// has been removed.
+    expected_position: Option<Vec2d>,
+
+    /// Whether the deviation has exceeded the tolerance.
+    failed: bool,
+}
+
+impl TwoBodyLayer {
+    /// Create a two-body layer checking the given galaxy, which must have been created by the
+    /// validation.
+    pub fn new(ctx: &mut Context,
+               config: &Config,
+               galaxy: Rc<RefCell<Galaxy>>,
+               validation: TwoBodyValidation) -> Result<Self, Box<dyn Error>>
+    {
+        let blend = BlendState::new(Equation::Add,
+                                    BlendFactor::Value(BlendValue::SourceAlpha),
+                                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha));
+        let textured_quad = TexturedQuad::with_blend(ctx,
+                                                     config.rendering.texture_width,
+                                                     config.rendering.texture_height,
+                                                     Some(blend))?;
+
+        Ok(Self {
+            textured_quad,
+            galaxy,
+            validation,
+            expected_position: None,
+            failed: false,
+        })
+    }
+
+    /// Draw a box where the secondary should be into the texture.
+    fn update_texture(&mut self, ctx: &mut Context) {
+        let (width, height) = (self.textured_quad.width, self.textured_quad.height);
+        let mut bytes = vec![0; 4 * width * height];
+
+        if let Some(position) = self.expected_position {
+            let (view_offset, view_size) = self.galaxy.borrow().renderer.view_bounds();
+            let pos = position - view_offset;
+            let center_x = (pos.x / view_size.x * width as f64) as i64;
+            let center_y = (pos.y / view_size.y * height as f64) as i64;
+
+            let [r, g, b] = EXPECTED_COLOR;
+            for offset in -MARKER_RADIUS..=MARKER_RADIUS {
+                for (x, y) in [(center_x + offset, center_y - MARKER_RADIUS),
+                               (center_x + offset, center_y + MARKER_RADIUS),
+                               (center_x - MARKER_RADIUS, center_y + offset),
+                               (center_x + MARKER_RADIUS, center_y + offset)]
+                {
+                    if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                        let idx = 4 * (y as usize * width + x as usize);
+                        bytes[idx..idx+4].copy_from_slice(&[r, g, b, 0xFF]);
+                    }
+                }
+            }
+        }
+
+        self.textured_quad.texture.update(ctx, &bytes);
+    }
+}
+
+impl Drawable for TwoBodyLayer {
+    /// Compare the bodies against the analytic orbit.
+    fn fixed_update(&mut self,
+                    ctx: &mut Context,
+                    _input_state: &InputState,
+                    _viewport: &Viewport,
+                    _time_delta: f64)
+    {
+        {
+            let galaxy = self.galaxy.borrow();
+            self.expected_position = self.validation.check(&galaxy.sim).map(|_| {
+                let primary = galaxy.sim.stars().component::<Position>(1).map_or(Vec2d::new(0.0, 0.0), |position| position.0);
+                primary + self.validation.expected_separation(galaxy.sim.elapsed_time)
+            });
+        }
+
+        if !self.failed {
+            if let Err(err) = self.validation.assert_within(validation::TOLERANCE) {
+                log::error!("{err}");
+                self.failed = true;
+            }
+        }
+
+        self.update_texture(ctx);
+    }
+
+    /// Build the "Two-body validation" window.
+    fn ui(&mut self, _ctx: &mut Context, ui: &imgui::Ui) {
+        ui.window("Two-body validation")
+            .size([300.0, 130.0], imgui::Condition::FirstUseEver)
+            .position([630.0, 120.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                match self.expected_position {
+                    Some(_) => {
+                        ui.label_text("Deviation", format!("{:.1} pc", self.validation.deviation));
+                        ui.label_text("Max deviation", format!("{:.1} pc ({:.2}%)",
+                                                               self.validation.max_deviation,
+                                                               self.validation.max_relative_deviation() * 100.0));
+                        ui.label_text("Period", format!("{:.0} Myr", self.validation.period_myr()));
+                    },
+                    None => ui.text("A body has been removed"),
+                }
+
+                if self.failed {
+                    ui.text_colored(WARNING_COLOR, format!("Deviated by more than {:.0}% of the semi-major axis",
+                                                           validation::TOLERANCE * 100.0));
+                }
+            });
+    }
+
+    /// Draw the marker where the secondary should be.
+    fn render(&mut self, ctx: &mut Context, _alpha: f64) {
+        self.textured_quad.draw(ctx);
+    }
+}
@@ -1,30 +1,38 @@
 use miniquad::EventHandler;
 
-/// A simple helper struct that lets you combine stages and execute them in sequence.
-pub struct CombinedStage {
-    stages: Vec<Box<dyn EventHandler>>,
+use crate::drawable::ImguiRenderer;
+
+/// Something that builds an imgui UI, once per frame.
+pub trait UiBuilder {
+    /// Build this frame's windows.
+    fn build_ui(&mut self, ctx: &mut miniquad::Context, ui: &imgui::Ui);
+}
+
+/// A stage with an imgui UI drawn over it. Events go to the stage and then to imgui, and each frame
+/// the stage is drawn and then asked to build its UI, which is rendered on top.
+pub struct UiStage<S> {
+    stage: S,
+    imgui: ImguiRenderer,
 }
 
-impl CombinedStage {
-    /// Create a new CombinedStage from the provided list of stages.
-    pub fn new(stages: Vec<Box<dyn EventHandler>>) -> Self {
+impl<S: EventHandler + UiBuilder> UiStage<S> {
+    /// Create a new UiStage drawing the given stage with the given imgui renderer.
+    pub fn new(stage: S, imgui: ImguiRenderer) -> Self {
         Self {
-            stages,
+            stage,
+            imgui,
         }
     }
 }
 
-impl EventHandler for CombinedStage {
+impl<S: EventHandler + UiBuilder> EventHandler for UiStage<S> {
     fn update(&mut self, ctx: &mut miniquad::Context) {
-        for stage in &mut self.stages {
-            stage.update(ctx);
-        }
+        self.stage.update(ctx);
     }
 
     fn draw(&mut self, ctx: &mut miniquad::Context) {
-        for stage in &mut self.stages {
-            stage.draw(ctx);
-        }
+        self.stage.draw(ctx);
+        self.imgui.frame(ctx, |ctx, ui| self.stage.build_ui(ctx, ui));
     }
 
     fn char_event(&mut self,
@@ -33,9 +41,8 @@ impl EventHandler for CombinedStage {
                   keymods: miniquad::KeyMods,
                   repeat: bool)
     {
-        for stage in &mut self.stages {
-            stage.char_event(ctx, character, keymods, repeat);
-        }
+        self.stage.char_event(ctx, character, keymods, repeat);
+        self.imgui.char_event(ctx, character, keymods, repeat);
     }
 
     fn touch_event(&mut self,
@@ -43,15 +50,12 @@ impl EventHandler for CombinedStage {
                    phase: miniquad::TouchPhase,
                    id: u64, x: f32, y: f32)
     {
-        for stage in &mut self.stages {
-            stage.touch_event(ctx, phase, id, x, y);
-        }
+        self.stage.touch_event(ctx, phase, id, x, y);
     }
 
     fn resize_event(&mut self, ctx: &mut miniquad::Context, width: f32, height: f32) {
-        for stage in &mut self.stages {
-            stage.resize_event(ctx, width, height);
-        }
+        self.stage.resize_event(ctx, width, height);
+        self.imgui.resize_event(ctx, width, height);
     }
 
     fn key_up_event(&mut self,
@@ -59,9 +63,8 @@ impl EventHandler for CombinedStage {
                     keycode: miniquad::KeyCode,
                     keymods: miniquad::KeyMods)
     {
-        for stage in &mut self.stages {
-            stage.key_up_event(ctx, keycode, keymods);
-        }
+        self.stage.key_up_event(ctx, keycode, keymods);
+        self.imgui.key_up_event(ctx, keycode, keymods);
     }
 
     fn key_down_event(&mut self,
@@ -70,39 +73,30 @@ impl EventHandler for CombinedStage {
                       keymods: miniquad::KeyMods,
                       repeat: bool)
     {
-        for stage in &mut self.stages {
-            stage.key_down_event(ctx, keycode, keymods, repeat);
-        }
+        self.stage.key_down_event(ctx, keycode, keymods, repeat);
+        self.imgui.key_down_event(ctx, keycode, keymods, repeat);
     }
 
     fn raw_mouse_motion(&mut self, ctx: &mut miniquad::Context, dx: f32, dy: f32) {
-        for stage in &mut self.stages {
-            stage.raw_mouse_motion(ctx, dx, dy);
-        }
+        self.stage.raw_mouse_motion(ctx, dx, dy);
     }
 
     fn mouse_wheel_event(&mut self, ctx: &mut miniquad::Context, x: f32, y: f32) {
-        for stage in &mut self.stages {
-            stage.mouse_wheel_event(ctx, x, y);
-        }
+        self.stage.mouse_wheel_event(ctx, x, y);
+        self.imgui.mouse_wheel_event(ctx, x, y);
     }
 
     fn mouse_motion_event(&mut self, ctx: &mut miniquad::Context, x: f32, y: f32) {
-        for stage in &mut self.stages {
-            stage.mouse_motion_event(ctx, x, y);
-        }
+        self.stage.mouse_motion_event(ctx, x, y);
+        self.imgui.mouse_motion_event(ctx, x, y);
     }
 
     fn files_dropped_event(&mut self, ctx: &mut miniquad::Context) {
-        for stage in &mut self.stages {
-            stage.files_dropped_event(ctx);
-        }
+        self.stage.files_dropped_event(ctx);
     }
 
     fn quit_requested_event(&mut self, ctx: &mut miniquad::Context) {
-        for stage in &mut self.stages {
-            stage.quit_requested_event(ctx);
-        }
+        self.stage.quit_requested_event(ctx);
     }
 
     fn mouse_button_up_event(&mut self,
@@ -111,21 +105,16 @@ impl EventHandler for CombinedStage {
                              x: f32,
                              y: f32)
     {
-        for stage in &mut self.stages {
-            stage.mouse_button_up_event(ctx, button, x, y);
-        }
+        self.stage.mouse_button_up_event(ctx, button, x, y);
+        self.imgui.mouse_button_up_event(ctx, button, x, y);
     }
 
     fn window_restored_event(&mut self, ctx: &mut miniquad::Context) {
-        for stage in &mut self.stages {
-            stage.window_restored_event(ctx);
-        }
+        self.stage.window_restored_event(ctx);
     }
 
     fn window_minimized_event(&mut self, ctx: &mut miniquad::Context) {
-        for stage in &mut self.stages {
-            stage.window_minimized_event(ctx);
-        }
+        self.stage.window_minimized_event(ctx);
     }
 
     fn mouse_button_down_event(&mut self,
@@ -133,8 +122,7 @@ impl EventHandler for CombinedStage {
                                button: miniquad::MouseButton,
                                x: f32, y: f32)
     {
-        for stage in &mut self.stages {
-            stage.mouse_button_down_event(ctx, button, x, y);
-        }
+        self.stage.mouse_button_down_event(ctx, button, x, y);
+        self.imgui.mouse_button_down_event(ctx, button, x, y);
     }
 }
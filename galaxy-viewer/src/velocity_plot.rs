@@ -0,0 +1,117 @@
+use galaxy_core::analysis::velocity_distribution::VelocityDistribution;
+use galaxy_core::simulation::GalaxySim;
+
+/// The number of bins in each histogram.
+const BIN_COUNT: usize = 40;
+
+/// How many steps there are between each measurement.
+const UPDATE_INTERVAL: u64 = 10;
+
+/// The colour of the Maxwellian fit drawn over the speed histogram.
+const FIT_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+
+/// The height of each histogram, in pixels.
+const PLOT_HEIGHT: f32 = 60.0;
+
+/// Plots histograms of the speeds of the stars and of the radial and tangential components of
+/// their velocities, with the Maxwellian distribution that best fits the speeds drawn over them.
+pub struct VelocityPlot {
+    distribution: VelocityDistribution,
+
+    /// Whether to draw the Maxwellian fit.
+    show_fit: bool,
+
+    /// The number of steps since the last measurement, or None if there hasn't been one.
+    steps_since_update: Option<u64>,
+}
+
+impl VelocityPlot {
+    /// Create an empty plot.
+    pub fn new() -> Self {
+        Self {
+            distribution: VelocityDistribution::default(),
+            show_fit: true,
+            steps_since_update: None,
+        }
+    }
+
+    /// Clear the plot, e.g. when the galaxy is replaced.
+    pub fn reset(&mut self) {
+        self.distribution = VelocityDistribution::default();
+        self.steps_since_update = None;
+    }
+
+    /// Measure the velocity distribution, if it's time to.
+    pub fn record(&mut self, sim: &GalaxySim) {
+        self.steps_since_update = match self.steps_since_update {
+            Some(steps) if steps + 1 < UPDATE_INTERVAL => Some(steps + 1),
+            _ => {
+                self.distribution = VelocityDistribution::measure(&sim.stars().to_vec(), BIN_COUNT);
+                Some(0)
+            },
+        };
+    }
+
+    /// Build the velocity distribution window.
+    pub fn ui(&mut self, ui: &imgui::Ui) {
+        ui.window("Velocity distribution")
+            .size([300.0, 330.0], imgui::Condition::FirstUseEver)
+            .position([940.0, 10.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let distribution = &self.distribution;
+                if distribution.speeds.is_empty() {
+                    return;
+                }
+
+                ui.checkbox("Maxwellian fit", &mut self.show_fit);
+                ui.text(format!("Speed, 0 to {:.1} km/s (sigma = {:.1} km/s)", distribution.max_speed, distribution.dispersion));
+
+                let fit = self.show_fit.then(|| distribution.maxwellian());
+                Self::histogram(ui, "##speed", &distribution.speeds, fit.as_deref());
+
+                ui.text(format!("Radial, -{0:.1} to {0:.1} km/s", distribution.max_speed));
+                Self::histogram(ui, "##radial", &distribution.radial, None);
+
+                ui.text(format!("Tangential, -{0:.1} to {0:.1} km/s", distribution.max_speed));
+                Self::histogram(ui, "##tangential", &distribution.tangential, None);
+            });
+    }
+
+    /// Plot a histogram, optionally with a curve of expected counts drawn over it.
+    fn histogram(ui: &imgui::Ui, label: &str, counts: &[usize], fit: Option<&[f64]>) {
+        let values: Vec<f32> = counts.iter().map(|&count| count as f32).collect();
+
+        // Both use the same scale, so the fit lines up with the bars.
+        let scale_max = values.iter()
+            .copied()
+            .chain(fit.unwrap_or_default().iter().map(|&expected| expected as f32))
+            .fold(1.0, f32::max);
+
+        ui.plot_histogram(label, &values)
+            .graph_size([0.0, PLOT_HEIGHT])
+            .scale_min(0.0)
+            .scale_max(scale_max)
+            .build();
+
+        if let Some(fit) = fit {
+            // The plot is drawn inside the frame padding of its rectangle, with each value at the
+            // center of its bar.
+            let padding = ui.clone_style().frame_padding;
+            let min = ui.item_rect_min();
+            let max = ui.item_rect_max();
+            let (left, bottom) = (min[0] + padding[0], max[1] - padding[1]);
+            let width = max[0] - padding[0] - left;
+            let height = bottom - (min[1] + padding[1]);
+
+            let points: Vec<[f32; 2]> = fit.iter().enumerate().map(|(bin, &expected)| {
+                [left + (bin as f32 + 0.5) / fit.len() as f32 * width,
+                 bottom - expected as f32 / scale_max * height]
+            }).collect();
+
+            ui.get_window_draw_list()
+                .add_polyline(points, FIT_COLOR)
+                .thickness(1.5)
+                .build();
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use galaxy_core::types::Vec2d;
+use miniquad::Context;
+
+/// An area of the window that something is drawn in, in pixels from the top left of the window, as
+/// mouse positions are. Drawables use it to map the mouse into the area they're drawn in, so that
+/// picking and panning work for any window size and layout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+
+    /// The height of the whole window, which OpenGL's viewports are measured from the bottom of.
+    window_height: f32,
+}
+
+impl Viewport {
+    /// A viewport covering the whole of a window of the given size.
+    pub fn new(window_width: f32, window_height: f32) -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width: window_width,
+            height: window_height,
+            window_height,
+        }
+    }
+
+    /// Resize the window the viewport covers.
+    pub fn resize(&mut self, window_width: f32, window_height: f32) {
+        *self = Self::new(window_width, window_height);
+    }
+
+    /// Split the viewport into two squares side by side, each as large as fits in its half,
+    /// centred vertically.
+    pub fn split_horizontally(&self) -> (Viewport, Viewport) {
+        let size = f32::min(self.width / 2.0, self.height);
+        let y = self.y + (self.height - size) / 2.0;
+
+        let left = Self { x: self.x, y, width: size, height: size, ..*self };
+        let right = Self { x: self.x + size, ..left };
+        (left, right)
+    }
+
+    /// A small square in the top right corner of the viewport, for an inset view.
+    pub fn inset(&self) -> Viewport {
+        const MARGIN: f32 = 10.0;
+        let size = f32::min(self.width, self.height) * 0.3;
+        Self {
+            x: self.x + self.width - size - MARGIN,
+            y: self.y + MARGIN,
+            width: size,
+            height: size,
+            ..*self
+        }
+    }
+
+    /// Draw into this viewport until the next render pass.
+    pub fn apply(&self, ctx: &mut Context) {
+        let bottom = self.window_height - self.y - self.height;
+        ctx.apply_viewport(self.x as i32, bottom as i32, self.width as i32, self.height as i32);
+    }
+
+    /// Map a position in the window to the viewport, from (0, 0) at its bottom left to (1, 1) at
+    /// its top right.
+    pub fn normalize(&self, (x, y): (f32, f32)) -> Vec2d {
+        Vec2d::new(((x - self.x) / self.width) as f64,
+                   1.0 - ((y - self.y) / self.height) as f64)
+    }
+
+    /// Whether a position in the window is inside the viewport.
+    pub fn contains(&self, (x, y): (f32, f32)) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// The size of the viewport in pixels.
+    pub fn size(&self) -> Vec2d {
+        Vec2d::new(self.width as f64, self.height as f64)
+    }
+}
@@ -0,0 +1,158 @@
+//! Python bindings for the galaxy simulation, so that it can be driven and analysed from Python
+//! without the viewer. Build with maturin, e.g. `maturin develop --release` from this directory.
+
+use std::error::Error;
+use std::path::PathBuf;
+
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+
+use galaxy_core::checkpoint::Checkpoint;
+use galaxy_core::config::{Config, DEFAULT_CONFIG_PATH};
+use galaxy_core::rng::RngStreams;
+use galaxy_core::simulation::GalaxySim;
+use galaxy_core::star_world::{Age, Mass, Metallicity, Position, Velocity};
+
+/// The default time delta of a step, the same as the viewer's fixed timestep.
+const DEFAULT_TIME_DELTA: f64 = 1.0 / 60.0;
+
+/// Convert an error from the simulation into a Python exception.
+fn to_py_err(err: Box<dyn Error>) -> PyErr {
+    PyRuntimeError::new_err(err.to_string())
+}
+
+/// A snapshot of the state of all stars at a point in time. Positions are in parsecs, velocities
+/// in km/s, masses in solar masses, ages in Gyr and metallicities in dex. The first star is the
+/// supermassive black hole, if the galaxy has one.
+#[pyclass]
+pub struct Snapshot {
+    #[pyo3(get)]
+    elapsed_myr: f64,
+
+    #[pyo3(get)]
+    positions: Vec<(f64, f64)>,
+
+    #[pyo3(get)]
+    velocities: Vec<(f64, f64)>,
+
+    #[pyo3(get)]
+    masses: Vec<f64>,
+
+    #[pyo3(get)]
+    ages: Vec<f64>,
+
+    #[pyo3(get)]
+    metallicities: Vec<f64>,
+}
+
+/// A galaxy simulation.
+#[pyclass]
+pub struct Simulation {
+    sim: GalaxySim,
+    seed: u64,
+}
+
+#[pymethods]
+impl Simulation {
+    /// Generate a new galaxy. The config is loaded from `config_path` if given, which must exist,
+    /// or galaxy.toml in the working directory if it exists. The seed defaults to the one in the
+    /// config.
+    #[new]
+    #[pyo3(signature = (seed=None, config_path=None))]
+    fn new(seed: Option<u64>, config_path: Option<PathBuf>) -> PyResult<Self> {
+        let config_path = match config_path {
+            Some(config_path) if !config_path.exists() => {
+                return Err(PyRuntimeError::new_err(format!("Config file {} not found", config_path.display())));
+            },
+            Some(config_path) => config_path,
+            None => PathBuf::from(DEFAULT_CONFIG_PATH),
+        };
+        let config = Config::load(config_path).map_err(to_py_err)?;
+        let seed = seed.unwrap_or(config.generation.seed);
+
+        let sim = GalaxySim::new(&config, &RngStreams::new(seed)).map_err(to_py_err)?;
+
+        Ok(Self { sim, seed })
+    }
+
+    /// Load a simulation from a checkpoint file written by the viewer or save_checkpoint.
+    #[staticmethod]
+    fn load_checkpoint(path: PathBuf) -> PyResult<Self> {
+        let checkpoint = Checkpoint::load(path).map_err(to_py_err)?;
+        let seed = checkpoint.seed;
+        let sim = checkpoint.into_sim().map_err(to_py_err)?;
+
+        Ok(Self { sim, seed })
+    }
+
+    /// Write a checkpoint of the simulation to a file.
+    fn save_checkpoint(&self, path: PathBuf) -> PyResult<()> {
+        Checkpoint::from_sim(&self.sim, self.seed).save(path).map_err(to_py_err)
+    }
+
+    /// Step the simulation `steps` times. Each step advances the simulation by `time_delta`
+    /// seconds of real time at its rate in Myr per second.
+    #[pyo3(signature = (time_delta=DEFAULT_TIME_DELTA, steps=1))]
+    fn step(&mut self, py: Python<'_>, time_delta: f64, steps: usize) {
+        py.allow_threads(|| {
+            for _ in 0..steps {
+                self.sim.step(time_delta);
+            }
+        });
+    }
+
+    /// Move the stars into the center of momentum frame, recentering them on their barycenter.
+    fn recenter(&mut self) {
+        self.sim.recenter();
+    }
+
+    /// Take a snapshot of the current state of all stars.
+    fn snapshot(&self) -> Snapshot {
+        let stars = self.sim.stars();
+        Snapshot {
+            elapsed_myr: self.sim.elapsed_myr(),
+            positions: stars.query::<&Position>().map(|position| (position.0.x, position.0.y)).collect(),
+            velocities: stars.query::<&Velocity>().map(|velocity| (velocity.0.x, velocity.0.y)).collect(),
+            masses: stars.query::<&Mass>().map(|mass| mass.0.0).collect(),
+            ages: stars.query::<&Age>().map(|age| age.0).collect(),
+            metallicities: stars.query::<&Metallicity>().map(|metallicity| metallicity.0).collect(),
+        }
+    }
+
+    /// The seed the galaxy was generated with.
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The number of stars, including the supermassive black hole.
+    #[getter]
+    fn star_count(&self) -> usize {
+        self.sim.stars().len()
+    }
+
+    /// The simulated time elapsed so far, in Myr.
+    #[getter]
+    fn elapsed_myr(&self) -> f64 {
+        self.sim.elapsed_myr()
+    }
+
+    /// The rate of the simulation, in Myr of simulated time per second of real time.
+    #[getter]
+    fn myr_per_second(&self) -> f64 {
+        self.sim.myr_per_second
+    }
+
+    #[setter]
+    fn set_myr_per_second(&mut self, myr_per_second: f64) {
+        self.sim.myr_per_second = myr_per_second;
+    }
+}
+
+/// The galaxy_sim Python module.
+#[pymodule]
+fn galaxy_sim(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add_class::<Simulation>()?;
+    module.add_class::<Snapshot>()?;
+    Ok(())
+}